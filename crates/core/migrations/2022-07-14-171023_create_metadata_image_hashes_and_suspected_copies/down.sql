@@ -0,0 +1,2 @@
+drop table if exists suspected_copies;
+drop table if exists metadata_image_hashes;
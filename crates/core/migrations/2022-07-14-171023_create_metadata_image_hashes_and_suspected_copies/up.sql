@@ -0,0 +1,32 @@
+create table metadata_image_hashes (
+  metadata_address              varchar(48)     primary key,
+  image_fingerprint             bytea           not null,
+  dhash                         bigint          not null,
+  updated_at                    timestamp       not null
+);
+
+-- Each 16-bit band of the hash is indexed separately; two hashes within a
+-- Hamming distance of 3 are guaranteed to share at least one band exactly.
+create index if not exists metadata_image_hashes_band_0_idx on
+  metadata_image_hashes ((dhash & 65535));
+
+create index if not exists metadata_image_hashes_band_1_idx on
+  metadata_image_hashes (((dhash >> 16) & 65535));
+
+create index if not exists metadata_image_hashes_band_2_idx on
+  metadata_image_hashes (((dhash >> 32) & 65535));
+
+create index if not exists metadata_image_hashes_band_3_idx on
+  metadata_image_hashes (((dhash >> 48) & 65535));
+
+create table suspected_copies (
+  original_address              varchar(48)     not null,
+  copy_address                  varchar(48)     not null,
+  similarity                    double precision not null,
+  detected_at                   timestamp       not null,
+
+  primary key (original_address, copy_address)
+);
+
+create index if not exists suspected_copies_copy_address_idx on
+  suspected_copies (copy_address);
@@ -0,0 +1,6 @@
+drop table if exists store_config_json_versions;
+
+alter table store_config_jsons
+  drop column raw_content,
+  drop column model,
+  drop column updated_at;
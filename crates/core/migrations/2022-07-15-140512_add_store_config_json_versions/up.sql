@@ -0,0 +1,16 @@
+alter table store_config_jsons
+  add column raw_content jsonb,
+  add column model text,
+  add column updated_at timestamp not null default now();
+
+create table store_config_json_versions (
+  id                            uuid            primary key default gen_random_uuid(),
+  config_address                varchar(48)     not null,
+  raw_content                   jsonb           not null,
+  model                         text            not null,
+  fetch_uri                     text            not null,
+  created_at                    timestamp       not null
+);
+
+create index if not exists store_config_json_versions_config_address_created_at_idx on
+  store_config_json_versions using btree (config_address, created_at desc);
@@ -0,0 +1 @@
+drop table if exists arweave_backfill_cursors;
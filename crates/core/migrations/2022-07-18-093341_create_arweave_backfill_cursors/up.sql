@@ -0,0 +1,5 @@
+create table arweave_backfill_cursors (
+  name                          text            primary key,
+  cursor                        text            not null,
+  updated_at                    timestamp       not null
+);
@@ -0,0 +1,11 @@
+create table search_documents (
+  index_name                    text            not null,
+  id                            text            not null,
+  body                          jsonb           not null,
+  ts_index                      tsvector        not null
+    generated always as (jsonb_to_tsvector('simple', body, '["string"]')) stored,
+
+  primary key (index_name, id)
+);
+
+create index search_documents_ts_index on search_documents using gin (ts_index);
@@ -0,0 +1,8 @@
+drop trigger current_metadata_owners_update_notify on current_metadata_owners;
+drop trigger current_metadata_owners_insert_notify on current_metadata_owners;
+drop trigger feed_events_notify on feed_events;
+drop trigger purchases_notify on purchases;
+drop trigger offers_notify on offers;
+drop trigger listings_notify on listings;
+
+drop function notify_row_change();
@@ -0,0 +1,49 @@
+-- Publishes each new or changed row as JSON on a channel named after its
+-- table, consumed by GraphQL subscriptions
+create or replace function notify_row_change()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name, row_to_json(new)::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listings_notify
+  after insert
+  on listings
+  for each row
+  execute procedure notify_row_change();
+
+create trigger offers_notify
+  after insert
+  on offers
+  for each row
+  execute procedure notify_row_change();
+
+create trigger purchases_notify
+  after insert
+  on purchases
+  for each row
+  execute procedure notify_row_change();
+
+create trigger feed_events_notify
+  after insert
+  on feed_events
+  for each row
+  execute procedure notify_row_change();
+
+create trigger current_metadata_owners_insert_notify
+  after insert
+  on current_metadata_owners
+  for each row
+  execute procedure notify_row_change();
+
+create trigger current_metadata_owners_update_notify
+  after update of owner_address
+  on current_metadata_owners
+  for each row
+  when (old.owner_address is distinct from new.owner_address)
+  execute procedure notify_row_change();
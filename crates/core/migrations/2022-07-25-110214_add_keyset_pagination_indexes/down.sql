@@ -0,0 +1,21 @@
+drop index if exists listings_created_at_id_idx;
+
+drop index if exists listings_auction_house_created_at_id_idx;
+
+drop index if exists listings_metadata_created_at_id_idx;
+
+drop index if exists offers_created_at_id_idx;
+
+drop index if exists offers_auction_house_created_at_id_idx;
+
+drop index if exists offers_metadata_created_at_id_idx;
+
+drop index if exists purchases_created_at_id_idx;
+
+drop index if exists purchases_auction_house_created_at_id_idx;
+
+drop index if exists purchases_metadata_created_at_id_idx;
+
+drop index if exists graph_connections_from_connected_at_address_idx;
+
+drop index if exists graph_connections_to_connected_at_address_idx;
@@ -0,0 +1,34 @@
+create index if not exists listings_created_at_id_idx on
+  listings (created_at desc, id desc);
+
+create index if not exists listings_auction_house_created_at_id_idx on
+  listings (auction_house, created_at desc, id desc);
+
+create index if not exists listings_metadata_created_at_id_idx on
+  listings (metadata, created_at desc, id desc);
+
+create index if not exists offers_created_at_id_idx on
+  offers (created_at desc, id desc);
+
+create index if not exists offers_auction_house_created_at_id_idx on
+  offers (auction_house, created_at desc, id desc);
+
+create index if not exists offers_metadata_created_at_id_idx on
+  offers (metadata, created_at desc, id desc);
+
+create index if not exists purchases_created_at_id_idx on
+  purchases (created_at desc, id desc);
+
+create index if not exists purchases_auction_house_created_at_id_idx on
+  purchases (auction_house, created_at desc, id desc);
+
+create index if not exists purchases_metadata_created_at_id_idx on
+  purchases (metadata, created_at desc, id desc);
+
+create index if not exists graph_connections_from_connected_at_address_idx on
+  graph_connections (from_account, connected_at desc, address desc)
+  where disconnected_at is null;
+
+create index if not exists graph_connections_to_connected_at_address_idx on
+  graph_connections (to_account, connected_at desc, address desc)
+  where disconnected_at is null;
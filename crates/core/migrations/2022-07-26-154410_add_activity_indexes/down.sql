@@ -0,0 +1,15 @@
+drop index if exists listings_canceled_at_id_idx;
+
+drop index if exists listings_auction_house_canceled_at_id_idx;
+
+drop index if exists listings_seller_created_at_id_idx;
+
+drop index if exists offers_canceled_at_id_idx;
+
+drop index if exists offers_auction_house_canceled_at_id_idx;
+
+drop index if exists offers_buyer_created_at_id_idx;
+
+drop index if exists purchases_seller_created_at_id_idx;
+
+drop index if exists purchases_buyer_created_at_id_idx;
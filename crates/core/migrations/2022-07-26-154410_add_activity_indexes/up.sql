@@ -0,0 +1,23 @@
+create index if not exists listings_canceled_at_id_idx on
+  listings (canceled_at desc, id desc) where canceled_at is not null;
+
+create index if not exists listings_auction_house_canceled_at_id_idx on
+  listings (auction_house, canceled_at desc, id desc) where canceled_at is not null;
+
+create index if not exists listings_seller_created_at_id_idx on
+  listings (seller, created_at desc, id desc);
+
+create index if not exists offers_canceled_at_id_idx on
+  offers (canceled_at desc, id desc) where canceled_at is not null;
+
+create index if not exists offers_auction_house_canceled_at_id_idx on
+  offers (auction_house, canceled_at desc, id desc) where canceled_at is not null;
+
+create index if not exists offers_buyer_created_at_id_idx on
+  offers (buyer, created_at desc, id desc);
+
+create index if not exists purchases_seller_created_at_id_idx on
+  purchases (seller, created_at desc, id desc);
+
+create index if not exists purchases_buyer_created_at_id_idx on
+  purchases (buyer, created_at desc, id desc);
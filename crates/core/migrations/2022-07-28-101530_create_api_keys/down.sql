@@ -0,0 +1,2 @@
+drop table graphql_usage;
+drop table api_keys;
@@ -0,0 +1,27 @@
+create table api_keys (
+  id                            uuid            primary key default gen_random_uuid(),
+  name                          text            not null,
+  key_hash                      text            not null unique,
+  requests_per_minute           integer         not null,
+  burst                         integer         not null,
+  trusted                       boolean         not null default false,
+  created_at                    timestamp       not null default now(),
+  revoked_at                    timestamp       null
+);
+
+create table graphql_usage (
+  api_key_id                    uuid            not null references api_keys (id),
+  day                           date            not null,
+  operation_name                text            not null,
+  request_count                 bigint          not null default 0,
+  error_count                   bigint          not null default 0,
+  latency_buckets               bigint[]        not null default '{}',
+  latency_max_ms                integer         not null default 0,
+  latency_p50_ms                integer         not null default 0,
+  latency_p95_ms                integer         not null default 0,
+  latency_p99_ms                integer         not null default 0,
+
+  primary key (api_key_id, day, operation_name)
+);
+
+create index graphql_usage_day_idx on graphql_usage (day);
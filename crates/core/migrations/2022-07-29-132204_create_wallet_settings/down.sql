@@ -0,0 +1,7 @@
+drop table profile_nft_orders;
+drop table hidden_nfts;
+drop table notification_preferences;
+drop table collection_watchlists;
+drop table nft_watchlists;
+drop table wallet_sessions;
+drop table wallet_auth_nonces;
@@ -0,0 +1,62 @@
+create table wallet_auth_nonces (
+  nonce                         text            primary key,
+  wallet_address                varchar(48)     not null,
+  expires_at                    timestamp       not null
+);
+
+create index wallet_auth_nonces_expires_at_idx on wallet_auth_nonces (expires_at);
+
+create table wallet_sessions (
+  token_hash                    text            primary key,
+  wallet_address                varchar(48)     not null,
+  created_at                    timestamp       not null default now(),
+  expires_at                    timestamp       not null
+);
+
+create index wallet_sessions_wallet_address_idx on wallet_sessions (wallet_address);
+
+create table nft_watchlists (
+  wallet_address                varchar(48)     not null,
+  metadata_address              varchar(48)     not null,
+  created_at                    timestamp       not null default now(),
+
+  primary key (wallet_address, metadata_address)
+);
+
+create table collection_watchlists (
+  wallet_address                varchar(48)     not null,
+  collection_address            varchar(48)     not null,
+  created_at                    timestamp       not null default now(),
+
+  primary key (wallet_address, collection_address)
+);
+
+create table notification_preferences (
+  wallet_address                varchar(48)     primary key,
+  offer_received                boolean         not null default true,
+  offer_accepted                boolean         not null default true,
+  listing_sold                  boolean         not null default true,
+  outbid                        boolean         not null default true,
+  watchlist_activity            boolean         not null default true,
+  new_follower                  boolean         not null default true,
+  updated_at                    timestamp       not null default now()
+);
+
+create table hidden_nfts (
+  wallet_address                varchar(48)     not null,
+  metadata_address              varchar(48)     not null,
+  created_at                    timestamp       not null default now(),
+
+  primary key (wallet_address, metadata_address)
+);
+
+create table profile_nft_orders (
+  wallet_address                varchar(48)     not null,
+  metadata_address              varchar(48)     not null,
+  position                      integer         not null,
+
+  primary key (wallet_address, metadata_address)
+);
+
+create index profile_nft_orders_wallet_position_idx
+  on profile_nft_orders (wallet_address, position);
@@ -0,0 +1,3 @@
+drop index if exists metadatas_slot_idx;
+
+drop table slot_times;
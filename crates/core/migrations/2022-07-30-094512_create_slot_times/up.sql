@@ -0,0 +1,6 @@
+create table slot_times (
+  slot                          bigint          primary key,
+  block_time                    timestamp       not null
+);
+
+create index if not exists metadatas_slot_idx on metadatas (slot);
@@ -0,0 +1,5 @@
+-- Timestamps corrected by the up migration are not restored.
+
+drop index if exists purchases_slot_idx;
+drop index if exists offers_slot_idx;
+drop index if exists listings_slot_idx;
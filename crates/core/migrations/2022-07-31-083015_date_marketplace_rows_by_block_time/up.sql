@@ -0,0 +1,62 @@
+create index if not exists listings_slot_idx on listings (slot);
+create index if not exists offers_slot_idx on offers (slot);
+create index if not exists purchases_slot_idx on purchases (slot);
+
+-- Rows indexed from instructions were dated by the time they were processed.
+-- Re-date those whose slot times are already known; the slot time backfill
+-- corrects the rest as it records their slots.
+update purchases p set created_at = st.block_time
+from slot_times st
+where st.slot = p.slot and p.write_version is null;
+
+update listings l set created_at = st.block_time
+from slot_times st
+where st.slot = l.slot and l.write_version is null and l.canceled_at is null;
+
+update listings l set canceled_at = st.block_time
+from slot_times st
+where st.slot = l.slot and l.write_version is null and l.canceled_at is not null;
+
+update offers o set created_at = st.block_time
+from slot_times st
+where st.slot = o.slot and o.write_version is null and o.canceled_at is null;
+
+update offers o set canceled_at = st.block_time
+from slot_times st
+where st.slot = o.slot and o.write_version is null and o.canceled_at is not null;
+
+update buy_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update cancel_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update deposit_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update execute_sale_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update public_buy_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update sell_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update withdraw_from_fee_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update withdraw_from_treasury_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
+
+update withdraw_instructions i set created_at = st.block_time
+from slot_times st
+where st.slot = i.slot;
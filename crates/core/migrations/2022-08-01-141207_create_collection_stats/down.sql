@@ -0,0 +1,8 @@
+drop trigger if exists purchases_collection_stats on purchases;
+drop trigger if exists listings_collection_stats on listings;
+drop trigger if exists listings_collection_stats_insert on listings;
+
+drop function if exists collection_stats_marketplace_activity();
+drop function if exists refresh_collection_stats(varchar, varchar);
+
+drop table collection_stats;
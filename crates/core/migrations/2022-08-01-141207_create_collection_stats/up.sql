@@ -0,0 +1,161 @@
+create table collection_stats (
+  collection_address            varchar(48)     not null,
+  treasury_mint                 varchar(48)     not null,
+  floor_price                   bigint,
+  listed_count                  bigint          not null default 0,
+  holder_count                  bigint          not null default 0,
+  supply                        bigint          not null default 0,
+  volume_1d                     bigint          not null default 0,
+  volume_7d                     bigint          not null default 0,
+  volume_30d                    bigint          not null default 0,
+  volume_all                    bigint          not null default 0,
+  sales_count                   bigint          not null default 0,
+  average_price                 bigint,
+  updated_at                    timestamp       not null default now(),
+  dirty                         boolean         not null default false,
+  primary key (collection_address, treasury_mint)
+);
+
+create index collection_stats_updated_at_idx on collection_stats (updated_at);
+create index collection_stats_volume_all_idx on collection_stats (treasury_mint, volume_all desc);
+create index collection_stats_dirty_idx on collection_stats (updated_at) where dirty;
+
+-- Recompute the statistics of one collection for one treasury mint
+create or replace function refresh_collection_stats(collection varchar, mint varchar)
+  returns void
+  as
+$$
+begin
+  insert into collection_stats (
+    collection_address,
+    treasury_mint,
+    floor_price,
+    listed_count,
+    holder_count,
+    supply,
+    volume_1d,
+    volume_7d,
+    volume_30d,
+    volume_all,
+    sales_count,
+    average_price,
+    updated_at,
+    dirty
+  )
+  select
+    collection,
+    mint,
+    l.floor_price,
+    l.listed_count,
+    h.holder_count,
+    h.supply,
+    p.volume_1d,
+    p.volume_7d,
+    p.volume_30d,
+    p.volume_all,
+    p.sales_count,
+    p.average_price,
+    now(),
+    false
+  from (
+    select min(listings.price) as floor_price, count(distinct listings.metadata) as listed_count
+    from listings
+    inner join metadata_collection_keys mck on (mck.metadata_address = listings.metadata)
+    inner join auction_houses ah on (ah.address = listings.auction_house)
+    where mck.collection_address = collection
+      and mck.verified
+      and ah.treasury_mint = mint
+      and listings.purchase_id is null
+      and listings.canceled_at is null
+  ) l, (
+    select count(distinct cmo.owner_address) as holder_count, count(*) as supply
+    from metadata_collection_keys mck
+    inner join metadatas md on (md.address = mck.metadata_address)
+    inner join current_metadata_owners cmo on (cmo.mint_address = md.mint_address)
+    where mck.collection_address = collection
+      and mck.verified
+  ) h, (
+    select
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '1 day'), 0)::bigint as volume_1d,
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '7 days'), 0)::bigint as volume_7d,
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '30 days'), 0)::bigint as volume_30d,
+      coalesce(sum(purchases.price), 0)::bigint as volume_all,
+      count(*) as sales_count,
+      avg(purchases.price)::bigint as average_price
+    from purchases
+    inner join metadata_collection_keys mck on (mck.metadata_address = purchases.metadata)
+    inner join auction_houses ah on (ah.address = purchases.auction_house)
+    where mck.collection_address = collection
+      and mck.verified
+      and ah.treasury_mint = mint
+  ) p
+  on conflict (collection_address, treasury_mint) do update set
+    floor_price = excluded.floor_price,
+    listed_count = excluded.listed_count,
+    holder_count = excluded.holder_count,
+    supply = excluded.supply,
+    volume_1d = excluded.volume_1d,
+    volume_7d = excluded.volume_7d,
+    volume_30d = excluded.volume_30d,
+    volume_all = excluded.volume_all,
+    sales_count = excluded.sales_count,
+    average_price = excluded.average_price,
+    updated_at = excluded.updated_at,
+    dirty = excluded.dirty;
+end;
+$$ language plpgsql;
+
+-- Mark the collections of an NFT listed or sold on an auction house as dirty.
+-- Recomputing a collection's statistics scans all of its marketplace rows, so
+-- it is left to the collection stats job rather than done once per row here.
+create or replace function collection_stats_marketplace_activity()
+  returns trigger
+  as
+$$
+begin
+  insert into collection_stats (collection_address, treasury_mint, updated_at, dirty)
+  select mck.collection_address, ah.treasury_mint, 'epoch'::timestamp, true
+  from metadata_collection_keys mck, auction_houses ah
+  where mck.metadata_address = new.metadata
+    and mck.verified
+    and ah.address = new.auction_house
+  on conflict (collection_address, treasury_mint) do update set
+    dirty = true
+  where not collection_stats.dirty;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listings_collection_stats_insert
+after insert on listings
+for each row
+execute function collection_stats_marketplace_activity();
+
+-- Re-dating a cancellation does not change any statistics
+create trigger listings_collection_stats
+after update of price, canceled_at, purchase_id on listings
+for each row
+when (old.price is distinct from new.price
+  or (old.canceled_at is null) <> (new.canceled_at is null)
+  or old.purchase_id is distinct from new.purchase_id)
+execute function collection_stats_marketplace_activity();
+
+create trigger purchases_collection_stats
+after insert on purchases
+for each row
+execute function collection_stats_marketplace_activity();
+
+-- Register every traded collection; rows start out dirty so the collection
+-- stats job computes them
+insert into collection_stats (collection_address, treasury_mint, updated_at, dirty)
+select distinct mck.collection_address, ah.treasury_mint, 'epoch'::timestamp, true
+from (
+  select metadata, auction_house from listings
+  union
+  select metadata, auction_house from purchases
+) activity
+inner join metadata_collection_keys mck on (mck.metadata_address = activity.metadata)
+inner join auction_houses ah on (ah.address = activity.auction_house)
+where mck.verified
+on conflict do nothing;
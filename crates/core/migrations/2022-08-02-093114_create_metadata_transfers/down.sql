@@ -0,0 +1,5 @@
+drop trigger if exists current_metadata_owners_transfer on current_metadata_owners;
+
+drop function if exists record_metadata_transfer();
+
+drop table metadata_transfers;
@@ -0,0 +1,41 @@
+create table metadata_transfers (
+  id              uuid        primary key default gen_random_uuid(),
+  mint_address    varchar(48) not null,
+  from_address    varchar(48) not null,
+  to_address      varchar(48) not null,
+  transferred_at  timestamp   not null,
+  slot            bigint      not null
+);
+
+create index if not exists metadata_transfers_transferred_at_id_idx on
+  metadata_transfers (transferred_at desc, id desc);
+
+create index if not exists metadata_transfers_mint_transferred_at_id_idx on
+  metadata_transfers (mint_address, transferred_at desc, id desc);
+
+create index if not exists metadata_transfers_from_transferred_at_id_idx on
+  metadata_transfers (from_address, transferred_at desc, id desc);
+
+create index if not exists metadata_transfers_to_transferred_at_id_idx on
+  metadata_transfers (to_address, transferred_at desc, id desc);
+
+-- current_metadata_owners only holds the latest owner of each mint, so record
+-- every change of owner as it is indexed.  Transfers made before this
+-- migration are not recoverable.
+create or replace function record_metadata_transfer()
+  returns trigger
+  as
+$$
+begin
+  insert into metadata_transfers (mint_address, from_address, to_address, transferred_at, slot)
+  values (new.mint_address, old.owner_address, new.owner_address, new.updated_at, new.slot);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger current_metadata_owners_transfer
+after update of owner_address on current_metadata_owners
+for each row
+when (old.owner_address is distinct from new.owner_address)
+execute function record_metadata_transfer();
@@ -0,0 +1,4 @@
+drop trigger offers_update_notify on offers;
+drop trigger listings_update_notify on listings;
+
+drop function notify_row_update();
@@ -0,0 +1,31 @@
+-- Publishes listings and offers as they are canceled, sold or repriced, on a
+-- channel separate from new rows so subscriptions to new rows are not sent
+-- updates.  Consumed by GraphQL cache invalidation.
+create or replace function notify_row_update()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name || '_updated', row_to_json(new)::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listings_update_notify
+  after update of price, canceled_at, purchase_id
+  on listings
+  for each row
+  when (old.price is distinct from new.price
+    or old.canceled_at is distinct from new.canceled_at
+    or old.purchase_id is distinct from new.purchase_id)
+  execute procedure notify_row_update();
+
+create trigger offers_update_notify
+  after update of price, canceled_at, purchase_id
+  on offers
+  for each row
+  when (old.price is distinct from new.price
+    or old.canceled_at is distinct from new.canceled_at
+    or old.purchase_id is distinct from new.purchase_id)
+  execute procedure notify_row_update();
@@ -0,0 +1,6 @@
+drop trigger metadatas_keep_created_slot on metadatas;
+
+drop function keep_metadata_created_slot();
+
+alter table metadatas
+drop column created_slot;
@@ -0,0 +1,29 @@
+alter table metadatas
+add column created_slot bigint;
+
+-- Rows indexed before this migration only know the slot of their most recent
+-- update, which is the best available estimate of when they were created
+update metadatas set created_slot = slot;
+
+-- Keeps created_slot at the first slot a metadata account was indexed at,
+-- regardless of what later upserts write to it
+create or replace function keep_metadata_created_slot()
+  returns trigger
+  as
+$$
+begin
+  if tg_op = 'INSERT' then
+    new.created_slot := coalesce(new.created_slot, new.slot);
+  else
+    new.created_slot := coalesce(old.created_slot, new.created_slot, new.slot);
+  end if;
+
+  return new;
+end;
+$$ language plpgsql;
+
+create trigger metadatas_keep_created_slot
+  before insert or update
+  on metadatas
+  for each row
+  execute procedure keep_metadata_created_slot();
@@ -0,0 +1,3 @@
+drop trigger listing_denylist_notify on listing_denylist;
+
+drop function notify_listing_denylist_change();
@@ -0,0 +1,25 @@
+-- Publishes the address of each listing added to, changed in or removed from
+-- the denylist, consumed by the search indexer to keep denylisted NFTs out of
+-- search
+create or replace function notify_listing_denylist_change()
+  returns trigger
+  as
+$$
+begin
+  if tg_op in ('UPDATE', 'DELETE') then
+    perform pg_notify('listing_denylist', old.listing_address);
+  end if;
+
+  if tg_op in ('INSERT', 'UPDATE') then
+    perform pg_notify('listing_denylist', new.listing_address);
+  end if;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listing_denylist_notify
+  after insert or update or delete
+  on listing_denylist
+  for each row
+  execute procedure notify_listing_denylist_change();
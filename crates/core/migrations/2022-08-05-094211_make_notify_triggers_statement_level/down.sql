@@ -0,0 +1,113 @@
+drop trigger listing_denylist_delete_notify on listing_denylist;
+drop trigger listing_denylist_update_notify on listing_denylist;
+drop trigger listing_denylist_insert_notify on listing_denylist;
+drop trigger offers_update_notify on offers;
+drop trigger listings_update_notify on listings;
+drop trigger current_metadata_owners_update_notify on current_metadata_owners;
+drop trigger current_metadata_owners_insert_notify on current_metadata_owners;
+drop trigger feed_events_notify on feed_events;
+drop trigger purchases_notify on purchases;
+drop trigger offers_notify on offers;
+drop trigger listings_notify on listings;
+
+drop function notify_owner_change();
+
+create or replace function notify_row_change()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name, row_to_json(new)::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create or replace function notify_row_update()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name || '_updated', row_to_json(new)::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create or replace function notify_listing_denylist_change()
+  returns trigger
+  as
+$$
+begin
+  if tg_op in ('UPDATE', 'DELETE') then
+    perform pg_notify('listing_denylist', old.listing_address);
+  end if;
+
+  if tg_op in ('INSERT', 'UPDATE') then
+    perform pg_notify('listing_denylist', new.listing_address);
+  end if;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listings_notify
+  after insert
+  on listings
+  for each row
+  execute procedure notify_row_change();
+
+create trigger offers_notify
+  after insert
+  on offers
+  for each row
+  execute procedure notify_row_change();
+
+create trigger purchases_notify
+  after insert
+  on purchases
+  for each row
+  execute procedure notify_row_change();
+
+create trigger feed_events_notify
+  after insert
+  on feed_events
+  for each row
+  execute procedure notify_row_change();
+
+create trigger current_metadata_owners_insert_notify
+  after insert
+  on current_metadata_owners
+  for each row
+  execute procedure notify_row_change();
+
+create trigger current_metadata_owners_update_notify
+  after update of owner_address
+  on current_metadata_owners
+  for each row
+  when (old.owner_address is distinct from new.owner_address)
+  execute procedure notify_row_change();
+
+create trigger listings_update_notify
+  after update of price, canceled_at, purchase_id
+  on listings
+  for each row
+  when (old.price is distinct from new.price
+    or old.canceled_at is distinct from new.canceled_at
+    or old.purchase_id is distinct from new.purchase_id)
+  execute procedure notify_row_update();
+
+create trigger offers_update_notify
+  after update of price, canceled_at, purchase_id
+  on offers
+  for each row
+  when (old.price is distinct from new.price
+    or old.canceled_at is distinct from new.canceled_at
+    or old.purchase_id is distinct from new.purchase_id)
+  execute procedure notify_row_update();
+
+create trigger listing_denylist_notify
+  after insert or update or delete
+  on listing_denylist
+  for each row
+  execute procedure notify_listing_denylist_change();
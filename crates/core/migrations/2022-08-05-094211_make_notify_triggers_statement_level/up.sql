@@ -0,0 +1,150 @@
+-- Fires the notify triggers once per statement rather than once per row, so
+-- bulk loads don't pay for a trigger call on every row.  Each changed row is
+-- still published as its own notification.
+drop trigger listings_notify on listings;
+drop trigger offers_notify on offers;
+drop trigger purchases_notify on purchases;
+drop trigger feed_events_notify on feed_events;
+drop trigger current_metadata_owners_insert_notify on current_metadata_owners;
+drop trigger current_metadata_owners_update_notify on current_metadata_owners;
+drop trigger listings_update_notify on listings;
+drop trigger offers_update_notify on offers;
+drop trigger listing_denylist_notify on listing_denylist;
+
+create or replace function notify_row_change()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name, row_to_json(n)::text) from new_rows n;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create or replace function notify_owner_change()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name, row_to_json(n)::text)
+  from new_rows n
+  inner join old_rows o on (o.mint_address = n.mint_address)
+  where o.owner_address is distinct from n.owner_address;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create or replace function notify_row_update()
+  returns trigger
+  as
+$$
+begin
+  perform pg_notify(tg_table_name || '_updated', row_to_json(n)::text)
+  from new_rows n
+  inner join old_rows o on (o.id = n.id)
+  where o.price is distinct from n.price
+    or o.canceled_at is distinct from n.canceled_at
+    or o.purchase_id is distinct from n.purchase_id;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create or replace function notify_listing_denylist_change()
+  returns trigger
+  as
+$$
+begin
+  if tg_op in ('UPDATE', 'DELETE') then
+    perform pg_notify('listing_denylist', o.listing_address) from old_rows o;
+  end if;
+
+  if tg_op in ('INSERT', 'UPDATE') then
+    perform pg_notify('listing_denylist', n.listing_address) from new_rows n;
+  end if;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listings_notify
+  after insert
+  on listings
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_row_change();
+
+create trigger offers_notify
+  after insert
+  on offers
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_row_change();
+
+create trigger purchases_notify
+  after insert
+  on purchases
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_row_change();
+
+create trigger feed_events_notify
+  after insert
+  on feed_events
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_row_change();
+
+create trigger current_metadata_owners_insert_notify
+  after insert
+  on current_metadata_owners
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_row_change();
+
+-- Transition tables can't be combined with a column list, so updates are
+-- filtered by comparing the old and new rows instead
+create trigger current_metadata_owners_update_notify
+  after update
+  on current_metadata_owners
+  referencing old table as old_rows new table as new_rows
+  for each statement
+  execute procedure notify_owner_change();
+
+create trigger listings_update_notify
+  after update
+  on listings
+  referencing old table as old_rows new table as new_rows
+  for each statement
+  execute procedure notify_row_update();
+
+create trigger offers_update_notify
+  after update
+  on offers
+  referencing old table as old_rows new table as new_rows
+  for each statement
+  execute procedure notify_row_update();
+
+-- Triggers with transition tables may only fire on a single event
+create trigger listing_denylist_insert_notify
+  after insert
+  on listing_denylist
+  referencing new table as new_rows
+  for each statement
+  execute procedure notify_listing_denylist_change();
+
+create trigger listing_denylist_update_notify
+  after update
+  on listing_denylist
+  referencing old table as old_rows new table as new_rows
+  for each statement
+  execute procedure notify_listing_denylist_change();
+
+create trigger listing_denylist_delete_notify
+  after delete
+  on listing_denylist
+  referencing old table as old_rows
+  for each statement
+  execute procedure notify_listing_denylist_change();
@@ -0,0 +1,159 @@
+alter table collection_stats
+  add column top_offer bigint,
+  add column offer_count bigint not null default 0,
+  add column version bigint not null default 0;
+
+drop function refresh_collection_stats(varchar, varchar);
+
+-- Recompute the statistics of one collection for one treasury mint.  The row
+-- is only marked clean if its version is still `seen_version`, so activity
+-- recorded while the statistics were computed leaves it dirty.
+create function refresh_collection_stats(collection varchar, mint varchar, seen_version bigint)
+  returns void
+  as
+$$
+begin
+  insert into collection_stats (
+    collection_address,
+    treasury_mint,
+    floor_price,
+    listed_count,
+    top_offer,
+    offer_count,
+    holder_count,
+    supply,
+    volume_1d,
+    volume_7d,
+    volume_30d,
+    volume_all,
+    sales_count,
+    average_price,
+    updated_at,
+    dirty,
+    version
+  )
+  select
+    collection,
+    mint,
+    l.floor_price,
+    l.listed_count,
+    o.top_offer,
+    o.offer_count,
+    h.holder_count,
+    h.supply,
+    p.volume_1d,
+    p.volume_7d,
+    p.volume_30d,
+    p.volume_all,
+    p.sales_count,
+    p.average_price,
+    now(),
+    false,
+    seen_version
+  from (
+    select min(listings.price) as floor_price, count(distinct listings.metadata) as listed_count
+    from listings
+    inner join metadata_collection_keys mck on (mck.metadata_address = listings.metadata)
+    inner join auction_houses ah on (ah.address = listings.auction_house)
+    where mck.collection_address = collection
+      and mck.verified
+      and ah.treasury_mint = mint
+      and listings.purchase_id is null
+      and listings.canceled_at is null
+  ) l, (
+    select max(offers.price) as top_offer, count(*) as offer_count
+    from offers
+    inner join metadata_collection_keys mck on (mck.metadata_address = offers.metadata)
+    inner join auction_houses ah on (ah.address = offers.auction_house)
+    where mck.collection_address = collection
+      and mck.verified
+      and ah.treasury_mint = mint
+      and offers.purchase_id is null
+      and offers.canceled_at is null
+  ) o, (
+    select count(distinct cmo.owner_address) as holder_count, count(*) as supply
+    from metadata_collection_keys mck
+    inner join metadatas md on (md.address = mck.metadata_address)
+    inner join current_metadata_owners cmo on (cmo.mint_address = md.mint_address)
+    where mck.collection_address = collection
+      and mck.verified
+  ) h, (
+    select
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '1 day'), 0)::bigint as volume_1d,
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '7 days'), 0)::bigint as volume_7d,
+      coalesce(sum(purchases.price) filter (where purchases.created_at >= now() - interval '30 days'), 0)::bigint as volume_30d,
+      coalesce(sum(purchases.price), 0)::bigint as volume_all,
+      count(*) as sales_count,
+      avg(purchases.price)::bigint as average_price
+    from purchases
+    inner join metadata_collection_keys mck on (mck.metadata_address = purchases.metadata)
+    inner join auction_houses ah on (ah.address = purchases.auction_house)
+    where mck.collection_address = collection
+      and mck.verified
+      and ah.treasury_mint = mint
+  ) p
+  on conflict (collection_address, treasury_mint) do update set
+    floor_price = excluded.floor_price,
+    listed_count = excluded.listed_count,
+    top_offer = excluded.top_offer,
+    offer_count = excluded.offer_count,
+    holder_count = excluded.holder_count,
+    supply = excluded.supply,
+    volume_1d = excluded.volume_1d,
+    volume_7d = excluded.volume_7d,
+    volume_30d = excluded.volume_30d,
+    volume_all = excluded.volume_all,
+    sales_count = excluded.sales_count,
+    average_price = excluded.average_price,
+    updated_at = excluded.updated_at,
+    dirty = collection_stats.dirty and collection_stats.version <> seen_version;
+end;
+$$ language plpgsql;
+
+-- Mark the collections of an NFT listed, offered on or sold on an auction
+-- house as dirty, bumping the version of their statistics so a refresh
+-- already underway does not mark them clean
+create or replace function collection_stats_marketplace_activity()
+  returns trigger
+  as
+$$
+begin
+  insert into collection_stats (collection_address, treasury_mint, updated_at, dirty, version)
+  select mck.collection_address, ah.treasury_mint, 'epoch'::timestamp, true, 1
+  from metadata_collection_keys mck, auction_houses ah
+  where mck.metadata_address = new.metadata
+    and mck.verified
+    and ah.address = new.auction_house
+  on conflict (collection_address, treasury_mint) do update set
+    dirty = true,
+    version = collection_stats.version + 1;
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger offers_collection_stats_insert
+after insert on offers
+for each row
+execute function collection_stats_marketplace_activity();
+
+-- Re-dating a cancellation does not change any statistics
+create trigger offers_collection_stats
+after update of price, canceled_at, purchase_id on offers
+for each row
+when (old.price is distinct from new.price
+  or (old.canceled_at is null) <> (new.canceled_at is null)
+  or old.purchase_id is distinct from new.purchase_id)
+execute function collection_stats_marketplace_activity();
+
+-- Register collections with offers but no other activity, and recompute
+-- every collection to fill in offer statistics
+insert into collection_stats (collection_address, treasury_mint, updated_at, dirty)
+select distinct mck.collection_address, ah.treasury_mint, 'epoch'::timestamp, true
+from offers
+inner join metadata_collection_keys mck on (mck.metadata_address = offers.metadata)
+inner join auction_houses ah on (ah.address = offers.auction_house)
+where mck.verified
+on conflict do nothing;
+
+update collection_stats set dirty = true, version = version + 1;
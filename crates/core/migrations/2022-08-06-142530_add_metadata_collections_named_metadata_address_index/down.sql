@@ -0,0 +1 @@
+drop index if exists metadata_collections_named_metadata_address_idx;
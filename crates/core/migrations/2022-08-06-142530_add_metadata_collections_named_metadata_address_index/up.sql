@@ -0,0 +1,4 @@
+-- Supports excluding NFTs in a named collection from creator-keyed
+-- collections
+create index if not exists metadata_collections_named_metadata_address_idx on
+metadata_collections using btree (metadata_address) where name is not null;
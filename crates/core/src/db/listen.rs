@@ -0,0 +1,163 @@
+//! Support for listening to notifications sent with `pg_notify`
+
+use futures_util::{stream, StreamExt};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use tokio::sync::{mpsc, mpsc::error::TryRecvError};
+use tokio_postgres::AsyncMessage;
+use url::Url;
+
+use crate::prelude::*;
+
+/// A notification received from Postgres
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// The channel the notification was sent on
+    pub channel: String,
+    /// The notification payload
+    pub payload: String,
+}
+
+/// A dedicated Postgres connection listening for notifications
+#[derive(Debug)]
+pub struct Listener {
+    // The connection is closed once the client is dropped
+    _client: tokio_postgres::Client,
+    rx: mpsc::UnboundedReceiver<Result<Notification>>,
+}
+
+impl Listener {
+    /// Connect to the database at `url` and listen for notifications on each
+    /// of `channels`
+    ///
+    /// LISTEN is not supported by read replicas, so `url` should point to the
+    /// primary database.
+    ///
+    /// The server certificate is verified unless the URL's `sslmode` is
+    /// `prefer` or `require`, in which case the connection is encrypted but
+    /// not authenticated as with libpq.  `verify-ca` verifies the certificate
+    /// chain but not the hostname, and `verify-full` verifies both.
+    ///
+    /// # Errors
+    /// This function fails if the connection cannot be established or the
+    /// LISTEN statements fail.
+    pub async fn connect(url: &str, channels: &[&str]) -> Result<Self> {
+        let (url, tls) = tls_config(url)?;
+
+        let (client, mut conn) = tokio_postgres::connect(&url, MakeTlsConnector::new(tls))
+            .await
+            .context("Failed to connect to Postgres")?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut messages = stream::poll_fn(move |cx| conn.poll_message(cx));
+
+            while let Some(msg) = messages.next().await {
+                match msg {
+                    Ok(AsyncMessage::Notification(n)) => {
+                        let notification = Notification {
+                            channel: n.channel().to_owned(),
+                            payload: n.payload().to_owned(),
+                        };
+
+                        if tx.send(Ok(notification)).is_err() {
+                            return;
+                        }
+                    },
+                    Ok(AsyncMessage::Notice(n)) => debug!("Postgres notice: {}", n),
+                    Ok(_) => (),
+                    Err(e) => {
+                        tx.send(Err(e).context("Postgres connection failed")).ok();
+                        return;
+                    },
+                }
+            }
+
+            tx.send(Err(anyhow!("Postgres connection closed"))).ok();
+        });
+
+        let stmt: String = channels.iter().map(|c| format!("LISTEN {};", c)).collect();
+
+        client
+            .batch_execute(&stmt)
+            .await
+            .context("Failed to listen for notifications")?;
+
+        Ok(Self {
+            _client: client,
+            rx,
+        })
+    }
+
+    /// Wait for the next notification
+    ///
+    /// # Errors
+    /// This function fails if the connection has been lost.
+    pub async fn recv(&mut self) -> Result<Notification> {
+        self.rx
+            .recv()
+            .await
+            .unwrap_or_else(|| Err(anyhow!("Postgres connection closed")))
+    }
+
+    /// Take the next notification if one has already been received
+    ///
+    /// # Errors
+    /// This function fails if the connection has been lost.
+    pub fn try_recv(&mut self) -> Result<Option<Notification>> {
+        match self.rx.try_recv() {
+            Ok(r) => r.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(anyhow!("Postgres connection closed")),
+        }
+    }
+}
+
+/// Build the TLS connector for the `sslmode` of a connection URL, returning
+/// it with the URL to pass to tokio-postgres, which does not recognize the
+/// `verify-ca` and `verify-full` modes
+fn tls_config(url: &str) -> Result<(String, TlsConnector)> {
+    let mut parsed = match Url::parse(url) {
+        Ok(u) => u,
+        // Key-value connection strings are passed through and verified
+        Err(_) => {
+            let tls = TlsConnector::new().context("Failed to build TLS connector")?;
+
+            return Ok((url.to_owned(), tls));
+        },
+    };
+    let mode = parsed
+        .query_pairs()
+        .find(|(k, _)| k == "sslmode")
+        .map(|(_, v)| v.into_owned());
+    let mut builder = TlsConnector::builder();
+
+    match mode.as_deref() {
+        Some("prefer" | "require") => {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        },
+        Some("verify-ca") => {
+            builder.danger_accept_invalid_hostnames(true);
+        },
+        _ => (),
+    }
+
+    if matches!(mode.as_deref(), Some("verify-ca" | "verify-full")) {
+        let pairs: Vec<_> = parsed
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if k == "sslmode" { "require".into() } else { v };
+
+                (k.into_owned(), v.into_owned())
+            })
+            .collect();
+
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let tls = builder.build().context("Failed to build TLS connector")?;
+
+    Ok((parsed.into(), tls))
+}
@@ -1,6 +1,8 @@
 //! Interface with the indexer database
 
 pub mod custom_types;
+#[cfg(feature = "listen")]
+pub mod listen;
 pub mod models;
 pub mod queries;
 #[allow(missing_docs, unused_imports)]
@@ -66,7 +68,7 @@ pub enum ConnectionType {
 }
 
 /// Arguments for establishing a database connection
-#[derive(Debug, clap::Args)]
+#[derive(Debug, Clone, clap::Args)]
 pub struct ConnectArgs {
     /// Connection string for a read-only database
     #[clap(long, env, conflicts_with("database-write-url"))]
@@ -85,6 +87,40 @@ pub struct ConnectArgs {
     database_url: Option<String>,
 }
 
+impl ConnectArgs {
+    /// Select the connection string to use for the given connection mode,
+    /// along with a hint indicating if the database is writable.
+    ///
+    /// # Errors
+    /// This function fails if no connection string suitable for the mode was
+    /// provided.
+    pub fn into_url(self, mode: ConnectMode) -> Result<(ConnectionType, String)> {
+        let Self {
+            database_read_url,
+            database_write_url,
+            database_url,
+        } = self;
+
+        let mode_url = match mode {
+            ConnectMode::Read => database_read_url,
+            ConnectMode::Write => database_write_url,
+        };
+
+        mode_url
+            .map(|u| (mode.into(), u))
+            .or_else(|| database_url.map(|u| (ConnectionType::Default, u)))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid database URL, expected a {} connection string",
+                    match mode {
+                        ConnectMode::Read => "read-only",
+                        ConnectMode::Write => "writable",
+                    }
+                )
+            })
+    }
+}
+
 impl From<ConnectMode> for ConnectionType {
     fn from(mode: ConnectMode) -> Self {
         match mode {
@@ -101,29 +137,7 @@ impl From<ConnectMode> for ConnectionType {
 /// This function fails if Diesel fails to construct a connection pool or if any
 /// pending database migrations fail to run.
 pub fn connect(args: ConnectArgs, mode: ConnectMode) -> Result<(Pool, ConnectionType)> {
-    let ConnectArgs {
-        database_read_url,
-        database_write_url,
-        database_url,
-    } = args;
-
-    let mode_url = match mode {
-        ConnectMode::Read => database_read_url,
-        ConnectMode::Write => database_write_url,
-    };
-
-    let (ty, url) = mode_url
-        .map(|u| (mode.into(), u))
-        .or_else(|| database_url.map(|u| (ConnectionType::Default, u)))
-        .ok_or_else(|| {
-            anyhow!(
-                "Invalid database URL, expected a {} connection string",
-                match mode {
-                    ConnectMode::Read => "read-only",
-                    ConnectMode::Write => "writable",
-                }
-            )
-        })?;
+    let (ty, url) = args.into_url(mode)?;
 
     debug!("Connecting to db: {:?}", url);
 
@@ -4,7 +4,7 @@
 
 use std::borrow::Cow;
 
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::sql_types::{Array, Bool, Int4, Int8, Nullable, Text, Timestamp, Timestamptz, VarChar};
 use uuid::Uuid;
 
@@ -188,6 +188,9 @@ pub struct Metadata<'a> {
     pub slot: Option<i64>,
     /// Indicates whether the NFT was burned
     pub burned: bool,
+    /// The slot number this metadata account was first indexed at.  Kept
+    /// unchanged by updates to the row.
+    pub created_slot: Option<i64>,
 }
 
 /// A row in the `storefronts` table
@@ -295,9 +298,9 @@ pub struct NftActivity {
     #[sql_type = "VarChar"]
     pub metadata: String,
 
-    /// The auction house activity generated from
-    #[sql_type = "VarChar"]
-    pub auction_house: String,
+    /// The auction house activity generated from, if any
+    #[sql_type = "Nullable<VarChar>"]
+    pub auction_house: Option<String>,
 
     /// The price of listing or purchase
     #[sql_type = "Int8"]
@@ -609,6 +612,32 @@ pub struct StoreConfigJson<'a> {
     pub owner_address: Cow<'a, str>,
     /// Storefront address
     pub store_address: Option<Cow<'a, str>>,
+    /// Store config URI raw JSON
+    pub raw_content: Option<Cow<'a, serde_json::Value>>,
+    /// Model the JSON was parsed with
+    pub model: Option<Cow<'a, str>>,
+    /// The time this record was last updated
+    pub updated_at: NaiveDateTime,
+}
+
+/// A row in the `store_config_json_versions` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(treat_none_as_null = true)]
+pub struct StoreConfigJsonVersion<'a> {
+    /// Random Uuid primary key
+    /// Optional so that it can be generated randomly when other fields are inserted into table
+    #[diesel(deserialize_as = "Uuid")]
+    pub id: Option<Uuid>,
+    /// The address of the StoreConfig account this record refers to
+    pub config_address: Cow<'a, str>,
+    /// Store config URI raw JSON
+    pub raw_content: Cow<'a, serde_json::Value>,
+    /// Model the JSON was parsed with
+    pub model: Cow<'a, str>,
+    /// The URI from which the data in this row was retrieved
+    pub fetch_uri: Cow<'a, str>,
+    /// The time this version was first seen
+    pub created_at: NaiveDateTime,
 }
 
 /// A row in the `auction_houses` table
@@ -2448,3 +2477,350 @@ pub struct CardinalNamespace<'a> {
     /// Solana write version
     pub write_version: i64,
 }
+
+/// A row in the `metadata_image_hashes` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[table_name = "metadata_image_hashes"]
+#[diesel(treat_none_as_null = true)]
+pub struct MetadataImageHash<'a> {
+    /// Metadata address
+    pub metadata_address: Cow<'a, str>,
+    /// Fingerprint of the image URL the hash was computed from
+    pub image_fingerprint: Cow<'a, Vec<u8>>,
+    /// 64-bit difference hash of the image, bit-cast to a signed integer
+    pub dhash: i64,
+    /// The time this hash was computed
+    pub updated_at: NaiveDateTime,
+}
+
+/// A row in the `suspected_copies` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[table_name = "suspected_copies"]
+#[diesel(treat_none_as_null = true)]
+pub struct SuspectedCopy<'a> {
+    /// Metadata address of the NFT believed to be the original
+    pub original_address: Cow<'a, str>,
+    /// Metadata address of the NFT believed to be a copy
+    pub copy_address: Cow<'a, str>,
+    /// Perceptual similarity of the two images, from 0.0 to 1.0
+    pub similarity: f64,
+    /// The time the copy was detected
+    pub detected_at: NaiveDateTime,
+}
+
+/// An NFT whose image hash shares at least one band with a queried hash
+#[derive(Debug, Clone, QueryableByName)]
+pub struct ImageHashCandidate {
+    /// Metadata address
+    #[sql_type = "VarChar"]
+    pub metadata_address: String,
+
+    /// 64-bit difference hash of the image, bit-cast to a signed integer
+    #[sql_type = "Int8"]
+    pub dhash: i64,
+
+    /// The slot number the metadata account was created at
+    #[sql_type = "Nullable<Int8>"]
+    pub created_slot: Option<i64>,
+
+    /// Addresses of the verified creators of the NFT
+    #[sql_type = "Array<VarChar>"]
+    pub verified_creators: Vec<String>,
+}
+
+/// A row in the `arweave_backfill_cursors` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(treat_none_as_null = true)]
+pub struct ArweaveBackfillCursor<'a> {
+    /// Unique name of the backfill job
+    pub name: Cow<'a, str>,
+    /// The Arweave GraphQL cursor of the last fully-processed page
+    pub cursor: Cow<'a, str>,
+    /// The time the cursor was last saved
+    pub updated_at: NaiveDateTime,
+}
+
+/// A row in the `search_documents` table
+///
+/// The `ts_index` column is generated from the string values in `body`.
+#[derive(Debug, Clone, Insertable, AsChangeset)]
+pub struct SearchDocument<'a> {
+    /// The search index this document belongs to
+    pub index_name: Cow<'a, str>,
+    /// The document ID, unique within its index
+    pub id: Cow<'a, str>,
+    /// The document body
+    pub body: Cow<'a, serde_json::Value>,
+}
+
+/// A row in the `api_keys` table
+#[derive(Debug, Clone, Queryable)]
+pub struct ApiKey<'a> {
+    /// Unique ID of the key
+    pub id: Uuid,
+    /// Human-readable name of the key holder
+    pub name: Cow<'a, str>,
+    /// Hex-encoded SHA-256 hash of the key
+    pub key_hash: Cow<'a, str>,
+    /// Number of requests per minute allowed for this key
+    pub requests_per_minute: i32,
+    /// Number of requests this key may make in a burst above its rate limit
+    pub burst: i32,
+    /// Whether requests with this key are exempt from query limits
+    pub trusted: bool,
+    /// The time the key was issued
+    pub created_at: NaiveDateTime,
+    /// The time the key was revoked, if any
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// An insertable row in the `api_keys` table
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "api_keys"]
+pub struct NewApiKey<'a> {
+    /// Human-readable name of the key holder
+    pub name: Cow<'a, str>,
+    /// Hex-encoded SHA-256 hash of the key
+    pub key_hash: Cow<'a, str>,
+    /// Number of requests per minute allowed for this key
+    pub requests_per_minute: i32,
+    /// Number of requests this key may make in a burst above its rate limit
+    pub burst: i32,
+    /// Whether requests with this key are exempt from query limits
+    pub trusted: bool,
+}
+
+/// A row in the `graphql_usage` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[table_name = "graphql_usage"]
+pub struct GraphqlUsage<'a> {
+    /// The API key the requests were made with
+    pub api_key_id: Uuid,
+    /// The UTC day the requests were made on
+    pub day: NaiveDate,
+    /// The GraphQL operation name of the requests, or an empty string for
+    /// anonymous operations
+    pub operation_name: Cow<'a, str>,
+    /// The number of requests made
+    pub request_count: i64,
+    /// The number of requests whose response contained errors
+    pub error_count: i64,
+    /// Request counts for each latency bucket, see
+    /// [`queries::api_keys::LATENCY_BUCKETS_MS`](crate::db::queries::api_keys::LATENCY_BUCKETS_MS)
+    pub latency_buckets: Vec<i64>,
+    /// The highest request latency, in milliseconds
+    pub latency_max_ms: i32,
+    /// The median request latency, in milliseconds
+    pub latency_p50_ms: i32,
+    /// The 95th percentile request latency, in milliseconds
+    pub latency_p95_ms: i32,
+    /// The 99th percentile request latency, in milliseconds
+    pub latency_p99_ms: i32,
+}
+
+/// A row in the `wallet_auth_nonces` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "wallet_auth_nonces"]
+pub struct WalletAuthNonce<'a> {
+    /// The random nonce a wallet must sign to authenticate
+    pub nonce: Cow<'a, str>,
+    /// The wallet the nonce was issued to
+    pub wallet_address: Cow<'a, str>,
+    /// The time after which the nonce can no longer be used
+    pub expires_at: NaiveDateTime,
+}
+
+/// A row in the `wallet_sessions` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "wallet_sessions"]
+pub struct WalletSession<'a> {
+    /// Hex-encoded SHA-256 hash of the session token
+    pub token_hash: Cow<'a, str>,
+    /// The wallet authenticated by the session
+    pub wallet_address: Cow<'a, str>,
+    /// The time the session was created
+    pub created_at: NaiveDateTime,
+    /// The time after which the session is no longer valid
+    pub expires_at: NaiveDateTime,
+}
+
+/// A row in the `nft_watchlists` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "nft_watchlists"]
+pub struct NftWatchlist<'a> {
+    /// The wallet watching the NFT
+    pub wallet_address: Cow<'a, str>,
+    /// The metadata address of the watched NFT
+    pub metadata_address: Cow<'a, str>,
+    /// The time the NFT was added to the watchlist
+    pub created_at: NaiveDateTime,
+}
+
+/// A row in the `collection_watchlists` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "collection_watchlists"]
+pub struct CollectionWatchlist<'a> {
+    /// The wallet watching the collection
+    pub wallet_address: Cow<'a, str>,
+    /// The metadata address of the watched collection NFT
+    pub collection_address: Cow<'a, str>,
+    /// The time the collection was added to the watchlist
+    pub created_at: NaiveDateTime,
+}
+
+/// A row in the `notification_preferences` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[table_name = "notification_preferences"]
+#[allow(clippy::struct_excessive_bools)]
+pub struct NotificationPreference<'a> {
+    /// The wallet these preferences belong to
+    pub wallet_address: Cow<'a, str>,
+    /// Notify the wallet of offers on its NFTs
+    pub offer_received: bool,
+    /// Notify the wallet when its offers are accepted
+    pub offer_accepted: bool,
+    /// Notify the wallet when its listings sell
+    pub listing_sold: bool,
+    /// Notify the wallet when it is outbid
+    pub outbid: bool,
+    /// Notify the wallet of activity on its watched NFTs and collections
+    pub watchlist_activity: bool,
+    /// Notify the wallet of new followers
+    pub new_follower: bool,
+    /// The time the preferences were last changed
+    pub updated_at: NaiveDateTime,
+}
+
+/// A row in the `hidden_nfts` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "hidden_nfts"]
+pub struct HiddenNft<'a> {
+    /// The wallet hiding the NFT from its profile
+    pub wallet_address: Cow<'a, str>,
+    /// The metadata address of the hidden NFT
+    pub metadata_address: Cow<'a, str>,
+    /// The time the NFT was hidden
+    pub created_at: NaiveDateTime,
+}
+
+/// A row in the `profile_nft_orders` table
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[table_name = "profile_nft_orders"]
+pub struct ProfileNftOrder<'a> {
+    /// The wallet whose profile is ordered
+    pub wallet_address: Cow<'a, str>,
+    /// The metadata address of the NFT
+    pub metadata_address: Cow<'a, str>,
+    /// The position of the NFT on the profile, starting from zero
+    pub position: i32,
+}
+
+/// A row in the `slot_times` table
+#[derive(Debug, Clone, Copy, Queryable, Insertable)]
+#[table_name = "slot_times"]
+pub struct SlotTime {
+    /// The slot number
+    pub slot: i64,
+    /// The time the block for the slot was produced
+    pub block_time: NaiveDateTime,
+}
+
+/// A row in the `collection_stats` table
+#[derive(Debug, Clone, Queryable)]
+pub struct CollectionStats<'a> {
+    /// The mint address of the collection NFT
+    pub collection_address: Cow<'a, str>,
+    /// The treasury mint of the auction houses the statistics cover
+    pub treasury_mint: Cow<'a, str>,
+    /// The lowest price of an active listing
+    pub floor_price: Option<i64>,
+    /// The number of NFTs with active listings
+    pub listed_count: i64,
+    /// The number of distinct wallets holding NFTs in the collection
+    pub holder_count: i64,
+    /// The number of NFTs in the collection
+    pub supply: i64,
+    /// The sum of sale prices over the last day
+    pub volume_1d: i64,
+    /// The sum of sale prices over the last seven days
+    pub volume_7d: i64,
+    /// The sum of sale prices over the last thirty days
+    pub volume_30d: i64,
+    /// The sum of all sale prices
+    pub volume_all: i64,
+    /// The number of sales
+    pub sales_count: i64,
+    /// The mean sale price
+    pub average_price: Option<i64>,
+    /// The time the statistics were last computed
+    pub updated_at: NaiveDateTime,
+    /// Whether marketplace activity has occurred since the statistics were
+    /// last computed
+    pub dirty: bool,
+    /// The highest price of an open offer
+    pub top_offer: Option<i64>,
+    /// The number of open offers
+    pub offer_count: i64,
+    /// A counter incremented by marketplace activity, used to tell whether
+    /// activity occurred while the statistics were being computed
+    pub version: i64,
+}
+
+/// A row in a `collections::holders` query, representing a wallet holding
+/// NFTs in a collection
+#[derive(Debug, Clone, QueryableByName)]
+pub struct CollectionHolder {
+    /// The address of the holding wallet
+    #[sql_type = "VarChar"]
+    pub owner_address: String,
+
+    /// The Twitter handle of the holding wallet, if any
+    #[sql_type = "Nullable<Text>"]
+    pub twitter_handle: Option<String>,
+
+    /// The number of the collection's NFTs held by the wallet
+    #[sql_type = "Int8"]
+    pub nfts: i64,
+}
+
+/// A row in a `collections::attributes` query, representing the number of
+/// NFTs in a collection with an attribute value
+#[derive(Debug, Clone, QueryableByName)]
+pub struct AttributeCount {
+    /// Attribute trait type
+    #[sql_type = "Text"]
+    pub trait_type: String,
+
+    /// Attribute value
+    #[sql_type = "Nullable<Text>"]
+    pub value: Option<String>,
+
+    /// The number of NFTs with this attribute value
+    #[sql_type = "Int8"]
+    pub count: i64,
+}
+
+/// A row in a `collections::keys` query, identifying the collection an NFT
+/// belongs to
+#[derive(Debug, Clone, QueryableByName)]
+pub struct NftCollectionKey {
+    /// Metadata address of the NFT
+    #[sql_type = "VarChar"]
+    pub metadata_address: String,
+
+    /// The mint address of the NFT's verified collection, if any
+    #[sql_type = "Nullable<VarChar>"]
+    pub collection_address: Option<String>,
+
+    /// The collection name from the NFT's metadata JSON, if any
+    #[sql_type = "Nullable<Text>"]
+    pub name: Option<String>,
+
+    /// The collection family from the NFT's metadata JSON, if any
+    #[sql_type = "Nullable<Text>"]
+    pub family: Option<String>,
+
+    /// The NFT's first verified creator, if any
+    #[sql_type = "Nullable<VarChar>"]
+    pub creator_address: Option<String>,
+}
@@ -1,16 +1,18 @@
 //! Query utilities for NFT activity.
 
 use anyhow::Context;
+use chrono::NaiveDateTime;
 use diesel::{
     pg::Pg,
-    sql_types::{Array, Nullable, Text},
+    sql_types::{Array, Int8, Nullable, Text, Timestamp},
     types::ToSql,
     RunQueryDsl,
 };
 
 use crate::{
-    db::{models::NftActivity, Connection},
+    db::{models::NftActivity, queries::collections::CollectionKey, Connection},
     error::Result,
+    uuid::Uuid,
 };
 
 const ACTIVITES_QUERY: &str = r"
@@ -57,3 +59,238 @@ pub fn list(
         .load(conn)
         .context("Failed to load activities")
 }
+
+/// A kind of NFT activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumIter)]
+pub enum ActivityType {
+    /// An NFT was listed for sale
+    Listing,
+    /// An NFT was sold
+    Purchase,
+    /// An offer was made on an NFT
+    Offer,
+    /// A listing was canceled
+    CancelListing,
+    /// An offer was canceled
+    CancelOffer,
+    /// An NFT changed owners
+    Transfer,
+}
+
+impl ActivityType {
+    /// The `activity_type` value of activities of this kind
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Listing => "listing",
+            Self::Purchase => "purchase",
+            Self::Offer => "offer",
+            Self::CancelListing => "cancel_listing",
+            Self::CancelOffer => "cancel_offer",
+            Self::Transfer => "transfer",
+        }
+    }
+
+    /// Render the query selecting activities of this kind.  Every kind shares
+    /// the parameters of [`list_page`], so the branches can be joined into a
+    /// single union.  `members` selects the metadata addresses of a
+    /// collection to restrict the activities to, if any.
+    ///
+    /// The cursor is compared against the time and ID columns alone so the
+    /// branch can seek on their index; the constant activity type only breaks
+    /// ties between a listing or offer and its cancellation.
+    ///
+    /// Transfers are not made on an auction house, so they have no auction
+    /// house, a price of zero, and are excluded when filtering by auction
+    /// house.
+    fn branch(self, members: Option<&str>) -> String {
+        let (table, time, wallets): (_, _, &[_]) = match self {
+            Self::Listing => ("listings", "created_at", &["seller"]),
+            Self::Purchase => ("purchases", "created_at", &["seller", "buyer"]),
+            Self::Offer => ("offers", "created_at", &["buyer"]),
+            Self::CancelListing => ("listings", "canceled_at", &["seller"]),
+            Self::CancelOffer => ("offers", "canceled_at", &["buyer"]),
+            Self::Transfer => ("metadata_transfers", "transferred_at", &[
+                "from_address",
+                "to_address",
+            ]),
+        };
+
+        let (metadata, auction_house, price, metadata_join, auction_house_filter) = match self {
+            Self::Transfer => (
+                "md.address".to_owned(),
+                "NULL::varchar".to_owned(),
+                "0::int8".to_owned(),
+                "\n        INNER JOIN metadatas md on md.mint_address = metadata_transfers.mint_address",
+                "$1 is null".to_owned(),
+            ),
+            _ => (
+                format!("{}.metadata", table),
+                format!("{}.auction_house", table),
+                format!("{}.price", table),
+                "",
+                format!("($1 is null OR {}.auction_house = ANY($1))", table),
+            ),
+        };
+
+        let time = format!("{}.{}", table, time);
+        let handles: Vec<_> = (0..wallets.len()).map(|i| format!("th{}", i)).collect();
+
+        let wallet_cols: Vec<_> = wallets.iter().map(|w| format!("{}.{}", table, w)).collect();
+        let handle_cols: Vec<_> = handles
+            .iter()
+            .map(|h| format!("{}.twitter_handle", h))
+            .collect();
+        let joins: String = wallet_cols
+            .iter()
+            .zip(&handles)
+            .map(|(w, h)| {
+                format!(
+                    "\n        LEFT JOIN twitter_handle_name_services {} on ({}.wallet_address = {})",
+                    h, h, w
+                )
+            })
+            .collect();
+        let collection = members.map_or_else(String::new, |m| {
+            format!("\n        AND {} IN ({})", metadata, m)
+        });
+        let wallet_filter: Vec<_> = wallet_cols
+            .iter()
+            .map(|w| format!("{} = ANY($4)", w))
+            .collect();
+
+        format!(
+            r"
+    (SELECT {table}.id as id, {metadata} as metadata, {auction_house} as auction_house,
+    {price} as price, {time} as created_at,
+    array[{wallets}] as wallets,
+    array[{handles}] as wallet_twitter_handles,
+    '{activity_type}' as activity_type
+        FROM {table}{metadata_join}{joins}
+        WHERE {time} IS NOT NULL
+        AND {auction_house_filter}
+        AND ($2 is null OR EXISTS (
+            SELECT 1 FROM metadata_creators mc
+            WHERE mc.metadata_address = {metadata} AND mc.creator_address = ANY($2)))
+        AND ($3 is null OR EXISTS (
+            SELECT 1 FROM metadata_collection_keys mck
+            WHERE mck.metadata_address = {metadata} AND mck.collection_address = ANY($3)
+            AND mck.verified)){collection}
+        AND ($4 is null OR {wallet_filter})
+        AND ($5 is null OR {time} >= $5)
+        AND ($6 is null OR {time} < $6)
+        AND ($7 is null OR (({time}, {table}.id) <= ($7, $8)
+            AND (({time}, {table}.id) < ($7, $8) OR '{activity_type}' < $9)))
+        ORDER BY {time} DESC, {table}.id DESC
+        LIMIT $10)",
+            table = table,
+            metadata = metadata,
+            auction_house = auction_house,
+            price = price,
+            time = time,
+            wallets = wallet_cols.join(", "),
+            handles = handle_cols.join(", "),
+            activity_type = self.as_str(),
+            metadata_join = metadata_join,
+            joins = joins,
+            auction_house_filter = auction_house_filter,
+            wallet_filter = wallet_filter.join(" OR "),
+            collection = collection,
+        )
+    }
+}
+
+/// Filters for [`list_page`].  Unset filters match all activities.
+#[derive(Debug, Default)]
+pub struct ActivityFilters {
+    /// Only include activities on these auction houses
+    pub auction_houses: Option<Vec<String>>,
+    /// Only include activities on NFTs by these creators
+    pub creators: Option<Vec<String>>,
+    /// Only include activities on NFTs in these verified collections
+    pub collections: Option<Vec<String>>,
+    /// Only include activities on NFTs in this collection, including legacy
+    /// collections
+    pub collection_key: Option<CollectionKey>,
+    /// Only include activities involving these wallets
+    pub wallets: Option<Vec<String>>,
+    /// Only include activities of these kinds
+    pub activity_types: Option<Vec<ActivityType>>,
+    /// Only include activities at or after this time
+    pub start_date: Option<NaiveDateTime>,
+    /// Only include activities before this time
+    pub end_date: Option<NaiveDateTime>,
+}
+
+/// Load a page of activities matching the given filters, newest first,
+/// starting after the activity with the given time, ID and activity type if
+/// one is given.  A listing and its cancellation share an ID, and may share
+/// a time, so the activity type is needed to tell them apart.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn list_page(
+    conn: &Connection,
+    filters: ActivityFilters,
+    after: Option<(NaiveDateTime, Uuid, String)>,
+    limit: i64,
+) -> Result<Vec<NftActivity>> {
+    use strum::IntoEnumIterator;
+
+    let ActivityFilters {
+        auction_houses,
+        creators,
+        collections,
+        collection_key,
+        wallets,
+        activity_types,
+        start_date,
+        end_date,
+    } = filters;
+
+    let members = collection_key.map(|c| c.members_sql());
+    let (after_time, after_id, after_type) = match after {
+        Some((t, i, a)) => (Some(t), Some(i), Some(a)),
+        None => (None, None, None),
+    };
+
+    let branches: Vec<_> = ActivityType::iter()
+        .filter(|t| activity_types.as_ref().map_or(true, |a| a.contains(t)))
+        .map(|t| t.branch(members.as_deref()))
+        .collect();
+
+    if branches.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let query = format!(
+        "{}
+    ORDER BY created_at DESC, id DESC, activity_type DESC
+    LIMIT $10;
+ -- $1: auction_houses::text[]
+ -- $2: creators::text[]
+ -- $3: collections::text[]
+ -- $4: wallets::text[]
+ -- $5: start_date::timestamp
+ -- $6: end_date::timestamp
+ -- $7: after_created_at::timestamp
+ -- $8: after_id::uuid
+ -- $9: after_activity_type::text
+ -- $10: limit::int8",
+        branches.join("\n    UNION ALL")
+    );
+
+    diesel::sql_query(query)
+        .bind::<Nullable<Array<Text>>, _>(auction_houses)
+        .bind::<Nullable<Array<Text>>, _>(creators)
+        .bind::<Nullable<Array<Text>>, _>(collections)
+        .bind::<Nullable<Array<Text>>, _>(wallets)
+        .bind::<Nullable<Timestamp>, _>(start_date)
+        .bind::<Nullable<Timestamp>, _>(end_date)
+        .bind::<Nullable<Timestamp>, _>(after_time)
+        .bind::<Nullable<diesel::sql_types::Uuid>, _>(after_id)
+        .bind::<Nullable<Text>, _>(after_type)
+        .bind::<Int8, _>(limit)
+        .load(conn)
+        .context("Failed to load activities")
+}
@@ -0,0 +1,248 @@
+//! Query utilities for GraphQL API keys and their usage records.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::{
+        models::{ApiKey, GraphqlUsage, NewApiKey},
+        tables::{api_keys, graphql_usage},
+        Connection,
+    },
+    error::prelude::*,
+    uuid::Uuid,
+};
+
+/// Prefix of all issued API keys, to make them easy to recognize
+pub const KEY_PREFIX: &str = "hpx_";
+
+/// Upper bounds, in milliseconds, of the latency buckets recorded in the
+/// `graphql_usage` table
+///
+/// Requests slower than the last bound are counted in an additional overflow
+/// bucket.
+pub const LATENCY_BUCKETS_MS: &[i32] = &[10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Hash an API key for storage or lookup
+#[must_use]
+pub fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Issue a new API key, returning the stored row and the plaintext key
+///
+/// The plaintext key is not stored and cannot be recovered after this call.
+///
+/// # Errors
+/// This function fails if the key cannot be inserted.
+pub fn issue(
+    conn: &Connection,
+    name: &str,
+    requests_per_minute: i32,
+    burst: i32,
+    trusted: bool,
+) -> Result<(ApiKey<'static>, String)> {
+    let mut bytes = [0_u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let key: String = std::iter::once(KEY_PREFIX.to_owned())
+        .chain(bytes.iter().map(|b| format!("{:02x}", b)))
+        .collect();
+
+    let row = diesel::insert_into(api_keys::table)
+        .values(NewApiKey {
+            name: name.into(),
+            key_hash: hash(&key).into(),
+            requests_per_minute,
+            burst,
+            trusted,
+        })
+        .get_result(conn)
+        .context("Failed to insert API key")?;
+
+    Ok((row, key))
+}
+
+/// Revoke an API key, returning false if no active key has the given ID
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn revoke(conn: &Connection, id: Uuid) -> Result<bool> {
+    let rows = diesel::update(
+        api_keys::table
+            .filter(api_keys::id.eq(id))
+            .filter(api_keys::revoked_at.is_null()),
+    )
+    .set(api_keys::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)
+    .context("Failed to revoke API key")?;
+
+    Ok(rows > 0)
+}
+
+/// List all API keys, including revoked ones, oldest first
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn list(conn: &Connection) -> Result<Vec<ApiKey<'static>>> {
+    api_keys::table
+        .order(api_keys::created_at.asc())
+        .load(conn)
+        .context("Failed to load API keys")
+}
+
+/// Look up an active API key by its plaintext value
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn get_active(conn: &Connection, key: &str) -> Result<Option<ApiKey<'static>>> {
+    api_keys::table
+        .filter(api_keys::key_hash.eq(hash(key)))
+        .filter(api_keys::revoked_at.is_null())
+        .first(conn)
+        .optional()
+        .context("Failed to look up API key")
+}
+
+/// Estimate a latency percentile from a histogram over
+/// [`LATENCY_BUCKETS_MS`], reporting the upper bound of the bucket containing
+/// it
+fn percentile(buckets: &[i64], max_ms: i32, p: f64) -> i32 {
+    let total: i64 = buckets.iter().sum();
+
+    if total == 0 {
+        return 0;
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    let rank = ((total as f64) * p).ceil() as i64;
+    let mut seen = 0;
+
+    for (i, count) in buckets.iter().enumerate() {
+        seen += count;
+
+        if seen >= rank {
+            return LATENCY_BUCKETS_MS
+                .get(i)
+                .map_or(max_ms, |b| (*b).min(max_ms));
+        }
+    }
+
+    max_ms
+}
+
+/// Add a batch of usage counts to the stored totals for its key, day and
+/// operation, recomputing the stored latency percentiles
+///
+/// `latency_buckets` must have one entry per bound in [`LATENCY_BUCKETS_MS`]
+/// plus one for the overflow bucket.  The percentile fields of `usage` are
+/// ignored.
+///
+/// # Errors
+/// This function fails if the underlying queries fail to execute.
+pub fn record_usage(conn: &Connection, usage: &GraphqlUsage) -> Result<()> {
+    let len = LATENCY_BUCKETS_MS.len() + 1;
+    debug_assert_eq!(usage.latency_buckets.len(), len);
+
+    conn.build_transaction()
+        .read_write()
+        .run(|| {
+            // Make sure the row exists so concurrent writers serialize on its lock
+            diesel::insert_into(graphql_usage::table)
+                .values((
+                    graphql_usage::api_key_id.eq(usage.api_key_id),
+                    graphql_usage::day.eq(usage.day),
+                    graphql_usage::operation_name.eq(usage.operation_name.as_ref()),
+                    graphql_usage::latency_buckets.eq(vec![0_i64; len]),
+                ))
+                .on_conflict((
+                    graphql_usage::api_key_id,
+                    graphql_usage::day,
+                    graphql_usage::operation_name,
+                ))
+                .do_nothing()
+                .execute(conn)?;
+
+            let mut row: GraphqlUsage = graphql_usage::table
+                .filter(graphql_usage::api_key_id.eq(usage.api_key_id))
+                .filter(graphql_usage::day.eq(usage.day))
+                .filter(graphql_usage::operation_name.eq(usage.operation_name.as_ref()))
+                .for_update()
+                .first(conn)?;
+
+            row.latency_buckets.resize(len, 0);
+
+            for (total, count) in row.latency_buckets.iter_mut().zip(&usage.latency_buckets) {
+                *total += count;
+            }
+
+            row.request_count += usage.request_count;
+            row.error_count += usage.error_count;
+            row.latency_max_ms = row.latency_max_ms.max(usage.latency_max_ms);
+            row.latency_p50_ms = percentile(&row.latency_buckets, row.latency_max_ms, 0.5);
+            row.latency_p95_ms = percentile(&row.latency_buckets, row.latency_max_ms, 0.95);
+            row.latency_p99_ms = percentile(&row.latency_buckets, row.latency_max_ms, 0.99);
+
+            diesel::insert_into(graphql_usage::table)
+                .values(&row)
+                .on_conflict((
+                    graphql_usage::api_key_id,
+                    graphql_usage::day,
+                    graphql_usage::operation_name,
+                ))
+                .do_update()
+                .set(&row)
+                .execute(conn)
+                .map(|_| ())
+        })
+        .context("Failed to record GraphQL usage")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(counts: &[(usize, i64)]) -> Vec<i64> {
+        let mut buckets = vec![0; LATENCY_BUCKETS_MS.len() + 1];
+
+        for (i, count) in counts {
+            buckets[*i] = *count;
+        }
+
+        buckets
+    }
+
+    #[test]
+    fn percentile_of_empty_histogram_is_zero() {
+        assert_eq!(percentile(&histogram(&[]), 1_234, 0.5), 0);
+    }
+
+    #[test]
+    fn percentile_reports_bucket_upper_bounds() {
+        // 5 requests under 50ms and 5 under 100ms
+        let buckets = histogram(&[(2, 5), (3, 5)]);
+
+        assert_eq!(percentile(&buckets, 1_000, 0.5), 50);
+        assert_eq!(percentile(&buckets, 1_000, 0.51), 100);
+        assert_eq!(percentile(&buckets, 1_000, 0.99), 100);
+    }
+
+    #[test]
+    fn percentile_is_capped_by_max_latency() {
+        assert_eq!(percentile(&histogram(&[(9, 3)]), 6_000, 0.5), 6_000);
+        assert_eq!(
+            percentile(&histogram(&[(LATENCY_BUCKETS_MS.len(), 3)]), 60_000, 0.5),
+            60_000
+        );
+    }
+
+    #[test]
+    fn hashes_keys_with_sha256() {
+        assert_eq!(
+            hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
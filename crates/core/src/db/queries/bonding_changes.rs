@@ -3,7 +3,7 @@
 use diesel::{
     serialize::ToSql,
     sql_query,
-    sql_types::{Int4, Text, Timestamp},
+    sql_types::{Int4, Int8, Nullable, Text, Timestamp},
 };
 
 use crate::{
@@ -33,6 +33,51 @@ limit $4 offset $5;
  -- $5: offset::integer
  ";
 
+const CHANGES_PAGE_QUERY: &str = r"
+select * from (
+  select
+    address,
+    slot,
+    insert_ts,
+    current_reserves_from_bonding - lag(current_reserves_from_bonding, 1) over (partition by address order by slot desc) reserve_change,
+    current_supply_from_bonding - lag(current_supply_from_bonding, 1) over (partition by address order by slot desc) supply_change
+  from bonding_changes
+  where address = $1 and
+        insert_ts >= $2 and insert_ts < $3
+) s
+where supply_change is not null and reserve_change <> 0
+  and ($4 is null or (insert_ts, slot) < ($4, $5))
+order by insert_ts desc, slot desc
+limit $6;
+ -- $1: address::text
+ -- $2: start_ts::timestamp
+ -- $3: stop_ts::timestamp
+ -- $4: after_insert_ts::timestamp
+ -- $5: after_slot::int8
+ -- $6: limit::int8
+ ";
+
+const CHANGES_COUNT_QUERY: &str = r"
+select count(*) as count from (
+  select
+    current_reserves_from_bonding - lag(current_reserves_from_bonding, 1) over (partition by address order by slot desc) reserve_change,
+    current_supply_from_bonding - lag(current_supply_from_bonding, 1) over (partition by address order by slot desc) supply_change
+  from bonding_changes
+  where address = $1 and
+        insert_ts >= $2 and insert_ts < $3
+) s
+where supply_change is not null and reserve_change <> 0;
+ -- $1: address::text
+ -- $2: start_ts::timestamp
+ -- $3: stop_ts::timestamp
+ ";
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[sql_type = "Int8"]
+    count: i64,
+}
+
 /// Return changes to the bonding supply and reserves over the time interval
 ///
 /// # Errors
@@ -54,3 +99,47 @@ pub fn list(
         .load(conn)
         .context("Failed to load enriched bonding changes")
 }
+
+/// Return a page of changes to the bonding supply and reserves over the time
+/// interval, newest first, starting after the change with the given time and
+/// slot if one is given
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn list_page(
+    conn: &Connection,
+    address: &str,
+    start_ts: NaiveDateTime,
+    stop_ts: NaiveDateTime,
+    after: Option<(NaiveDateTime, i64)>,
+    limit: i64,
+) -> Result<Vec<EnrichedBondingChange<'static>>> {
+    sql_query(CHANGES_PAGE_QUERY)
+        .bind::<Text, _>(address)
+        .bind::<Timestamp, _>(start_ts)
+        .bind::<Timestamp, _>(stop_ts)
+        .bind::<Nullable<Timestamp>, _>(after.map(|(t, _)| t))
+        .bind::<Nullable<Int8>, _>(after.map(|(_, s)| s))
+        .bind::<Int8, _>(limit)
+        .load(conn)
+        .context("Failed to load page of enriched bonding changes")
+}
+
+/// Count the changes to the bonding supply and reserves over the time interval
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn count(
+    conn: &Connection,
+    address: &str,
+    start_ts: NaiveDateTime,
+    stop_ts: NaiveDateTime,
+) -> Result<i64> {
+    sql_query(CHANGES_COUNT_QUERY)
+        .bind::<Text, _>(address)
+        .bind::<Timestamp, _>(start_ts)
+        .bind::<Timestamp, _>(stop_ts)
+        .get_result::<CountRow>(conn)
+        .map(|r| r.count)
+        .context("Failed to count enriched bonding changes")
+}
@@ -10,11 +10,20 @@ use diesel::{
 };
 
 use crate::{
-    db::{models::PricePoint, Connection},
+    db::{models::PricePoint, queries::collections::CollectionKey, Connection},
     error::Result,
 };
 
-const FLOOR_PRICES_QUERY: &str = r"
+/// Render the condition restricting a chart to the NFTs of a collection
+fn collection_filter(collection: Option<&CollectionKey>) -> String {
+    collection.map_or_else(String::new, |c| {
+        format!("and md.address in ({})", c.members_sql())
+    })
+}
+
+fn make_floor_prices_query_string(collection: Option<&CollectionKey>) -> String {
+    format!(
+        r"
 select series as date,
        coalesce(min(price), 0)::bigint as price
 from generate_series($3::date, $4::date, '1 day'::interval) as series
@@ -23,9 +32,11 @@ left join (
     from listing_receipts lr
     inner join metadatas md
     on lr.metadata = md.address
-    inner join metadata_creators mc
-    on md.address = mc.metadata_address
-    where lr.auction_house = ANY($1) and ($2 is null OR mc.creator_address = ANY($2)) and lr.created_at >= $3 and lr.created_at <= $4 and lr.canceled_at is null and lr.purchase_receipt is null
+    where lr.auction_house = ANY($1) and ($2 is null OR exists (
+        select 1 from metadata_creators mc
+        where mc.metadata_address = md.address and mc.creator_address = ANY($2)
+    )) {collection}
+    and lr.created_at >= $3 and lr.created_at <= $4 and lr.canceled_at is null and lr.purchase_receipt is null
 ) as i
 on i.created_at_day = series
 group by date
@@ -33,7 +44,10 @@ order by date asc;
  -- $1: auction house addresses::text[]
  -- $2: creators addresses::text[]
  -- $3: start date::timestamp
- -- $4: end date::timestamp";
+ -- $4: end date::timestamp",
+        collection = collection_filter(collection)
+    )
+}
 
 /// Load floor prices during a given date range for the desired auction house address per day
 ///
@@ -43,10 +57,11 @@ pub fn floor_prices(
     conn: &Connection,
     auction_houses: impl ToSql<Array<Text>, Pg>,
     creators: impl ToSql<Nullable<Array<Text>>, Pg>,
+    collection: Option<&CollectionKey>,
     start_date: NaiveDateTime,
     end_date: NaiveDateTime,
 ) -> Result<Vec<PricePoint>> {
-    diesel::sql_query(FLOOR_PRICES_QUERY)
+    diesel::sql_query(make_floor_prices_query_string(collection))
         .bind(auction_houses)
         .bind(creators)
         .bind::<Timestamp, _>(start_date)
@@ -55,7 +70,9 @@ pub fn floor_prices(
         .context("Failed to load floor prices")
 }
 
-const AVERAGE_PRICES_QUERY: &str = r"
+fn make_average_prices_query_string(collection: Option<&CollectionKey>) -> String {
+    format!(
+        r"
 select series as date,
        coalesce(round(avg(price)), 0)::bigint as price
 from generate_series($3::date, $4::date, '1 day'::interval) as series
@@ -64,9 +81,11 @@ left join (
     from purchase_receipts pr
     inner join metadatas md
     on pr.metadata = md.address
-    inner join metadata_creators mc
-    on md.address = mc.metadata_address
-        where pr.auction_house = ANY($1) and ($2 is null OR mc.creator_address = ANY($2)) and pr.created_at >= $3 and pr.created_at <= $4
+        where pr.auction_house = ANY($1) and ($2 is null OR exists (
+        select 1 from metadata_creators mc
+        where mc.metadata_address = md.address and mc.creator_address = ANY($2)
+    )) {collection}
+    and pr.created_at >= $3 and pr.created_at <= $4
 ) as i
 on i.created_at_day = series
 group by date
@@ -74,7 +93,10 @@ order by date asc;
  -- $1: auction house addresses::text[]
  -- $2: creators addresses::text[]
  -- $3: start date::timestamp
- -- $4: end date::timestamp";
+ -- $4: end date::timestamp",
+        collection = collection_filter(collection)
+    )
+}
 
 /// Load average prices during a given date range for the desired auction house address per day
 ///
@@ -84,10 +106,11 @@ pub fn average_prices(
     conn: &Connection,
     creators: impl ToSql<Nullable<Array<Text>>, Pg>,
     auction_houses: impl ToSql<Array<Text>, Pg>,
+    collection: Option<&CollectionKey>,
     start_date: NaiveDateTime,
     end_date: NaiveDateTime,
 ) -> Result<Vec<PricePoint>> {
-    diesel::sql_query(AVERAGE_PRICES_QUERY)
+    diesel::sql_query(make_average_prices_query_string(collection))
         .bind(auction_houses)
         .bind(creators)
         .bind::<Timestamp, _>(start_date)
@@ -96,7 +119,9 @@ pub fn average_prices(
         .context("Failed to load average prices")
 }
 
-const TOTAL_VOLUME_QUERY: &str = r"
+fn make_total_volume_query_string(collection: Option<&CollectionKey>) -> String {
+    format!(
+        r"
 select series as date,
        coalesce(round(sum(price)), 0)::bigint as price
 from generate_series($3::date, $4::date, '1 day'::interval) as series
@@ -105,9 +130,11 @@ left join (
     from purchases p
     inner join metadatas md
     on p.metadata = md.address
-    inner join metadata_creators mc
-    on md.address = mc.metadata_address
-        where p.auction_house = ANY($1) and ($2 is null OR mc.creator_address = ANY($2)) and p.created_at >= $3 and p.created_at <= $4
+        where p.auction_house = ANY($1) and ($2 is null OR exists (
+        select 1 from metadata_creators mc
+        where mc.metadata_address = md.address and mc.creator_address = ANY($2)
+    )) {collection}
+    and p.created_at >= $3 and p.created_at <= $4
 ) as i
 on i.created_at_day = series
 group by date
@@ -115,7 +142,10 @@ order by date asc;
  -- $1: auction house addresses::text[]
  -- $2: creators addresses::text[]
  -- $3: start date::timestamp
- -- $4: end date::timestamp";
+ -- $4: end date::timestamp",
+        collection = collection_filter(collection)
+    )
+}
 
 /// Load total sales volum during a given date range for the desired auction house address per day
 ///
@@ -125,10 +155,11 @@ pub fn total_volume_prices(
     conn: &Connection,
     auction_houses: impl ToSql<Array<Text>, Pg>,
     creators: impl ToSql<Nullable<Array<Text>>, Pg>,
+    collection: Option<&CollectionKey>,
     start_date: NaiveDateTime,
     end_date: NaiveDateTime,
 ) -> Result<Vec<PricePoint>> {
-    diesel::sql_query(TOTAL_VOLUME_QUERY)
+    diesel::sql_query(make_total_volume_query_string(collection))
         .bind(auction_houses)
         .bind(creators)
         .bind::<Timestamp, _>(start_date)
@@ -0,0 +1,79 @@
+//! Query utilities for the `collection_stats` table.
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::*,
+    sql_types::{Int8, VarChar},
+};
+
+use crate::{
+    db::{models::CollectionStats, tables::collection_stats, Connection},
+    error::prelude::*,
+};
+
+/// Load the statistics of the given collections for every treasury mint
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn load<A: AsRef<str>>(
+    conn: &Connection,
+    collections: &[A],
+) -> Result<Vec<CollectionStats<'static>>> {
+    let collections: Vec<_> = collections.iter().map(AsRef::as_ref).collect();
+
+    collection_stats::table
+        .filter(collection_stats::collection_address.eq_any(collections))
+        .load(conn)
+        .context("Failed to load collection stats")
+}
+
+/// List up to `limit` collection and treasury mint pairs marked dirty by
+/// marketplace activity or whose statistics were last computed before
+/// `older_than`, dirty pairs first, then least recently computed first.
+/// Each pair is returned with the version of its statistics, to be passed to
+/// [`refresh`].
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn stale(
+    conn: &Connection,
+    older_than: NaiveDateTime,
+    limit: i64,
+) -> Result<Vec<(String, String, i64)>> {
+    collection_stats::table
+        .filter(collection_stats::dirty.or(collection_stats::updated_at.lt(older_than)))
+        .order((
+            collection_stats::dirty.desc(),
+            collection_stats::updated_at.asc(),
+        ))
+        .limit(limit)
+        .select((
+            collection_stats::collection_address,
+            collection_stats::treasury_mint,
+            collection_stats::version,
+        ))
+        .load(conn)
+        .context("Failed to load stale collection stats")
+}
+
+/// Recompute the statistics of a collection for a treasury mint.  The
+/// statistics are only marked clean if their version is still `version`, so
+/// marketplace activity during the refresh leaves them dirty.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn refresh(
+    conn: &Connection,
+    collection: &str,
+    treasury_mint: &str,
+    version: i64,
+) -> Result<()> {
+    diesel::sql_query("select refresh_collection_stats($1, $2, $3);")
+        .bind::<VarChar, _>(collection)
+        .bind::<VarChar, _>(treasury_mint)
+        .bind::<Int8, _>(version)
+        .execute(conn)
+        .context("Failed to refresh collection stats")?;
+
+    Ok(())
+}
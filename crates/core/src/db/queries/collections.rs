@@ -3,17 +3,48 @@
 use anyhow::Context;
 use chrono::{DateTime, Utc};
 use diesel::{
+    dsl::{exists, select},
     pg::Pg,
-    sql_types::{Array, Integer, Nullable, Text, Timestamp},
+    prelude::*,
+    sql_types::{Array, Int8, Integer, Nullable, Text, Timestamp},
     types::ToSql,
-    RunQueryDsl,
 };
+use sea_query::{Expr, Iden, PostgresQueryBuilder, Query, SelectStatement};
 
 use crate::{
-    db::{custom_types::OrderDirection, models::Nft, Connection},
+    db::{
+        custom_types::OrderDirection,
+        models::{AttributeCount, CollectionHolder, Nft, NftCollectionKey},
+        tables::{metadata_collection_keys, metadata_collections, metadata_creators},
+        Connection,
+    },
     error::Result,
 };
 
+#[derive(Iden)]
+enum MetadataCollectionKeys {
+    Table,
+    MetadataAddress,
+    CollectionAddress,
+    Verified,
+}
+
+#[derive(Iden)]
+enum MetadataCollections {
+    Table,
+    MetadataAddress,
+    Name,
+    Family,
+}
+
+#[derive(Iden)]
+enum MetadataCreators {
+    Table,
+    MetadataAddress,
+    CreatorAddress,
+    Verified,
+}
+
 /// Query collections ordered by volume
 ///
 /// # Errors
@@ -167,3 +198,316 @@ fn make_by_market_cap_query_string(order_direction: OrderDirection) -> String {
         order_direction = order_direction
     )
 }
+
+/// Subquery selecting the first verified creator of an NFT, given the column
+/// holding its metadata address.  Creators without a position sort last, and
+/// ties are broken by address so every query agrees on the same creator.
+fn first_verified_creator(metadata_address: &str) -> String {
+    format!(
+        "SELECT first_creator.creator_address FROM metadata_creators first_creator
+    WHERE first_creator.metadata_address = {} AND first_creator.verified
+    ORDER BY first_creator.position ASC NULLS LAST, first_creator.creator_address
+    LIMIT 1",
+        metadata_address
+    )
+}
+
+/// Condition excluding NFTs in a verified collection, given the column
+/// holding their metadata address
+fn not_in_verified_collection(metadata_address: &str) -> String {
+    format!(
+        "NOT EXISTS (
+    SELECT 1 FROM metadata_collection_keys verified_keys
+    WHERE verified_keys.metadata_address = {} AND verified_keys.verified)",
+        metadata_address
+    )
+}
+
+/// Condition excluding NFTs whose metadata JSON names a collection, given the
+/// column holding their metadata address
+fn not_in_named_collection(metadata_address: &str) -> String {
+    format!(
+        "NOT EXISTS (
+    SELECT 1 FROM metadata_collections named
+    WHERE named.metadata_address = {} AND named.name IS NOT NULL)",
+        metadata_address
+    )
+}
+
+/// Identifies the member NFTs of a collection.
+///
+/// NFTs belong to their verified collection if they have one.  Older NFTs
+/// without one are grouped by the collection named in their metadata JSON,
+/// and failing that by their first verified creator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CollectionKey {
+    /// NFTs verified as members of the collection with this mint address
+    Verified(String),
+    /// NFTs without a verified collection whose metadata JSON names this
+    /// collection
+    Family {
+        /// The collection name
+        name: String,
+        /// The collection family
+        family: Option<String>,
+    },
+    /// NFTs without a verified or named collection whose first verified
+    /// creator is this address
+    Creator(String),
+}
+
+impl CollectionKey {
+    /// Select the metadata addresses of the members of this collection
+    pub(crate) fn members(&self) -> SelectStatement {
+        fn verified() -> SelectStatement {
+            Query::select()
+                .column((
+                    MetadataCollectionKeys::Table,
+                    MetadataCollectionKeys::MetadataAddress,
+                ))
+                .from(MetadataCollectionKeys::Table)
+                .and_where(
+                    Expr::col((
+                        MetadataCollectionKeys::Table,
+                        MetadataCollectionKeys::Verified,
+                    ))
+                    .eq(true),
+                )
+                .take()
+        }
+
+        match self {
+            Self::Verified(mint) => verified()
+                .and_where(
+                    Expr::col((
+                        MetadataCollectionKeys::Table,
+                        MetadataCollectionKeys::CollectionAddress,
+                    ))
+                    .eq(mint.as_str()),
+                )
+                .take(),
+            Self::Family { name, family } => Query::select()
+                .column((
+                    MetadataCollections::Table,
+                    MetadataCollections::MetadataAddress,
+                ))
+                .from(MetadataCollections::Table)
+                .and_where(
+                    Expr::col((MetadataCollections::Table, MetadataCollections::Name))
+                        .eq(name.as_str()),
+                )
+                .and_where(match family {
+                    Some(family) => {
+                        Expr::col((MetadataCollections::Table, MetadataCollections::Family))
+                            .eq(family.as_str())
+                    },
+                    None => Expr::col((MetadataCollections::Table, MetadataCollections::Family))
+                        .is_null(),
+                })
+                .and_where(Expr::cust(&not_in_verified_collection(
+                    "metadata_collections.metadata_address",
+                )))
+                .take(),
+            Self::Creator(creator) => Query::select()
+                .column((MetadataCreators::Table, MetadataCreators::MetadataAddress))
+                .from(MetadataCreators::Table)
+                .and_where(
+                    Expr::col((MetadataCreators::Table, MetadataCreators::CreatorAddress))
+                        .eq(creator.as_str()),
+                )
+                .and_where(
+                    Expr::col((MetadataCreators::Table, MetadataCreators::Verified)).eq(true),
+                )
+                .and_where(Expr::cust(&format!(
+                    "metadata_creators.creator_address = ({})",
+                    first_verified_creator("metadata_creators.metadata_address")
+                )))
+                .and_where(Expr::cust(&not_in_verified_collection(
+                    "metadata_creators.metadata_address",
+                )))
+                .and_where(Expr::cust(&not_in_named_collection(
+                    "metadata_creators.metadata_address",
+                )))
+                .take(),
+        }
+    }
+
+    /// Render [`members`](Self::members) for embedding in a raw SQL query
+    pub(crate) fn members_sql(&self) -> String {
+        self.members().to_string(PostgresQueryBuilder)
+    }
+}
+
+/// Look up the collection identified by an address, which may be either the
+/// mint address of a verified collection or the address of a verified creator
+///
+/// # Errors
+/// returns an error when the underlying queries throw an error
+pub fn find(conn: &Connection, address: &str) -> Result<Option<CollectionKey>> {
+    let verified: bool = select(exists(
+        metadata_collection_keys::table
+            .filter(metadata_collection_keys::collection_address.eq(address))
+            .filter(metadata_collection_keys::verified),
+    ))
+    .get_result(conn)
+    .context("Failed to check for verified collection")?;
+
+    if verified {
+        return Ok(Some(CollectionKey::Verified(address.to_owned())));
+    }
+
+    let creator: bool = select(exists(
+        metadata_creators::table
+            .filter(metadata_creators::creator_address.eq(address))
+            .filter(metadata_creators::verified),
+    ))
+    .get_result(conn)
+    .context("Failed to check for creator collection")?;
+
+    Ok(creator.then(|| CollectionKey::Creator(address.to_owned())))
+}
+
+/// Look up the collection named by metadata JSON with the given name and
+/// family
+///
+/// # Errors
+/// returns an error when the underlying query throws an error
+pub fn find_family(
+    conn: &Connection,
+    name: String,
+    family: Option<String>,
+) -> Result<Option<CollectionKey>> {
+    let found: bool = select(exists(
+        metadata_collections::table
+            .filter(metadata_collections::name.eq(name.as_str()))
+            .filter(metadata_collections::family.is_not_distinct_from(family.as_deref())),
+    ))
+    .get_result(conn)
+    .context("Failed to check for named collection")?;
+
+    Ok(found.then(|| CollectionKey::Family { name, family }))
+}
+
+fn keys_query() -> String {
+    format!(
+        r"
+select m.address as metadata_address,
+    mck.collection_address,
+    mc.name,
+    mc.family,
+    fvc.creator_address
+from unnest($1::text[]) as m (address)
+left join lateral (
+    select collection_address from metadata_collection_keys
+    where metadata_address = m.address and verified
+    limit 1
+) mck on true
+left join metadata_collections mc
+    on mc.metadata_address = m.address and mc.name is not null
+left join lateral (
+    {first_verified_creator}
+) fvc on true;
+ -- $1: metadata addresses::text[]",
+        first_verified_creator = first_verified_creator("m.address")
+    )
+}
+
+/// Look up the collections the given NFTs belong to
+///
+/// # Errors
+/// returns an error when the underlying query throws an error
+pub fn keys(
+    conn: &Connection,
+    addresses: impl ToSql<Array<Text>, Pg>,
+) -> Result<Vec<(String, CollectionKey)>> {
+    let rows: Vec<NftCollectionKey> = diesel::sql_query(keys_query())
+        .bind(addresses)
+        .load(conn)
+        .context("Failed to load NFT collections")?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(
+            |NftCollectionKey {
+                 metadata_address,
+                 collection_address,
+                 name,
+                 family,
+                 creator_address,
+             }| {
+                let key = match (collection_address, name, creator_address) {
+                    (Some(mint), ..) => CollectionKey::Verified(mint),
+                    (None, Some(name), _) => CollectionKey::Family { name, family },
+                    (None, None, Some(creator)) => CollectionKey::Creator(creator),
+                    (None, None, None) => return None,
+                };
+
+                Some((metadata_address, key))
+            },
+        )
+        .collect())
+}
+
+/// Count the NFTs held by each holder of a collection, largest holders first
+///
+/// # Errors
+/// returns an error when the underlying query throws an error
+pub fn holders(
+    conn: &Connection,
+    collection: &CollectionKey,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CollectionHolder>> {
+    let query = format!(
+        r"
+        SELECT holders.owner_address,
+            (
+                SELECT twitter_handle FROM twitter_handle_name_services
+                WHERE wallet_address = holders.owner_address
+                LIMIT 1
+            ) AS twitter_handle,
+            holders.nfts
+        FROM (
+            SELECT current_metadata_owners.owner_address, COUNT(*) AS nfts
+                FROM metadatas
+                INNER JOIN current_metadata_owners
+                    ON (current_metadata_owners.mint_address = metadatas.mint_address)
+                WHERE metadatas.address IN ({members})
+                    AND NOT metadatas.burned
+                GROUP BY current_metadata_owners.owner_address
+                ORDER BY nfts DESC, current_metadata_owners.owner_address
+                LIMIT $1
+                OFFSET $2
+        ) holders
+        ORDER BY holders.nfts DESC, holders.owner_address
+    -- $1: limit::bigint
+    -- $2: offset::bigint",
+        members = collection.members_sql()
+    );
+
+    diesel::sql_query(query)
+        .bind::<Int8, _>(limit)
+        .bind::<Int8, _>(offset)
+        .load(conn)
+        .context("Failed to load collection holders")
+}
+
+/// Count the NFTs in a collection with each attribute value
+///
+/// # Errors
+/// returns an error when the underlying query throws an error
+pub fn attributes(conn: &Connection, collection: &CollectionKey) -> Result<Vec<AttributeCount>> {
+    let query = format!(
+        r"
+        SELECT attributes.trait_type, attributes.value, COUNT(*) AS count
+            FROM attributes
+            WHERE attributes.metadata_address IN ({members})
+                AND attributes.trait_type IS NOT NULL
+            GROUP BY attributes.trait_type, attributes.value",
+        members = collection.members_sql()
+    );
+
+    diesel::sql_query(query)
+        .load(conn)
+        .context("Failed to load collection attributes")
+}
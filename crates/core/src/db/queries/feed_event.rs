@@ -1,6 +1,9 @@
 //! Query utilities for feed events.
 
-use diesel::prelude::*;
+use diesel::{
+    prelude::*,
+    sql_types::{Nullable, Text},
+};
 use sea_query::{
     Alias, CommonTableExpression, Expr, Iden, Order, PostgresQueryBuilder, Query,
     QueryStatementWriter,
@@ -9,8 +12,31 @@ use sea_query::{
 use crate::{
     db::{models::CompleteFeedEvent, Connection},
     error::prelude::*,
+    uuid::Uuid,
 };
 
+const GET_QUERY: &str = r"
+    SELECT feed_events.id, feed_events.created_at, feed_event_wallets.wallet_address,
+    twitter_handle_name_services.twitter_handle, mint_events.metadata_address,
+    purchase_events.purchase_id, offer_events.offer_id,
+    offer_events.lifecycle as offer_lifecycle, listing_events.listing_id,
+    listing_events.lifecycle as listing_lifecycle, follow_events.graph_connection_address
+        FROM feed_events
+        INNER JOIN feed_event_wallets on (feed_event_wallets.feed_event_id = feed_events.id)
+        LEFT JOIN twitter_handle_name_services on (twitter_handle_name_services.wallet_address = feed_event_wallets.wallet_address)
+        LEFT JOIN follow_events on (follow_events.feed_event_id = feed_events.id)
+        LEFT JOIN mint_events on (mint_events.feed_event_id = feed_events.id)
+        LEFT JOIN purchase_events on (purchase_events.feed_event_id = feed_events.id)
+        LEFT JOIN offer_events on (offer_events.feed_event_id = feed_events.id)
+        LEFT JOIN listing_events on (listing_events.feed_event_id = feed_events.id)
+        WHERE feed_events.id = $1
+        AND ($2 is null OR feed_event_wallets.wallet_address IN (
+            SELECT to_account FROM graph_connections
+            WHERE from_account = $2 AND disconnected_at is null))
+        LIMIT 1;
+ -- $1: id::uuid
+ -- $2: wallet::text";
+
 #[derive(Iden)]
 enum FeedEvents {
     Table,
@@ -370,3 +396,21 @@ pub fn list_relay(
         .load(conn)
         .context("Failed to load feed events")
 }
+
+/// Load a single feed event, optionally only if it belongs in the feed of the
+/// given wallet
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn get(
+    conn: &Connection,
+    id: Uuid,
+    wallet: Option<String>,
+) -> Result<Option<CompleteFeedEvent>> {
+    diesel::sql_query(GET_QUERY)
+        .bind::<diesel::sql_types::Uuid, _>(id)
+        .bind::<Nullable<Text>, _>(wallet)
+        .get_result(conn)
+        .optional()
+        .context("Failed to load feed event")
+}
@@ -4,11 +4,11 @@ use diesel::{
     pg::Pg,
     serialize::ToSql,
     sql_query,
-    sql_types::{Array, Int4, Text},
+    sql_types::{Array, Int4, Int8, Nullable, Text, Timestamp, VarChar},
 };
 
 use crate::{
-    db::{models::TwitterEnrichedGraphConnection, Connection},
+    db::{models::TwitterEnrichedGraphConnection, tables::graph_connections, Connection},
     error::Result,
     prelude::*,
 };
@@ -47,6 +47,73 @@ pub fn connections(
         .context("failed to load twitter enriched graph connections by parameters")
 }
 
+const PAGE_QUERY: &str = r"
+SELECT gc.address AS connection_address, from_account, to_account, connected_at, disconnected_at, fth.twitter_handle AS from_twitter_handle, tth.twitter_handle AS to_twitter_handle
+    FROM graph_connections gc
+    LEFT JOIN twitter_handle_name_services fth ON gc.from_account = fth.wallet_address
+    LEFT JOIN twitter_handle_name_services tth ON gc.to_account = tth.wallet_address
+    WHERE ($1 = '{}' OR from_account = ANY($1)) AND ($2 = '{}' OR to_account = ANY($2)) AND disconnected_at is null
+        AND ($3 is null OR (connected_at, gc.address) < ($3, $4))
+    ORDER BY connected_at DESC, connection_address DESC
+    LIMIT $5;
+ -- $1: from::text[]
+ -- $2: to::text[]
+ -- $3: after_connected_at::timestamp
+ -- $4: after_address::text
+ -- $5: limit::int8
+ ";
+
+/// Return a page of connections based on from and to filters, most recently
+/// connected first, starting after the connection with the given time and
+/// address if one is given
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn connections_page(
+    conn: &Connection,
+    from: &[String],
+    to: &[String],
+    after: Option<(NaiveDateTime, String)>,
+    limit: i64,
+) -> Result<Vec<TwitterEnrichedGraphConnection>> {
+    let (after_connected_at, after_address) = match after {
+        Some((t, a)) => (Some(t), Some(a)),
+        None => (None, None),
+    };
+
+    sql_query(PAGE_QUERY)
+        .bind::<Array<Text>, _>(from)
+        .bind::<Array<Text>, _>(to)
+        .bind::<Nullable<Timestamp>, _>(after_connected_at)
+        .bind::<Nullable<VarChar>, _>(after_address)
+        .bind::<Int8, _>(limit)
+        .load(conn)
+        .context("failed to load page of twitter enriched graph connections")
+}
+
+/// Count the active connections matching the from and to filters
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn count(conn: &Connection, from: &[String], to: &[String]) -> Result<i64> {
+    let mut query = graph_connections::table
+        .filter(graph_connections::disconnected_at.is_null())
+        .into_boxed();
+
+    if !from.is_empty() {
+        query = query.filter(graph_connections::from_account.eq(any(from)));
+    }
+
+    if !to.is_empty() {
+        query = query.filter(graph_connections::to_account.eq(any(to)));
+    }
+
+    query
+        .count()
+        .get_result(conn)
+        .context("failed to count graph connections")
+}
+
 const LIST_QUERY: &str = r"
 SELECT gc.address AS connection_address, from_account, to_account, connected_at, disconnected_at, fth.twitter_handle AS from_twitter_handle, tth.twitter_handle AS to_twitter_handle
     FROM graph_connections gc
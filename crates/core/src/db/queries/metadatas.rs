@@ -12,6 +12,7 @@ use sea_query::{
 use crate::{
     db::{
         models::{Nft, NftActivity},
+        queries::collections::CollectionKey,
         tables::{current_metadata_owners, metadata_jsons, metadatas},
         Connection,
     },
@@ -107,12 +108,47 @@ enum MetadataCollectionKeys {
     CollectionAddress,
 }
 
+#[derive(Iden)]
+enum HiddenNfts {
+    Table,
+    WalletAddress,
+    MetadataAddress,
+}
+
+#[derive(Iden)]
+enum ProfileNftOrders {
+    Table,
+    WalletAddress,
+    MetadataAddress,
+    Position,
+}
+
+#[derive(Iden)]
+enum SuspectedCopies {
+    Table,
+    CopyAddress,
+}
+
+/// Sort orders for [`list`]
+#[derive(Debug, Clone, Copy)]
+pub enum ListSort {
+    /// Listed NFTs first, cheapest first
+    PriceAsc,
+    /// Listed NFTs first, most expensive first
+    PriceDesc,
+    /// Alphabetical by name
+    NameAsc,
+    /// Reverse alphabetical by name
+    NameDesc,
+}
+
 /// List query options
 #[derive(Debug)]
 pub struct ListQueryOptions {
     /// NFT metadata addresses (combines with other filters)
     pub addresses: Option<Vec<String>>,
-    /// nft owners
+    /// nft owners, excluding NFTs they have hidden and ordering their pinned
+    /// NFTs first
     pub owners: Option<Vec<String>>,
     /// nft update_authorities
     pub update_authorities: Option<Vec<String>>,
@@ -130,6 +166,12 @@ pub struct ListQueryOptions {
     pub with_offers: Option<bool>,
     /// nft in one or more specific collections
     pub collections: Option<Vec<String>>,
+    /// nft in a collection, including legacy collections
+    pub collection_key: Option<CollectionKey>,
+    /// exclude nfts suspected of copying another nft's image
+    pub exclude_suspected_copies: bool,
+    /// sort order, defaulting to [`ListSort::PriceAsc`]
+    pub sort: Option<ListSort>,
     /// limit to apply to query
     pub limit: u64,
     /// offset to apply to query
@@ -192,6 +234,9 @@ pub fn list(
         listed,
         with_offers,
         collections,
+        collection_key,
+        exclude_suspected_copies,
+        sort,
         limit,
         offset,
     }: ListQueryOptions,
@@ -269,15 +314,77 @@ pub fn list(
         .and_where(Expr::col(Metadatas::Burned).eq(false))
         .limit(limit)
         .offset(offset)
-        .order_by((Listings::Table, Listings::Price), Order::Asc)
         .take();
 
-    if let Some(addresses) = addresses {
-        query.and_where(Expr::col(Metadatas::Address).is_in(addresses));
+    // Listing a wallet's NFTs respects its profile settings: NFTs the owner
+    // has hidden are excluded, and pinned NFTs come first in the owner's order
+    if let Some(owners) = owners {
+        query
+            .and_where(Expr::col(CurrentMetadataOwners::OwnerAddress).is_in(owners))
+            .and_where(
+                Expr::col((Metadatas::Table, Metadatas::Address)).not_in_subquery(
+                    Query::select()
+                        .column((HiddenNfts::Table, HiddenNfts::MetadataAddress))
+                        .from(HiddenNfts::Table)
+                        .and_where(
+                            Expr::tbl(HiddenNfts::Table, HiddenNfts::WalletAddress).equals(
+                                CurrentMetadataOwners::Table,
+                                CurrentMetadataOwners::OwnerAddress,
+                            ),
+                        )
+                        .take(),
+                ),
+            )
+            .left_join(
+                ProfileNftOrders::Table,
+                Condition::all()
+                    .add(
+                        Expr::tbl(ProfileNftOrders::Table, ProfileNftOrders::WalletAddress).equals(
+                            CurrentMetadataOwners::Table,
+                            CurrentMetadataOwners::OwnerAddress,
+                        ),
+                    )
+                    .add(
+                        Expr::tbl(ProfileNftOrders::Table, ProfileNftOrders::MetadataAddress)
+                            .equals(Metadatas::Table, Metadatas::Address),
+                    ),
+            )
+            .order_by_expr(
+                Expr::col((ProfileNftOrders::Table, ProfileNftOrders::Position)).is_null(),
+                Order::Asc,
+            )
+            .order_by(
+                (ProfileNftOrders::Table, ProfileNftOrders::Position),
+                Order::Asc,
+            );
     }
 
-    if let Some(owners) = owners {
-        query.and_where(Expr::col(CurrentMetadataOwners::OwnerAddress).is_in(owners));
+    match sort.unwrap_or(ListSort::PriceAsc) {
+        ListSort::PriceAsc => {
+            query.order_by((Listings::Table, Listings::Price), Order::Asc);
+        },
+        ListSort::PriceDesc => {
+            query
+                .order_by_expr(
+                    Expr::col((Listings::Table, Listings::Price)).is_null(),
+                    Order::Asc,
+                )
+                .order_by((Listings::Table, Listings::Price), Order::Desc);
+        },
+        ListSort::NameAsc => {
+            query.order_by((Metadatas::Table, Metadatas::Name), Order::Asc);
+        },
+        ListSort::NameDesc => {
+            query.order_by((Metadatas::Table, Metadatas::Name), Order::Desc);
+        },
+    }
+
+    if sort.is_some() {
+        query.order_by((Metadatas::Table, Metadatas::Address), Order::Asc);
+    }
+
+    if let Some(addresses) = addresses {
+        query.and_where(Expr::col(Metadatas::Address).is_in(addresses));
     }
 
     if let Some(update_authorities) = update_authorities {
@@ -391,6 +498,23 @@ pub fn list(
         );
     }
 
+    if let Some(collection_key) = collection_key {
+        query.and_where(
+            Expr::col((Metadatas::Table, Metadatas::Address)).in_subquery(collection_key.members()),
+        );
+    }
+
+    if exclude_suspected_copies {
+        query.and_where(
+            Expr::col((Metadatas::Table, Metadatas::Address)).not_in_subquery(
+                Query::select()
+                    .column((SuspectedCopies::Table, SuspectedCopies::CopyAddress))
+                    .from(SuspectedCopies::Table)
+                    .take(),
+            ),
+        );
+    }
+
     let query = query.to_string(PostgresQueryBuilder);
 
     diesel::sql_query(query)
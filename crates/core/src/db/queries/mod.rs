@@ -1,8 +1,10 @@
 //! Reusable query operations for common or complicated queries.
 
 pub mod activities;
+pub mod api_keys;
 pub mod bonding_changes;
 pub mod charts;
+pub mod collection_stats;
 pub mod collections;
 pub mod featured_listings;
 pub mod feed_event;
@@ -11,6 +13,13 @@ pub mod listing_denylist;
 pub mod metadata_edition;
 pub mod metadatas;
 pub mod nft_count;
+pub mod slot_times;
 pub mod stats;
+#[cfg(feature = "store-config")]
+pub mod store_configs;
 pub mod store_denylist;
+pub mod suspected_copies;
 pub mod twitter_handle_name_service;
+pub mod wallet_auth;
+pub mod wallet_settings;
+pub mod wallets;
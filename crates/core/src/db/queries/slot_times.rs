@@ -0,0 +1,182 @@
+//! Query utilities for looking up and recording the block times of slots.
+
+use std::collections::BTreeSet;
+
+use diesel::{
+    prelude::*,
+    sql_types::{Array, Int8},
+};
+
+use crate::{
+    db::{models::SlotTime, tables::slot_times, Connection},
+    error::prelude::*,
+    hash::HashSet,
+};
+
+/// Tables whose rows reference the slot they were indexed at, each with an
+/// index on `slot`
+const SLOT_TABLES: &[&str] = &["metadatas", "listings", "offers", "purchases"];
+
+/// Statements setting the timestamps of rows indexed from marketplace
+/// instructions to the block times of their slots.  Rows written from account
+/// updates carry on-chain timestamps and are left alone.
+///
+/// Listings and offers store the slot of their cancellation once canceled,
+/// so for those rows the slot dates `canceled_at` rather than `created_at`.
+const CORRECT_CREATED_AT_QUERIES: &[&str] = &[
+    r"
+update purchases p set created_at = st.block_time
+from slot_times st
+where st.slot = p.slot and p.slot = any($1) and p.write_version is null
+    and p.created_at <> st.block_time;",
+    r"
+update listings l set created_at = st.block_time
+from slot_times st
+where st.slot = l.slot and l.slot = any($1) and l.write_version is null
+    and l.canceled_at is null and l.created_at <> st.block_time;",
+    r"
+update listings l set canceled_at = st.block_time
+from slot_times st
+where st.slot = l.slot and l.slot = any($1) and l.write_version is null
+    and l.canceled_at <> st.block_time;",
+    r"
+update offers o set created_at = st.block_time
+from slot_times st
+where st.slot = o.slot and o.slot = any($1) and o.write_version is null
+    and o.canceled_at is null and o.created_at <> st.block_time;",
+    r"
+update offers o set canceled_at = st.block_time
+from slot_times st
+where st.slot = o.slot and o.slot = any($1) and o.write_version is null
+    and o.canceled_at <> st.block_time;",
+];
+
+/// Auction house instruction tables whose `created_at` is dated by `slot`
+const INSTRUCTION_TABLES: &[&str] = &[
+    "buy_instructions",
+    "cancel_instructions",
+    "deposit_instructions",
+    "execute_sale_instructions",
+    "public_buy_instructions",
+    "sell_instructions",
+    "withdraw_from_fee_instructions",
+    "withdraw_from_treasury_instructions",
+    "withdraw_instructions",
+];
+
+#[derive(QueryableByName)]
+struct SlotRow {
+    #[sql_type = "Int8"]
+    slot: i64,
+}
+
+/// Load the recorded block times of the given slots
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn load(conn: &Connection, slots: &[i64]) -> Result<Vec<SlotTime>> {
+    slot_times::table
+        .filter(slot_times::slot.eq_any(slots))
+        .load(conn)
+        .context("Failed to load slot times")
+}
+
+/// Record block times for slots, ignoring slots already recorded
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn insert(conn: &Connection, rows: &[SlotTime]) -> Result<usize> {
+    diesel::insert_into(slot_times::table)
+        .values(rows)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert slot times")
+}
+
+/// Find slots referenced by indexed metadata or marketplace activity that
+/// have no recorded block time, newest first, examining up to `limit`
+/// distinct slots below `before` if given.
+///
+/// Returns the missing slots found and the lowest slot examined, below which
+/// the next call should continue, or `None` if no referenced slots remain.
+///
+/// # Errors
+/// This function fails if any of the underlying queries fail to execute.
+pub fn missing(
+    conn: &Connection,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<(Vec<i64>, Option<i64>)> {
+    let before = before.unwrap_or(i64::MAX);
+    let mut candidates = BTreeSet::new();
+
+    // Each table is walked down its slot index separately; a single query over
+    // their union would scan every table in full
+    for table in SLOT_TABLES {
+        let rows: Vec<SlotRow> = diesel::sql_query(format!(
+            "select distinct slot from {} where slot is not null and slot < $1 order by slot \
+             desc limit $2;",
+            table
+        ))
+        .bind::<Int8, _>(before)
+        .bind::<Int8, _>(limit)
+        .load(conn)
+        .context("Failed to load referenced slots")?;
+
+        candidates.extend(rows.into_iter().map(|r| r.slot));
+    }
+
+    let limit = limit.try_into().unwrap_or(usize::MAX);
+    let candidates: Vec<_> = candidates.into_iter().rev().take(limit).collect();
+
+    // Fewer than `limit` slots means every table has been exhausted
+    let next = if candidates.len() < limit {
+        None
+    } else {
+        candidates.last().copied()
+    };
+
+    let recorded: HashSet<i64> = slot_times::table
+        .filter(slot_times::slot.eq_any(&candidates))
+        .select(slot_times::slot)
+        .load(conn)
+        .context("Failed to load recorded slots")?
+        .into_iter()
+        .collect();
+
+    Ok((
+        candidates
+            .into_iter()
+            .filter(|s| !recorded.contains(s))
+            .collect(),
+        next,
+    ))
+}
+
+/// Replace the processing-time timestamps of marketplace rows indexed in the
+/// given slots with the recorded block times of those slots, returning the
+/// number of rows changed
+///
+/// # Errors
+/// This function fails if any of the underlying queries fail to execute.
+pub fn correct_created_at(conn: &Connection, slots: &[i64]) -> Result<usize> {
+    let instruction_queries = INSTRUCTION_TABLES.iter().map(|table| {
+        format!(
+            "update {table} i set created_at = st.block_time from slot_times st where st.slot = \
+             i.slot and i.slot = any($1) and i.created_at <> st.block_time;",
+            table = table
+        )
+    });
+
+    CORRECT_CREATED_AT_QUERIES
+        .iter()
+        .map(|q| (*q).to_owned())
+        .chain(instruction_queries)
+        .map(|q| {
+            diesel::sql_query(q)
+                .bind::<Array<Int8>, _>(slots)
+                .execute(conn)
+                .context("Failed to correct marketplace timestamps")
+        })
+        .sum()
+}
@@ -0,0 +1,226 @@
+//! Query utilities for storefront config JSONs and their version history.
+
+use chrono::Local;
+use diesel::{
+    prelude::*,
+    sql_types::{Jsonb, Text, Timestamp},
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        models::{StoreAuctionHouse, StoreConfigJson, StoreConfigJsonVersion, StoreCreator},
+        tables::{
+            store_auction_houses, store_config_json_versions, store_config_jsons, store_creators,
+        },
+        Connection, DatabaseErrorKind, Error,
+    },
+    prelude::*,
+    store_config::{self, ParsedConfig},
+};
+
+const INSERT_VERSION_QUERY: &str = r"
+insert into store_config_json_versions (config_address, raw_content, model, fetch_uri, created_at)
+select $1, $2, $3, $4, $5
+where not exists (
+    select 1 from (
+        select raw_content from store_config_json_versions
+        where config_address = $1
+        order by created_at desc
+        limit 1
+    ) latest
+    where latest.raw_content = $2
+);
+-- $1: config address::text
+-- $2: raw content::jsonb
+-- $3: model::text
+-- $4: fetch URI::text
+-- $5: created at::timestamp";
+
+/// Record a version of a store config JSON, returning false if it is
+/// identical to the latest version recorded for the config.
+///
+/// Only the latest version is compared, so returning to an earlier config
+/// records it again as the newest version.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn insert_version(conn: &Connection, version: &StoreConfigJsonVersion) -> Result<bool> {
+    let rows = diesel::sql_query(INSERT_VERSION_QUERY)
+        .bind::<Text, _>(version.config_address.as_ref())
+        .bind::<Jsonb, _>(version.raw_content.as_ref())
+        .bind::<Text, _>(version.model.as_ref())
+        .bind::<Text, _>(version.fetch_uri.as_ref())
+        .bind::<Timestamp, _>(version.created_at)
+        .execute(conn)
+        .context("Failed to insert store config JSON version")?;
+
+    Ok(rows > 0)
+}
+
+/// Write a parsed store config JSON to `store_config_jsons`, and replace the
+/// creators and auction houses of the store config with the ones it lists.
+///
+/// Returns false without writing anything if the config is missing a
+/// subdomain or owner.
+///
+/// # Errors
+/// This function fails if the underlying queries fail to execute.
+pub fn apply(
+    conn: &Connection,
+    addr: &str,
+    config: ParsedConfig,
+    raw_content: Value,
+) -> Result<bool> {
+    let (subdomain, owner) = if let Some(v) = config.subdomain.zip(config.owner) {
+        v
+    } else {
+        info!(
+            "Store config JSON for {} is missing a subdomain or owner, skipping",
+            addr
+        );
+        return Ok(false);
+    };
+
+    let row = StoreConfigJson {
+        config_address: Borrowed(addr),
+        name: Owned(config.name),
+        description: Owned(config.description),
+        logo_url: Owned(config.logo_url),
+        banner_url: Owned(config.banner_url),
+        subdomain: Owned(subdomain),
+        owner_address: Owned(owner),
+        store_address: config.store.map(Owned),
+        raw_content: Some(Owned(raw_content)),
+        model: Some(Owned(config.model)),
+        updated_at: Local::now().naive_utc(),
+    };
+
+    match diesel::insert_into(store_config_jsons::table)
+        .values(&row)
+        .on_conflict(store_config_jsons::config_address)
+        .do_update()
+        .set(&row)
+        .execute(conn)
+    {
+        Ok(_) => (),
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            warn!(
+                "Rejecting storefront upsert for {:?} (subdomain {:?}) violating unique \
+                 constraint",
+                row.name, row.subdomain
+            );
+        },
+        Err(e) => return Err(e).context("Failed to insert store config JSON"),
+    }
+
+    if let Some(creators) = config.creators {
+        conn.build_transaction()
+            .read_write()
+            .run(|| {
+                diesel::delete(
+                    store_creators::table
+                        .filter(store_creators::store_config_address.eq(addr))
+                        .filter(not(store_creators::creator_address.eq(any(&creators)))),
+                )
+                .execute(conn)?;
+
+                creators.iter().try_for_each(|creator| {
+                    let row = StoreCreator {
+                        store_config_address: Borrowed(addr),
+                        creator_address: Borrowed(creator),
+                    };
+
+                    diesel::insert_into(store_creators::table)
+                        .values(&row)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .map(|_| ())
+                })
+            })
+            .context("Failed to replace store creators")?;
+    }
+
+    if let Some(auction_houses) = config.auction_houses {
+        conn.build_transaction()
+            .read_write()
+            .run(|| {
+                diesel::delete(
+                    store_auction_houses::table
+                        .filter(store_auction_houses::store_config_address.eq(addr))
+                        .filter(not(
+                            store_auction_houses::auction_house_address.eq(any(&auction_houses))
+                        )),
+                )
+                .execute(conn)?;
+
+                auction_houses.iter().try_for_each(|house| {
+                    let row = StoreAuctionHouse {
+                        store_config_address: Borrowed(addr),
+                        auction_house_address: Borrowed(house),
+                    };
+
+                    diesel::insert_into(store_auction_houses::table)
+                        .values(&row)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .map(|_| ())
+                })
+            })
+            .context("Failed to replace store auction houses")?;
+    }
+
+    Ok(true)
+}
+
+/// Restore a previously recorded version of a store config JSON on behalf of
+/// the marketplace's owner.
+///
+/// This only changes what the indexer serves: the store config account still
+/// points at its newest JSON, which is applied again the next time the account
+/// is updated on-chain.  Returns false if no such version exists for the
+/// config.
+///
+/// # Errors
+/// This function fails if `owner` does not own the marketplace or the version
+/// being restored, if the version cannot be parsed, or if the underlying
+/// queries fail to execute.
+pub fn revert(conn: &Connection, addr: &str, version: Uuid, owner: &str) -> Result<bool> {
+    let current_owner: Option<String> = store_config_jsons::table
+        .filter(store_config_jsons::config_address.eq(addr))
+        .select(store_config_jsons::owner_address)
+        .first(conn)
+        .optional()
+        .context("Failed to load marketplace owner")?;
+
+    if current_owner.as_deref() != Some(owner) {
+        bail!("Only the owner of a marketplace can revert its config");
+    }
+
+    let raw_content: Option<Value> = store_config_json_versions::table
+        .filter(store_config_json_versions::id.eq(version))
+        .filter(store_config_json_versions::config_address.eq(addr))
+        .select(store_config_json_versions::raw_content)
+        .first(conn)
+        .optional()
+        .context("Failed to load store config JSON version")?;
+
+    let raw_content = match raw_content {
+        Some(c) => c,
+        None => return Ok(false),
+    };
+
+    let config = store_config::parse(addr, &raw_content)
+        .ok_or_else(|| anyhow!("Store config JSON version is not an object"))?;
+
+    if config.store_config.as_deref() != Some(addr) || config.owner.as_deref() != Some(owner) {
+        bail!("Store config JSON version belongs to a different marketplace or owner");
+    }
+
+    if !apply(conn, addr, config, raw_content)? {
+        bail!("Store config JSON version is missing a subdomain or owner");
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,57 @@
+//! Query utilities for perceptual image hashes and suspected copies.
+
+use anyhow::Context;
+use diesel::{
+    sql_types::{Int4, Int8, Text},
+    RunQueryDsl,
+};
+
+use crate::{
+    db::{models::ImageHashCandidate, Connection},
+    error::Result,
+};
+
+const CANDIDATES_QUERY: &str = r"
+    select h.metadata_address, h.dhash, m.created_slot,
+        array(
+            select mc.creator_address
+                from metadata_creators mc
+                where mc.metadata_address = h.metadata_address and mc.verified
+        ) as verified_creators
+        from metadata_image_hashes h
+        inner join metadatas m on (m.address = h.metadata_address)
+        where h.metadata_address <> $1
+            and m.burned = false
+            and length(replace(h.dhash::bit(64)::text, '0', '')) between $3 and $4
+            and ((h.dhash & 65535) = ($2 & 65535)
+                or ((h.dhash >> 16) & 65535) = (($2 >> 16) & 65535)
+                or ((h.dhash >> 32) & 65535) = (($2 >> 32) & 65535)
+                or ((h.dhash >> 48) & 65535) = (($2 >> 48) & 65535));
+ -- $1: address::text
+ -- $2: dhash::int8
+ -- $3: min_ones::int4
+ -- $4: max_ones::int4";
+
+/// Load every NFT whose image hash matches at least one 16-bit band of the
+/// given hash, excluding the NFT at `address` and any hash with fewer than
+/// `min_ones` or more than `max_ones` bits set.
+///
+/// Any hash within a Hamming distance of 3 of `dhash` is guaranteed to be
+/// returned; callers are expected to compute the exact distance themselves.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn candidates(
+    conn: &Connection,
+    address: &str,
+    dhash: i64,
+    (min_ones, max_ones): (i32, i32),
+) -> Result<Vec<ImageHashCandidate>> {
+    diesel::sql_query(CANDIDATES_QUERY)
+        .bind::<Text, _>(address)
+        .bind::<Int8, _>(dhash)
+        .bind::<Int4, _>(min_ones)
+        .bind::<Int4, _>(max_ones)
+        .load(conn)
+        .context("Failed to load image hash candidates")
+}
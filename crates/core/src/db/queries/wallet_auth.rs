@@ -0,0 +1,158 @@
+//! Query utilities for authenticating wallets by signed nonces and the
+//! sessions issued for them.
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::{
+        models::{WalletAuthNonce, WalletSession},
+        tables::{wallet_auth_nonces, wallet_sessions},
+        Connection,
+    },
+    error::prelude::*,
+};
+
+/// Number of minutes an issued nonce may be signed within
+pub const NONCE_TTL_MINUTES: i64 = 10;
+
+/// Maximum number of unexpired nonces a wallet may hold at once, past which
+/// the oldest is replaced
+pub const MAX_PENDING_NONCES: i64 = 5;
+
+/// Number of days a session remains valid
+pub const SESSION_TTL_DAYS: i64 = 30;
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0_u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// The message a wallet must sign to prove ownership for the given nonce
+#[must_use]
+pub fn message(wallet: &str, nonce: &str) -> String {
+    format!(
+        "Sign this message to authenticate with Holaplex.\n\nWallet: {}\nNonce: {}",
+        wallet, nonce
+    )
+}
+
+/// Issue a new nonce for a wallet to sign, removing any expired nonces and,
+/// if the wallet already holds [`MAX_PENDING_NONCES`] unexpired nonces, its
+/// oldest ones.
+///
+/// Requests for a nonce are not authenticated, so refusing new nonces past
+/// the limit would let anyone lock a wallet out of signing in.
+///
+/// # Errors
+/// This function fails if the underlying queries fail to execute.
+pub fn create_nonce(conn: &Connection, wallet: &str) -> Result<WalletAuthNonce<'static>> {
+    let now = Utc::now().naive_utc();
+
+    diesel::delete(wallet_auth_nonces::table.filter(wallet_auth_nonces::expires_at.lt(now)))
+        .execute(conn)
+        .context("Failed to delete expired nonces")?;
+
+    let kept = wallet_auth_nonces::table
+        .filter(wallet_auth_nonces::wallet_address.eq(wallet))
+        .order(wallet_auth_nonces::expires_at.desc())
+        .select(wallet_auth_nonces::nonce)
+        .limit(MAX_PENDING_NONCES - 1);
+
+    diesel::delete(
+        wallet_auth_nonces::table
+            .filter(wallet_auth_nonces::wallet_address.eq(wallet))
+            .filter(wallet_auth_nonces::nonce.ne_all(kept)),
+    )
+    .execute(conn)
+    .context("Failed to delete oldest pending nonces")?;
+
+    let issued = WalletAuthNonce {
+        nonce: random_hex(16).into(),
+        wallet_address: wallet.to_owned().into(),
+        expires_at: now + Duration::minutes(NONCE_TTL_MINUTES),
+    };
+
+    diesel::insert_into(wallet_auth_nonces::table)
+        .values(&issued)
+        .execute(conn)
+        .context("Failed to insert nonce")?;
+
+    Ok(issued)
+}
+
+/// Consume an unexpired nonce issued to a wallet, returning false if no such
+/// nonce exists
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn consume_nonce(conn: &Connection, wallet: &str, nonce: &str) -> Result<bool> {
+    let rows = diesel::delete(
+        wallet_auth_nonces::table
+            .filter(wallet_auth_nonces::nonce.eq(nonce))
+            .filter(wallet_auth_nonces::wallet_address.eq(wallet))
+            .filter(wallet_auth_nonces::expires_at.gt(Utc::now().naive_utc())),
+    )
+    .execute(conn)
+    .context("Failed to consume nonce")?;
+
+    Ok(rows > 0)
+}
+
+/// Create a session for a wallet, returning the plaintext session token and
+/// its expiry time
+///
+/// # Errors
+/// This function fails if the session cannot be inserted.
+pub fn create_session(conn: &Connection, wallet: &str) -> Result<(String, NaiveDateTime)> {
+    let now = Utc::now().naive_utc();
+    let token = random_hex(32);
+    let expires_at = now + Duration::days(SESSION_TTL_DAYS);
+
+    diesel::insert_into(wallet_sessions::table)
+        .values(WalletSession {
+            token_hash: hash(&token).into(),
+            wallet_address: wallet.to_owned().into(),
+            created_at: now,
+            expires_at,
+        })
+        .execute(conn)
+        .context("Failed to insert wallet session")?;
+
+    Ok((token, expires_at))
+}
+
+/// Get the wallet authenticated by an unexpired session token
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn session_wallet(conn: &Connection, token: &str) -> Result<Option<String>> {
+    wallet_sessions::table
+        .filter(wallet_sessions::token_hash.eq(hash(token)))
+        .filter(wallet_sessions::expires_at.gt(Utc::now().naive_utc()))
+        .select(wallet_sessions::wallet_address)
+        .first(conn)
+        .optional()
+        .context("Failed to look up wallet session")
+}
+
+/// End a session, returning false if no such session exists
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn delete_session(conn: &Connection, token: &str) -> Result<bool> {
+    let rows =
+        diesel::delete(wallet_sessions::table.filter(wallet_sessions::token_hash.eq(hash(token))))
+            .execute(conn)
+            .context("Failed to delete wallet session")?;
+
+    Ok(rows > 0)
+}
@@ -0,0 +1,248 @@
+//! Query utilities for per-wallet watchlists, notification preferences and
+//! profile display settings.
+
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::{
+    db::{
+        models::{
+            CollectionWatchlist, HiddenNft, NftWatchlist, NotificationPreference, ProfileNftOrder,
+        },
+        tables::{
+            collection_watchlists, hidden_nfts, nft_watchlists, notification_preferences,
+            profile_nft_orders,
+        },
+        Connection,
+    },
+    error::prelude::*,
+};
+
+/// Add an NFT to a wallet's watchlist, returning false if it was already
+/// present
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn watch_nft(conn: &Connection, wallet: &str, nft: &str) -> Result<bool> {
+    let rows = diesel::insert_into(nft_watchlists::table)
+        .values(NftWatchlist {
+            wallet_address: wallet.into(),
+            metadata_address: nft.into(),
+            created_at: Utc::now().naive_utc(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert NFT watchlist entry")?;
+
+    Ok(rows > 0)
+}
+
+/// Remove an NFT from a wallet's watchlist, returning false if it was not
+/// present
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn unwatch_nft(conn: &Connection, wallet: &str, nft: &str) -> Result<bool> {
+    let rows = diesel::delete(
+        nft_watchlists::table
+            .filter(nft_watchlists::wallet_address.eq(wallet))
+            .filter(nft_watchlists::metadata_address.eq(nft)),
+    )
+    .execute(conn)
+    .context("Failed to delete NFT watchlist entry")?;
+
+    Ok(rows > 0)
+}
+
+/// List the NFTs on a wallet's watchlist, most recently added first
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn nft_watchlist(conn: &Connection, wallet: &str) -> Result<Vec<String>> {
+    nft_watchlists::table
+        .filter(nft_watchlists::wallet_address.eq(wallet))
+        .order(nft_watchlists::created_at.desc())
+        .select(nft_watchlists::metadata_address)
+        .load(conn)
+        .context("Failed to load NFT watchlist")
+}
+
+/// Add a collection to a wallet's watchlist, returning false if it was
+/// already present
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn watch_collection(conn: &Connection, wallet: &str, collection: &str) -> Result<bool> {
+    let rows = diesel::insert_into(collection_watchlists::table)
+        .values(CollectionWatchlist {
+            wallet_address: wallet.into(),
+            collection_address: collection.into(),
+            created_at: Utc::now().naive_utc(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert collection watchlist entry")?;
+
+    Ok(rows > 0)
+}
+
+/// Remove a collection from a wallet's watchlist, returning false if it was
+/// not present
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn unwatch_collection(conn: &Connection, wallet: &str, collection: &str) -> Result<bool> {
+    let rows = diesel::delete(
+        collection_watchlists::table
+            .filter(collection_watchlists::wallet_address.eq(wallet))
+            .filter(collection_watchlists::collection_address.eq(collection)),
+    )
+    .execute(conn)
+    .context("Failed to delete collection watchlist entry")?;
+
+    Ok(rows > 0)
+}
+
+/// List the collections on a wallet's watchlist, most recently added first
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn collection_watchlist(conn: &Connection, wallet: &str) -> Result<Vec<String>> {
+    collection_watchlists::table
+        .filter(collection_watchlists::wallet_address.eq(wallet))
+        .order(collection_watchlists::created_at.desc())
+        .select(collection_watchlists::collection_address)
+        .load(conn)
+        .context("Failed to load collection watchlist")
+}
+
+/// Get a wallet's notification preferences, if it has set any
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn notification_preferences(
+    conn: &Connection,
+    wallet: &str,
+) -> Result<Option<NotificationPreference<'static>>> {
+    notification_preferences::table
+        .filter(notification_preferences::wallet_address.eq(wallet))
+        .first(conn)
+        .optional()
+        .context("Failed to load notification preferences")
+}
+
+/// Insert or replace a wallet's notification preferences
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn set_notification_preferences(
+    conn: &Connection,
+    prefs: &NotificationPreference,
+) -> Result<()> {
+    diesel::insert_into(notification_preferences::table)
+        .values(prefs)
+        .on_conflict(notification_preferences::wallet_address)
+        .do_update()
+        .set(prefs)
+        .execute(conn)
+        .context("Failed to save notification preferences")?;
+
+    Ok(())
+}
+
+/// Hide an NFT from a wallet's profile, returning false if it was already
+/// hidden
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn hide_nft(conn: &Connection, wallet: &str, nft: &str) -> Result<bool> {
+    let rows = diesel::insert_into(hidden_nfts::table)
+        .values(HiddenNft {
+            wallet_address: wallet.into(),
+            metadata_address: nft.into(),
+            created_at: Utc::now().naive_utc(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .context("Failed to insert hidden NFT")?;
+
+    Ok(rows > 0)
+}
+
+/// Show a previously hidden NFT on a wallet's profile, returning false if it
+/// was not hidden
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn unhide_nft(conn: &Connection, wallet: &str, nft: &str) -> Result<bool> {
+    let rows = diesel::delete(
+        hidden_nfts::table
+            .filter(hidden_nfts::wallet_address.eq(wallet))
+            .filter(hidden_nfts::metadata_address.eq(nft)),
+    )
+    .execute(conn)
+    .context("Failed to delete hidden NFT")?;
+
+    Ok(rows > 0)
+}
+
+/// List the NFTs a wallet has hidden from its profile
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn hidden_nfts(conn: &Connection, wallet: &str) -> Result<Vec<String>> {
+    hidden_nfts::table
+        .filter(hidden_nfts::wallet_address.eq(wallet))
+        .order(hidden_nfts::created_at.desc())
+        .select(hidden_nfts::metadata_address)
+        .load(conn)
+        .context("Failed to load hidden NFTs")
+}
+
+/// Replace the display order of NFTs on a wallet's profile
+///
+/// # Errors
+/// This function fails if the underlying queries fail to execute.
+pub fn set_profile_nft_order(conn: &Connection, wallet: &str, nfts: &[String]) -> Result<()> {
+    let rows = nfts
+        .iter()
+        .enumerate()
+        .map(|(i, nft)| -> Result<_> {
+            Ok(ProfileNftOrder {
+                wallet_address: wallet.into(),
+                metadata_address: nft.into(),
+                position: i.try_into()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    conn.build_transaction()
+        .read_write()
+        .run(|| {
+            diesel::delete(
+                profile_nft_orders::table.filter(profile_nft_orders::wallet_address.eq(wallet)),
+            )
+            .execute(conn)?;
+
+            diesel::insert_into(profile_nft_orders::table)
+                .values(&rows)
+                .on_conflict_do_nothing()
+                .execute(conn)
+        })
+        .context("Failed to save profile NFT order")?;
+
+    Ok(())
+}
+
+/// List the NFTs on a wallet's profile in their display order
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn profile_nft_order(conn: &Connection, wallet: &str) -> Result<Vec<String>> {
+    profile_nft_orders::table
+        .filter(profile_nft_orders::wallet_address.eq(wallet))
+        .order(profile_nft_orders::position.asc())
+        .select(profile_nft_orders::metadata_address)
+        .load(conn)
+        .context("Failed to load profile NFT order")
+}
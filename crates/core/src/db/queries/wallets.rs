@@ -0,0 +1,58 @@
+//! Query utilities for aggregating per-wallet statistics.
+
+use diesel::{
+    prelude::*,
+    sql_types::{Array, Int8, Text},
+};
+
+use crate::{db::Connection, error::prelude::*};
+
+const COUNTS_QUERY: &str = r"
+select
+    w.address,
+    (select count(distinct mck.collection_address)
+        from metadata_creators mc
+        inner join metadata_collection_keys mck
+            on (mck.metadata_address = mc.metadata_address)
+        where mc.creator_address = w.address and mc.verified and mck.verified
+    )::bigint as verified_collections,
+    (select count(*)
+        from graph_connections gc
+        where gc.to_account = w.address and gc.disconnected_at is null
+    )::bigint as followers,
+    (select count(*)
+        from graph_connections gc
+        where gc.from_account = w.address and gc.disconnected_at is null
+    )::bigint as following
+from unnest($1::text[]) as w(address);
+ -- $1: addresses::text[]";
+
+/// Collection and follower counts for a wallet
+#[derive(Debug, Clone, QueryableByName)]
+pub struct WalletCounts {
+    /// The wallet address
+    #[sql_type = "Text"]
+    pub address: String,
+    /// The number of distinct verified collections the wallet is a verified
+    /// creator of
+    #[sql_type = "Int8"]
+    pub verified_collections: i64,
+    /// The number of wallets following the wallet
+    #[sql_type = "Int8"]
+    pub followers: i64,
+    /// The number of wallets the wallet follows
+    #[sql_type = "Int8"]
+    pub following: i64,
+}
+
+/// Load the collection and follower counts of the given wallets, one row per
+/// address
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn counts(conn: &Connection, addresses: &[String]) -> Result<Vec<WalletCounts>> {
+    diesel::sql_query(COUNTS_QUERY)
+        .bind::<Array<Text>, _>(addresses)
+        .load(conn)
+        .context("Failed to load wallet counts")
+}
@@ -1,3 +1,32 @@
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    api_keys (id) {
+        id -> Uuid,
+        name -> Text,
+        key_hash -> Text,
+        requests_per_minute -> Int4,
+        burst -> Int4,
+        trusted -> Bool,
+        created_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    arweave_backfill_cursors (name) {
+        name -> Text,
+        cursor -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -491,6 +520,44 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    collection_stats (collection_address, treasury_mint) {
+        collection_address -> Varchar,
+        treasury_mint -> Varchar,
+        floor_price -> Nullable<Int8>,
+        listed_count -> Int8,
+        holder_count -> Int8,
+        supply -> Int8,
+        volume_1d -> Int8,
+        volume_7d -> Int8,
+        volume_30d -> Int8,
+        volume_all -> Int8,
+        sales_count -> Int8,
+        average_price -> Nullable<Int8>,
+        updated_at -> Timestamp,
+        dirty -> Bool,
+        top_offer -> Nullable<Int8>,
+        offer_count -> Int8,
+        version -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    collection_watchlists (wallet_address, collection_address) {
+        wallet_address -> Varchar,
+        collection_address -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -685,6 +752,37 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    graphql_usage (api_key_id, day, operation_name) {
+        api_key_id -> Uuid,
+        day -> Date,
+        operation_name -> Text,
+        request_count -> Int8,
+        error_count -> Int8,
+        latency_buckets -> Array<Int8>,
+        latency_max_ms -> Int4,
+        latency_p50_ms -> Int4,
+        latency_p95_ms -> Int4,
+        latency_p99_ms -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    hidden_nfts (wallet_address, metadata_address) {
+        wallet_address -> Varchar,
+        metadata_address -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -916,6 +1014,19 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    metadata_image_hashes (metadata_address) {
+        metadata_address -> Varchar,
+        image_fingerprint -> Bytea,
+        dhash -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -938,6 +1049,21 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    metadata_transfers (id) {
+        id -> Uuid,
+        mint_address -> Varchar,
+        from_address -> Varchar,
+        to_address -> Varchar,
+        transferred_at -> Timestamp,
+        slot -> Int8,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -958,6 +1084,7 @@ table! {
         token_standard -> Nullable<Token_standard>,
         slot -> Nullable<Int8>,
         burned -> Bool,
+        created_slot -> Nullable<Int8>,
     }
 }
 
@@ -972,6 +1099,35 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    nft_watchlists (wallet_address, metadata_address) {
+        wallet_address -> Varchar,
+        metadata_address -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    notification_preferences (wallet_address) {
+        wallet_address -> Varchar,
+        offer_received -> Bool,
+        offer_accepted -> Bool,
+        listing_sold -> Bool,
+        outbid -> Bool,
+        watchlist_activity -> Bool,
+        new_follower -> Bool,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1007,6 +1163,18 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    profile_nft_orders (wallet_address, metadata_address) {
+        wallet_address -> Varchar,
+        metadata_address -> Varchar,
+        position -> Int4,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1148,6 +1316,19 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    search_documents (index_name, id) {
+        index_name -> Text,
+        id -> Text,
+        body -> Jsonb,
+        ts_index -> Tsvector,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1174,6 +1355,17 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    slot_times (slot) {
+        slot -> Int8,
+        block_time -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1214,6 +1406,21 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    store_config_json_versions (id) {
+        id -> Uuid,
+        config_address -> Varchar,
+        raw_content -> Jsonb,
+        model -> Text,
+        fetch_uri -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1228,6 +1435,9 @@ table! {
         subdomain -> Text,
         owner_address -> Varchar,
         store_address -> Nullable<Varchar>,
+        raw_content -> Nullable<Jsonb>,
+        model -> Nullable<Text>,
+        updated_at -> Timestamp,
     }
 }
 
@@ -1308,6 +1518,19 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    suspected_copies (original_address, copy_address) {
+        original_address -> Varchar,
+        copy_address -> Varchar,
+        similarity -> Float8,
+        detected_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1384,6 +1607,31 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    wallet_auth_nonces (nonce) {
+        nonce -> Text,
+        wallet_address -> Varchar,
+        expires_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{ListingEventLifecycle as Listingeventlifecycle, Mode, OfferEventLifecycle as Offereventlifecycle, SettingType as Settingtype, TokenStandard as Token_standard, };
+
+    wallet_sessions (token_hash) {
+        token_hash -> Text,
+        wallet_address -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -1468,12 +1716,15 @@ joinable!(cardinal_token_manager_invalidators -> cardinal_token_managers (token_
 joinable!(feed_event_wallets -> feed_events (feed_event_id));
 joinable!(follow_events -> feed_events (feed_event_id));
 joinable!(follow_events -> graph_connections (graph_connection_address));
+joinable!(graphql_usage -> api_keys (api_key_id));
 joinable!(listing_events -> feed_events (feed_event_id));
 joinable!(mint_events -> feed_events (feed_event_id));
 joinable!(offer_events -> feed_events (feed_event_id));
 joinable!(purchase_events -> feed_events (feed_event_id));
 
 allow_tables_to_appear_in_same_query!(
+    api_keys,
+    arweave_backfill_cursors,
     attributes,
     auction_caches,
     auction_datas,
@@ -1501,6 +1752,8 @@ allow_tables_to_appear_in_same_query!(
     cardinal_token_manager_invalidators,
     cardinal_token_managers,
     cardinal_use_invalidators,
+    collection_stats,
+    collection_watchlists,
     current_metadata_owners,
     deposit_instructions,
     editions,
@@ -1513,6 +1766,8 @@ allow_tables_to_appear_in_same_query!(
     governance_parameters,
     governors,
     graph_connections,
+    graphql_usage,
+    hidden_nfts,
     ins_buffer_bundle_ins_keys,
     ins_buffer_bundle_instructions,
     ins_buffer_bundles,
@@ -1529,11 +1784,16 @@ allow_tables_to_appear_in_same_query!(
     metadata_collection_keys,
     metadata_collections,
     metadata_creators,
+    metadata_image_hashes,
     metadata_jsons,
+    metadata_transfers,
     metadatas,
     mint_events,
+    nft_watchlists,
+    notification_preferences,
     offer_events,
     offers,
+    profile_nft_orders,
     proposal_account_metas,
     proposal_instructions,
     proposal_metas,
@@ -1542,10 +1802,13 @@ allow_tables_to_appear_in_same_query!(
     purchase_events,
     purchase_receipts,
     purchases,
+    search_documents,
     sell_instructions,
+    slot_times,
     smart_wallet_owners,
     smart_wallets,
     store_auction_houses,
+    store_config_json_versions,
     store_config_jsons,
     store_configs,
     store_creators,
@@ -1553,11 +1816,14 @@ allow_tables_to_appear_in_same_query!(
     storefronts,
     stores,
     sub_account_infos,
+    suspected_copies,
     transactions,
     twitter_handle_name_services,
     tx_instruction_keys,
     tx_instructions,
     votes,
+    wallet_auth_nonces,
+    wallet_sessions,
     wallet_totals,
     whitelisted_creators,
     withdraw_from_fee_instructions,
@@ -35,6 +35,10 @@ pub mod hash;
 pub mod meilisearch;
 #[cfg(feature = "solana")]
 pub mod pubkeys;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(feature = "store-config")]
+pub mod store_config;
 pub mod util;
 
 /// Commonly used utilities
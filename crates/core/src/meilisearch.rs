@@ -15,6 +15,18 @@ pub struct Args {
 }
 
 impl Args {
+    /// The Meilisearch database endpoint
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.meili_url
+    }
+
+    /// The Meilisearch database API key
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.meili_key
+    }
+
     /// Construct a Meilisearch client from the provided arguments
     #[must_use]
     pub fn into_client(self) -> client::Client {
@@ -0,0 +1,196 @@
+use serde_json::Value;
+
+use super::{
+    Document, FacetedHits, FacetedQuery, Filter, Hits, Query, RangeFilter, SearchBackend, Sort,
+};
+use crate::{
+    meilisearch::{client::Client, search::Selectors},
+    prelude::*,
+};
+
+/// Search backend storing documents in a Meilisearch server
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MeiliBackend {
+    client: Client,
+    url: String,
+    key: String,
+}
+
+impl MeiliBackend {
+    /// Construct a new backend for the given Meilisearch server
+    #[must_use]
+    pub fn new(url: String, key: String) -> Self {
+        Self {
+            client: Client::new(url.clone(), key.clone()),
+            url,
+            key,
+        }
+    }
+
+    /// Get a reference to the Meilisearch client
+    #[must_use]
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The Meilisearch database endpoint
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The Meilisearch database API key
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Render a value for a Meilisearch filter expression, quoting strings
+fn filter_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        v => v.to_string(),
+    }
+}
+
+/// Render filters and range filters as a Meilisearch filter expression
+fn filter_expr(filters: &[Filter], ranges: &[RangeFilter]) -> Option<String> {
+    let clauses: Vec<_> = filters
+        .iter()
+        .map(|Filter { field, values }| {
+            let values: Vec<_> = values
+                .iter()
+                .map(|v| format!("{} = {}", field, filter_value(v)))
+                .collect();
+
+            format!("({})", values.join(" OR "))
+        })
+        .chain(ranges.iter().flat_map(|RangeFilter { field, min, max }| {
+            min.map(|m| format!("{} >= {}", field, m))
+                .into_iter()
+                .chain(max.map(|m| format!("{} <= {}", field, m)))
+        }))
+        .collect();
+
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(clauses.join(" AND "))
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for MeiliBackend {
+    async fn upsert(&self, index: &str, docs: Vec<Document>) -> Result<()> {
+        self.client
+            .index(index)
+            .add_or_replace(&docs, None)
+            .await
+            .context("Failed to upsert Meilisearch documents")?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, index: &str, ids: Vec<String>) -> Result<()> {
+        self.client
+            .index(index)
+            .delete_documents(&ids)
+            .await
+            .context("Failed to delete Meilisearch documents")?;
+
+        Ok(())
+    }
+
+    async fn query(&self, query: Query<'_>) -> Result<Hits> {
+        let Query {
+            index,
+            term,
+            filters,
+            limit,
+            offset,
+        } = query;
+
+        let filter = filter_expr(filters, &[]);
+        let index = self.client.index(index);
+        let mut search = index.search();
+        search
+            .with_query(term)
+            .with_limit(limit)
+            .with_offset(offset);
+
+        if let Some(ref filter) = filter {
+            search.with_filter(filter);
+        }
+
+        let results = search
+            .execute::<Value>()
+            .await
+            .context("Meilisearch query failed")?;
+
+        Ok(Hits {
+            hits: results.hits.into_iter().map(|h| h.result).collect(),
+            total: results.nb_hits,
+        })
+    }
+
+    async fn faceted_query(&self, query: FacetedQuery<'_>) -> Result<FacetedHits> {
+        let FacetedQuery {
+            query:
+                Query {
+                    index,
+                    term,
+                    filters,
+                    limit,
+                    offset,
+                },
+            ranges,
+            sort,
+            facet,
+        } = query;
+
+        let filter = filter_expr(filters, ranges);
+        let sort = sort.map(|Sort { field, descending }| {
+            format!("{}:{}", field, if descending { "desc" } else { "asc" })
+        });
+        let sort = sort.as_deref().map(|s| [s]);
+        let facets = [facet];
+        let index = self.client.index(index);
+        let mut search = index.search();
+        search
+            .with_query(term)
+            .with_limit(limit)
+            .with_offset(offset)
+            .with_facets_distribution(Selectors::Some(&facets));
+
+        if let Some(ref filter) = filter {
+            search.with_filter(filter);
+        }
+
+        if let Some(ref sort) = sort {
+            search.with_sort(sort);
+        }
+
+        let results = search
+            .execute::<Value>()
+            .await
+            .context("Meilisearch query failed")?;
+
+        Ok(FacetedHits {
+            hits: Hits {
+                hits: results.hits.into_iter().map(|h| h.result).collect(),
+                total: results.nb_hits,
+            },
+            facets: results
+                .facets_distribution
+                .and_then(|mut d| d.remove(facet))
+                .map(|d| d.into_iter().collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn as_meili(&self) -> Option<&MeiliBackend> {
+        Some(self)
+    }
+}
@@ -0,0 +1,218 @@
+//! Backend-agnostic full-text search over schemaless JSON documents
+
+#[cfg(feature = "meilisearch")]
+mod meili;
+mod postgres;
+
+use std::{fmt, sync::Arc};
+
+#[cfg(feature = "meilisearch")]
+pub use meili::MeiliBackend;
+pub use postgres::PostgresBackend;
+use serde_json::Value;
+
+use crate::{db::Pool, hash::HashMap, prelude::*};
+
+/// The maximum number of values counted for a facet by a faceted query
+pub const MAX_FACET_VALUES: usize = 2000;
+
+#[cfg(feature = "meilisearch")]
+const DEFAULT_BACKEND: &str = "meilisearch";
+#[cfg(not(feature = "meilisearch"))]
+const DEFAULT_BACKEND: &str = "postgres";
+
+/// The kind of search backend to connect to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum BackendKind {
+    /// A Meilisearch server
+    #[cfg(feature = "meilisearch")]
+    Meilisearch,
+    /// Postgres full-text search over the `search_documents` table
+    Postgres,
+}
+
+/// Arguments for connecting to a search backend
+#[derive(Debug, Clone, clap::Args)]
+pub struct Args {
+    /// The search backend to use
+    #[clap(long, env, arg_enum, default_value = DEFAULT_BACKEND)]
+    search_backend: BackendKind,
+
+    /// Meilisearch database endpoint
+    #[cfg(feature = "meilisearch")]
+    #[clap(long, env, required_if_eq("search-backend", "meilisearch"))]
+    meili_url: Option<String>,
+
+    /// Meilisearch database API key
+    #[cfg(feature = "meilisearch")]
+    #[clap(long, env, required_if_eq("search-backend", "meilisearch"))]
+    meili_key: Option<String>,
+}
+
+impl Args {
+    /// Connect to the search backend selected by these arguments.  The
+    /// database pool is only used by the Postgres backend.
+    ///
+    /// # Errors
+    /// This function fails if the arguments for the selected backend are
+    /// incomplete.
+    pub fn connect(self, db: Pool) -> Result<Arc<dyn SearchBackend>> {
+        #[cfg(feature = "meilisearch")]
+        let Self {
+            search_backend,
+            meili_url,
+            meili_key,
+        } = self;
+        #[cfg(not(feature = "meilisearch"))]
+        let Self { search_backend } = self;
+
+        Ok(match search_backend {
+            #[cfg(feature = "meilisearch")]
+            BackendKind::Meilisearch => Arc::new(MeiliBackend::new(
+                meili_url.ok_or_else(|| anyhow!("Missing Meilisearch URL"))?,
+                meili_key.ok_or_else(|| anyhow!("Missing Meilisearch API key"))?,
+            )),
+            BackendKind::Postgres => Arc::new(PostgresBackend::new(db)),
+        })
+    }
+}
+
+/// A schemaless search document
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Document {
+    /// The document ID, unique within its index
+    pub id: String,
+    /// The document fields, excluding the ID
+    #[serde(flatten)]
+    pub body: Value,
+}
+
+/// A filter matching documents where a field equals any of a set of values.
+///
+/// If the field holds an array, the filter matches if any element of the
+/// array equals any of the values.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    /// The field to filter on
+    pub field: String,
+    /// The values to accept
+    pub values: Vec<Value>,
+}
+
+impl Filter {
+    /// Construct a filter accepting any of the given values
+    pub fn any_of<T: Into<Value>>(
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Parameters for a search query
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    /// The index to search
+    pub index: &'a str,
+    /// The search terms
+    pub term: &'a str,
+    /// Filters to apply; a hit must match all of them
+    pub filters: &'a [Filter],
+    /// Maximum number of hits to return
+    pub limit: usize,
+    /// Number of hits to skip
+    pub offset: usize,
+}
+
+/// A filter matching documents where a numeric field lies within a range.
+/// Documents without the field never match.
+#[derive(Debug, Clone)]
+pub struct RangeFilter {
+    /// The field to filter on
+    pub field: String,
+    /// The inclusive lower bound, if any
+    pub min: Option<i64>,
+    /// The inclusive upper bound, if any
+    pub max: Option<i64>,
+}
+
+/// An order for search hits.  Documents without the sort field come last.
+#[derive(Debug, Clone, Copy)]
+pub struct Sort<'a> {
+    /// The field to sort by
+    pub field: &'a str,
+    /// Whether to sort in descending order
+    pub descending: bool,
+}
+
+/// Parameters for a faceted search query
+#[derive(Debug, Clone, Copy)]
+pub struct FacetedQuery<'a> {
+    /// The index, search terms, filters and page of hits to return
+    pub query: Query<'a>,
+    /// Range filters to apply; a hit must match all of them
+    pub ranges: &'a [RangeFilter],
+    /// The order of hits, or `None` to order them by relevance
+    pub sort: Option<Sort<'a>>,
+    /// An array field whose values are counted across all matching documents
+    pub facet: &'a str,
+}
+
+/// The results of a search query
+#[derive(Debug, Clone)]
+pub struct Hits {
+    /// The requested page of matching documents, including their IDs
+    pub hits: Vec<Value>,
+    /// The total number of documents matching the query, possibly estimated
+    pub total: usize,
+}
+
+/// The results of a faceted search query
+#[derive(Debug, Clone)]
+pub struct FacetedHits {
+    /// The requested page of matching documents
+    pub hits: Hits,
+    /// The number of matching documents containing each value of the facet
+    /// field, for up to [`MAX_FACET_VALUES`] values
+    pub facets: HashMap<String, usize>,
+}
+
+/// A full-text search engine storing documents in named indexes
+#[async_trait::async_trait]
+#[allow(clippy::module_name_repetitions)]
+pub trait SearchBackend: fmt::Debug + Send + Sync {
+    /// Insert or replace documents in an index
+    ///
+    /// # Errors
+    /// This function fails if the backend rejects the documents.
+    async fn upsert(&self, index: &str, docs: Vec<Document>) -> Result<()>;
+
+    /// Delete documents from an index by ID
+    ///
+    /// # Errors
+    /// This function fails if the backend rejects the deletion.
+    async fn delete(&self, index: &str, ids: Vec<String>) -> Result<()>;
+
+    /// Search an index, returning matching documents in order of relevance
+    ///
+    /// # Errors
+    /// This function fails if the backend query fails.
+    async fn query(&self, query: Query<'_>) -> Result<Hits>;
+
+    /// Search an index with range filters and an optional sort order, and
+    /// count the values of a facet field across all matching documents
+    ///
+    /// # Errors
+    /// This function fails if the backend query fails.
+    async fn faceted_query(&self, query: FacetedQuery<'_>) -> Result<FacetedHits>;
+
+    /// Get the underlying Meilisearch backend, if this is one.  Used for
+    /// features Meilisearch supports that other backends do not.
+    #[cfg(feature = "meilisearch")]
+    fn as_meili(&self) -> Option<&MeiliBackend> {
+        None
+    }
+}
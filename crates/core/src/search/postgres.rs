@@ -0,0 +1,414 @@
+use std::fmt;
+
+use diesel::{
+    dsl::sql,
+    pg::{upsert::excluded, Pg},
+    sql_types::{Array, BigInt, Bool, Float, Jsonb, Nullable, Text},
+};
+use serde_json::{json, Value};
+
+use super::{
+    Document, FacetedHits, FacetedQuery, Filter, Hits, Query, RangeFilter, SearchBackend, Sort,
+    MAX_FACET_VALUES,
+};
+use crate::{
+    db::{
+        delete, insert_into, models::SearchDocument, tables::search_documents, Connection, Pool,
+        TsQuery,
+    },
+    prelude::*,
+};
+
+/// Counts the values of an array field across the documents matching a
+/// search, using the same matching rules as [`matching`]
+const FACET_QUERY: &str = r"
+select value, count(*) as count
+from search_documents, jsonb_array_elements_text(body -> $2) value
+where index_name = $1
+    and ($3::text is null or ts_index @@ websearch_to_tsquery('simple', $3))
+    and not exists (
+        select 1 from jsonb_array_elements($4) f
+        where not exists (
+            select 1 from jsonb_array_elements(f -> 1) v
+            where body -> (f ->> 0) @> v
+        )
+    )
+    and not exists (
+        select 1 from jsonb_to_recordset($5) r(field text, min numeric, max numeric)
+        where (r.min is not null and not coalesce((body ->> r.field)::numeric >= r.min, false))
+            or (r.max is not null and not coalesce((body ->> r.field)::numeric <= r.max, false))
+    )
+group by value
+order by count desc, value
+limit $6;
+ -- $1: index::text
+ -- $2: facet field::text
+ -- $3: search term::text
+ -- $4: filters as [field, [values...]] pairs::jsonb
+ -- $5: range filters as {field, min, max} objects::jsonb
+ -- $6: limit::bigint";
+
+#[derive(QueryableByName)]
+struct FacetCount {
+    #[sql_type = "Text"]
+    value: String,
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// Search backend storing documents in the `search_documents` table, matched
+/// using Postgres full-text search.
+///
+/// Unlike Meilisearch, terms are matched as whole words with no typo
+/// tolerance.  Index settings such as searchable attributes are ignored; all
+/// string fields of a document are searched.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct PostgresBackend {
+    db: Pool,
+}
+
+impl fmt::Debug for PostgresBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresBackend").finish_non_exhaustive()
+    }
+}
+
+impl PostgresBackend {
+    /// Construct a new backend using the given database
+    #[must_use]
+    pub fn new(db: Pool) -> Self {
+        Self { db }
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let db = self.db.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = db.get().context("Failed to acquire database connection")?;
+
+            f(&conn)
+        })
+        .await
+        .context("Blocking task failed")?
+    }
+}
+
+fn check_field(field: &str) -> Result<&str> {
+    if !field.is_empty()
+        && field
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+    {
+        Ok(field)
+    } else {
+        Err(anyhow!("Invalid search filter field {:?}", field))
+    }
+}
+
+/// Select the documents of an index matching a search term, a set of
+/// `(field, values)` filters and a set of `(field, min, max)` range filters
+fn matching<'a>(
+    index: &str,
+    term: Option<&str>,
+    filters: &[(String, Vec<Value>)],
+    ranges: &[(String, Option<i64>, Option<i64>)],
+) -> search_documents::BoxedQuery<'a, Pg> {
+    let mut q = search_documents::table
+        .filter(search_documents::index_name.eq(index.to_owned()))
+        .into_boxed();
+
+    if let Some(term) = term {
+        q = q.filter(
+            search_documents::ts_index.matches(
+                sql::<TsQuery>("websearch_to_tsquery('simple', ")
+                    .bind::<Text, _>(term.to_owned())
+                    .sql(")"),
+            ),
+        );
+    }
+
+    // A field matches if it equals one of the values or, for arrays,
+    // contains one of them
+    for (field, values) in filters {
+        q = q.filter(
+            sql::<Bool>(&format!("(body -> '{}') @> any(", field))
+                .bind::<Array<Jsonb>, _>(values.clone())
+                .sql(")"),
+        );
+    }
+
+    // Documents without the field compare as null and never match
+    for (field, min, max) in ranges {
+        let value = format!("(body ->> '{}')::numeric", field);
+
+        if let Some(min) = min {
+            q = q.filter(sql::<Bool>(&format!("{} >= ", value)).bind::<BigInt, _>(*min));
+        }
+
+        if let Some(max) = max {
+            q = q.filter(sql::<Bool>(&format!("{} <= ", value)).bind::<BigInt, _>(*max));
+        }
+    }
+
+    q
+}
+
+fn check_filters(filters: &[Filter]) -> Result<Vec<(String, Vec<Value>)>> {
+    filters
+        .iter()
+        .map(|Filter { field, values }| Ok((check_field(field)?.to_owned(), values.clone())))
+        .collect()
+}
+
+fn check_ranges(ranges: &[RangeFilter]) -> Result<Vec<(String, Option<i64>, Option<i64>)>> {
+    ranges
+        .iter()
+        .map(|RangeFilter { field, min, max }| Ok((check_field(field)?.to_owned(), *min, *max)))
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl SearchBackend for PostgresBackend {
+    async fn upsert(&self, index: &str, docs: Vec<Document>) -> Result<()> {
+        let rows = docs
+            .into_iter()
+            .map(|doc| {
+                let id = doc.id.clone();
+                let body = serde_json::to_value(doc).context("Failed to serialize document")?;
+
+                Ok(SearchDocument {
+                    index_name: Owned(index.to_owned()),
+                    id: Owned(id),
+                    body: Owned(body),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.run(move |db| {
+            insert_into(search_documents::table)
+                .values(&rows)
+                .on_conflict((search_documents::index_name, search_documents::id))
+                .do_update()
+                .set(search_documents::body.eq(excluded(search_documents::body)))
+                .execute(db)
+                .context("Failed to upsert search documents")
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, index: &str, ids: Vec<String>) -> Result<()> {
+        let index = index.to_owned();
+
+        self.run(move |db| {
+            delete(
+                search_documents::table.filter(
+                    search_documents::index_name
+                        .eq(index)
+                        .and(search_documents::id.eq(any(ids))),
+                ),
+            )
+            .execute(db)
+            .context("Failed to delete search documents")
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn query(&self, query: Query<'_>) -> Result<Hits> {
+        let Query {
+            index,
+            term,
+            filters,
+            limit,
+            offset,
+        } = query;
+
+        let index = index.to_owned();
+        let term = Some(term.trim())
+            .filter(|t| !t.is_empty())
+            .map(ToOwned::to_owned);
+        let filters = check_filters(filters)?;
+        let limit: i64 = limit.try_into().context("Search limit too large")?;
+        let offset: i64 = offset.try_into().context("Search offset too large")?;
+
+        self.run(move |db| {
+            let filtered = || matching(&index, term.as_deref(), &filters, &[]);
+
+            let total: i64 = filtered()
+                .count()
+                .get_result(db)
+                .context("Failed to count search hits")?;
+
+            let mut hits = filtered();
+
+            if let Some(ref term) = term {
+                hits = hits.order(
+                    sql::<Float>("ts_rank(ts_index, websearch_to_tsquery('simple', ")
+                        .bind::<Text, _>(term.clone())
+                        .sql(")) desc"),
+                );
+            }
+
+            let hits: Vec<Value> = hits
+                .then_order_by(search_documents::id)
+                .select(search_documents::body)
+                .limit(limit)
+                .offset(offset)
+                .load(db)
+                .context("Failed to load search hits")?;
+
+            Ok(Hits {
+                hits,
+                total: total.try_into().unwrap_or_default(),
+            })
+        })
+        .await
+    }
+
+    async fn faceted_query(&self, query: FacetedQuery<'_>) -> Result<FacetedHits> {
+        let FacetedQuery {
+            query:
+                Query {
+                    index,
+                    term,
+                    filters,
+                    limit,
+                    offset,
+                },
+            ranges,
+            sort,
+            facet,
+        } = query;
+
+        let index = index.to_owned();
+        let term = Some(term.trim())
+            .filter(|t| !t.is_empty())
+            .map(ToOwned::to_owned);
+        let filters = check_filters(filters)?;
+        let ranges = check_ranges(ranges)?;
+        let sort = sort
+            .map(|Sort { field, descending }| Ok((check_field(field)?.to_owned(), descending)))
+            .transpose()?;
+        let facet = facet.to_owned();
+        let limit: i64 = limit.try_into().context("Search limit too large")?;
+        let offset: i64 = offset.try_into().context("Search offset too large")?;
+
+        self.run(move |db| {
+            let filtered = || matching(&index, term.as_deref(), &filters, &ranges);
+
+            let total: i64 = filtered()
+                .count()
+                .get_result(db)
+                .context("Failed to count search hits")?;
+
+            let mut hits = filtered();
+
+            if let Some((ref field, descending)) = sort {
+                hits = hits.order(sql::<Jsonb>(&format!(
+                    "body -> '{}' {} nulls last",
+                    field,
+                    if descending { "desc" } else { "asc" }
+                )));
+            } else if let Some(ref term) = term {
+                hits = hits.order(
+                    sql::<Float>("ts_rank(ts_index, websearch_to_tsquery('simple', ")
+                        .bind::<Text, _>(term.clone())
+                        .sql(")) desc"),
+                );
+            }
+
+            let hits: Vec<Value> = hits
+                .then_order_by(search_documents::id)
+                .select(search_documents::body)
+                .limit(limit)
+                .offset(offset)
+                .load(db)
+                .context("Failed to load search hits")?;
+
+            let facets: Vec<FacetCount> = diesel::sql_query(FACET_QUERY)
+                .bind::<Text, _>(&index)
+                .bind::<Text, _>(&facet)
+                .bind::<Nullable<Text>, _>(term.as_deref())
+                .bind::<Jsonb, _>(json!(filters))
+                .bind::<Jsonb, _>(Value::Array(
+                    ranges
+                        .iter()
+                        .map(|(field, min, max)| json!({ "field": field, "min": min, "max": max }))
+                        .collect(),
+                ))
+                .bind::<BigInt, _>(i64::try_from(MAX_FACET_VALUES)?)
+                .load(db)
+                .context("Failed to count facet values")?;
+
+            Ok(FacetedHits {
+                hits: Hits {
+                    hits,
+                    total: total.try_into().unwrap_or_default(),
+                },
+                facets: facets
+                    .into_iter()
+                    .map(|FacetCount { value, count }| {
+                        (value, count.try_into().unwrap_or_default())
+                    })
+                    .collect(),
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::debug_query;
+
+    use super::*;
+
+    #[test]
+    fn renders_filters() {
+        let filters = vec![
+            ("creators".to_owned(), vec![
+                Value::from("a"),
+                Value::from("b"),
+            ]),
+            ("verified".to_owned(), vec![Value::Bool(true)]),
+        ];
+        let sql = debug_query::<Pg, _>(&matching("nfts", Some("cat"), &filters, &[])).to_string();
+
+        assert!(
+            sql.contains("websearch_to_tsquery('simple', $2)"),
+            "{}",
+            sql
+        );
+        assert!(sql.contains("(body -> 'creators') @> any($3)"), "{}", sql);
+        assert!(sql.contains("(body -> 'verified') @> any($4)"), "{}", sql);
+        assert!(
+            sql.contains(r#"["nfts", "cat", [String("a"), String("b")], [Bool(true)]]"#),
+            "{}",
+            sql
+        );
+    }
+
+    #[test]
+    fn omits_empty_clauses() {
+        let sql = debug_query::<Pg, _>(&matching("nfts", None, &[], &[])).to_string();
+
+        assert!(!sql.contains("websearch_to_tsquery"), "{}", sql);
+        assert!(!sql.contains("body ->"), "{}", sql);
+    }
+
+    #[test]
+    fn rejects_unsafe_filter_fields() {
+        assert_eq!(check_field("creator_address").unwrap(), "creator_address");
+
+        for field in ["", "a b", "x') or true --", "body->'x'"] {
+            assert!(check_field(field).is_err(), "{:?} was accepted", field);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Tolerant parsing of storefront config JSON documents
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::prelude::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Creator {
+    address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AuctionHouse {
+    address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Metadata {
+    name: String,
+    description: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Address {
+    owner: String,
+    store: Option<String>,
+    store_config: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Upload {
+    url: String,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    ty: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Theme {
+    banner: Upload,
+    logo: Upload,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SettingUri {
+    meta: Metadata,
+    theme: Theme,
+    subdomain: String,
+    address: Address,
+    creators: Option<Vec<Creator>>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+    auction_houses: Option<Vec<AuctionHouse>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SettingUriMinimal {
+    #[serde(default)]
+    meta: Value,
+    #[serde(default)]
+    theme: Value,
+    #[serde(default)]
+    subdomain: Value,
+    #[serde(default)]
+    address: Value,
+    #[serde(default)]
+    creators: Value,
+    #[serde(default)]
+    auction_houses: Value,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+/// Store config fields extracted by either the full or the minimal parser
+#[derive(Debug)]
+pub struct ParsedConfig {
+    /// Storefront name
+    pub name: String,
+    /// Storefront description
+    pub description: String,
+    /// Storefront logo URL
+    pub logo_url: String,
+    /// Storefront banner URL
+    pub banner_url: String,
+    /// Storefront subdomain, if present
+    pub subdomain: Option<String>,
+    /// Storefront owner address, if present
+    pub owner: Option<String>,
+    /// Storefront address, if present
+    pub store: Option<String>,
+    /// Address of the StoreConfig account the JSON claims to belong to
+    pub store_config: Option<String>,
+    /// Addresses of the storefront's creators, if listed
+    pub creators: Option<Vec<String>>,
+    /// Addresses of the storefront's auction houses, if listed
+    pub auction_houses: Option<Vec<String>>,
+    /// Description of the parser used to read the JSON
+    pub model: String,
+}
+
+impl From<SettingUri> for ParsedConfig {
+    fn from(json: SettingUri) -> Self {
+        Self {
+            name: json.meta.name,
+            description: json.meta.description,
+            logo_url: json.theme.logo.url,
+            banner_url: json.theme.banner.url,
+            subdomain: Some(json.subdomain),
+            owner: Some(json.address.owner),
+            store: json.address.store,
+            store_config: Some(json.address.store_config),
+            creators: json
+                .creators
+                .map(|c| c.into_iter().map(|c| c.address).collect()),
+            auction_houses: json
+                .auction_houses
+                .map(|h| h.into_iter().map(|h| h.address).collect()),
+            model: "full".into(),
+        }
+    }
+}
+
+impl ParsedConfig {
+    fn from_minimal(json: &SettingUriMinimal, full_err: &serde_json::Error) -> Self {
+        fn string(v: &Value, pointer: &str) -> Option<String> {
+            v.pointer(pointer).and_then(Value::as_str).map(Into::into)
+        }
+
+        fn addresses(v: &Value) -> Option<Vec<String>> {
+            v.as_array()
+                .map(|a| a.iter().filter_map(|v| string(v, "/address")).collect())
+        }
+
+        Self {
+            name: string(&json.meta, "/name").unwrap_or_default(),
+            description: string(&json.meta, "/description").unwrap_or_default(),
+            logo_url: string(&json.theme, "/logo/url").unwrap_or_default(),
+            banner_url: string(&json.theme, "/banner/url").unwrap_or_default(),
+            subdomain: json.subdomain.as_str().map(Into::into),
+            owner: string(&json.address, "/owner"),
+            store: string(&json.address, "/store"),
+            store_config: string(&json.address, "/storeConfig"),
+            creators: addresses(&json.creators),
+            auction_houses: addresses(&json.auction_houses),
+            model: format!("minimal ({})", full_err),
+        }
+    }
+}
+
+/// Parse a store config JSON document, falling back to a minimal parser that
+/// extracts whichever fields are present if the full model does not match.
+///
+/// `source` is used to identify the document in log messages.  Returns `None`
+/// if the document is not a JSON object.
+#[must_use]
+pub fn parse(source: &str, raw_content: &Value) -> Option<ParsedConfig> {
+    let full_err = match serde_json::from_value::<SettingUri>(raw_content.clone()) {
+        Ok(json) => return Some(json.into()),
+        Err(e) => e,
+    };
+
+    trace!(
+        "Failed to parse full store config JSON for {:?}: {:?}",
+        source,
+        full_err
+    );
+
+    match serde_json::from_value::<SettingUriMinimal>(raw_content.clone()) {
+        Ok(json) => Some(ParsedConfig::from_minimal(&json, &full_err)),
+        Err(e) => {
+            trace!(
+                "Failed to parse minimal store config JSON for {:?}: {:?}",
+                source,
+                e
+            );
+
+            None
+        },
+    }
+}
@@ -63,3 +63,69 @@ pub fn get_end_info(
 
     Ok((ends_at, ended))
 }
+
+/// Encode an NFT attribute as a `trait_type:value` string for search filters
+/// and facets.  Backslashes and colons in the trait type are escaped, so the
+/// first unescaped colon always separates it from the value.
+#[must_use]
+pub fn encode_attribute(trait_type: &str, value: &str) -> String {
+    let mut out = String::with_capacity(trait_type.len() + value.len() + 1);
+
+    for c in trait_type.chars() {
+        if matches!(c, '\\' | ':') {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out.push(':');
+    out.push_str(value);
+
+    out
+}
+
+/// Split an attribute string produced by [`encode_attribute`] into its trait
+/// type and value, returning `None` if it has no separator
+#[must_use]
+pub fn decode_attribute(s: &str) -> Option<(String, &str)> {
+    let mut trait_type = String::new();
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => trait_type.push(chars.next()?.1),
+            ':' => return Some((trait_type, &s[i + 1..])),
+            c => trait_type.push(c),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_attribute, encode_attribute};
+
+    #[test]
+    fn attributes_round_trip() {
+        for (trait_type, value) in [
+            ("Background", "Blue"),
+            ("Eyes: Left", "Red"),
+            ("Path\\", "a:b"),
+            ("", ""),
+        ] {
+            let encoded = encode_attribute(trait_type, value);
+
+            assert_eq!(
+                decode_attribute(&encoded),
+                Some((trait_type.to_owned(), value)),
+                "{:?}",
+                encoded
+            );
+        }
+
+        assert_eq!(encode_attribute("Eyes: Left", "Red"), "Eyes\\: Left:Red");
+        assert_eq!(decode_attribute("no separator"), None);
+    }
+}
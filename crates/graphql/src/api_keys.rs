@@ -0,0 +1,365 @@
+//! API key authentication, per-key rate limiting and usage accounting
+
+use std::{sync::Arc, time::Instant};
+
+use indexer_core::{
+    clap,
+    db::{models::GraphqlUsage, queries::api_keys, Pool},
+    hash::{DashMap, HashMap},
+    prelude::*,
+    uuid::Uuid,
+};
+use serde_json::Value as Json;
+use tokio::sync::Mutex;
+
+use crate::rate_limit::RateLimiter;
+
+/// Time for which an unknown API key is remembered, so repeated requests
+/// with it do not each query the database
+const MISS_TTL: StdDuration = StdDuration::from_secs(10);
+
+/// Maximum number of unknown API keys remembered at once
+const MAX_CACHED_MISSES: usize = 10_000;
+
+/// Options for API key authentication
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Args {
+    /// Reject requests made without an API key
+    #[clap(long, env)]
+    require_api_key: bool,
+
+    /// Number of seconds API key lookups are cached for, bounding how long a
+    /// revoked key remains usable
+    #[clap(long, env, default_value_t = 60)]
+    api_key_cache_secs: u64,
+
+    /// Number of seconds between writes of usage statistics to the database
+    #[clap(long, env, default_value_t = 60)]
+    usage_flush_secs: u64,
+}
+
+/// An active API key
+#[derive(Debug)]
+pub struct Key {
+    /// The ID of the key
+    pub id: Uuid,
+    /// Whether requests with this key are exempt from query limits
+    pub trusted: bool,
+    requests_per_minute: f64,
+    burst: f64,
+}
+
+/// Reason a request was denied
+#[derive(Debug, Clone, Copy)]
+pub enum Denial {
+    /// No API key was provided but one is required
+    Missing,
+    /// The API key provided is unknown or revoked
+    Invalid,
+    /// The API key has exceeded its rate limit
+    RateLimited {
+        /// Number of seconds until the next request would be allowed
+        retry_after: u64,
+    },
+    /// The API key could not be checked
+    Unavailable,
+}
+
+impl Denial {
+    /// Render this denial as a GraphQL error response body
+    #[must_use]
+    pub fn to_response(self) -> Json {
+        let (message, extensions) = match self {
+            Self::Missing => (
+                "An API key is required".to_owned(),
+                serde_json::json!({ "code": "UNAUTHENTICATED" }),
+            ),
+            Self::Invalid => (
+                "Invalid or revoked API key".to_owned(),
+                serde_json::json!({ "code": "UNAUTHENTICATED" }),
+            ),
+            Self::RateLimited { retry_after } => (
+                format!("Rate limit exceeded, retry in {}s", retry_after),
+                serde_json::json!({ "code": "RATE_LIMITED", "retryAfter": retry_after }),
+            ),
+            Self::Unavailable => (
+                "Failed to check API key".to_owned(),
+                serde_json::json!({ "code": "INTERNAL_SERVER_ERROR" }),
+            ),
+        };
+
+        serde_json::json!({
+            "errors": [{ "message": message, "extensions": extensions }],
+        })
+    }
+}
+
+#[derive(Debug)]
+struct CachedKey {
+    key: Arc<Key>,
+    fetched: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    requests: i64,
+    errors: i64,
+    buckets: Vec<i64>,
+    max_ms: i32,
+}
+
+impl Usage {
+    fn merge(&mut self, other: Self) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.max_ms = self.max_ms.max(other.max_ms);
+
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+
+        for (a, b) in self.buckets.iter_mut().zip(other.buckets) {
+            *a += b;
+        }
+    }
+}
+
+type UsageKey = (Uuid, NaiveDate, String);
+
+/// API key lookups, rate limiter state and buffered usage statistics
+#[derive(Debug)]
+pub struct ApiKeys {
+    args: Args,
+    keys: DashMap<String, CachedKey>,
+    misses: DashMap<String, Instant>,
+    limiter: RateLimiter<Uuid>,
+    usage: Mutex<HashMap<UsageKey, Usage>>,
+}
+
+impl ApiKeys {
+    /// Construct a new API key store
+    #[must_use]
+    pub fn new(args: Args) -> Self {
+        Self {
+            args,
+            keys: DashMap::default(),
+            misses: DashMap::default(),
+            limiter: RateLimiter::default(),
+            usage: Mutex::default(),
+        }
+    }
+
+    async fn lookup(&self, db: &Arc<Pool>, key: &str) -> Result<Option<Arc<Key>>> {
+        let ttl = StdDuration::from_secs(self.args.api_key_cache_secs);
+
+        if let Some(cached) = self.keys.get(key) {
+            if cached.fetched.elapsed() < ttl {
+                return Ok(Some(cached.key.clone()));
+            }
+        }
+
+        if let Some(missed) = self.misses.get(key) {
+            if missed.elapsed() < MISS_TTL {
+                return Ok(None);
+            }
+        }
+
+        // Diesel blocks, so query from a blocking thread to keep the request
+        // workers free
+        let found = tokio::task::spawn_blocking({
+            let db = Arc::clone(db);
+            let key = key.to_owned();
+
+            move || {
+                let conn = db.get().context("Failed to connect to the database")?;
+
+                api_keys::get_active(&conn, &key)
+            }
+        })
+        .await
+        .context("API key lookup task failed")??
+        .map(|k| {
+            Arc::new(Key {
+                id: k.id,
+                trusted: k.trusted,
+                requests_per_minute: k.requests_per_minute.into(),
+                burst: k.burst.into(),
+            })
+        });
+
+        // A key revoked since it was cached is dropped here
+        match found {
+            Some(ref k) => {
+                self.misses.remove(key);
+                self.keys.insert(key.to_owned(), CachedKey {
+                    key: k.clone(),
+                    fetched: Instant::now(),
+                });
+            },
+            None => {
+                self.keys.remove(key);
+                self.remember_miss(key);
+            },
+        }
+
+        Ok(found)
+    }
+
+    /// Remember an unknown key for [`MISS_TTL`], unless the cache of unknown
+    /// keys is full, so arbitrary bearer tokens cannot grow it without bound
+    fn remember_miss(&self, key: &str) {
+        if self.misses.len() >= MAX_CACHED_MISSES {
+            self.misses.retain(|_, m| m.elapsed() < MISS_TTL);
+
+            if self.misses.len() >= MAX_CACHED_MISSES {
+                return;
+            }
+        }
+
+        self.misses.insert(key.to_owned(), Instant::now());
+    }
+
+    /// Authenticate a request by its API key, if any, and charge it against
+    /// the key's rate limit
+    ///
+    /// # Errors
+    /// This function fails if the request should be denied.
+    pub async fn authenticate(
+        &self,
+        db: &Arc<Pool>,
+        api_key: Option<&str>,
+    ) -> Result<Option<Arc<Key>>, Denial> {
+        let api_key = match api_key {
+            Some(k) => k,
+            None if self.args.require_api_key => return Err(Denial::Missing),
+            None => return Ok(None),
+        };
+
+        let key = self
+            .lookup(db, api_key)
+            .await
+            .map_err(|e| {
+                error!("Failed to look up API key: {:?}", e);
+                Denial::Unavailable
+            })?
+            .ok_or(Denial::Invalid)?;
+
+        self.limiter
+            .take(key.id, key.requests_per_minute, key.burst)
+            .map_err(|retry_after| Denial::RateLimited { retry_after })?;
+
+        Ok(Some(key))
+    }
+
+    /// Record the outcome of a request made with the given key
+    pub async fn record(
+        &self,
+        key: &Key,
+        operation_name: Option<&str>,
+        latency: StdDuration,
+        is_error: bool,
+    ) {
+        let ms = latency.as_millis().try_into().unwrap_or(i32::MAX);
+        let bucket = api_keys::LATENCY_BUCKETS_MS
+            .iter()
+            .position(|b| ms <= *b)
+            .unwrap_or(api_keys::LATENCY_BUCKETS_MS.len());
+
+        let mut usage = self.usage.lock().await;
+        let entry = usage
+            .entry((
+                key.id,
+                Utc::now().date().naive_utc(),
+                operation_name.unwrap_or_default().to_owned(),
+            ))
+            .or_insert_with(|| Usage {
+                buckets: vec![0; api_keys::LATENCY_BUCKETS_MS.len() + 1],
+                ..Usage::default()
+            });
+
+        entry.requests += 1;
+        entry.errors += i64::from(is_error);
+        entry.buckets[bucket] += 1;
+        entry.max_ms = entry.max_ms.max(ms);
+    }
+
+    /// Write buffered usage to the database, removing each entry from `usage`
+    /// once it has been written
+    fn write_usage(db: &Pool, usage: &mut HashMap<UsageKey, Usage>) -> Result<()> {
+        let conn = db.get().context("Failed to connect to the database")?;
+        let keys: Vec<_> = usage.keys().cloned().collect();
+
+        for key in keys {
+            let (api_key_id, day, ref operation_name) = key;
+            let entry = &usage[&key];
+
+            api_keys::record_usage(&conn, &GraphqlUsage {
+                api_key_id,
+                day,
+                operation_name: operation_name.into(),
+                request_count: entry.requests,
+                error_count: entry.errors,
+                latency_buckets: entry.buckets.clone(),
+                latency_max_ms: entry.max_ms,
+                latency_p50_ms: 0,
+                latency_p95_ms: 0,
+                latency_p99_ms: 0,
+            })?;
+
+            usage.remove(&key);
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&self, db: &Arc<Pool>) -> Result<()> {
+        let unwritten = std::mem::take(&mut *self.usage.lock().await);
+
+        if unwritten.is_empty() {
+            return Ok(());
+        }
+
+        // Diesel blocks, so write from a blocking thread to keep the request
+        // workers free
+        let db = Arc::clone(db);
+        let (res, unwritten) = tokio::task::spawn_blocking(move || {
+            let mut unwritten = unwritten;
+            let res = Self::write_usage(&db, &mut unwritten);
+
+            (res, unwritten)
+        })
+        .await
+        .expect("Blocking task panicked");
+
+        // Put back anything left over from a failed write so it is retried on
+        // the next flush, merging it with usage recorded in the meantime
+        if !unwritten.is_empty() {
+            let mut usage = self.usage.lock().await;
+
+            for (key, entry) in unwritten {
+                usage.entry(key).or_default().merge(entry);
+            }
+        }
+
+        res
+    }
+
+    /// Periodically write buffered usage statistics to the database and
+    /// evict expired API key lookups
+    pub async fn run_flush(self: Arc<Self>, db: Arc<Pool>) {
+        let ttl = StdDuration::from_secs(self.args.api_key_cache_secs);
+        let mut interval =
+            tokio::time::interval(StdDuration::from_secs(self.args.usage_flush_secs));
+
+        loop {
+            interval.tick().await;
+
+            self.keys.retain(|_, c| c.fetched.elapsed() < ttl);
+            self.misses.retain(|_, m| m.elapsed() < MISS_TTL);
+
+            if let Err(e) = self.flush(&db).await {
+                error!("Failed to write GraphQL usage: {:?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Administrative tool for issuing and revoking GraphQL API keys
+
+#![deny(
+    clippy::disallowed_method,
+    clippy::suspicious,
+    clippy::style,
+    missing_debug_implementations,
+    missing_copy_implementations
+)]
+#![warn(clippy::pedantic, clippy::cargo, missing_docs)]
+
+use indexer_core::{
+    clap,
+    clap::Parser,
+    db,
+    db::{models::ApiKey, queries::api_keys},
+    prelude::*,
+    uuid::Uuid,
+};
+
+#[derive(Debug, Parser)]
+struct Opts {
+    #[clap(flatten)]
+    db: db::ConnectArgs,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Issue a new API key and print it
+    Issue {
+        /// Name of the key holder
+        name: String,
+
+        /// Number of requests per minute allowed for the key
+        #[clap(long, default_value_t = 600)]
+        requests_per_minute: i32,
+
+        /// Number of requests the key may make in a burst above its rate limit
+        #[clap(long, default_value_t = 60)]
+        burst: i32,
+
+        /// Exempt requests made with the key from query depth and cost limits
+        #[clap(long)]
+        trusted: bool,
+    },
+
+    /// Revoke an API key by its ID
+    Revoke {
+        /// The ID of the key to revoke
+        id: Uuid,
+    },
+
+    /// List all issued API keys
+    List,
+}
+
+fn print_key(key: &ApiKey) {
+    println!(
+        "{}\t{}\t{}/min\tburst {}\t{}\tcreated {}\t{}",
+        key.id,
+        key.name,
+        key.requests_per_minute,
+        key.burst,
+        if key.trusted { "trusted" } else { "untrusted" },
+        key.created_at,
+        key.revoked_at
+            .map_or_else(|| "active".to_owned(), |t| format!("revoked {}", t)),
+    );
+}
+
+fn main() {
+    indexer_core::run(|| {
+        let Opts { db, command } = Opts::parse();
+
+        let (db, _) =
+            db::connect(db, db::ConnectMode::Write).context("Failed to connect to Postgres")?;
+        let conn = db.get().context("Failed to connect to the database")?;
+
+        match command {
+            Command::Issue {
+                name,
+                requests_per_minute,
+                burst,
+                trusted,
+            } => {
+                ensure!(
+                    requests_per_minute >= 0 && burst >= 1,
+                    "Rate limit must be non-negative and burst must be at least 1"
+                );
+
+                let (row, key) =
+                    api_keys::issue(&conn, &name, requests_per_minute, burst, trusted)?;
+
+                print_key(&row);
+                println!("{}", key);
+                eprintln!("Store this key now, it cannot be displayed again.");
+            },
+            Command::Revoke { id } => {
+                if !api_keys::revoke(&conn, id)? {
+                    bail!("No active API key with ID {}", id);
+                }
+
+                info!("Revoked API key {}", id);
+            },
+            Command::List => {
+                for key in api_keys::list(&conn)? {
+                    print_key(&key);
+                }
+            },
+        }
+
+        Ok(())
+    });
+}
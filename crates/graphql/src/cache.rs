@@ -0,0 +1,805 @@
+//! Shared cache for the results of expensive aggregate resolvers, with
+//! per-resolver TTLs, request coalescing and tag-based invalidation
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use indexer_core::{
+    clap,
+    db::{
+        tables::{metadata_collection_keys, metadata_creators},
+        Pool,
+    },
+    hash::{DashMap, HashSet},
+    prelude::*,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::{broadcast::error::RecvError, Mutex, OnceCell};
+
+use crate::listener;
+
+/// Prefix of all keys written to a Redis cache backend
+const REDIS_PREFIX: &str = "holaplex:graphql:cache:";
+
+/// Number of seconds a Redis invalidation tag outlives its last use
+const REDIS_TAG_TTL_SECS: usize = 24 * 60 * 60;
+
+/// Number of invalidated tags remembered before the record is reset
+const MAX_INVALIDATED_TAGS: usize = 10_000;
+
+/// Options for the resolver cache
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Args {
+    /// URL of a Redis-compatible server to share cached results through,
+    /// rather than caching in memory
+    #[clap(long, env)]
+    cache_redis_url: Option<String>,
+
+    /// Maximum number of results held by the in-memory cache
+    #[clap(long, env, default_value_t = 10_000)]
+    cache_capacity: usize,
+
+    /// Number of seconds featured collection lists are cached for
+    #[clap(long, env, default_value_t = 300)]
+    cache_ttl_featured_collections: u64,
+
+    /// Number of seconds NFT statistics are cached for
+    #[clap(long, env, default_value_t = 60)]
+    cache_ttl_nfts_stats: u64,
+
+    /// Number of seconds profile statistics are cached for
+    #[clap(long, env, default_value_t = 300)]
+    cache_ttl_profiles_stats: u64,
+
+    /// Number of seconds price charts are cached for
+    #[clap(long, env, default_value_t = 120)]
+    cache_ttl_charts: u64,
+
+    /// Price chart date ranges are widened to multiples of this many seconds,
+    /// so requests for nearly the same range share a cached result
+    #[clap(long, env, default_value_t = 300)]
+    cache_chart_bucket_secs: u64,
+
+    /// Featured collection date ranges are rounded to multiples of this many
+    /// seconds, so requests for nearly the same range share a cached result
+    #[clap(long, env, default_value_t = 300)]
+    cache_featured_collections_bucket_secs: u64,
+
+    /// Number of seconds block times fetched over RPC are cached for
+    #[clap(long, env, default_value_t = 86_400)]
+    cache_ttl_block_times: u64,
+}
+
+/// A resolver whose results are cached
+#[derive(Debug, Clone, Copy)]
+pub enum Resolver {
+    /// `collectionsFeaturedByVolume` and `collectionsFeaturedByMarketCap`
+    FeaturedCollections,
+    /// Fields of `NftsStats`
+    NftsStats,
+    /// Fields of `ProfilesStats`
+    ProfilesStats,
+    /// Fields of `PriceChart`
+    Charts,
+    /// RPC fallback for block times not yet indexed
+    BlockTimes,
+}
+
+/// A key used to invalidate cached results
+#[derive(Debug, Clone)]
+pub enum Tag {
+    /// Results derived from activity on an auction house
+    AuctionHouse(String),
+    /// Results derived from activity in a collection, keyed by the mint
+    /// address of the collection NFT
+    Collection(String),
+    /// Results derived from activity on NFTs with this verified creator
+    Creator(String),
+}
+
+impl Tag {
+    fn key(&self) -> String {
+        match self {
+            Self::AuctionHouse(a) => format!("tag:auction_house:{}", a),
+            Self::Collection(c) => format!("tag:collection:{}", c),
+            Self::Creator(c) => format!("tag:creator:{}", c),
+        }
+    }
+}
+
+/// Storage for cached results
+#[async_trait]
+pub trait Backend: fmt::Debug + Send + Sync {
+    /// Get the value stored under a key, if it has not expired
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Store a value under a key for the given duration, registering it
+    /// under each of the given tags
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: StdDuration, tags: &[String])
+    -> Result<()>;
+
+    /// Remove all values registered under a tag
+    async fn invalidate(&self, tag: &str) -> Result<()>;
+}
+
+/// In-process cache backend
+#[derive(Debug)]
+pub struct Memory {
+    capacity: usize,
+    entries: DashMap<String, (Vec<u8>, Instant)>,
+    tags: DashMap<String, HashSet<String>>,
+}
+
+impl Memory {
+    /// Construct an empty in-memory backend holding at most `capacity`
+    /// values
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: DashMap::default(),
+            tags: DashMap::default(),
+        }
+    }
+
+    fn purge_expired(&self) {
+        let now = Instant::now();
+
+        self.entries.retain(|_, (_, expires)| *expires > now);
+        self.tags.retain(|_, keys| {
+            keys.retain(|k| self.entries.contains_key(k));
+            !keys.is_empty()
+        });
+    }
+}
+
+#[async_trait]
+impl Backend for Memory {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .entries
+            .get(key)
+            .filter(|e| e.1 > Instant::now())
+            .map(|e| e.0.clone()))
+    }
+
+    async fn set(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: StdDuration,
+        tags: &[String],
+    ) -> Result<()> {
+        if self.entries.len() >= self.capacity {
+            self.purge_expired();
+
+            if self.entries.len() >= self.capacity {
+                debug!("Resolver cache full, not caching {:?}", key);
+                return Ok(());
+            }
+        }
+
+        self.entries
+            .insert(key.to_owned(), (value, Instant::now() + ttl));
+
+        for tag in tags {
+            self.tags
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.to_owned());
+        }
+
+        Ok(())
+    }
+
+    async fn invalidate(&self, tag: &str) -> Result<()> {
+        if let Some((_, keys)) = self.tags.remove(tag) {
+            for key in keys {
+                self.entries.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cache backend storing values in a Redis-compatible server
+pub struct Redis {
+    client: redis::Client,
+    conn: OnceCell<redis::aio::ConnectionManager>,
+}
+
+impl fmt::Debug for Redis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redis").finish_non_exhaustive()
+    }
+}
+
+impl Redis {
+    /// Construct a backend for the server at `url`, connecting lazily
+    ///
+    /// # Errors
+    /// This function fails if `url` is not a valid Redis URL.
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url).context("Invalid Redis URL")?,
+            conn: OnceCell::new(),
+        })
+    }
+
+    async fn conn(&self) -> Result<redis::aio::ConnectionManager> {
+        self.conn
+            .get_or_try_init(|| redis::aio::ConnectionManager::new(self.client.clone()))
+            .await
+            .map(Clone::clone)
+            .context("Failed to connect to Redis")
+    }
+}
+
+#[async_trait]
+impl Backend for Redis {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        redis::cmd("GET")
+            .arg(format!("{}{}", REDIS_PREFIX, key))
+            .query_async(&mut self.conn().await?)
+            .await
+            .context("Redis GET failed")
+    }
+
+    async fn set(
+        &self,
+        key: &str,
+        value: Vec<u8>,
+        ttl: StdDuration,
+        tags: &[String],
+    ) -> Result<()> {
+        let key = format!("{}{}", REDIS_PREFIX, key);
+        let mut pipe = redis::pipe();
+
+        pipe.cmd("SET")
+            .arg(&key)
+            .arg(value)
+            .arg("PX")
+            .arg(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX))
+            .ignore();
+
+        for tag in tags {
+            let tag = format!("{}{}", REDIS_PREFIX, tag);
+
+            pipe.cmd("SADD").arg(&tag).arg(&key).ignore();
+            pipe.cmd("EXPIRE")
+                .arg(&tag)
+                .arg(REDIS_TAG_TTL_SECS)
+                .ignore();
+        }
+
+        pipe.query_async(&mut self.conn().await?)
+            .await
+            .context("Redis SET failed")
+    }
+
+    async fn invalidate(&self, tag: &str) -> Result<()> {
+        let tag = format!("{}{}", REDIS_PREFIX, tag);
+        let mut conn = self.conn().await?;
+
+        let keys: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(&tag)
+            .query_async(&mut conn)
+            .await
+            .context("Redis SMEMBERS failed")?;
+
+        redis::cmd("DEL")
+            .arg(keys)
+            .arg(&tag)
+            .query_async(&mut conn)
+            .await
+            .context("Redis DEL failed")
+    }
+}
+
+/// Cache for the results of expensive resolvers
+#[derive(Debug)]
+pub struct Cache {
+    args: Args,
+    backend: Arc<dyn Backend>,
+    inflight: DashMap<String, Arc<Mutex<()>>>,
+    /// Counter advanced by each invalidation
+    generation: AtomicU64,
+    /// The generation at which each tag was last invalidated
+    invalidated: DashMap<String, u64>,
+    /// The generation through which entries of `invalidated` may have been
+    /// discarded
+    forgotten: AtomicU64,
+}
+
+impl Cache {
+    /// Construct a cache using the backend selected by `args`
+    ///
+    /// # Errors
+    /// This function fails if the Redis backend cannot be configured.
+    pub fn new(args: Args) -> Result<Self> {
+        let backend: Arc<dyn Backend> = match args.cache_redis_url {
+            Some(ref url) => Arc::new(Redis::new(url)?),
+            None => Arc::new(Memory::new(args.cache_capacity)),
+        };
+
+        Ok(Self::with_backend(args, backend))
+    }
+
+    /// Construct a cache using the given backend
+    #[must_use]
+    pub fn with_backend(args: Args, backend: Arc<dyn Backend>) -> Self {
+        Self {
+            args,
+            backend,
+            inflight: DashMap::default(),
+            generation: AtomicU64::new(0),
+            invalidated: DashMap::default(),
+            forgotten: AtomicU64::new(0),
+        }
+    }
+
+    fn ttl(&self, resolver: Resolver) -> StdDuration {
+        StdDuration::from_secs(match resolver {
+            Resolver::FeaturedCollections => self.args.cache_ttl_featured_collections,
+            Resolver::NftsStats => self.args.cache_ttl_nfts_stats,
+            Resolver::ProfilesStats => self.args.cache_ttl_profiles_stats,
+            Resolver::Charts => self.args.cache_ttl_charts,
+            Resolver::BlockTimes => self.args.cache_ttl_block_times,
+        })
+    }
+
+    /// Widen a price chart's date range to whole buckets, so requests for
+    /// nearly the same range, e.g. ending at the current time, share a cached
+    /// result.  Points outside the requested range must be filtered out of
+    /// the result.
+    #[must_use]
+    pub fn chart_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        bucket_range(start, end, self.args.cache_chart_bucket_secs)
+    }
+
+    /// Round a featured collection ranking's date range to whole buckets, so
+    /// requests for nearly the same range share a cached result
+    ///
+    /// Rankings aggregate over the whole range and cannot be narrowed after
+    /// loading, so they are computed over the rounded range.
+    #[must_use]
+    pub fn featured_collections_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        bucket_range(start, end, self.args.cache_featured_collections_bucket_secs)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.backend.get(key).await {
+            Ok(Some(bytes)) => serde_json::from_slice(&bytes)
+                .map_err(|e| warn!("Failed to decode cached {:?}: {}", key, e))
+                .ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Failed to read resolver cache: {:?}", e);
+                None
+            },
+        }
+    }
+
+    /// Get the cached result of a resolver for the given arguments, or load
+    /// and cache it
+    ///
+    /// Concurrent calls for the same resolver and arguments wait for a single
+    /// load.  `tags` lists the invalidation tags a loaded result should be
+    /// registered under.  Failed loads are not cached, nor are results with a
+    /// tag invalidated while they were loading, since they may predate the
+    /// change that caused the invalidation.
+    ///
+    /// # Errors
+    /// This function fails if `load` fails.
+    pub async fn get_or_load<T, F, Fut>(
+        &self,
+        resolver: Resolver,
+        args: impl fmt::Debug,
+        tags: impl FnOnce(&T) -> Vec<Tag>,
+        load: F,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let key = format!("{:?}:{:?}", resolver, args);
+
+        if let Some(value) = self.get(&key).await {
+            return Ok(value);
+        }
+
+        let lock = self.inflight.entry(key.clone()).or_default().clone();
+        let _guard = lock.lock().await;
+
+        // Another request may have loaded the value while this one waited
+        if let Some(value) = self.get(&key).await {
+            return Ok(value);
+        }
+
+        let started = self.generation.load(Ordering::SeqCst);
+        let result = load().await;
+
+        if let Ok(ref value) = result {
+            let tags: Vec<_> = tags(value).iter().map(Tag::key).collect();
+
+            if self.invalidated_since(started, &tags) {
+                debug!("Not caching {:?}, invalidated while loading", key);
+            } else {
+                self.store(&key, resolver, value, &tags).await;
+            }
+        }
+
+        // Waiters hold their own handle to the lock and will find the value
+        // in the cache
+        self.inflight.remove(&key);
+
+        result
+    }
+
+    /// Whether any of `tags` has been invalidated since `generation`
+    fn invalidated_since(&self, generation: u64, tags: &[String]) -> bool {
+        // Invalidations of discarded tags cannot be ruled out
+        generation < self.forgotten.load(Ordering::SeqCst)
+            || tags
+                .iter()
+                .any(|t| self.invalidated.get(t).map_or(false, |g| *g > generation))
+    }
+
+    async fn store<T: Serialize>(&self, key: &str, resolver: Resolver, value: &T, tags: &[String]) {
+        match serde_json::to_vec(value) {
+            Ok(bytes) => {
+                if let Err(e) = self.backend.set(key, bytes, self.ttl(resolver), tags).await {
+                    warn!("Failed to write resolver cache: {:?}", e);
+                }
+            },
+            Err(e) => warn!("Failed to encode {:?} for caching: {}", key, e),
+        }
+    }
+
+    /// Remove cached results derived from activity on an auction house
+    pub async fn invalidate_auction_house(&self, address: &str) {
+        self.invalidate(Tag::AuctionHouse(address.to_owned())).await;
+    }
+
+    /// Remove cached results derived from activity in a collection
+    pub async fn invalidate_collection(&self, address: &str) {
+        self.invalidate(Tag::Collection(address.to_owned())).await;
+    }
+
+    /// Remove cached results derived from activity on a creator's NFTs
+    pub async fn invalidate_creator(&self, address: &str) {
+        self.invalidate(Tag::Creator(address.to_owned())).await;
+    }
+
+    async fn invalidate(&self, tag: Tag) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if self.invalidated.len() >= MAX_INVALIDATED_TAGS {
+            self.forgotten.store(generation, Ordering::SeqCst);
+            self.invalidated.clear();
+        }
+
+        self.invalidated.insert(tag.key(), generation);
+
+        if let Err(e) = self.backend.invalidate(&tag.key()).await {
+            warn!("Failed to invalidate {:?}: {:?}", tag, e);
+        }
+    }
+}
+
+/// Round `start` down and `end` up to multiples of `secs` seconds
+fn bucket_range(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    secs: u64,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let secs = i64::try_from(secs.max(1)).unwrap_or(i64::MAX);
+    let floor = |t: i64| t - t.rem_euclid(secs);
+    let end = end.timestamp() + i64::from(end.timestamp_subsec_nanos() > 0);
+
+    (
+        Utc.timestamp(floor(start.timestamp()), 0),
+        Utc.timestamp(floor(end.saturating_add(secs - 1)), 0),
+    )
+}
+
+/// The columns of a `listings`, `offers` or `purchases` notification, or of a
+/// `listings_updated` or `offers_updated` notification, used for
+/// invalidation
+#[derive(Debug, serde::Deserialize)]
+struct TradeRow {
+    auction_house: String,
+    metadata: String,
+}
+
+/// Invalidate cached results as trades are indexed, canceled or sold
+pub async fn run_invalidation(cache: Arc<Cache>, db: Arc<Pool>, notifications: listener::Sender) {
+    let mut rx = notifications.subscribe();
+
+    loop {
+        let notification = match rx.recv().await {
+            Ok(n) => n,
+            Err(RecvError::Lagged(n)) => {
+                warn!("Cache invalidation missed {} notification(s)", n);
+                continue;
+            },
+            Err(RecvError::Closed) => break,
+        };
+
+        if !matches!(
+            notification.channel.as_str(),
+            "listings" | "offers" | "purchases" | "listings_updated" | "offers_updated"
+        ) {
+            continue;
+        }
+
+        let row: TradeRow = match serde_json::from_str(&notification.payload) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!(
+                    "Failed to parse {:?} notification: {}",
+                    notification.channel, e
+                );
+                continue;
+            },
+        };
+
+        // Results scoped to a collection or creator are tagged with it
+        // rather than the auction house, so this only drops unscoped results
+        cache.invalidate_auction_house(&row.auction_house).await;
+
+        let db = Arc::clone(&db);
+        let scopes = tokio::task::spawn_blocking(move || trade_scopes(&db, &row.metadata))
+            .await
+            .expect("Blocking task panicked");
+
+        match scopes {
+            Ok((collections, creators)) => {
+                for collection in collections {
+                    cache.invalidate_collection(&collection).await;
+                }
+
+                for creator in creators {
+                    cache.invalidate_creator(&creator).await;
+                }
+            },
+            Err(e) => warn!("Failed to invalidate collections and creators: {:?}", e),
+        }
+    }
+}
+
+/// Load the verified collections and verified creators of a traded NFT
+fn trade_scopes(db: &Pool, metadata: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let conn = db.get().context("Failed to connect to the database")?;
+
+    let collections = metadata_collection_keys::table
+        .filter(metadata_collection_keys::metadata_address.eq(metadata))
+        .filter(metadata_collection_keys::verified)
+        .select(metadata_collection_keys::collection_address)
+        .load(&conn)
+        .context("Failed to load NFT collections")?;
+
+    let creators = metadata_creators::table
+        .filter(metadata_creators::metadata_address.eq(metadata))
+        .filter(metadata_creators::verified)
+        .select(metadata_creators::creator_address)
+        .load(&conn)
+        .context("Failed to load NFT creators")?;
+
+    Ok((collections, creators))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use indexer_core::db::ConnectionManager;
+
+    use super::*;
+
+    /// Backend storing values in memory and recording the tags invalidated
+    #[derive(Debug)]
+    struct Fake {
+        memory: Memory,
+        invalidated: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Backend for Fake {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.memory.get(key).await
+        }
+
+        async fn set(
+            &self,
+            key: &str,
+            value: Vec<u8>,
+            ttl: StdDuration,
+            tags: &[String],
+        ) -> Result<()> {
+            self.memory.set(key, value, ttl, tags).await
+        }
+
+        async fn invalidate(&self, tag: &str) -> Result<()> {
+            self.invalidated.lock().unwrap().push(tag.to_owned());
+            self.memory.invalidate(tag).await
+        }
+    }
+
+    fn cache() -> (Arc<Cache>, Arc<Fake>) {
+        let fake = Arc::new(Fake {
+            memory: Memory::new(16),
+            invalidated: std::sync::Mutex::default(),
+        });
+        let cache = Cache::with_backend(<Args as clap::Parser>::parse_from(["test"]), fake.clone());
+
+        (Arc::new(cache), fake)
+    }
+
+    /// Load a value tagged with an auction house through the cache, counting
+    /// the loads that were not served from the cache
+    async fn load(cache: &Cache, loads: &AtomicUsize) -> Result<u64> {
+        cache
+            .get_or_load(
+                Resolver::NftsStats,
+                "args",
+                |_| vec![Tag::AuctionHouse("ah".to_owned())],
+                || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(StdDuration::from_millis(20)).await;
+
+                    Ok(42)
+                },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn coalesces_concurrent_loads() {
+        let (cache, _) = cache();
+        let loads = AtomicUsize::new(0);
+
+        let results = futures_util::future::join_all((0..8).map(|_| load(&cache, &loads))).await;
+
+        assert!(results.into_iter().all(|r| r.unwrap() == 42));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failed_loads() {
+        let (cache, _) = cache();
+        let loads = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            let res = cache
+                .get_or_load(
+                    Resolver::NftsStats,
+                    "args",
+                    |_: &u64| vec![],
+                    || async {
+                        loads.fetch_add(1, Ordering::SeqCst);
+
+                        Err(anyhow!("load failed"))
+                    },
+                )
+                .await;
+
+            assert!(res.is_err());
+        }
+
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidates_tagged_results() {
+        let (cache, _) = cache();
+        let loads = AtomicUsize::new(0);
+
+        load(&cache, &loads).await.unwrap();
+        load(&cache, &loads).await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        cache.invalidate_auction_house("other").await;
+        load(&cache, &loads).await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        cache.invalidate_auction_house("ah").await;
+        load(&cache, &loads).await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn skips_results_invalidated_while_loading() {
+        let (cache, _) = cache();
+        let loads = AtomicUsize::new(0);
+
+        let (res, ()) = tokio::join!(load(&cache, &loads), async {
+            tokio::time::sleep(StdDuration::from_millis(5)).await;
+            cache.invalidate_auction_house("ah").await;
+        });
+        assert_eq!(res.unwrap(), 42);
+
+        load(&cache, &loads).await.unwrap();
+        load(&cache, &loads).await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidates_on_trade_updates() {
+        let (cache, fake) = cache();
+        let notifications = listener::channel();
+        // Collection lookups fail quickly against an unreachable database,
+        // which only skips collection invalidation
+        let db = Pool::builder()
+            .connection_timeout(StdDuration::from_millis(10))
+            .build_unchecked(ConnectionManager::new("postgres://127.0.0.1:1/none"));
+
+        tokio::spawn(run_invalidation(cache, Arc::new(db), notifications.clone()));
+        tokio::task::yield_now().await;
+
+        for (channel, auction_house) in [
+            ("feed_events", "feed"),
+            ("listings_updated", "canceled"),
+            ("offers_updated", "sold"),
+        ] {
+            notifications
+                .send(listener::Notification {
+                    channel: channel.to_owned(),
+                    payload: serde_json::json!({
+                        "auction_house": auction_house,
+                        "metadata": "nft",
+                    })
+                    .to_string(),
+                })
+                .unwrap();
+        }
+
+        tokio::time::timeout(StdDuration::from_secs(5), async {
+            while fake.invalidated.lock().unwrap().len() < 2 {
+                tokio::time::sleep(StdDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*fake.invalidated.lock().unwrap(), [
+            "tag:auction_house:canceled",
+            "tag:auction_house:sold",
+        ]);
+    }
+
+    #[test]
+    fn buckets_chart_ranges() {
+        let start = Utc.ymd(2022, 8, 1).and_hms(10, 7, 30);
+        let end = Utc.ymd(2022, 8, 2).and_hms_milli(10, 7, 30, 1);
+
+        assert_eq!(
+            bucket_range(start, end, 300),
+            (
+                Utc.ymd(2022, 8, 1).and_hms(10, 5, 0),
+                Utc.ymd(2022, 8, 2).and_hms(10, 10, 0),
+            )
+        );
+
+        let aligned = Utc.ymd(2022, 8, 2).and_hms(10, 10, 0);
+        assert_eq!(bucket_range(aligned, aligned, 300), (aligned, aligned));
+    }
+}
@@ -0,0 +1,175 @@
+//! `Cache-Control` headers for query responses, derived from per-field cache
+//! hints
+
+use graphql_parser::query::{
+    parse_query, Document, FragmentDefinition, OperationDefinition, Selection, SelectionSet,
+};
+use indexer_core::clap;
+
+use crate::cost;
+
+/// Maximum age in seconds of responses to root query fields
+const ROOT_HINTS: &[(&str, u32)] = &[
+    ("nftsStats", 300),
+    ("profilesStats", 300),
+    ("marketplaces", 300),
+    ("marketplace", 300),
+    ("storefronts", 300),
+    ("storefront", 300),
+    ("collectionsFeaturedByMarketCap", 300),
+    ("collectionsFeaturedByVolume", 300),
+    ("denylist", 60),
+    ("featuredListings", 60),
+    ("nftCounts", 60),
+    ("creator", 60),
+    ("nft", 30),
+    ("nftByMintAddress", 30),
+    ("nftsByMintAddress", 30),
+    ("viewer", 0),
+];
+
+/// Maximum age in seconds of responses selecting these fields at any depth,
+/// for data which must be fresh
+const FIELD_HINTS: &[(&str, u32)] = &[
+    ("offers", 0),
+    ("offersConnection", 0),
+    ("listings", 0),
+    ("listingsConnection", 0),
+];
+
+/// Options for response caching
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Args {
+    /// Maximum age in seconds of cached responses to root query fields with
+    /// no cache hint
+    #[clap(long, env, default_value_t = 0)]
+    default_cache_max_age: u32,
+}
+
+/// Caching properties of a GraphQL operation
+#[derive(Debug, Clone, Copy)]
+pub struct Hint {
+    /// Whether the operation is a query, and therefore safe to serve over GET
+    pub is_query: bool,
+    /// Maximum age in seconds of a response to the operation
+    pub max_age: u32,
+}
+
+impl Hint {
+    /// The `Cache-Control` header value for a response to the operation.
+    /// Responses to requests carrying credentials may differ per caller, so
+    /// they are marked `private` to keep them out of shared caches.
+    #[must_use]
+    pub fn header_value(self, credentialed: bool) -> String {
+        if self.is_query && self.max_age > 0 {
+            format!(
+                "{}, max-age={}",
+                if credentialed { "private" } else { "public" },
+                self.max_age
+            )
+        } else {
+            "no-store".into()
+        }
+    }
+}
+
+impl Args {
+    /// Compute the caching properties of the operation to be executed,
+    /// returning `None` if the query cannot be parsed or has no such
+    /// operation
+    #[must_use]
+    pub fn hint(&self, query: &str, operation_name: Option<&str>) -> Option<Hint> {
+        let doc: Document<String> = parse_query(query).ok()?;
+        let operation = cost::operation(&doc, operation_name)?;
+
+        let is_query = matches!(
+            operation,
+            OperationDefinition::Query(_) | OperationDefinition::SelectionSet(_)
+        );
+
+        let max_age = if is_query {
+            let fragments = cost::fragments(&doc);
+            let max_age = self.selection_set(
+                cost::selection_set(operation),
+                &fragments,
+                true,
+                &mut vec![],
+            );
+
+            // Only introspection fields were selected
+            if max_age == u32::MAX {
+                self.default_cache_max_age
+            } else {
+                max_age
+            }
+        } else {
+            0
+        };
+
+        Some(Hint { is_query, max_age })
+    }
+
+    fn selection_set<'a>(
+        &self,
+        set: &'a SelectionSet<'a, String>,
+        fragments: &[&'a FragmentDefinition<'a, String>],
+        root: bool,
+        spreads: &mut Vec<&'a str>,
+    ) -> u32 {
+        let mut max_age = u32::MAX;
+
+        for selection in &set.items {
+            let age = match selection {
+                Selection::Field(f) => {
+                    // Introspection fields carry no cache hint
+                    if f.name.starts_with("__") {
+                        continue;
+                    }
+
+                    let hint = if root {
+                        Some(
+                            ROOT_HINTS
+                                .iter()
+                                .find(|(n, _)| *n == f.name)
+                                .map_or(self.default_cache_max_age, |(_, a)| *a),
+                        )
+                    } else {
+                        FIELD_HINTS
+                            .iter()
+                            .find(|(n, _)| *n == f.name)
+                            .map(|(_, a)| *a)
+                    };
+
+                    let children = self.selection_set(&f.selection_set, fragments, false, spreads);
+
+                    hint.map_or(children, |h| h.min(children))
+                },
+                Selection::InlineFragment(f) => {
+                    self.selection_set(&f.selection_set, fragments, root, spreads)
+                },
+                Selection::FragmentSpread(s) => {
+                    let name = s.fragment_name.as_str();
+
+                    if spreads.contains(&name) {
+                        continue;
+                    }
+
+                    let fragment = match fragments.iter().find(|f| f.name == name) {
+                        Some(f) => *f,
+                        None => continue,
+                    };
+
+                    spreads.push(name);
+                    let age = self.selection_set(&fragment.selection_set, fragments, root, spreads);
+                    spreads.pop();
+
+                    age
+                },
+            };
+
+            max_age = max_age.min(age);
+        }
+
+        max_age
+    }
+}
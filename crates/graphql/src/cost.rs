@@ -0,0 +1,589 @@
+//! Static cost analysis for GraphQL queries, run before execution to reject
+//! queries that would be too expensive to resolve
+
+use graphql_parser::query::{
+    parse_query, Definition, Document, Field, FragmentDefinition, OperationDefinition, Selection,
+    SelectionSet, Value, VariableDefinition,
+};
+use indexer_core::{clap, prelude::*};
+use serde_json::Value as Json;
+
+/// Fields resolved with a call to an external API or an expensive query, and
+/// their cost relative to a plain field
+const FIELD_COSTS: &[(&str, u64)] = &[
+    ("profile", 10),
+    ("activities", 10),
+    ("charts", 10),
+    ("searchNfts", 5),
+    ("searchAll", 5),
+];
+
+/// List fields which take no size argument or were given none, assumed to
+/// hold [`Args::default_list_size`] items
+const UNBOUNDED_LISTS: &[&str] = &[
+    "activities",
+    "attributeGroups",
+    "attributes",
+    "auctionHouses",
+    "bidReceipts",
+    "bids",
+    "collectionWatchlist",
+    "configHistory",
+    "creators",
+    "facets",
+    "files",
+    "hiddenNfts",
+    "highlights",
+    "hits",
+    "holders",
+    "listingFloor",
+    "listingReceipts",
+    "listings",
+    "matches",
+    "nftWatchlist",
+    "nfts",
+    "nftsByMintAddress",
+    "offers",
+    "preview",
+    "profileNftOrder",
+    "purchaseReceipts",
+    "purchases",
+    "salesAverage",
+    "stats",
+    "storefronts",
+    "totalVolume",
+    "values",
+    "variants",
+    "wallets",
+];
+
+/// Arguments bounding the number of items a list field returns
+const SIZE_ARGS: &[&str] = &["limit", "first", "limitPerType"];
+
+/// List arguments naming each item a list field returns
+const KEY_LIST_ARGS: &[&str] = &["addresses"];
+
+/// Limits applied to incoming GraphQL queries
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Args {
+    /// Maximum selection depth of a GraphQL query
+    #[clap(long, env, default_value_t = 10)]
+    max_query_depth: usize,
+
+    /// Maximum estimated cost of a GraphQL query
+    #[clap(long, env, default_value_t = 50_000)]
+    max_query_cost: u64,
+
+    /// Assumed size of list fields with no limit argument when estimating
+    /// query cost
+    #[clap(long, env, default_value_t = 10)]
+    default_list_size: u64,
+
+    /// Assumed size of list fields whose size argument is null, negative, or
+    /// refers to a variable with no value when estimating query cost
+    #[clap(long, env, default_value_t = 1000)]
+    max_list_size: u64,
+
+    /// API keys whose requests are exempt from query limits
+    #[clap(long, env, use_value_delimiter(true))]
+    trusted_api_keys: Vec<String>,
+}
+
+/// Reason a query was rejected
+#[derive(Debug, Clone, Copy)]
+pub enum Rejection {
+    /// The query could not be parsed
+    Invalid,
+    /// The query nests selections deeper than allowed
+    TooDeep {
+        /// The maximum depth allowed
+        max: usize,
+    },
+    /// The estimated cost of the query is over budget
+    TooExpensive {
+        /// The estimated cost of the query
+        cost: u64,
+        /// The maximum cost allowed
+        max: u64,
+    },
+}
+
+impl Rejection {
+    /// Render this rejection as a GraphQL error response body
+    #[must_use]
+    pub fn to_response(self) -> Json {
+        let (message, extensions) = match self {
+            Self::Invalid => (
+                "Failed to parse query".to_owned(),
+                serde_json::json!({ "code": "GRAPHQL_PARSE_FAILED" }),
+            ),
+            Self::TooDeep { max } => (
+                format!("Query exceeds the maximum depth of {}", max),
+                serde_json::json!({ "code": "QUERY_TOO_DEEP", "maxDepth": max }),
+            ),
+            Self::TooExpensive { cost, max } => (
+                format!(
+                    "Query has an estimated cost of {}, exceeding the maximum of {}",
+                    cost, max
+                ),
+                serde_json::json!({
+                    "code": "QUERY_TOO_EXPENSIVE",
+                    "cost": cost,
+                    "maxCost": max,
+                }),
+            ),
+        };
+
+        serde_json::json!({
+            "errors": [{ "message": message, "extensions": extensions }],
+        })
+    }
+}
+
+/// Find the operation to be executed in a query document
+pub(crate) fn operation<'a>(
+    doc: &'a Document<'a, String>,
+    operation_name: Option<&str>,
+) -> Option<&'a OperationDefinition<'a, String>> {
+    let mut operations = doc.definitions.iter().filter_map(|d| match d {
+        Definition::Operation(o) => Some(o),
+        Definition::Fragment(_) => None,
+    });
+
+    match operation_name {
+        Some(name) => operations.find(|o| {
+            let op_name = match o {
+                OperationDefinition::SelectionSet(_) => None,
+                OperationDefinition::Query(q) => q.name.as_deref(),
+                OperationDefinition::Mutation(m) => m.name.as_deref(),
+                OperationDefinition::Subscription(s) => s.name.as_deref(),
+            };
+
+            op_name == Some(name)
+        }),
+        None => operations.next(),
+    }
+}
+
+/// Get the top-level selections of an operation
+pub(crate) fn selection_set<'a>(
+    operation: &'a OperationDefinition<'a, String>,
+) -> &'a SelectionSet<'a, String> {
+    match operation {
+        OperationDefinition::SelectionSet(s) => s,
+        OperationDefinition::Query(q) => &q.selection_set,
+        OperationDefinition::Mutation(m) => &m.selection_set,
+        OperationDefinition::Subscription(s) => &s.selection_set,
+    }
+}
+
+/// Get the variable definitions of an operation
+fn variable_definitions<'a>(
+    operation: &'a OperationDefinition<'a, String>,
+) -> &'a [VariableDefinition<'a, String>] {
+    match operation {
+        OperationDefinition::SelectionSet(_) => &[],
+        OperationDefinition::Query(q) => &q.variable_definitions,
+        OperationDefinition::Mutation(m) => &m.variable_definitions,
+        OperationDefinition::Subscription(s) => &s.variable_definitions,
+    }
+}
+
+/// Collect the fragment definitions in a query document
+pub(crate) fn fragments<'a>(
+    doc: &'a Document<'a, String>,
+) -> Vec<&'a FragmentDefinition<'a, String>> {
+    doc.definitions
+        .iter()
+        .filter_map(|d| match d {
+            Definition::Fragment(f) => Some(f),
+            Definition::Operation(_) => None,
+        })
+        .collect()
+}
+
+struct Analysis<'a> {
+    args: &'a Args,
+    fragments: Vec<&'a FragmentDefinition<'a, String>>,
+    definitions: &'a [VariableDefinition<'a, String>],
+    variables: &'a Json,
+}
+
+impl Args {
+    /// Check if the given API key exempts a request from query limits
+    #[must_use]
+    pub fn is_trusted(&self, api_key: Option<&str>) -> bool {
+        api_key.map_or(false, |k| self.trusted_api_keys.iter().any(|t| t == k))
+    }
+
+    /// Estimate the cost of the operation to be executed, rejecting it if it
+    /// is too deep or too expensive
+    ///
+    /// # Errors
+    /// This function fails if the query cannot be parsed or exceeds the
+    /// configured limits.
+    pub fn check(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        variables: &Json,
+    ) -> Result<u64, Rejection> {
+        let doc: Document<String> = parse_query(query).map_err(|_| Rejection::Invalid)?;
+
+        // Let execution report a missing operation
+        let operation = match operation(&doc, operation_name) {
+            Some(o) => o,
+            None => return Ok(0),
+        };
+
+        let analysis = Analysis {
+            args: self,
+            fragments: fragments(&doc),
+            definitions: variable_definitions(operation),
+            variables,
+        };
+
+        let cost = analysis.selection_set(selection_set(operation), 0, &mut vec![])?;
+
+        if cost > self.max_query_cost {
+            return Err(Rejection::TooExpensive {
+                cost,
+                max: self.max_query_cost,
+            });
+        }
+
+        Ok(cost)
+    }
+}
+
+impl<'a> Analysis<'a> {
+    fn selection_set(
+        &self,
+        set: &'a SelectionSet<'a, String>,
+        depth: usize,
+        spreads: &mut Vec<&'a str>,
+    ) -> Result<u64, Rejection> {
+        let mut cost = 0_u64;
+
+        for selection in &set.items {
+            let item = match selection {
+                Selection::Field(f) => self.field(f, depth, spreads)?,
+                Selection::InlineFragment(f) => {
+                    self.selection_set(&f.selection_set, depth, spreads)?
+                },
+                Selection::FragmentSpread(s) => {
+                    let name = s.fragment_name.as_str();
+
+                    // Cyclic fragments are rejected during validation
+                    if spreads.contains(&name) {
+                        continue;
+                    }
+
+                    let fragment = match self.fragments.iter().find(|f| f.name == name) {
+                        Some(f) => *f,
+                        None => continue,
+                    };
+
+                    spreads.push(name);
+                    let cost = self.selection_set(&fragment.selection_set, depth, spreads);
+                    spreads.pop();
+
+                    cost?
+                },
+            };
+
+            cost = cost.saturating_add(item);
+        }
+
+        Ok(cost)
+    }
+
+    fn field(
+        &self,
+        field: &'a Field<'a, String>,
+        depth: usize,
+        spreads: &mut Vec<&'a str>,
+    ) -> Result<u64, Rejection> {
+        // Introspection is resolved from the schema without touching any
+        // data sources
+        if field.name.starts_with("__") {
+            return Ok(0);
+        }
+
+        let depth = depth + 1;
+
+        if depth > self.args.max_query_depth {
+            return Err(Rejection::TooDeep {
+                max: self.args.max_query_depth,
+            });
+        }
+
+        let children = self.selection_set(&field.selection_set, depth, spreads)?;
+        let cost = FIELD_COSTS
+            .iter()
+            .find(|(n, _)| *n == field.name)
+            .map_or(1, |(_, c)| *c);
+
+        Ok(cost.saturating_add(self.list_size(field).saturating_mul(children)))
+    }
+
+    /// Resolve an integer argument value, reading variables from the
+    /// request or, if a variable is not given, from its default value
+    fn int_value(&self, value: &Value<String>) -> Option<i64> {
+        match value {
+            Value::Int(i) => i.as_i64(),
+            Value::Variable(v) => match self.variables.get(v) {
+                Some(j) => j.as_i64(),
+                None => self
+                    .definitions
+                    .iter()
+                    .find(|d| d.name == *v)
+                    .and_then(|d| d.default_value.as_ref())
+                    .and_then(|d| self.int_value(d)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Resolve the length of a list argument value, reading variables as in
+    /// [`Self::int_value`]
+    fn list_len(&self, value: &Value<String>) -> Option<usize> {
+        match value {
+            Value::List(l) => Some(l.len()),
+            Value::Variable(v) => match self.variables.get(v) {
+                Some(j) => j.as_array().map(Vec::len),
+                None => self
+                    .definitions
+                    .iter()
+                    .find(|d| d.name == *v)
+                    .and_then(|d| d.default_value.as_ref())
+                    .and_then(|d| self.list_len(d)),
+            },
+            _ => None,
+        }
+    }
+
+    /// The number of items a field is expected to return
+    fn list_size(&self, field: &Field<String>) -> u64 {
+        let arg = |names: &[&str]| {
+            field
+                .arguments
+                .iter()
+                .find(|(name, _)| names.contains(&name.as_str()))
+                .map(|(_, value)| value)
+        };
+
+        if let Some(value) = arg(SIZE_ARGS) {
+            return self
+                .int_value(value)
+                .and_then(|s| s.try_into().ok())
+                .unwrap_or(self.args.max_list_size);
+        }
+
+        if let Some(value) = arg(KEY_LIST_ARGS) {
+            return self
+                .list_len(value)
+                .and_then(|l| l.try_into().ok())
+                .unwrap_or(self.args.max_list_size);
+        }
+
+        if UNBOUNDED_LISTS.contains(&field.name.as_str()) {
+            self.args.default_list_size
+        } else {
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> Args {
+        Args {
+            max_query_depth: 4,
+            max_query_cost: 1_000,
+            default_list_size: 10,
+            max_list_size: 100,
+            trusted_api_keys: vec!["trusted".to_owned()],
+        }
+    }
+
+    fn check(query: &str, variables: &Json) -> Result<u64, Rejection> {
+        args().check(query, None, variables)
+    }
+
+    #[test]
+    fn multiplies_children_by_list_size() {
+        let vars = Json::Null;
+
+        assert_eq!(check("{ nfts(limit: 10) { name } }", &vars).unwrap(), 11);
+        assert_eq!(
+            check("{ nfts(limit: 10) { name creators { address } } }", &vars).unwrap(),
+            1 + 10 * (1 + (1 + 10))
+        );
+        assert_eq!(
+            check(
+                "{ connection(first: 20) { edges { node { name } } } }",
+                &vars
+            )
+            .unwrap(),
+            1 + 20 * (1 + (1 + 1))
+        );
+        assert_eq!(check("{ nfts(limit: -5) { name } }", &vars).unwrap(), 101);
+    }
+
+    #[test]
+    fn assumes_default_size_for_unbounded_lists() {
+        let vars = Json::Null;
+
+        assert_eq!(
+            check(
+                "{ collection(address: \"x\") { holders { address } } }",
+                &vars
+            )
+            .unwrap(),
+            1 + (1 + 10)
+        );
+        assert_eq!(
+            check(
+                "{ creator(address: \"x\") { attributeGroups { variants { name } } } }",
+                &vars
+            )
+            .unwrap(),
+            1 + (1 + 10 * (1 + 10))
+        );
+    }
+
+    #[test]
+    fn reads_sizes_from_key_lists() {
+        assert_eq!(
+            check(
+                "{ nftsByMintAddress(addresses: [\"a\", \"b\", \"c\"]) { name } }",
+                &Json::Null
+            )
+            .unwrap(),
+            1 + 3
+        );
+
+        let query = "query W($a: [PublicKey!]!) { wallets(addresses: $a) { address } }";
+
+        assert_eq!(
+            check(query, &serde_json::json!({ "a": ["a", "b"] })).unwrap(),
+            1 + 2
+        );
+        assert_eq!(check(query, &serde_json::json!({})).unwrap(), 1 + 100);
+    }
+
+    #[test]
+    fn weights_expensive_fields() {
+        assert_eq!(
+            check(
+                "{ nfts(limit: 2) { owner { profile { handle } } } }",
+                &Json::Null
+            )
+            .unwrap(),
+            1 + 2 * (1 + (10 + 1))
+        );
+    }
+
+    #[test]
+    fn reads_sizes_from_variables() {
+        let query = "query Nfts($n: Int) { nfts(limit: $n) { name } }";
+
+        assert_eq!(check(query, &serde_json::json!({ "n": 50 })).unwrap(), 51);
+        assert_eq!(check(query, &serde_json::json!({})).unwrap(), 101);
+        assert_eq!(
+            check(query, &serde_json::json!({ "n": null })).unwrap(),
+            101
+        );
+    }
+
+    #[test]
+    fn reads_sizes_from_variable_defaults() {
+        let query = "query Nfts($n: Int = 5) { nfts(limit: $n) { name } }";
+
+        assert_eq!(check(query, &serde_json::json!({})).unwrap(), 6);
+        assert_eq!(check(query, &serde_json::json!({ "n": 50 })).unwrap(), 51);
+        assert_eq!(
+            check(query, &serde_json::json!({ "n": null })).unwrap(),
+            101
+        );
+    }
+
+    #[test]
+    fn rejects_deep_queries() {
+        assert_eq!(check("{ a { b { c { d } } } }", &Json::Null).unwrap(), 4);
+        assert!(matches!(
+            check("{ a { b { c { d { e } } } } }", &Json::Null),
+            Err(Rejection::TooDeep { max: 4 })
+        ));
+    }
+
+    #[test]
+    fn rejects_expensive_queries() {
+        assert!(matches!(
+            check("{ nfts(limit: 1000) { name } }", &Json::Null),
+            Err(Rejection::TooExpensive {
+                cost: 1001,
+                max: 1_000
+            })
+        ));
+    }
+
+    #[test]
+    fn follows_fragments_without_cycling() {
+        let query = "
+            query { nft(address: \"x\") { ...A } }
+            fragment A on Nft { name ...B }
+            fragment B on Nft { address ...A }
+        ";
+
+        assert_eq!(check(query, &Json::Null).unwrap(), 1 + (1 + 1));
+    }
+
+    #[test]
+    fn counts_fragment_depth() {
+        let query = "
+            query { a { ...F } }
+            fragment F on A { b { c { d { e } } } }
+        ";
+
+        assert!(matches!(
+            check(query, &Json::Null),
+            Err(Rejection::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn selects_the_named_operation() {
+        let query = "
+            query Cheap { a }
+            query Expensive { nfts(limit: 5000) { name } }
+        ";
+
+        assert_eq!(args().check(query, Some("Cheap"), &Json::Null).unwrap(), 1);
+        assert!(args().check(query, Some("Expensive"), &Json::Null).is_err());
+    }
+
+    #[test]
+    fn ignores_introspection_and_invalid_queries() {
+        assert_eq!(
+            check("{ __schema { types { name } } }", &Json::Null).unwrap(),
+            0
+        );
+        assert!(matches!(
+            check("{ nfts(", &Json::Null),
+            Err(Rejection::Invalid)
+        ));
+    }
+
+    #[test]
+    fn trusts_configured_keys() {
+        let args = args();
+
+        assert!(args.is_trusted(Some("trusted")));
+        assert!(!args.is_trusted(Some("other")));
+        assert!(!args.is_trusted(None));
+    }
+}
@@ -0,0 +1,56 @@
+//! Fan-out of Postgres notifications to GraphQL subscriptions
+
+use std::time::Duration;
+
+pub use indexer_core::db::listen::Notification;
+use indexer_core::{db::listen::Listener, prelude::*};
+use tokio::sync::broadcast;
+
+/// Channels notified by the `notify_row_change` database triggers, named
+/// after the table whose rows they carry, and by the `notify_row_update`
+/// triggers, named after the table with an `_updated` suffix
+pub const CHANNELS: &[&str] = &[
+    "listings",
+    "offers",
+    "purchases",
+    "feed_events",
+    "current_metadata_owners",
+    "listings_updated",
+    "offers_updated",
+];
+
+/// Number of notifications buffered for each subscriber before the oldest
+/// are dropped
+const CAPACITY: usize = 1024;
+
+/// Broadcast channel carrying notifications to subscribers
+pub type Sender = broadcast::Sender<Notification>;
+
+/// Create a notification broadcast channel with no subscribers
+#[must_use]
+pub fn channel() -> Sender {
+    broadcast::channel(CAPACITY).0
+}
+
+/// Forward notifications from the database at `url` to `tx`, reconnecting
+/// whenever the connection fails
+pub async fn run(url: String, tx: Sender) {
+    loop {
+        if let Err(e) = listen(&url, &tx).await {
+            error!("Postgres notification listener failed: {:?}", e);
+        }
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen(url: &str, tx: &Sender) -> Result<()> {
+    let mut listener = Listener::connect(url, CHANNELS).await?;
+
+    info!("Listening for notifications on {}", CHANNELS.join(", "));
+
+    loop {
+        // Sending only fails if nobody is subscribed
+        tx.send(listener.recv().await?).ok();
+    }
+}
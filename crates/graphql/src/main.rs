@@ -9,10 +9,15 @@
 )]
 #![warn(clippy::pedantic, clippy::cargo, missing_docs)]
 
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
 
 use actix_cors::Cors;
-use actix_web::{dev::ConnectionInfo, http, web, App, Error, HttpResponse, HttpServer};
+use actix_web::{
+    dev::ConnectionInfo, http, web, App, Error, HttpRequest, HttpResponse, HttpServer,
+};
 use indexer_core::{
     assets::AssetProxyArgs,
     chrono::{Duration, Local},
@@ -20,18 +25,37 @@ use indexer_core::{
     clap::Parser,
     db,
     db::Pool,
-    meilisearch,
     prelude::*,
+    search::{self, SearchBackend},
     util::duration_hhmmssfff,
     ServerOpts,
 };
-use juniper::http::{graphiql::graphiql_source, GraphQLRequest};
+use juniper::{
+    http::{graphiql::graphiql_source, GraphQLRequest},
+    InputValue,
+};
+use juniper_graphql_ws::{ArcSchema, ConnectionConfig};
+use serde::Deserialize;
 // TODO: use nonblocking once we upgrade past 1.9
 use solana_client::rpc_client::RpcClient;
 
 use crate::schema::{AppContext, Schema};
 
+mod api_keys;
+mod cache;
+mod cache_control;
+mod cost;
+mod listener;
+mod persisted;
+mod rate_limit;
 mod schema;
+mod subscriptions;
+
+/// Header carrying the session token of an authenticated wallet
+const WALLET_SESSION_HEADER: &str = "x-wallet-session";
+
+/// Timeout for outgoing HTTP requests made while resolving queries
+const HTTP_TIMEOUT: StdDuration = StdDuration::from_secs(10);
 
 #[derive(Debug, Parser)]
 struct Opts {
@@ -48,7 +72,31 @@ struct Opts {
     asset_proxy: AssetProxyArgs,
 
     #[clap(flatten)]
-    search: meilisearch::Args,
+    search: search::Args,
+
+    #[clap(flatten)]
+    query_limits: cost::Args,
+
+    #[clap(flatten)]
+    api_keys: api_keys::Args,
+
+    #[clap(flatten)]
+    persisted_queries: persisted::Args,
+
+    #[clap(flatten)]
+    cache_control: cache_control::Args,
+
+    #[clap(flatten)]
+    cache: cache::Args,
+
+    /// Number of wallet nonces each client address may request per minute
+    #[clap(long, env, default_value_t = 10.0)]
+    wallet_nonces_per_minute: f64,
+
+    /// Addresses of reverse proxies trusted to report the client address in
+    /// forwarding headers
+    #[clap(long, env, use_value_delimiter(true))]
+    trusted_proxies: Vec<IpAddr>,
 
     #[clap(long, env)]
     solana_endpoint: String,
@@ -80,11 +128,25 @@ struct RedirectData {
 
 pub(crate) struct SharedData {
     schema: Schema,
+    query_limits: cost::Args,
+    api_keys: Arc<api_keys::ApiKeys>,
+    persisted_queries: persisted::PersistedQueries,
+    cache_control: cache_control::Args,
+    pub cache: Arc<cache::Cache>,
+    pub nonce_limiter: Arc<rate_limit::RateLimiter<String>>,
+    pub wallet_nonces_per_minute: f64,
+    trusted_proxies: Vec<IpAddr>,
     pub db: Arc<Pool>,
+    /// The primary database, if reachable, which is written to and which
+    /// reflects notified rows without replication lag
+    pub primary_db: Option<Arc<Pool>>,
     pub asset_proxy: AssetProxyArgs,
     pub twitter_bearer_token: String,
-    pub search: meilisearch::client::Client,
+    pub search: Arc<dyn SearchBackend>,
+    pub http: reqwest::Client,
+    pub notifications: listener::Sender,
     pub rpc: RpcClient,
+    pub solana_endpoint: String,
     pub follow_wallets_exclusions: Vec<String>,
     pub featured_listings_auction_houses: Vec<String>,
     pub featured_listings_seller_exclusions: Vec<String>,
@@ -111,17 +173,259 @@ async fn redirect_version(data: web::Data<RedirectData>) -> HttpResponse {
         ))
 }
 
-async fn graphql(
+/// Get the API key passed as a bearer token, if any
+fn api_key(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+/// Get the wallet session token sent with a request, if any
+fn wallet_session(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(WALLET_SESSION_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+/// Strip the port from a client address, which differs between connections
+/// from the same client
+fn strip_port(addr: &str) -> String {
+    addr.parse::<SocketAddr>()
+        .map_or_else(|_| addr.to_owned(), |a| a.ip().to_string())
+}
+
+/// Get the address of the client making a request, reading forwarding
+/// headers only from connections made by a trusted proxy
+fn client_ip(conn: &ConnectionInfo, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer = strip_port(conn.peer_addr()?);
+
+    if peer
+        .parse::<IpAddr>()
+        .map_or(false, |ip| trusted_proxies.contains(&ip))
+    {
+        conn.realip_remote_addr().map(strip_port)
+    } else {
+        Some(peer)
+    }
+}
+
+/// The `Cache-Control` header value for a response
+fn cache_control(req: &HttpRequest, hint: Option<cache_control::Hint>, is_ok: bool) -> String {
+    // Wallet sessions and API keys may change the response, so shared caches
+    // must not store it
+    let credentialed = req.headers().contains_key(http::header::AUTHORIZATION)
+        || req.headers().contains_key(WALLET_SESSION_HEADER);
+
+    match hint {
+        Some(h) if is_ok => h.header_value(credentialed),
+        _ => "no-store".into(),
+    }
+}
+
+/// Build the response for a request denied by API key authentication
+fn denied(denial: api_keys::Denial) -> HttpResponse {
+    let mut resp = match denial {
+        api_keys::Denial::Missing | api_keys::Denial::Invalid => HttpResponse::Unauthorized(),
+        api_keys::Denial::RateLimited { retry_after } => {
+            let mut resp = HttpResponse::TooManyRequests();
+            resp.insert_header((http::header::RETRY_AFTER, retry_after.to_string()));
+            resp
+        },
+        api_keys::Denial::Unavailable => HttpResponse::ServiceUnavailable(),
+    };
+
+    resp.json(denial.to_response())
+}
+
+/// A GraphQL request body, optionally carrying a persisted query hash in its
+/// extensions
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    query: Option<String>,
+    operation_name: Option<String>,
+    #[serde(default)]
+    variables: serde_json::Value,
+    extensions: Option<serde_json::Value>,
+}
+
+/// The query string of a GraphQL GET request, with JSON-encoded variables and
+/// extensions
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetRequest {
+    query: Option<String>,
+    operation_name: Option<String>,
+    variables: Option<String>,
+    extensions: Option<String>,
+}
+
+impl TryFrom<GetRequest> for Request {
+    type Error = serde_json::Error;
+
+    fn try_from(
+        GetRequest {
+            query,
+            operation_name,
+            variables,
+            extensions,
+        }: GetRequest,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            query,
+            operation_name,
+            variables: variables
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            extensions: extensions.map(|e| serde_json::from_str(&e)).transpose()?,
+        })
+    }
+}
+
+/// Build a GraphQL error response body with the given message
+fn error_response(message: impl std::fmt::Display) -> serde_json::Value {
+    serde_json::json!({
+        "errors": [{ "message": message.to_string() }],
+    })
+}
+
+/// Run static analysis over a request, rejecting it if it exceeds the
+/// configured query limits
+fn check_limits(
+    limits: &cost::Args,
+    query: &str,
+    operation_name: Option<&str>,
+    variables: &serde_json::Value,
+) -> Result<(), cost::Rejection> {
+    let cost = limits.check(query, operation_name, variables)?;
+    debug!("Estimated query cost: {}", cost);
+
+    Ok(())
+}
+
+async fn graphql_post(
     data: web::Data<SharedData>,
-    req: web::Json<GraphQLRequest>,
+    req: web::Json<Request>,
+    http_req: HttpRequest,
     conn: ConnectionInfo,
 ) -> Result<HttpResponse, Error> {
-    let ctx = AppContext::new(data.clone().into_inner());
+    execute(data, req.into_inner(), false, &http_req, &conn).await
+}
+
+async fn graphql_get(
+    data: web::Data<SharedData>,
+    req: web::Query<GetRequest>,
+    http_req: HttpRequest,
+    conn: ConnectionInfo,
+) -> Result<HttpResponse, Error> {
+    match Request::try_from(req.into_inner()) {
+        Ok(req) => execute(data, req, true, &http_req, &conn).await,
+        Err(e) => Ok(HttpResponse::BadRequest().json(error_response(format_args!(
+            "Invalid variables or extensions: {}",
+            e
+        )))),
+    }
+}
+
+async fn execute(
+    data: web::Data<SharedData>,
+    req: Request,
+    is_get: bool,
+    http_req: &HttpRequest,
+    conn: &ConnectionInfo,
+) -> Result<HttpResponse, Error> {
+    // Keys listed with --trusted-api-keys need not exist in the api_keys table
+    let trusted = data.query_limits.is_trusted(api_key(http_req));
+    let key = if trusted {
+        None
+    } else {
+        match data
+            .api_keys
+            .authenticate(&data.db, api_key(http_req))
+            .await
+        {
+            Ok(k) => k,
+            Err(denial) => return Ok(denied(denial)),
+        }
+    };
+
+    let Request {
+        query,
+        operation_name,
+        variables,
+        extensions,
+    } = req;
+
+    let query = match data.persisted_queries.resolve(query, extensions.as_ref()) {
+        Ok(q) => q,
+        // Clients respond to a miss by resending the full query
+        Err(e @ persisted::Error::NotFound) => {
+            return Ok(HttpResponse::Ok()
+                .insert_header((http::header::CACHE_CONTROL, "no-store"))
+                .json(e.to_response()));
+        },
+        Err(e) => return Ok(HttpResponse::BadRequest().json(e.to_response())),
+    };
+
+    let hint = data.cache_control.hint(&query, operation_name.as_deref());
+
+    if is_get && !hint.map_or(true, |h| h.is_query) {
+        return Ok(HttpResponse::MethodNotAllowed()
+            .json(error_response("Only queries may be sent with GET requests")));
+    }
+
+    if !trusted && !key.as_ref().map_or(false, |k| k.trusted) {
+        if let Err(rejection) = check_limits(
+            &data.query_limits,
+            &query,
+            operation_name.as_deref(),
+            &variables,
+        ) {
+            if let Some(ref key) = key {
+                data.api_keys
+                    .record(key, operation_name.as_deref(), StdDuration::ZERO, true)
+                    .await;
+            }
+
+            return Ok(HttpResponse::BadRequest().json(rejection.to_response()));
+        }
+    }
+
+    let input = match Option::<InputValue>::deserialize(&variables) {
+        Ok(v) => v,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest()
+                .json(error_response(format_args!("Invalid variables: {}", e))));
+        },
+    };
+    let gql_req = GraphQLRequest::new(query.to_string(), operation_name.clone(), input);
+
+    let ctx = AppContext::new(data.clone().into_inner())
+        .with_wallet_session(wallet_session(http_req))
+        .with_client_ip(client_ip(conn, &data.trusted_proxies));
     let start = Local::now();
 
-    let resp = req.execute(&data.schema, &ctx).await;
+    let resp = match data.schema {
+        Schema::ReadWrite(ref s) => gql_req.execute(s, &ctx).await,
+        Schema::ReadOnly(ref s) => gql_req.execute(s, &ctx).await,
+    };
     let end = Local::now();
     let duration = end - start;
+
+    if let Some(ref key) = key {
+        data.api_keys
+            .record(
+                key,
+                operation_name.as_deref(),
+                duration.to_std().unwrap_or_default(),
+                !resp.is_ok(),
+            )
+            .await;
+    }
+
     info!(
         "host={:?}, remote_addr={:?}, peer_addr={:?}",
         conn.host(),
@@ -129,45 +433,98 @@ async fn graphql(
         conn.peer_addr().unwrap_or(&String::new())
     );
     if duration > Duration::milliseconds(5000) {
-        #[derive(serde::Deserialize)]
-        struct Data {
-            #[serde(default)]
-            query: serde_json::Value,
-            #[serde(default)]
-            operation_name: serde_json::Value,
-            #[serde(default)]
-            variables: serde_json::Value,
-        }
+        warn!(
+            "Long graphql request query={}, operation={:?}, variables={}, duration={}",
+            query,
+            operation_name,
+            variables,
+            duration_hhmmssfff(duration),
+        );
+    }
 
-        match serde_json::to_value(&req).and_then(serde_json::from_value) {
-            Ok(Data {
-                query,
-                operation_name,
-                variables,
-            }) => warn!(
-                "Long graphql request query={}, operation={:?}, variables={}, duration={}",
-                query,
-                operation_name,
-                variables,
-                duration_hhmmssfff(duration),
-            ),
-            Err(e) => error!("Failed to format long query for printing: {}", e),
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            http::header::CACHE_CONTROL,
+            cache_control(http_req, hint, resp.is_ok()),
+        ))
+        .insert_header((
+            http::header::VARY,
+            format!("{}, {}", http::header::AUTHORIZATION, WALLET_SESSION_HEADER),
+        ))
+        .json(&resp))
+}
+
+async fn subscriptions(
+    data: web::Data<SharedData>,
+    req: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let trusted = data.query_limits.is_trusted(api_key(&req)) || {
+        match data.api_keys.authenticate(&data.db, api_key(&req)).await {
+            Ok(k) => k.map_or(false, |k| k.trusted),
+            Err(denial) => return Ok(denied(denial)),
         }
+    };
+    let limits = (!trusted).then(|| data.query_limits.clone());
+
+    // Notifications are sent by the primary database, so read the rows they
+    // announce from it rather than from a possibly lagging replica
+    let ctx = AppContext::new_primary(data.clone().into_inner());
+    let config = ConnectionConfig::new(ctx).with_keep_alive_interval(StdDuration::from_secs(15));
+
+    match data.schema {
+        Schema::ReadWrite(ref s) => {
+            subscriptions::handler(req, stream, ArcSchema(s.clone()), config, limits).await
+        },
+        Schema::ReadOnly(ref s) => {
+            subscriptions::handler(req, stream, ArcSchema(s.clone()), config, limits).await
+        },
+    }
+}
+
+/// Get a pool for the primary database, reusing `db` if it is not a read
+/// replica
+fn connect_primary(
+    db_args: db::ConnectArgs,
+    db_ty: db::ConnectionType,
+    db: &Arc<db::Pool>,
+) -> Option<Arc<db::Pool>> {
+    let primary_db = match db_ty {
+        db::ConnectionType::Read => match db::connect(db_args, db::ConnectMode::Write) {
+            Ok((pool, _)) => Some(Arc::new(pool)),
+            Err(e) => {
+                warn!("Failed to connect to the primary database: {:?}", e);
+                None
+            },
+        },
+        db::ConnectionType::Default | db::ConnectionType::Write => Some(db.clone()),
+    };
+
+    if primary_db.is_none() {
+        warn!("API usage accounting disabled: no primary database");
     }
 
-    Ok(HttpResponse::Ok().json(&resp))
+    primary_db
 }
 
+#[allow(clippy::too_many_lines)]
 fn main() {
     indexer_core::run(|| {
         let opts = Opts::parse();
         debug!("{:#?}", opts);
         let Opts {
             server,
-            db,
+            db: db_args,
             twitter_bearer_token,
             asset_proxy,
             search,
+            query_limits,
+            api_keys,
+            persisted_queries,
+            cache_control,
+            cache,
+            wallet_nonces_per_minute,
+            trusted_proxies,
             solana_endpoint,
             follow_wallets_exclusions,
             featured_listings_auction_houses,
@@ -181,20 +538,58 @@ fn main() {
 
         let twitter_bearer_token = twitter_bearer_token.unwrap_or_else(String::new);
 
-        // TODO: db_ty indicates if any actions that mutate the database can be run
-        let (db, _db_ty) =
-            db::connect(db, db::ConnectMode::Read).context("Failed to connect to Postgres")?;
+        // LISTEN is not supported by read replicas, so subscriptions need the
+        // primary database
+        let listen_url = match db_args.clone().into_url(db::ConnectMode::Write) {
+            Ok((_, url)) => Some(url),
+            Err(e) => {
+                warn!("Subscriptions disabled: {}", e);
+                None
+            },
+        };
+
+        let (db, db_ty) = db::connect(db_args.clone(), db::ConnectMode::Read)
+            .context("Failed to connect to Postgres")?;
+        let search = search
+            .connect(db.clone())
+            .context("Failed to connect to search backend")?;
         let db = Arc::new(db);
-        let search = search.into_client();
-        let rpc = RpcClient::new(solana_endpoint);
+
+        let mutations_enabled = !matches!(db_ty, db::ConnectionType::Read);
+
+        if !mutations_enabled {
+            warn!("Mutations disabled: database is read-only");
+        }
+
+        let primary_db = connect_primary(db_args, db_ty, &db);
+        let api_keys = Arc::new(api_keys::ApiKeys::new(api_keys));
+        let cache = Arc::new(cache::Cache::new(cache).context("Failed to set up resolver cache")?);
+        let nonce_limiter = Arc::new(rate_limit::RateLimiter::default());
+        let rpc = RpcClient::new(solana_endpoint.clone());
+        let notifications = listener::channel();
 
         let shared = web::Data::new(SharedData {
-            schema: schema::create(),
-            db,
+            schema: schema::create(mutations_enabled),
+            query_limits,
+            api_keys: api_keys.clone(),
+            persisted_queries: persisted::PersistedQueries::new(&persisted_queries),
+            cache_control,
+            cache: cache.clone(),
+            nonce_limiter: nonce_limiter.clone(),
+            wallet_nonces_per_minute,
+            trusted_proxies,
+            db: db.clone(),
+            primary_db: primary_db.clone(),
             asset_proxy,
             twitter_bearer_token,
             search,
+            http: reqwest::Client::builder()
+                .timeout(HTTP_TIMEOUT)
+                .build()
+                .context("Failed to build HTTP client")?,
+            notifications: notifications.clone(),
             rpc,
+            solana_endpoint,
             follow_wallets_exclusions,
             featured_listings_auction_houses,
             featured_listings_seller_exclusions,
@@ -216,7 +611,20 @@ fn main() {
         assert!(graphiql_data.uri.starts_with('/'));
 
         actix_web::rt::System::new()
-            .block_on(
+            .block_on(async move {
+                if let Some(url) = listen_url {
+                    actix_web::rt::spawn(cache::run_invalidation(cache, db, notifications.clone()));
+                    actix_web::rt::spawn(listener::run(url, notifications));
+                }
+
+                // Nonce buckets refill within a minute, so idle ones can go
+                actix_web::rt::spawn(nonce_limiter.run_eviction(StdDuration::from_secs(60)));
+
+                // Usage statistics are written to the primary database
+                if let Some(db) = primary_db {
+                    actix_web::rt::spawn(api_keys.run_flush(db));
+                }
+
                 HttpServer::new(move || {
                     App::new()
                         .wrap(
@@ -228,12 +636,19 @@ fn main() {
                                     http::header::ACCEPT,
                                 ])
                                 .allowed_header(http::header::CONTENT_TYPE)
+                                .allowed_header(WALLET_SESSION_HEADER)
                                 .max_age(3600),
                         )
                         .service(
                             web::resource(version_extension)
                                 .app_data(shared.clone())
-                                .route(web::post().to(graphql)),
+                                .route(web::post().to(graphql_post))
+                                .route(web::get().to(graphql_get)),
+                        )
+                        .service(
+                            web::resource(format!("{}/subscriptions", version_extension))
+                                .app_data(shared.clone())
+                                .route(web::get().to(subscriptions)),
                         )
                         .service(
                             web::resource(redirect_data.route)
@@ -247,8 +662,9 @@ fn main() {
                         )
                 })
                 .bind(addr)?
-                .run(),
-            )
+                .run()
+                .await
+            })
             .context("Actix server failed to run")
     });
 }
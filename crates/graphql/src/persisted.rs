@@ -0,0 +1,162 @@
+//! Automatic persisted queries, allowing clients to send a hash in place of a
+//! full query document
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use indexer_core::{clap, hash::DashMap};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+
+/// Options for persisted queries
+#[derive(Debug, Clone, clap::Parser)]
+pub struct Args {
+    /// Maximum number of persisted queries to hold in memory
+    #[clap(long, env, default_value_t = 10_000)]
+    persisted_query_capacity: usize,
+}
+
+/// Reason a persisted query could not be resolved
+#[derive(Debug, Clone, Copy)]
+pub enum Error {
+    /// The hash has not been registered and the query must be resent
+    NotFound,
+    /// The query sent does not match its hash
+    HashMismatch,
+    /// The persisted query extension is malformed or of an unknown version
+    Unsupported,
+    /// Neither a query nor a persisted query hash was sent
+    MissingQuery,
+}
+
+impl Error {
+    /// Render this error as a GraphQL error response body
+    #[must_use]
+    pub fn to_response(self) -> Json {
+        let (message, code) = match self {
+            Self::NotFound => ("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND"),
+            Self::HashMismatch => ("provided sha does not match query", "BAD_USER_INPUT"),
+            Self::Unsupported => (
+                "PersistedQueryNotSupported",
+                "PERSISTED_QUERY_NOT_SUPPORTED",
+            ),
+            Self::MissingQuery => ("Must provide a query string", "BAD_USER_INPUT"),
+        };
+
+        serde_json::json!({
+            "errors": [{ "message": message, "extensions": { "code": code } }],
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    query: Arc<str>,
+    /// Value of the store's clock when this entry was last used
+    used: AtomicU64,
+}
+
+/// In-memory store of persisted queries, keyed by their SHA-256 hash.  Once
+/// full, the least recently used queries are evicted to make room.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct PersistedQueries {
+    capacity: usize,
+    clock: AtomicU64,
+    queries: DashMap<String, Entry>,
+}
+
+impl PersistedQueries {
+    /// Construct a new, empty store
+    #[must_use]
+    pub fn new(args: &Args) -> Self {
+        Self {
+            capacity: args.persisted_query_capacity.max(1),
+            clock: AtomicU64::new(0),
+            queries: DashMap::default(),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Evict the least recently used eighth of the store, so eviction is not
+    /// repeated on every insert once the store is full
+    fn evict(&self) {
+        let mut used: Vec<_> = self
+            .queries
+            .iter()
+            .map(|e| e.used.load(Ordering::Relaxed))
+            .collect();
+
+        if used.is_empty() {
+            return;
+        }
+
+        let n = (self.capacity / 8).clamp(1, used.len()) - 1;
+        let (_, &mut cutoff, _) = used.select_nth_unstable(n);
+
+        self.queries
+            .retain(|_, e| e.used.load(Ordering::Relaxed) > cutoff);
+    }
+
+    /// Resolve the query document of a request, registering it if the request
+    /// carries both a query and its hash
+    ///
+    /// # Errors
+    /// This function fails if no query could be resolved for the request.
+    pub fn resolve(
+        &self,
+        query: Option<String>,
+        extensions: Option<&Json>,
+    ) -> Result<Arc<str>, Error> {
+        let persisted = match extensions.and_then(|e| e.get("persistedQuery")) {
+            Some(p) => p,
+            None => return query.map(Into::into).ok_or(Error::MissingQuery),
+        };
+
+        if persisted.get("version").and_then(Json::as_u64) != Some(1) {
+            return Err(Error::Unsupported);
+        }
+
+        let hash = persisted
+            .get("sha256Hash")
+            .and_then(Json::as_str)
+            .ok_or(Error::Unsupported)?
+            .to_ascii_lowercase();
+
+        let query = match query {
+            Some(q) => q,
+            None => {
+                return self
+                    .queries
+                    .get(&hash)
+                    .map(|e| {
+                        e.used.store(self.tick(), Ordering::Relaxed);
+                        e.query.clone()
+                    })
+                    .ok_or(Error::NotFound);
+            },
+        };
+
+        if format!("{:x}", Sha256::digest(query.as_bytes())) != hash {
+            return Err(Error::HashMismatch);
+        }
+
+        let query: Arc<str> = query.into();
+
+        if !self.queries.contains_key(&hash) && self.queries.len() >= self.capacity {
+            self.evict();
+        }
+
+        self.queries.insert(hash, Entry {
+            query: query.clone(),
+            used: AtomicU64::new(self.tick()),
+        });
+
+        Ok(query)
+    }
+}
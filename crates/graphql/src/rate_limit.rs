@@ -0,0 +1,79 @@
+//! Token bucket rate limiting
+
+use std::{hash::Hash, sync::Arc, time::Instant};
+
+use indexer_core::{hash::DashMap, prelude::*};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// A set of token buckets, one per key, each refilled continuously at a
+/// given rate up to a given burst size
+#[derive(Debug)]
+pub struct RateLimiter<K: Eq + Hash> {
+    buckets: DashMap<K, Bucket>,
+}
+
+impl<K: Eq + Hash> Default for RateLimiter<K> {
+    fn default() -> Self {
+        Self {
+            buckets: DashMap::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    /// Take a token from the bucket of the given key, returning the number of
+    /// seconds to wait if it is empty
+    ///
+    /// # Errors
+    /// This function fails if the bucket has no tokens left.
+    pub fn take(&self, key: K, requests_per_minute: f64, burst: f64) -> Result<(), u64> {
+        let now = Instant::now();
+        let rate = requests_per_minute / 60.0;
+        let mut bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+
+            return Ok(());
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let retry_after = if rate > 0.0 {
+            ((1.0 - bucket.tokens) / rate).ceil() as u64
+        } else {
+            60
+        };
+
+        Err(retry_after.max(1))
+    }
+
+    /// Drop buckets untouched for at least `idle`.  Once this exceeds the time
+    /// a bucket takes to refill, a dropped bucket is indistinguishable from a
+    /// new one.
+    pub fn evict_idle(&self, idle: StdDuration) {
+        self.buckets.retain(|_, b| b.updated.elapsed() < idle);
+    }
+
+    /// Periodically drop buckets untouched for at least `idle`
+    pub async fn run_eviction(self: Arc<Self>, idle: StdDuration) {
+        let mut interval = tokio::time::interval(idle);
+
+        loop {
+            interval.tick().await;
+
+            self.evict_idle(idle);
+        }
+    }
+}
@@ -1,18 +1,20 @@
-use dataloaders::{Batcher, Loader, TwitterBatcher};
-use indexer_core::uuid::Uuid;
+use dataloaders::{Batcher, Loader, RpcBatcher, TwitterBatcher};
+use indexer_core::{db::queries, uuid::Uuid};
 use objects::{
     ah_listing::AhListing,
     ah_offer::Offer as AhOffer,
     ah_purchase::Purchase as AhPurchase,
     auction_house::AuctionHouse,
     bid_receipt::BidReceipt,
+    collection::Collection,
     graph_connection::GraphConnection,
     listing::{Bid, Listing},
     listing_receipt::ListingReceipt,
+    marketplace::MarketplaceConfigVersion,
     nft::{CollectionNft, Nft, NftActivity, NftAttribute, NftCreator, NftFile, NftOwner},
     profile::TwitterProfile,
     purchase_receipt::PurchaseReceipt,
-    stats::{MarketStats, MintStats},
+    stats::{CollectionStats, MarketStats, MintStats},
     store_creator::StoreCreator,
     storefront::Storefront,
     wallet::Wallet,
@@ -24,6 +26,9 @@ use super::prelude::*;
 #[derive(Clone)]
 pub struct AppContext {
     pub(crate) shared: Arc<SharedData>,
+    db: Arc<Pool>,
+    wallet_session: Option<String>,
+    client_ip: Option<String>,
 
     // Postgres dataloaders
     pub ah_listing_loader: Loader<Uuid, Option<AhListing>>,
@@ -34,6 +39,8 @@ pub struct AppContext {
     pub bid_receipts_loader: Loader<PublicKey<Nft>, Vec<BidReceipt>>,
     pub collection_count_loader: Loader<PublicKey<StoreCreator>, Option<i32>>,
     pub collection_loader: Loader<PublicKey<StoreCreator>, Vec<Nft>>,
+    pub collection_stats_loader: Loader<PublicKey<Collection>, Vec<CollectionStats>>,
+    pub config_history_loader: Loader<PublicKey<StoreConfig>, Vec<MarketplaceConfigVersion>>,
     pub graph_connection_loader: Loader<PublicKey<GraphConnection>, Option<GraphConnection>>,
     pub listing_bids_loader: Loader<PublicKey<Listing>, Vec<Bid>>,
     pub listing_loader: Loader<PublicKey<Listing>, Option<Listing>>,
@@ -49,15 +56,19 @@ pub struct AppContext {
     pub nft_files_loader: Loader<PublicKey<Nft>, Vec<NftFile>>,
     pub nft_loader: Loader<PublicKey<Nft>, Option<Nft>>,
     pub nft_owner_loader: Loader<PublicKey<Nft>, Option<NftOwner>>,
+    pub nft_parent_collection_loader: Loader<PublicKey<Nft>, Option<Collection>>,
     pub offer_loader: Loader<Uuid, Option<AhOffer>>,
     pub offers_loader: Loader<PublicKey<Nft>, Vec<AhOffer>>,
     pub purchase_loader: Loader<Uuid, Option<AhPurchase>>,
     pub purchase_receipt_loader: Loader<PublicKey<PurchaseReceipt>, Option<PurchaseReceipt>>,
     pub purchase_receipts_loader: Loader<PublicKey<Nft>, Vec<PurchaseReceipt>>,
     pub purchases_loader: Loader<PublicKey<Nft>, Vec<AhPurchase>>,
+    pub slot_time_loader: Loader<i64, Option<DateTime<Utc>>>,
+    pub rpc_block_time_loader: Loader<i64, Option<i64>, RpcBatcher>,
     pub store_auction_houses_loader: Loader<PublicKey<AuctionHouse>, Option<AuctionHouse>>,
     pub store_creator_loader: Loader<PublicKey<StoreConfig>, Vec<StoreCreator>>,
     pub storefront_loader: Loader<PublicKey<Storefront>, Option<Storefront>>,
+    pub suspected_original_loader: Loader<PublicKey<Nft>, Option<PublicKey<Nft>>>,
     pub twitter_handle_loader: Loader<PublicKey<Wallet>, Option<String>>,
 
     // Twitter dataloaders
@@ -68,11 +79,32 @@ impl juniper::Context for AppContext {}
 
 impl AppContext {
     pub(crate) fn new(shared: Arc<SharedData>) -> AppContext {
-        let batcher = Batcher::new(shared.db.clone());
+        let db = shared.db.clone();
+
+        Self::with_db(shared, db)
+    }
+
+    /// Construct a context reading from the primary database, for resolving
+    /// rows announced by notifications before they reach a read replica
+    pub(crate) fn new_primary(shared: Arc<SharedData>) -> AppContext {
+        let db = shared
+            .primary_db
+            .clone()
+            .unwrap_or_else(|| shared.db.clone());
+
+        Self::with_db(shared, db)
+    }
+
+    fn with_db(shared: Arc<SharedData>, db: Arc<Pool>) -> AppContext {
+        let batcher = Batcher::new(db.clone());
         let twitter_batcher = TwitterBatcher::new(shared.clone());
+        let rpc_batcher = RpcBatcher::new(shared.clone());
 
         Self {
             shared,
+            db,
+            wallet_session: None,
+            client_ip: None,
 
             ah_listing_loader: Loader::new(batcher.clone()),
             ah_listings_loader: Loader::new(batcher.clone()),
@@ -82,6 +114,8 @@ impl AppContext {
             bid_receipts_loader: Loader::new(batcher.clone()),
             collection_count_loader: Loader::new(batcher.clone()),
             collection_loader: Loader::new(batcher.clone()),
+            collection_stats_loader: Loader::new(batcher.clone()),
+            config_history_loader: Loader::new(batcher.clone()),
             graph_connection_loader: Loader::new(batcher.clone()),
             listing_bids_loader: Loader::new(batcher.clone()),
             listing_loader: Loader::new(batcher.clone()),
@@ -97,21 +131,76 @@ impl AppContext {
             nft_files_loader: Loader::new(batcher.clone()),
             nft_loader: Loader::new(batcher.clone()),
             nft_owner_loader: Loader::new(batcher.clone()),
+            nft_parent_collection_loader: Loader::new(batcher.clone()),
             offer_loader: Loader::new(batcher.clone()),
             offers_loader: Loader::new(batcher.clone()),
             purchase_loader: Loader::new(batcher.clone()),
             purchase_receipt_loader: Loader::new(batcher.clone()),
             purchase_receipts_loader: Loader::new(batcher.clone()),
             purchases_loader: Loader::new(batcher.clone()),
+            slot_time_loader: Loader::new(batcher.clone()),
+            rpc_block_time_loader: Loader::new(rpc_batcher),
             store_auction_houses_loader: Loader::new(batcher.clone()),
             store_creator_loader: Loader::new(batcher.clone()),
             storefront_loader: Loader::new(batcher.clone()),
+            suspected_original_loader: Loader::new(batcher.clone()),
             twitter_handle_loader: Loader::new(batcher),
 
             twitter_profile_loader: Loader::new(twitter_batcher),
         }
     }
 
+    /// Attach the wallet session token sent with a request
+    #[must_use]
+    pub(crate) fn with_wallet_session(self, wallet_session: Option<String>) -> Self {
+        Self {
+            wallet_session,
+            ..self
+        }
+    }
+
+    /// Attach the address of the client making a request
+    #[must_use]
+    pub(crate) fn with_client_ip(self, client_ip: Option<String>) -> Self {
+        Self { client_ip, ..self }
+    }
+
+    /// The database pool this context's loaders read from
+    pub(crate) fn db(&self) -> &Pool {
+        &self.db
+    }
+
+    /// The wallet session token sent with the request, if any
+    pub(crate) fn wallet_session(&self) -> Option<&str> {
+        self.wallet_session.as_deref()
+    }
+
+    /// The address of the client making the request, if known
+    pub(crate) fn client_ip(&self) -> Option<&str> {
+        self.client_ip.as_deref()
+    }
+
+    /// Get the wallet authenticated by the request's session token
+    pub(crate) fn session_wallet(&self) -> FieldResult<PublicKey<Wallet>> {
+        let token = self.wallet_session().ok_or_else(|| {
+            FieldError::new(
+                "This field requires a wallet session",
+                graphql_value!({ "code": "UNAUTHENTICATED" }),
+            )
+        })?;
+
+        let conn = self.shared.db.get()?;
+
+        queries::wallet_auth::session_wallet(&conn, token)?
+            .map(Into::into)
+            .ok_or_else(|| {
+                FieldError::new(
+                    "Invalid or expired wallet session",
+                    graphql_value!({ "code": "UNAUTHENTICATED" }),
+                )
+            })
+    }
+
     #[inline]
     pub(crate) async fn wallet(&self, address: PublicKey<Wallet>) -> Result<Wallet> {
         let handle = self.twitter_handle_loader.load(address.clone()).await?;
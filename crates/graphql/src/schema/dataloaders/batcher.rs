@@ -126,6 +126,13 @@ pub struct TwitterBatcher {
     shared: Arc<SharedData>,
 }
 
+/// Batcher for calls to the Solana RPC endpoint, sent as JSON-RPC batch
+/// requests
+#[derive(Clone)]
+pub struct RpcBatcher {
+    shared: Arc<SharedData>,
+}
+
 impl Batcher {
     #[must_use]
     pub(crate) fn new(pool: Arc<Pool>) -> Self {
@@ -179,3 +186,45 @@ where
         }
     }
 }
+
+impl RpcBatcher {
+    #[must_use]
+    pub(crate) fn new(shared: Arc<SharedData>) -> Self {
+        Self { shared }
+    }
+
+    /// Send a JSON-RPC batch request to the Solana RPC endpoint
+    ///
+    /// # Errors
+    /// This function fails if the request fails or returns an invalid
+    /// response.
+    pub async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        calls: &[serde_json::Value],
+    ) -> Result<Vec<T>> {
+        self.shared
+            .http
+            .post(&self.shared.solana_endpoint)
+            .json(calls)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("Solana RPC request failed")?
+            .json()
+            .await
+            .context("Invalid Solana RPC response")
+    }
+}
+
+#[async_trait]
+impl<K: Clone + Eq + Hash + Sync, V> BatchFn<K, BatchResult<V>> for RpcBatcher
+where
+    RpcBatcher: TryBatchFn<K, V>,
+{
+    async fn load(&mut self, keys: &[K]) -> BatchMap<K, V> {
+        match TryBatchFn::load(self, keys).await {
+            Ok(m) => m,
+            Err(e) => keys.iter().cloned().map(|k| (k, Err(e.clone()))).collect(),
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use indexer_core::db::{
+    queries::collections,
     sql_query,
     sql_types::{Array, Text},
 };
-use objects::{nft::Nft, store_creator::StoreCreator};
+use objects::{collection::Collection, nft::Nft, store_creator::StoreCreator};
 use scalars::PublicKey;
 
 use super::prelude::*;
@@ -107,3 +108,19 @@ impl TryBatchFn<PublicKey<StoreCreator>, Vec<Nft>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<Collection>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<Collection>> {
+        let conn = self.db()?;
+        let rows = collections::keys(&conn, addresses)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, key)| (address, Collection::from(key)))
+            .batch(addresses))
+    }
+}
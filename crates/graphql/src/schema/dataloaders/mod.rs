@@ -9,6 +9,7 @@ pub mod listing;
 pub mod listing_receipt;
 pub mod nft;
 pub mod purchase_receipt;
+pub mod slot_time;
 pub mod stats;
 pub mod store_creator;
 pub mod storefront;
@@ -25,10 +26,10 @@ pub(self) mod prelude {
     pub(super) use super::{
         super::prelude::*,
         batcher::{
-            BatchIter, BatchMap, BatchResult, Batcher, Error, TryBatchFn, TryBatchMap,
+            BatchIter, BatchMap, BatchResult, Batcher, Error, RpcBatcher, TryBatchFn, TryBatchMap,
             TwitterBatcher,
         },
     };
 }
 
-pub use batcher::{BatchResult, Batcher, Error, Loader, TwitterBatcher};
+pub use batcher::{BatchResult, Batcher, Error, Loader, RpcBatcher, TwitterBatcher};
@@ -7,7 +7,7 @@ use objects::{
 use scalars::PublicKey;
 use tables::{
     attributes, current_metadata_owners, files, listing_receipts, metadata_creators,
-    metadata_jsons, metadatas, purchase_receipts, twitter_handle_name_services,
+    metadata_jsons, metadatas, purchase_receipts, suspected_copies, twitter_handle_name_services,
 };
 
 use super::prelude::*;
@@ -251,3 +251,29 @@ impl TryBatchFn<PublicKey<Nft>, Option<Nft>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<PublicKey<Nft>>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<PublicKey<Nft>>> {
+        let conn = self.db()?;
+
+        // Ordered by ascending similarity so the closest match is batched last
+        let rows: Vec<(String, String)> = suspected_copies::table
+            .filter(suspected_copies::copy_address.eq(any(addresses)))
+            .order(suspected_copies::similarity.asc())
+            .select((
+                suspected_copies::copy_address,
+                suspected_copies::original_address,
+            ))
+            .load(&conn)
+            .context("Failed to load suspected originals")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(copy, original)| (copy, PublicKey::from(original)))
+            .batch(addresses))
+    }
+}
@@ -0,0 +1,51 @@
+use indexer_core::db::queries::slot_times;
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<i64, Option<DateTime<Utc>>> for Batcher {
+    async fn load(&mut self, slots: &[i64]) -> TryBatchMap<i64, Option<DateTime<Utc>>> {
+        let conn = self.db()?;
+        let rows = slot_times::load(&conn, slots)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|t| (t.slot, DateTime::from_utc(t.block_time, Utc)))
+            .batch(slots))
+    }
+}
+
+/// A response to one call of a JSON-RPC batch request
+#[derive(Debug, serde::Deserialize)]
+struct BlockTimeResponse {
+    id: i64,
+    #[serde(default)]
+    result: Option<i64>,
+}
+
+/// Unix timestamps of slots the indexer has not recorded yet, fetched from
+/// the RPC endpoint in a single batch request.  Slots whose block time is
+/// unavailable, e.g. because they were skipped, have no value.
+#[async_trait]
+impl TryBatchFn<i64, Option<i64>> for RpcBatcher {
+    async fn load(&mut self, slots: &[i64]) -> TryBatchMap<i64, Option<i64>> {
+        let calls: Vec<_> = slots
+            .iter()
+            .map(|slot| {
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": slot,
+                    "method": "getBlockTime",
+                    "params": [slot],
+                })
+            })
+            .collect();
+
+        let responses: Vec<BlockTimeResponse> = self.call(&calls).await?;
+
+        Ok(responses
+            .into_iter()
+            .filter_map(|r| Some((r.id, r.result?)))
+            .batch(slots))
+    }
+}
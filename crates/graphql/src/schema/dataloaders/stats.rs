@@ -1,7 +1,8 @@
-use indexer_core::db::queries::stats;
+use indexer_core::db::queries::{collection_stats, stats};
 use objects::{
     auction_house::AuctionHouse,
-    stats::{MarketStats, MintStats},
+    collection::Collection,
+    stats::{CollectionStats, MarketStats, MintStats},
 };
 use scalars::{markers::StoreConfig, PublicKey};
 
@@ -38,3 +39,19 @@ impl TryBatchFn<PublicKey<StoreConfig>, Option<MarketStats>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Collection>, Vec<CollectionStats>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Collection>],
+    ) -> TryBatchMap<PublicKey<Collection>, Vec<CollectionStats>> {
+        let db = self.db()?;
+        let rows = collection_stats::load(&db, addresses)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|s| (s.collection_address.clone(), s.try_into()))
+            .batch(addresses))
+    }
+}
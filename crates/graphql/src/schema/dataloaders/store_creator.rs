@@ -1,7 +1,7 @@
 use indexer_core::db::{queries::nft_count, tables::twitter_handle_name_services};
-use objects::store_creator::StoreCreator;
+use objects::{marketplace::MarketplaceConfigVersion, store_creator::StoreCreator};
 use scalars::{markers::StoreConfig, PublicKey};
-use tables::store_creators;
+use tables::{store_config_json_versions, store_creators};
 
 use super::prelude::*;
 
@@ -32,6 +32,27 @@ impl TryBatchFn<PublicKey<StoreConfig>, Vec<StoreCreator>> for Batcher {
     }
 }
 
+#[async_trait]
+impl TryBatchFn<PublicKey<StoreConfig>, Vec<MarketplaceConfigVersion>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<StoreConfig>],
+    ) -> TryBatchMap<PublicKey<StoreConfig>, Vec<MarketplaceConfigVersion>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::StoreConfigJsonVersion> = store_config_json_versions::table
+            .filter(store_config_json_versions::config_address.eq(any(addresses)))
+            .order(store_config_json_versions::created_at.desc())
+            .load(&conn)
+            .context("Failed to load store config JSON versions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|v| (v.config_address.clone(), v.try_into()))
+            .batch(addresses))
+    }
+}
+
 #[async_trait]
 impl TryBatchFn<PublicKey<StoreCreator>, Option<i32>> for Batcher {
     async fn load(
@@ -1,4 +1,7 @@
-use indexer_core::db;
+use indexer_core::{
+    db::{self, queries},
+    search::Sort,
+};
 
 #[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
 #[graphql(description = "Sorts results ascending or descending")]
@@ -17,3 +20,102 @@ impl From<OrderDirection> for db::custom_types::OrderDirection {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
+#[graphql(description = "Sort orders for NFT search results and collection NFTs")]
+pub enum NftSearchSort {
+    #[graphql(name = "PRICE_ASC")]
+    PriceAsc,
+    #[graphql(name = "PRICE_DESC")]
+    PriceDesc,
+    #[graphql(name = "NAME_ASC")]
+    NameAsc,
+    #[graphql(name = "NAME_DESC")]
+    NameDesc,
+}
+
+impl NftSearchSort {
+    /// The search backend sort order for this order
+    #[must_use]
+    pub fn to_search(self) -> Sort<'static> {
+        let (field, descending) = match self {
+            Self::PriceAsc => ("price", false),
+            Self::PriceDesc => ("price", true),
+            Self::NameAsc => ("name", false),
+            Self::NameDesc => ("name", true),
+        };
+
+        Sort { field, descending }
+    }
+}
+
+impl From<NftSearchSort> for queries::metadatas::ListSort {
+    fn from(other: NftSearchSort) -> Self {
+        match other {
+            NftSearchSort::PriceAsc => Self::PriceAsc,
+            NftSearchSort::PriceDesc => Self::PriceDesc,
+            NftSearchSort::NameAsc => Self::NameAsc,
+            NftSearchSort::NameDesc => Self::NameDesc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
+#[graphql(description = "Kinds of NFT activity")]
+pub enum ActivityType {
+    #[graphql(name = "LISTING")]
+    Listing,
+    #[graphql(name = "PURCHASE")]
+    Purchase,
+    #[graphql(name = "OFFER")]
+    Offer,
+    #[graphql(name = "CANCEL_LISTING")]
+    CancelListing,
+    #[graphql(name = "CANCEL_OFFER")]
+    CancelOffer,
+    #[graphql(name = "TRANSFER")]
+    Transfer,
+}
+
+impl From<ActivityType> for queries::activities::ActivityType {
+    fn from(other: ActivityType) -> Self {
+        match other {
+            ActivityType::Listing => Self::Listing,
+            ActivityType::Purchase => Self::Purchase,
+            ActivityType::Offer => Self::Offer,
+            ActivityType::CancelListing => Self::CancelListing,
+            ActivityType::CancelOffer => Self::CancelOffer,
+            ActivityType::Transfer => Self::Transfer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
+#[graphql(description = "How the NFTs of a collection are grouped")]
+pub enum CollectionKind {
+    #[graphql(
+        name = "VERIFIED",
+        description = "NFTs verified as members of a collection NFT"
+    )]
+    Verified,
+    #[graphql(
+        name = "METADATA",
+        description = "NFTs naming the collection in their metadata JSON"
+    )]
+    Metadata,
+    #[graphql(
+        name = "CREATOR",
+        description = "NFTs sharing a first verified creator"
+    )]
+    Creator,
+}
+
+impl From<&queries::collections::CollectionKey> for CollectionKind {
+    fn from(other: &queries::collections::CollectionKey) -> Self {
+        match other {
+            queries::collections::CollectionKey::Verified(_) => Self::Verified,
+            queries::collections::CollectionKey::Family { .. } => Self::Metadata,
+            queries::collections::CollectionKey::Creator(_) => Self::Creator,
+        }
+    }
+}
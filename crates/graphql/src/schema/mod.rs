@@ -1,13 +1,17 @@
 #![allow(clippy::module_name_repetitions)]
 
-use juniper::{EmptyMutation, EmptySubscription, RootNode};
+use std::sync::Arc;
+
+use juniper::{EmptyMutation, RootNode};
 
 mod context;
 pub(self) mod dataloaders;
 pub mod enums;
+mod mutation_root;
 pub(self) mod objects;
 mod query_root;
 pub(self) mod scalars;
+mod subscription_root;
 
 pub(self) mod prelude {
     pub use std::{collections::HashMap, sync::Arc};
@@ -26,17 +30,38 @@ pub(self) mod prelude {
 
 pub use context::AppContext;
 
-pub type Schema = RootNode<
+pub type ReadWriteSchema = RootNode<
+    'static,
+    query_root::QueryRoot,
+    mutation_root::MutationRoot,
+    subscription_root::SubscriptionRoot,
+>;
+
+pub type ReadOnlySchema = RootNode<
     'static,
     query_root::QueryRoot,
     EmptyMutation<AppContext>,
-    EmptySubscription<AppContext>,
+    subscription_root::SubscriptionRoot,
 >;
 
-pub fn create() -> Schema {
-    Schema::new(
-        query_root::QueryRoot,
-        EmptyMutation::new(),
-        EmptySubscription::new(),
-    )
+/// The GraphQL schema, which only has mutations if the database is writable
+pub enum Schema {
+    ReadWrite(Arc<ReadWriteSchema>),
+    ReadOnly(Arc<ReadOnlySchema>),
+}
+
+pub fn create(mutations_enabled: bool) -> Schema {
+    if mutations_enabled {
+        Schema::ReadWrite(Arc::new(ReadWriteSchema::new(
+            query_root::QueryRoot,
+            mutation_root::MutationRoot,
+            subscription_root::SubscriptionRoot,
+        )))
+    } else {
+        Schema::ReadOnly(Arc::new(ReadOnlySchema::new(
+            query_root::QueryRoot,
+            EmptyMutation::new(),
+            subscription_root::SubscriptionRoot,
+        )))
+    }
 }
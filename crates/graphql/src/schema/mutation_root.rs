@@ -0,0 +1,270 @@
+use indexer_core::{
+    db::{queries, PooledConnection},
+    uuid::Uuid,
+};
+use objects::{
+    nft::Nft,
+    viewer::{NotificationPreferences, WalletNonce, WalletSession},
+    wallet::Wallet,
+};
+use scalars::{markers::StoreConfig, PublicKey};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use super::prelude::*;
+
+/// Maximum number of NFTs accepted by `setProfileNftOrder`
+const MAX_PROFILE_NFT_ORDER: usize = 1000;
+
+pub struct MutationRoot;
+
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(description = "The events a wallet wants to be notified of")]
+#[allow(clippy::struct_excessive_bools)]
+struct NotificationPreferencesInput {
+    offer_received: bool,
+    offer_accepted: bool,
+    listing_sold: bool,
+    outbid: bool,
+    watchlist_activity: bool,
+    new_follower: bool,
+}
+
+/// Get a database connection for a mutation.  The mutation root is only
+/// installed when the database is writable.
+fn writable_db(context: &AppContext) -> FieldResult<PooledConnection> {
+    context.shared.db.get().map_err(Into::into)
+}
+
+fn parse_wallet(wallet: &PublicKey<Wallet>) -> FieldResult<Pubkey> {
+    wallet.as_ref().parse().map_err(|_| {
+        FieldError::new(
+            "Invalid wallet address",
+            graphql_value!({ "invalid_parameter": "wallet" }),
+        )
+    })
+}
+
+#[graphql_object(Context = AppContext)]
+impl MutationRoot {
+    #[graphql(description = "Issue a nonce for a wallet to sign in order to create a session")]
+    fn create_wallet_nonce(
+        context: &AppContext,
+        wallet: PublicKey<Wallet>,
+    ) -> FieldResult<WalletNonce> {
+        parse_wallet(&wallet)?;
+
+        if let Some(ip) = context.client_ip() {
+            let rate = context.shared.wallet_nonces_per_minute;
+
+            context
+                .shared
+                .nonce_limiter
+                .take(ip.to_owned(), rate, rate)
+                .map_err(|retry_after| {
+                    let retry_after = i32::try_from(retry_after).unwrap_or(i32::MAX);
+
+                    FieldError::new(
+                        format!("Too many nonce requests, retry in {}s", retry_after),
+                        graphql_value!({ "code": "RATE_LIMITED", "retryAfter": retry_after }),
+                    )
+                })?;
+        }
+
+        let conn = writable_db(context)?;
+
+        let row = queries::wallet_auth::create_nonce(&conn, wallet.as_ref())?;
+
+        Ok(WalletNonce {
+            message: queries::wallet_auth::message(wallet.as_ref(), &row.nonce),
+            wallet,
+            nonce: row.nonce.into_owned(),
+            expires_at: DateTime::from_utc(row.expires_at, Utc),
+        })
+    }
+
+    #[graphql(description = "Create a session from a wallet's signature of a nonce message")]
+    fn create_wallet_session(
+        context: &AppContext,
+        wallet: PublicKey<Wallet>,
+        nonce: String,
+        #[graphql(description = "Base58-encoded signature of the nonce message")] signature: String,
+    ) -> FieldResult<WalletSession> {
+        let pubkey = parse_wallet(&wallet)?;
+        let signature: Signature = signature.parse().map_err(|_| {
+            FieldError::new(
+                "Invalid signature",
+                graphql_value!({ "invalid_parameter": "signature" }),
+            )
+        })?;
+        let conn = writable_db(context)?;
+
+        // Consume the nonce before checking the signature so each nonce can
+        // only be tried once
+        if !queries::wallet_auth::consume_nonce(&conn, wallet.as_ref(), &nonce)? {
+            return Err(FieldError::new(
+                "Unknown or expired nonce",
+                graphql_value!({ "invalid_parameter": "nonce" }),
+            ));
+        }
+
+        let message = queries::wallet_auth::message(wallet.as_ref(), &nonce);
+
+        if !signature.verify(pubkey.as_ref(), message.as_bytes()) {
+            return Err(FieldError::new(
+                "Signature verification failed",
+                graphql_value!({ "invalid_parameter": "signature" }),
+            ));
+        }
+
+        let (token, expires_at) = queries::wallet_auth::create_session(&conn, wallet.as_ref())?;
+
+        Ok(WalletSession {
+            wallet,
+            token,
+            expires_at: DateTime::from_utc(expires_at, Utc),
+        })
+    }
+
+    #[graphql(description = "End the current wallet session")]
+    fn end_wallet_session(context: &AppContext) -> FieldResult<bool> {
+        let conn = writable_db(context)?;
+
+        match context.wallet_session() {
+            Some(token) => queries::wallet_auth::delete_session(&conn, token).map_err(Into::into),
+            None => Ok(false),
+        }
+    }
+
+    #[graphql(description = "Add an NFT to the watchlist of the session wallet")]
+    fn watch_nft(context: &AppContext, nft: PublicKey<Nft>) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::watch_nft(&conn, wallet.as_ref(), nft.as_ref())
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Remove an NFT from the watchlist of the session wallet")]
+    fn unwatch_nft(context: &AppContext, nft: PublicKey<Nft>) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::unwatch_nft(&conn, wallet.as_ref(), nft.as_ref())
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Add a collection to the watchlist of the session wallet")]
+    fn watch_collection(
+        context: &AppContext,
+        #[graphql(description = "Metadata address of the collection NFT")] collection: PublicKey<
+            Nft,
+        >,
+    ) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::watch_collection(&conn, wallet.as_ref(), collection.as_ref())
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Remove a collection from the watchlist of the session wallet")]
+    fn unwatch_collection(
+        context: &AppContext,
+        #[graphql(description = "Metadata address of the collection NFT")] collection: PublicKey<
+            Nft,
+        >,
+    ) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::unwatch_collection(&conn, wallet.as_ref(), collection.as_ref())
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Set the notification preferences of the session wallet")]
+    fn set_notification_preferences(
+        context: &AppContext,
+        preferences: NotificationPreferencesInput,
+    ) -> FieldResult<NotificationPreferences> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        let NotificationPreferencesInput {
+            offer_received,
+            offer_accepted,
+            listing_sold,
+            outbid,
+            watchlist_activity,
+            new_follower,
+        } = preferences;
+
+        let row = models::NotificationPreference {
+            wallet_address: wallet.as_ref().into(),
+            offer_received,
+            offer_accepted,
+            listing_sold,
+            outbid,
+            watchlist_activity,
+            new_follower,
+            updated_at: Utc::now().naive_utc(),
+        };
+
+        queries::wallet_settings::set_notification_preferences(&conn, &row)?;
+
+        Ok(row.into())
+    }
+
+    #[graphql(description = "Hide an NFT from the profile of the session wallet")]
+    fn hide_nft(context: &AppContext, nft: PublicKey<Nft>) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::hide_nft(&conn, wallet.as_ref(), nft.as_ref()).map_err(Into::into)
+    }
+
+    #[graphql(description = "Show a hidden NFT on the profile of the session wallet")]
+    fn unhide_nft(context: &AppContext, nft: PublicKey<Nft>) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::wallet_settings::unhide_nft(&conn, wallet.as_ref(), nft.as_ref())
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Replace the display order of NFTs on the session wallet's profile")]
+    fn set_profile_nft_order(context: &AppContext, nfts: Vec<PublicKey<Nft>>) -> FieldResult<bool> {
+        if nfts.len() > MAX_PROFILE_NFT_ORDER {
+            return Err(FieldError::new(
+                "Too many NFTs to order",
+                graphql_value!({ "invalid_parameter": "nfts" }),
+            ));
+        }
+
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+        let nfts: Vec<String> = nfts.into_iter().map(Into::into).collect();
+
+        queries::wallet_settings::set_profile_nft_order(&conn, wallet.as_ref(), &nfts)?;
+
+        Ok(true)
+    }
+
+    #[graphql(
+        description = "Restore a version from a marketplace's config history.  Only the \
+                       marketplace's owner may do this, and the marketplace's next on-chain \
+                       config update replaces the restored version.  Returns false if the \
+                       version does not exist."
+    )]
+    fn revert_marketplace_config(
+        context: &AppContext,
+        #[graphql(description = "Address of the marketplace's store config")]
+        marketplace: PublicKey<StoreConfig>,
+        #[graphql(description = "ID of the version to restore, from configHistory")] version: Uuid,
+    ) -> FieldResult<bool> {
+        let wallet = context.session_wallet()?;
+        let conn = writable_db(context)?;
+
+        queries::store_configs::revert(&conn, marketplace.as_ref(), version, wallet.as_ref())
+            .map_err(Into::into)
+    }
+}
@@ -1,6 +1,7 @@
 use indexer_core::uuid::Uuid;
-use objects::{auction_house::AuctionHouse, nft::Nft, wallet::Wallet};
+use objects::{auction_house::AuctionHouse, nft::Nft, relay, wallet::Wallet};
 use scalars::{PublicKey, U64};
+use tables::listings;
 
 use super::prelude::*;
 
@@ -111,3 +112,12 @@ impl<'a> TryFrom<models::Listing<'a>> for AhListing {
         })
     }
 }
+
+relay::trade_connection!(
+    AhListingConnection,
+    AhListingEdge,
+    AhListing,
+    listings,
+    models::Listing,
+    "listings"
+);
@@ -1,6 +1,7 @@
 use indexer_core::uuid::Uuid;
-use objects::{auction_house::AuctionHouse, nft::Nft, wallet::Wallet};
+use objects::{auction_house::AuctionHouse, nft::Nft, relay, wallet::Wallet};
 use scalars::{PublicKey, U64};
+use tables::offers;
 
 use super::prelude::*;
 
@@ -118,3 +119,12 @@ impl<'a> TryFrom<models::Offer<'a>> for Offer {
         })
     }
 }
+
+relay::trade_connection!(
+    OfferConnection,
+    OfferEdge,
+    Offer,
+    offers,
+    models::Offer,
+    "offers"
+);
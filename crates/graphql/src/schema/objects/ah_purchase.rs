@@ -1,6 +1,7 @@
 use indexer_core::uuid::Uuid;
-use objects::{auction_house::AuctionHouse, nft::Nft, wallet::Wallet};
+use objects::{auction_house::AuctionHouse, nft::Nft, relay, wallet::Wallet};
 use scalars::{PublicKey, U64};
+use tables::purchases;
 
 use super::prelude::*;
 
@@ -90,3 +91,12 @@ impl<'a> TryFrom<models::Purchase<'a>> for Purchase {
         })
     }
 }
+
+relay::trade_connection!(
+    PurchaseConnection,
+    PurchaseEdge,
+    Purchase,
+    purchases,
+    models::Purchase,
+    "purchases"
+);
@@ -1,3 +1,5 @@
+use indexer_core::db::{queries, Connection};
+use objects::relay::{self, Keyset, KeysetCursor, PageArgs};
 use scalars::{I64, U64};
 
 use super::prelude::*;
@@ -33,3 +35,57 @@ impl<'a> TryFrom<models::EnrichedBondingChange<'a>> for EnrichedBondingChange {
         })
     }
 }
+
+impl Keyset for EnrichedBondingChange {
+    type Key = u64;
+
+    fn cursor(&self) -> KeysetCursor<u64> {
+        KeysetCursor {
+            created_at: self.insert_ts,
+            id: self.slot.into(),
+        }
+    }
+}
+
+relay::connection!(
+    EnrichedBondingChangeConnection,
+    EnrichedBondingChangeEdge,
+    EnrichedBondingChange
+);
+
+impl EnrichedBondingChangeConnection {
+    /// Load a page of changes to a bonding curve between two times, newest
+    /// first
+    pub fn load(
+        conn: &Connection,
+        address: &str,
+        start_ts: NaiveDateTime,
+        stop_ts: NaiveDateTime,
+        args: &PageArgs<u64>,
+    ) -> FieldResult<Self> {
+        let after = args
+            .after
+            .map(|c| Ok::<_, std::num::TryFromIntError>((c.created_at, c.id.try_into()?)))
+            .transpose()?;
+
+        let rows = queries::bonding_changes::list_page(
+            conn,
+            address,
+            start_ts,
+            stop_ts,
+            after,
+            args.limit(),
+        )?;
+        let total_count = queries::bonding_changes::count(conn, address, start_ts, stop_ts)?;
+
+        let nodes = rows
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            total_count: Some(total_count.try_into()?),
+            ..Self::new(nodes, args)
+        })
+    }
+}
@@ -1,13 +1,18 @@
-use indexer_core::db::queries::charts;
+use indexer_core::db::{
+    queries::{charts, collections::CollectionKey},
+    Connection,
+};
 use objects::{auction_house::AuctionHouse, creator::Creator};
 use scalars::{PublicKey, U64};
 
 use super::prelude::*;
+use crate::cache::{Resolver, Tag};
 
 #[derive(Debug, Clone)]
 pub struct PriceChart {
     pub auction_houses: Vec<PublicKey<AuctionHouse>>,
     pub creators: Option<Vec<PublicKey<Creator>>>,
+    pub collection: Option<CollectionKey>,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
 }
@@ -31,53 +36,128 @@ impl<'a> TryFrom<models::PricePoint> for PricePoint {
     }
 }
 
-#[graphql_object(Context = AppContext)]
 impl PriceChart {
-    pub fn listing_floor(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
-        let conn = ctx.shared.db.get()?;
-        let rows = charts::floor_prices(
-            &conn,
-            &self.auction_houses,
-            &self.creators,
-            self.start_date.naive_utc(),
-            self.end_date.naive_utc(),
-        )?;
-
-        rows.into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+    /// The invalidation tags of this chart's series, scoped as narrowly as
+    /// its filters allow so trades elsewhere on its auction houses do not
+    /// drop it
+    fn tags(&self) -> Vec<Tag> {
+        match (&self.collection, &self.creators) {
+            (Some(CollectionKey::Verified(mint)), _) => vec![Tag::Collection(mint.clone())],
+            (_, Some(creators)) => creators
+                .iter()
+                .map(|c| Tag::Creator(c.clone().into()))
+                .collect(),
+            (Some(CollectionKey::Creator(creator)), None) => vec![Tag::Creator(creator.clone())],
+            (Some(CollectionKey::Family { .. }) | None, None) => self
+                .auction_houses
+                .iter()
+                .map(|a| Tag::AuctionHouse(a.clone().into()))
+                .collect(),
+        }
     }
 
-    pub fn sales_average(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
-        let conn = ctx.shared.db.get()?;
-        let rows = charts::average_prices(
-            &conn,
-            &self.creators,
-            &self.auction_houses,
-            self.start_date.naive_utc(),
-            self.end_date.naive_utc(),
-        )?;
+    /// Load a price series through the resolver cache
+    ///
+    /// Points are cached as `(price, unix timestamp)` pairs.  The date range is
+    /// widened to the cache's chart buckets and passed to `load`, and points
+    /// outside the requested range are dropped from the result.
+    async fn series<'a, L>(
+        &'a self,
+        ctx: &'a AppContext,
+        name: &'static str,
+        load: L,
+    ) -> FieldResult<Vec<PricePoint>>
+    where
+        L: FnOnce(&Connection, NaiveDateTime, NaiveDateTime) -> Result<Vec<models::PricePoint>>
+            + Send
+            + 'a,
+    {
+        let (start_date, end_date) = ctx.shared.cache.chart_range(self.start_date, self.end_date);
+        let points: Vec<(i64, i64)> = ctx
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::Charts,
+                (
+                    name,
+                    &self.auction_houses,
+                    &self.creators,
+                    &self.collection,
+                    start_date,
+                    end_date,
+                ),
+                |_| self.tags(),
+                || async move {
+                    let conn = ctx.shared.db.get()?;
+
+                    Ok::<_, Error>(
+                        load(&*conn, start_date.naive_utc(), end_date.naive_utc())?
+                            .into_iter()
+                            .map(|p| (p.price, p.date.timestamp()))
+                            .collect(),
+                    )
+                },
+            )
+            .await?;
+
+        // Points are dated at the start of each day of the range
+        let first = self.start_date.date().and_hms(0, 0, 0).timestamp();
+        let last = self.end_date.timestamp();
 
-        rows.into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+        points
+            .into_iter()
+            .filter(|(_, date)| (first..=last).contains(date))
+            .map(|(price, date)| {
+                Ok(PricePoint {
+                    price: price.try_into()?,
+                    date: Utc.timestamp(date, 0),
+                })
+            })
+            .collect()
     }
+}
 
-    pub fn total_volume(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
-        let conn = ctx.shared.db.get()?;
-        let rows = charts::total_volume_prices(
-            &conn,
-            &self.auction_houses,
-            &self.creators,
-            self.start_date.naive_utc(),
-            self.end_date.naive_utc(),
-        )?;
+#[graphql_object(Context = AppContext)]
+impl PriceChart {
+    pub async fn listing_floor(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
+        self.series(ctx, "listing_floor", |conn, start_date, end_date| {
+            charts::floor_prices(
+                conn,
+                &self.auction_houses,
+                &self.creators,
+                self.collection.as_ref(),
+                start_date,
+                end_date,
+            )
+        })
+        .await
+    }
+
+    pub async fn sales_average(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
+        self.series(ctx, "sales_average", |conn, start_date, end_date| {
+            charts::average_prices(
+                conn,
+                &self.creators,
+                &self.auction_houses,
+                self.collection.as_ref(),
+                start_date,
+                end_date,
+            )
+        })
+        .await
+    }
 
-        rows.into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+    pub async fn total_volume(&self, ctx: &AppContext) -> FieldResult<Vec<PricePoint>> {
+        self.series(ctx, "total_volume", |conn, start_date, end_date| {
+            charts::total_volume_prices(
+                conn,
+                &self.auction_houses,
+                &self.creators,
+                self.collection.as_ref(),
+                start_date,
+                end_date,
+            )
+        })
+        .await
     }
 }
@@ -0,0 +1,317 @@
+use indexer_core::{
+    db::{
+        queries::{self, collections::CollectionKey},
+        tables::{current_metadata_owners, metadata_jsons, metadatas},
+    },
+    pubkeys,
+};
+use itertools::Itertools;
+use objects::{
+    auction_house::AuctionHouse,
+    chart::PriceChart,
+    creator::{AttributeGroup, AttributeVariant},
+    nft::{AttributeFilter, Nft, NftActivityConnection},
+    relay::PageArgs,
+    stats::CollectionStats,
+    wallet::Wallet,
+};
+use scalars::PublicKey;
+
+use super::prelude::*;
+use crate::schema::enums::{ActivityType, CollectionKind, NftSearchSort};
+
+#[derive(Debug, Clone)]
+pub struct Collection {
+    pub key: CollectionKey,
+}
+
+impl From<CollectionKey> for Collection {
+    fn from(key: CollectionKey) -> Self {
+        Self { key }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(Context = AppContext, description = "A wallet holding NFTs in a collection")]
+pub struct CollectionHolder {
+    pub wallet: Wallet,
+    #[graphql(description = "The number of the collection's NFTs held by the wallet")]
+    pub nft_count: i32,
+}
+
+impl TryFrom<models::CollectionHolder> for CollectionHolder {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::CollectionHolder {
+            owner_address,
+            twitter_handle,
+            nfts,
+        }: models::CollectionHolder,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            wallet: Wallet::new(owner_address.into(), twitter_handle),
+            nft_count: nfts.try_into()?,
+        })
+    }
+}
+
+#[graphql_object(Context = AppContext)]
+#[graphql(
+    description = "A collection of NFTs, identified by the mint address of its collection NFT, \
+                   or for NFTs without a verified collection by the collection named in their \
+                   metadata JSON or their first verified creator"
+)]
+impl Collection {
+    fn kind(&self) -> CollectionKind {
+        (&self.key).into()
+    }
+
+    #[graphql(
+        description = "The mint address of the collection NFT for verified collections, or the \
+                       creator address for creator collections"
+    )]
+    fn address(&self) -> Option<&str> {
+        match self.key {
+            CollectionKey::Verified(ref a) | CollectionKey::Creator(ref a) => Some(a.as_str()),
+            CollectionKey::Family { .. } => None,
+        }
+    }
+
+    #[graphql(description = "The collection name given by metadata JSON, for metadata collections")]
+    fn name(&self) -> Option<&str> {
+        match self.key {
+            CollectionKey::Family { ref name, .. } => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    #[graphql(
+        description = "The collection family given by metadata JSON, for metadata collections"
+    )]
+    fn family(&self) -> Option<&str> {
+        match self.key {
+            CollectionKey::Family { ref family, .. } => family.as_deref(),
+            _ => None,
+        }
+    }
+
+    #[graphql(description = "The collection NFT, for verified collections")]
+    fn nft(&self, ctx: &AppContext) -> FieldResult<Option<Nft>> {
+        let mint = match self.key {
+            CollectionKey::Verified(ref m) => m,
+            _ => return Ok(None),
+        };
+
+        let conn = ctx.shared.db.get()?;
+
+        metadatas::table
+            .inner_join(
+                metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+            )
+            .inner_join(
+                current_metadata_owners::table
+                    .on(current_metadata_owners::mint_address.eq(metadatas::mint_address)),
+            )
+            .filter(metadatas::mint_address.eq(mint))
+            .select(queries::metadatas::NFT_COLUMNS)
+            .first::<models::Nft>(&conn)
+            .optional()
+            .context("Failed to load collection NFT")?
+            .map(TryInto::try_into)
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "NFTs in the collection",
+        arguments(
+            attributes(description = "Filter on attributes"),
+            listed(description = "Filter only listed NFTs"),
+            auction_houses(description = "Filter NFTs associated to the list of auction houses"),
+            sort(
+                description = "Sort order for NFTs.  Defaults to listed NFTs first, cheapest \
+                                first."
+            ),
+            limit(description = "Limit for query"),
+            offset(description = "Offset for query"),
+        )
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn nfts(
+        &self,
+        ctx: &AppContext,
+        attributes: Option<Vec<AttributeFilter>>,
+        listed: Option<bool>,
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        sort: Option<NftSearchSort>,
+        limit: i32,
+        offset: i32,
+    ) -> FieldResult<Vec<Nft>> {
+        let conn = ctx.shared.db.get()?;
+
+        let nfts = queries::metadatas::list(&conn, queries::metadatas::ListQueryOptions {
+            addresses: None,
+            owners: None,
+            update_authorities: None,
+            auction_houses: auction_houses.map(|h| h.into_iter().map(Into::into).collect()),
+            creators: None,
+            offerers: None,
+            attributes: attributes.map(|a| a.into_iter().map(Into::into).collect()),
+            listed,
+            with_offers: None,
+            collections: None,
+            collection_key: Some(self.key.clone()),
+            exclude_suspected_copies: false,
+            sort: sort.map(Into::into),
+            limit: limit.try_into()?,
+            offset: offset.try_into()?,
+        })?;
+
+        nfts.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Wallets holding NFTs in the collection, largest holders first",
+        arguments(
+            limit(description = "Limit for query"),
+            offset(description = "Offset for query"),
+        )
+    )]
+    fn holders(
+        &self,
+        ctx: &AppContext,
+        limit: i32,
+        offset: i32,
+    ) -> FieldResult<Vec<CollectionHolder>> {
+        let conn = ctx.shared.db.get()?;
+
+        queries::collections::holders(&conn, &self.key, limit.into(), offset.into())?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "The attributes of NFTs in the collection, with their counts")]
+    fn attribute_groups(&self, ctx: &AppContext) -> FieldResult<Vec<AttributeGroup>> {
+        let conn = ctx.shared.db.get()?;
+
+        let counts = queries::collections::attributes(&conn, &self.key)?;
+
+        Ok(counts
+            .into_iter()
+            .map(
+                |models::AttributeCount {
+                     trait_type,
+                     value,
+                     count,
+                 }| {
+                    Result::<_>::Ok((trait_type, AttributeVariant {
+                        name: value.unwrap_or_default(),
+                        count: count.try_into()?,
+                    }))
+                },
+            )
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .into_group_map()
+            .into_iter()
+            .map(|(name, variants)| AttributeGroup {
+                name,
+                variants: variants.into_iter().sorted().collect(),
+            })
+            .sorted()
+            .collect())
+    }
+
+    #[graphql(
+        description = "Listings, purchases, offers, cancellations and transfers of NFTs in the \
+                       collection, newest first, paginated with opaque cursors",
+        arguments(
+            activity_types(description = "Only return activities of these kinds"),
+            first(description = "Maximum number of activities to return, at most 100"),
+            after(description = "Return activities after this cursor"),
+        )
+    )]
+    fn activities(
+        &self,
+        ctx: &AppContext,
+        activity_types: Option<Vec<ActivityType>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<NftActivityConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = ctx.shared.db.get()?;
+
+        let rows = queries::activities::list_page(
+            &conn,
+            queries::activities::ActivityFilters {
+                collection_key: Some(self.key.clone()),
+                activity_types: activity_types.map(|t| t.into_iter().map(Into::into).collect()),
+                ..queries::activities::ActivityFilters::default()
+            },
+            args.activities_after(),
+            args.limit(),
+        )?;
+
+        let nodes = rows
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+
+        Ok(NftActivityConnection::new(nodes, &args))
+    }
+
+    #[graphql(
+        description = "Marketplace statistics of the collection for a treasury mint, defaulting \
+                       to SOL.  Statistics are only computed for verified collections; this is \
+                       always null for collections keyed by a metadata JSON collection name or \
+                       a creator."
+    )]
+    pub async fn stats(
+        &self,
+        ctx: &AppContext,
+        treasury_mint: Option<String>,
+    ) -> FieldResult<Option<CollectionStats>> {
+        let mint = match self.key {
+            CollectionKey::Verified(ref m) => m,
+            _ => return Ok(None),
+        };
+
+        let treasury_mint = treasury_mint.unwrap_or_else(|| pubkeys::SOL.to_string());
+
+        Ok(ctx
+            .collection_stats_loader
+            .load(mint.clone().into())
+            .await?
+            .into_iter()
+            .find(|s| s.treasury_mint == treasury_mint))
+    }
+
+    #[graphql(
+        description = "Price charts of NFTs in the collection",
+        arguments(
+            auction_houses(description = "List of auction houses"),
+            start_date(description = "Start date for which we want to get the average price"),
+            end_date(description = "End date for which we want to get the average price"),
+        )
+    )]
+    fn charts(
+        &self,
+        auction_houses: Vec<PublicKey<AuctionHouse>>,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> PriceChart {
+        PriceChart {
+            auction_houses,
+            creators: None,
+            collection: Some(self.key.clone()),
+            start_date,
+            end_date,
+        }
+    }
+}
@@ -16,15 +16,15 @@ pub struct Creator {
 }
 
 #[derive(Debug, Clone, GraphQLObject, PartialEq, Eq, PartialOrd, Ord)]
-struct AttributeVariant {
-    name: String,
-    count: i32,
+pub struct AttributeVariant {
+    pub name: String,
+    pub count: i32,
 }
 
 #[derive(Debug, GraphQLObject, PartialEq, Eq, PartialOrd, Ord)]
-struct AttributeGroup {
-    name: String,
-    variants: Vec<AttributeVariant>,
+pub struct AttributeGroup {
+    pub name: String,
+    pub variants: Vec<AttributeVariant>,
 }
 #[derive(Debug, Clone)]
 struct CreatorCounts {
@@ -1,5 +1,8 @@
-use indexer_core::db::models;
-use objects::wallet::Wallet;
+use indexer_core::db::{models, queries, Connection};
+use objects::{
+    relay::{self, Keyset, KeysetCursor, PageArgs},
+    wallet::Wallet,
+};
 
 use super::prelude::*;
 
@@ -50,3 +53,47 @@ impl From<models::TwitterEnrichedGraphConnection> for GraphConnection {
         }
     }
 }
+
+impl Keyset for GraphConnection {
+    type Key = String;
+
+    fn cursor(&self) -> KeysetCursor<String> {
+        KeysetCursor {
+            created_at: self.connected_at.naive_utc(),
+            id: self.address.clone(),
+        }
+    }
+}
+
+relay::connection!(
+    GraphConnectionConnection,
+    GraphConnectionEdge,
+    GraphConnection
+);
+
+impl GraphConnectionConnection {
+    /// Load a page of active connections, most recently connected first, from
+    /// and to the given wallets.  An empty list of wallets matches any wallet.
+    pub fn load(
+        conn: &Connection,
+        from: &[String],
+        to: &[String],
+        args: &PageArgs<String>,
+    ) -> FieldResult<Self> {
+        let rows = queries::graph_connection::connections_page(
+            conn,
+            from,
+            to,
+            args.after.as_ref().map(|c| (c.created_at, c.id.clone())),
+            args.limit(),
+        )?;
+        let total_count = queries::graph_connection::count(conn, from, to)?;
+
+        let nodes = rows.into_iter().map(Into::into).collect();
+
+        Ok(Self {
+            total_count: Some(total_count.try_into()?),
+            ..Self::new(nodes, args)
+        })
+    }
+}
@@ -1,11 +1,82 @@
+use indexer_core::uuid::Uuid;
 use objects::{
-    auction_house::AuctionHouse, stats::MarketStats, store_creator::StoreCreator,
+    auction_house::AuctionHouse, relay, stats::MarketStats, store_creator::StoreCreator,
     storefront::Storefront,
 };
 
 use super::prelude::*;
 use crate::schema::scalars::{markers::StoreConfig, PublicKey};
 
+#[derive(Debug, Clone)]
+/// A historical version of a marketplace's config JSON
+pub struct MarketplaceConfigVersion {
+    pub id: Uuid,
+    pub config_address: PublicKey<StoreConfig>,
+    pub raw_content: serde_json::Value,
+    pub parser: String,
+    pub fetch_uri: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> TryFrom<models::StoreConfigJsonVersion<'a>> for MarketplaceConfigVersion {
+    type Error = Error;
+
+    fn try_from(
+        models::StoreConfigJsonVersion {
+            id,
+            config_address,
+            raw_content,
+            model,
+            fetch_uri,
+            created_at,
+        }: models::StoreConfigJsonVersion,
+    ) -> Result<Self> {
+        Ok(Self {
+            id: id.ok_or_else(|| anyhow!("Missing config version ID"))?,
+            config_address: config_address.into(),
+            raw_content: raw_content.into_owned(),
+            parser: model.into_owned(),
+            fetch_uri: fetch_uri.into_owned(),
+            created_at: DateTime::from_utc(created_at, Utc),
+        })
+    }
+}
+
+#[graphql_object(Context = AppContext)]
+impl MarketplaceConfigVersion {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn config_address(&self) -> &PublicKey<StoreConfig> {
+        &self.config_address
+    }
+
+    #[graphql(description = "The raw config JSON of this version, serialized as a string")]
+    pub fn raw_content(&self) -> String {
+        self.raw_content.to_string()
+    }
+
+    /// The JSON parser with which the config was processed by the indexer
+    ///
+    /// - `"full"` indicates the full parser was used.
+    /// - `"minimal"` (provided with an optional description of an error)
+    ///   indicates the full model failed to parse and a more lenient fallback
+    ///   parser was used instead.
+    pub fn parser(&self) -> &str {
+        &self.parser
+    }
+
+    pub fn fetch_uri(&self) -> &str {
+        &self.fetch_uri
+    }
+
+    #[graphql(description = "The time this version was first seen by the indexer")]
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
 #[derive(Debug, Clone)]
 /// An Holaplex marketplace
 pub struct Marketplace {
@@ -30,6 +101,7 @@ impl<'a> From<models::StoreConfigJson<'a>> for Marketplace {
             subdomain,
             owner_address,
             store_address,
+            ..
         }: models::StoreConfigJson,
     ) -> Self {
         Self {
@@ -45,6 +117,8 @@ impl<'a> From<models::StoreConfigJson<'a>> for Marketplace {
     }
 }
 
+relay::connection!(offset MarketplaceConnection, MarketplaceEdge, Marketplace);
+
 #[graphql_object(Context = AppContext)]
 impl Marketplace {
     pub fn config_address(&self) -> &PublicKey<StoreConfig> {
@@ -95,6 +169,17 @@ impl Marketplace {
             .map_err(Into::into)
     }
 
+    #[graphql(description = "Every version of this marketplace's config JSON, newest first")]
+    pub async fn config_history(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Vec<MarketplaceConfigVersion>> {
+        ctx.config_history_loader
+            .load(self.config_address.clone())
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn stats(&self, ctx: &AppContext) -> FieldResult<Option<MarketStats>> {
         ctx.market_stats_loader
             .load(self.config_address.clone())
@@ -5,6 +5,7 @@ pub mod auction_house;
 pub mod bid_receipt;
 pub mod bonding_change;
 pub mod chart;
+pub mod collection;
 pub mod creator;
 pub mod denylist;
 pub mod feed_event;
@@ -15,9 +16,12 @@ pub mod marketplace;
 pub mod nft;
 pub mod profile;
 pub mod purchase_receipt;
+pub mod relay;
+pub mod search;
 pub mod stats;
 pub mod store_creator;
 pub mod storefront;
+pub mod viewer;
 pub mod wallet;
 
 pub(self) mod prelude {
@@ -1,3 +1,5 @@
+use std::{fmt, str::FromStr};
+
 use indexer_core::{
     assets::{proxy_url, AssetIdentifier, ImageSize},
     db::{
@@ -8,14 +10,35 @@ use indexer_core::{
     uuid::Uuid,
 };
 use objects::{
-    ah_listing::AhListing, ah_offer::Offer, ah_purchase::Purchase, auction_house::AuctionHouse,
-    profile::TwitterProfile, wallet::Wallet,
+    ah_listing::{AhListing, AhListingConnection},
+    ah_offer::{Offer, OfferConnection},
+    ah_purchase::{Purchase, PurchaseConnection},
+    auction_house::AuctionHouse,
+    collection::Collection,
+    profile::TwitterProfile,
+    relay::{self, Keyset, KeysetCursor, PageArgs},
+    wallet::Wallet,
 };
 use reqwest::Url;
 use scalars::{PublicKey, U64};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::prelude::*;
+use crate::cache::Resolver;
+
+#[derive(GraphQLInputObject, Clone, Debug)]
+#[graphql(description = "Filter on NFT attributes")]
+pub struct AttributeFilter {
+    pub trait_type: String,
+    pub values: Vec<String>,
+}
+
+impl From<AttributeFilter> for queries::metadatas::AttributeFilter {
+    fn from(AttributeFilter { trait_type, values }: AttributeFilter) -> Self {
+        Self { trait_type, values }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct NftAttribute {
@@ -210,13 +233,69 @@ impl NftOwner {
 pub struct NftActivity {
     pub id: Uuid,
     pub metadata: PublicKey<Nft>,
-    pub auction_house: PublicKey<AuctionHouse>,
+    pub auction_house: Option<PublicKey<AuctionHouse>>,
     pub price: U64,
     pub created_at: DateTime<Utc>,
     pub wallets: Vec<Wallet>,
     pub activity_type: String,
 }
 
+/// Tiebreaker of an activity cursor.  A listing and its cancellation share an
+/// ID, so the activity type is part of the key.
+#[derive(Debug, Clone)]
+pub struct ActivityKey {
+    pub activity_type: String,
+    pub id: Uuid,
+}
+
+impl fmt::Display for ActivityKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.activity_type, self.id)
+    }
+}
+
+impl FromStr for ActivityKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (activity_type, id) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Missing activity type"))?;
+
+        Ok(Self {
+            activity_type: activity_type.to_owned(),
+            id: id.parse()?,
+        })
+    }
+}
+
+impl Keyset for NftActivity {
+    type Key = ActivityKey;
+
+    fn cursor(&self) -> KeysetCursor<ActivityKey> {
+        KeysetCursor {
+            created_at: self.created_at.naive_utc(),
+            id: ActivityKey {
+                activity_type: self.activity_type.clone(),
+                id: self.id,
+            },
+        }
+    }
+}
+
+impl PageArgs<ActivityKey> {
+    /// The position to resume a page of activities after, in the form taken
+    /// by [`queries::activities::list_page`]
+    #[must_use]
+    pub fn activities_after(&self) -> Option<(NaiveDateTime, Uuid, String)> {
+        self.after
+            .as_ref()
+            .map(|c| (c.created_at, c.id.id, c.id.activity_type.clone()))
+    }
+}
+
+relay::connection!(NftActivityConnection, NftActivityEdge, NftActivity);
+
 impl TryFrom<models::NftActivity> for NftActivity {
     type Error = std::num::TryFromIntError;
 
@@ -235,7 +314,7 @@ impl TryFrom<models::NftActivity> for NftActivity {
         Ok(Self {
             id,
             metadata: metadata.into(),
-            auction_house: auction_house.into(),
+            auction_house: auction_house.map(Into::into),
             price: price.try_into()?,
             created_at: DateTime::from_utc(created_at, Utc),
             wallets: wallets
@@ -281,16 +360,22 @@ impl NftActivity {
             .map_err(Into::into)
     }
 
+    #[graphql(description = "The auction house of the activity; null for transfers")]
     pub async fn auction_house(&self, context: &AppContext) -> FieldResult<Option<AuctionHouse>> {
+        let auction_house = match self.auction_house {
+            Some(ref a) => a.clone(),
+            None => return Ok(None),
+        };
+
         context
             .store_auction_houses_loader
-            .load(self.auction_house.clone())
+            .load(auction_house)
             .await
             .map_err(Into::into)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// An NFT
 pub struct Nft {
     pub address: String,
@@ -352,6 +437,8 @@ impl TryFrom<models::Nft> for Nft {
     }
 }
 
+relay::connection!(offset NftConnection, NftEdge, Nft);
+
 #[graphql_object(Context = AppContext)]
 impl Nft {
     pub fn address(&self) -> &str {
@@ -486,6 +573,64 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
+    #[graphql(
+        description = "All listings of this NFT, including canceled and sold ones, newest first",
+        arguments(
+            first(description = "Maximum number of listings to return, at most 100"),
+            after(description = "Return listings after this cursor"),
+        )
+    )]
+    pub fn listings_connection(
+        &self,
+        ctx: &AppContext,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<AhListingConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = ctx.shared.db.get()?;
+
+        AhListingConnection::load(&conn, None, Some(&[self.address.clone().into()]), &args)
+    }
+
+    #[graphql(
+        description = "All purchases of this NFT, newest first",
+        arguments(
+            first(description = "Maximum number of purchases to return, at most 100"),
+            after(description = "Return purchases after this cursor"),
+        )
+    )]
+    pub fn purchases_connection(
+        &self,
+        ctx: &AppContext,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<PurchaseConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = ctx.shared.db.get()?;
+
+        PurchaseConnection::load(&conn, None, Some(&[self.address.clone().into()]), &args)
+    }
+
+    #[graphql(
+        description = "All offers on this NFT, including canceled and accepted ones, newest \
+                       first",
+        arguments(
+            first(description = "Maximum number of offers to return, at most 100"),
+            after(description = "Return offers after this cursor"),
+        )
+    )]
+    pub fn offers_connection(
+        &self,
+        ctx: &AppContext,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<OfferConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = ctx.shared.db.get()?;
+
+        OfferConnection::load(&conn, None, Some(&[self.address.clone().into()]), &args)
+    }
+
     pub async fn files(&self, ctx: &AppContext) -> FieldResult<Vec<NftFile>> {
         ctx.nft_files_loader
             .load(self.address.clone().into())
@@ -500,24 +645,63 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
-    pub async fn created_at(&self, ctx: &AppContext) -> FieldResult<Option<DateTime<Utc>>> {
-        if let Some(slot) = self.slot {
-            let shared = ctx.shared.clone();
-
-            tokio::task::spawn_blocking(move || {
-                shared
-                    .rpc
-                    .get_block_time(slot.try_into().unwrap_or_default())
-                    .context("RPC call for block time failed")
-                    .and_then(|s| unix_timestamp(s).map(|t| DateTime::<Utc>::from_utc(t, Utc)))
-            })
+    #[graphql(
+        description = "The collection this NFT belongs to: its verified collection, or for NFTs \
+                       without one, the collection named in its metadata JSON or its first \
+                       verified creator"
+    )]
+    pub async fn parent_collection(&self, ctx: &AppContext) -> FieldResult<Option<Collection>> {
+        ctx.nft_parent_collection_loader
+            .load(self.address.clone().into())
             .await
-            .expect("Blocking task panicked")
-            .map(Some)
             .map_err(Into::into)
-        } else {
-            Ok(None)
+    }
+
+    #[graphql(
+        description = "The NFT this one appears to copy, if its image is a near-duplicate of an NFT from a different verified creator"
+    )]
+    pub async fn suspected_original(&self, ctx: &AppContext) -> FieldResult<Option<Nft>> {
+        let original = match ctx
+            .suspected_original_loader
+            .load(self.address.clone().into())
+            .await?
+        {
+            Some(o) => o,
+            None => return Ok(None),
+        };
+
+        ctx.nft_loader.load(original).await.map_err(Into::into)
+    }
+
+    pub async fn created_at(&self, ctx: &AppContext) -> FieldResult<Option<DateTime<Utc>>> {
+        let slot = match self.slot {
+            Some(s) => i64::from(s),
+            None => return Ok(None),
+        };
+
+        if let Some(time) = ctx.slot_time_loader.load(slot).await? {
+            return Ok(Some(time));
         }
+
+        // Fall back to RPC for slots the indexer has not recorded yet,
+        // batching the lookups of all NFTs resolved together
+        let timestamp = ctx
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::BlockTimes,
+                slot,
+                |_| vec![],
+                || async move {
+                    ctx.rpc_block_time_loader
+                        .load(slot)
+                        .await?
+                        .ok_or_else(|| anyhow!("Block time unavailable for slot {}", slot))
+                },
+            )
+            .await?;
+
+        Ok(Some(DateTime::from_utc(unix_timestamp(timestamp)?, Utc)))
     }
 }
 
@@ -632,8 +816,13 @@ pub struct MetadataJson {
     pub image: Option<String>,
     pub creator_address: Option<String>,
     pub creator_twitter_handle: Option<String>,
+    pub owner: Option<String>,
+    #[graphql(description = "The lowest active listing price, in lamports")]
+    pub price: Option<U64>,
 }
 
+relay::connection!(offset MetadataJsonConnection, MetadataJsonEdge, MetadataJson);
+
 impl From<serde_json::Value> for MetadataJson {
     fn from(value: serde_json::Value) -> Self {
         Self {
@@ -661,6 +850,8 @@ impl From<serde_json::Value> for MetadataJson {
                 .get("creator_twitter_handle")
                 .and_then(Value::as_str)
                 .map(Into::into),
+            owner: value.get("owner").and_then(Value::as_str).map(Into::into),
+            price: value.get("price").and_then(Value::as_u64).map(Into::into),
         }
     }
 }
@@ -671,43 +862,82 @@ pub struct NftsStats;
 #[graphql_object(Context = AppContext)]
 impl NftsStats {
     #[graphql(description = "The total number of indexed NFTs")]
-    fn total_nfts(&self, context: &AppContext) -> FieldResult<i32> {
-        let conn = context.shared.db.get()?;
-
-        let count: i64 = metadata_jsons::table
-            .count()
-            .get_result(&conn)
-            .context("failed to load total NFTs count")?;
-
-        Ok(count.try_into()?)
+    async fn total_nfts(&self, context: &AppContext) -> FieldResult<i32> {
+        let count = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::NftsStats,
+                "total_nfts",
+                |_| vec![],
+                || async move {
+                    let conn = context.shared.db.get()?;
+
+                    let count: i64 = metadata_jsons::table
+                        .count()
+                        .get_result(&conn)
+                        .context("failed to load total NFTs count")?;
+
+                    Ok::<_, Error>(count.try_into()?)
+                },
+            )
+            .await?;
+
+        Ok(count)
     }
 
     #[graphql(description = "The total number of buy-now listings")]
-    fn buy_now_listings(&self, context: &AppContext) -> FieldResult<i32> {
-        let conn = context.shared.db.get()?;
-
-        let count: i64 = listing_receipts::table
-            .filter(listing_receipts::price.is_not_null())
-            .filter(listing_receipts::purchase_receipt.is_null())
-            .filter(listing_receipts::canceled_at.is_null())
-            .count()
-            .get_result(&conn)
-            .context("failed to load listed nfts count")?;
-
-        Ok(count.try_into()?)
+    async fn buy_now_listings(&self, context: &AppContext) -> FieldResult<i32> {
+        let count = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::NftsStats,
+                "buy_now_listings",
+                |_| vec![],
+                || async move {
+                    let conn = context.shared.db.get()?;
+
+                    let count: i64 = listing_receipts::table
+                        .filter(listing_receipts::price.is_not_null())
+                        .filter(listing_receipts::purchase_receipt.is_null())
+                        .filter(listing_receipts::canceled_at.is_null())
+                        .count()
+                        .get_result(&conn)
+                        .context("failed to load listed nfts count")?;
+
+                    Ok::<_, Error>(count.try_into()?)
+                },
+            )
+            .await?;
+
+        Ok(count)
     }
 
     #[graphql(description = "The total number of NFTs with active offers")]
-    fn nfts_with_active_offers(&self, context: &AppContext) -> FieldResult<i32> {
-        let conn = context.shared.db.get()?;
-
-        let count: i64 = bid_receipts::table
-            .filter(bid_receipts::purchase_receipt.is_null())
-            .filter(bid_receipts::canceled_at.is_null())
-            .count()
-            .get_result(&conn)
-            .context("failed to load listed nfts count")?;
-
-        Ok(count.try_into()?)
+    async fn nfts_with_active_offers(&self, context: &AppContext) -> FieldResult<i32> {
+        let count = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::NftsStats,
+                "nfts_with_active_offers",
+                |_| vec![],
+                || async move {
+                    let conn = context.shared.db.get()?;
+
+                    let count: i64 = bid_receipts::table
+                        .filter(bid_receipts::purchase_receipt.is_null())
+                        .filter(bid_receipts::canceled_at.is_null())
+                        .count()
+                        .get_result(&conn)
+                        .context("failed to load listed nfts count")?;
+
+                    Ok::<_, Error>(count.try_into()?)
+                },
+            )
+            .await?;
+
+        Ok(count)
     }
 }
@@ -2,6 +2,7 @@ use serde::Deserialize;
 use tables::twitter_handle_name_services;
 
 use super::prelude::*;
+use crate::cache::Resolver;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct TwitterProfile {
@@ -59,14 +60,27 @@ pub struct ProfilesStats;
 #[graphql_object(Context = AppContext)]
 impl ProfilesStats {
     #[graphql(description = "The total number of indexed profiles")]
-    fn total_profiles(&self, context: &AppContext) -> FieldResult<i32> {
-        let conn = context.shared.db.get()?;
+    async fn total_profiles(&self, context: &AppContext) -> FieldResult<i32> {
+        let count = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::ProfilesStats,
+                "total_profiles",
+                |_| vec![],
+                || async move {
+                    let conn = context.shared.db.get()?;
 
-        let count: i64 = twitter_handle_name_services::table
-            .count()
-            .get_result(&conn)
-            .context("failed to load total profiles count")?;
+                    let count: i64 = twitter_handle_name_services::table
+                        .count()
+                        .get_result(&conn)
+                        .context("failed to load total profiles count")?;
 
-        Ok(count.try_into()?)
+                    Ok::<_, Error>(count.try_into()?)
+                },
+            )
+            .await?;
+
+        Ok(count)
     }
 }
@@ -0,0 +1,454 @@
+//! Relay-style cursor connections over rows ordered newest first, or over
+//! orderings with no usable keyset such as search relevance
+
+use std::{fmt::Display, str::FromStr};
+
+use indexer_core::uuid::Uuid;
+
+use super::prelude::*;
+
+/// Maximum number of nodes in a single page of a connection
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Information about the current page of a connection")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    #[graphql(description = "True if this page was requested with an `after` cursor")]
+    pub has_previous_page: bool,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
+}
+
+/// Position of a row ordered by descending creation time, with a unique key
+/// as a tiebreaker
+#[derive(Debug, Clone, Copy)]
+pub struct KeysetCursor<K = Uuid> {
+    pub created_at: NaiveDateTime,
+    pub id: K,
+}
+
+impl<K: Display + FromStr> KeysetCursor<K> {
+    #[must_use]
+    pub fn encode(&self) -> String {
+        base64::encode_config(
+            format!("{}:{}", self.created_at.timestamp_nanos(), self.id),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    pub fn decode(cursor: &str) -> FieldResult<Self> {
+        let invalid = || {
+            FieldError::new(
+                "Invalid cursor",
+                graphql_value!({ "invalid_parameter": "after" }),
+            )
+        };
+
+        let cursor = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|c| String::from_utf8(c).ok())
+            .ok_or_else(invalid)?;
+        let (nanos, id) = cursor.split_once(':').ok_or_else(invalid)?;
+        let nanos: i64 = nanos.parse().map_err(|_| invalid())?;
+
+        Ok(Self {
+            created_at: NaiveDateTime::from_timestamp_opt(
+                nanos.div_euclid(1_000_000_000),
+                nanos
+                    .rem_euclid(1_000_000_000)
+                    .try_into()
+                    .map_err(|_| invalid())?,
+            )
+            .ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A node that can be located with a [`KeysetCursor`]
+pub trait Keyset {
+    type Key;
+
+    fn cursor(&self) -> KeysetCursor<Self::Key>;
+}
+
+/// Position of a node in an ordering with no usable keyset, such as search
+/// relevance or a sort over several columns.  Connections using these share
+/// the costs of offset pagination and may skip or repeat nodes as rows change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetCursor(pub usize);
+
+impl OffsetCursor {
+    #[must_use]
+    pub fn encode(&self) -> String {
+        base64::encode_config(format!("offset:{}", self.0), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> FieldResult<Self> {
+        base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|c| String::from_utf8(c).ok())
+            .and_then(|c| c.strip_prefix("offset:")?.parse().ok())
+            .map(Self)
+            .ok_or_else(|| {
+                FieldError::new(
+                    "Invalid cursor",
+                    graphql_value!({ "invalid_parameter": "after" }),
+                )
+            })
+    }
+}
+
+fn page_size(first: i32) -> FieldResult<usize> {
+    if !(0..=MAX_PAGE_SIZE).contains(&first) {
+        return Err(FieldError::new(
+            "first must be between 0 and 100",
+            graphql_value!({ "invalid_parameter": "first" }),
+        ));
+    }
+
+    Ok(first.try_into()?)
+}
+
+/// Validated `first` and `after` arguments of a connection field
+#[derive(Debug, Clone)]
+pub struct PageArgs<K = Uuid> {
+    pub first: usize,
+    pub after: Option<KeysetCursor<K>>,
+}
+
+impl<K: Display + FromStr> PageArgs<K> {
+    pub fn new(first: i32, after: Option<&str>) -> FieldResult<Self> {
+        Ok(Self {
+            first: page_size(first)?,
+            after: after.map(KeysetCursor::decode).transpose()?,
+        })
+    }
+
+    /// The number of rows to load, one more than the page size to detect
+    /// whether a following page exists
+    #[must_use]
+    pub fn limit(&self) -> i64 {
+        (self.first + 1).try_into().unwrap_or(i64::MAX)
+    }
+}
+
+/// Validated `first` and `after` arguments of a connection field paginated
+/// with [`OffsetCursor`]s
+#[derive(Debug, Clone)]
+pub struct OffsetPageArgs {
+    pub first: usize,
+    pub after: Option<OffsetCursor>,
+}
+
+impl OffsetPageArgs {
+    pub fn new(first: i32, after: Option<&str>) -> FieldResult<Self> {
+        Ok(Self {
+            first: page_size(first)?,
+            after: after.map(OffsetCursor::decode).transpose()?,
+        })
+    }
+
+    /// The number of rows to skip
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.after.map_or(0, |OffsetCursor(o)| o + 1)
+    }
+
+    /// The number of rows to load, one more than the page size to detect
+    /// whether a following page exists
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.first + 1
+    }
+}
+
+/// Define a connection and edge type for a node type, either implementing
+/// [`Keyset`] with a `Display` key or, with the `offset` prefix, paginated
+/// with [`OffsetCursor`]s
+macro_rules! connection {
+    (@types $conn:ident, $edge:ident, $node:ty) => {
+        #[derive(Debug, Clone, GraphQLObject)]
+        #[graphql(Context = AppContext)]
+        pub struct $edge {
+            #[graphql(
+                description = "An opaque cursor to pass as `after` to resume after this node"
+            )]
+            pub cursor: String,
+            pub node: $node,
+        }
+
+        #[derive(Debug, Clone, GraphQLObject)]
+        #[graphql(Context = AppContext)]
+        pub struct $conn {
+            pub edges: Vec<$edge>,
+            pub page_info: objects::relay::PageInfo,
+            #[graphql(
+                description = "The number of nodes matching the filters across all pages, or \
+                               null where counting them would be expensive"
+            )]
+            pub total_count: Option<i32>,
+        }
+    };
+
+    (offset $conn:ident, $edge:ident, $node:ty) => {
+        objects::relay::connection!(@types $conn, $edge, $node);
+
+        impl $conn {
+            /// Build a page from nodes loaded using [`OffsetPageArgs::offset`]
+            /// and [`OffsetPageArgs::limit`]
+            ///
+            /// [`OffsetPageArgs::offset`]: objects::relay::OffsetPageArgs::offset
+            /// [`OffsetPageArgs::limit`]: objects::relay::OffsetPageArgs::limit
+            #[must_use]
+            pub fn new(mut nodes: Vec<$node>, args: &objects::relay::OffsetPageArgs) -> Self {
+                let has_next_page = nodes.len() > args.first;
+                nodes.truncate(args.first);
+
+                let offset = args.offset();
+                let edges: Vec<_> = nodes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, node)| $edge {
+                        cursor: objects::relay::OffsetCursor(offset + i).encode(),
+                        node,
+                    })
+                    .collect();
+
+                Self {
+                    page_info: objects::relay::PageInfo {
+                        has_next_page,
+                        has_previous_page: args.after.is_some(),
+                        start_cursor: edges.first().map(|e| e.cursor.clone()),
+                        end_cursor: edges.last().map(|e| e.cursor.clone()),
+                    },
+                    edges,
+                    total_count: None,
+                }
+            }
+        }
+    };
+
+    ($conn:ident, $edge:ident, $node:ty) => {
+        objects::relay::connection!(@types $conn, $edge, $node);
+
+        impl $conn {
+            /// Build a page from nodes loaded using [`PageArgs::limit`]
+            ///
+            /// [`PageArgs::limit`]: objects::relay::PageArgs::limit
+            #[must_use]
+            pub fn new(
+                mut nodes: Vec<$node>,
+                args: &objects::relay::PageArgs<<$node as objects::relay::Keyset>::Key>,
+            ) -> Self {
+                use objects::relay::Keyset;
+
+                let has_next_page = nodes.len() > args.first;
+                nodes.truncate(args.first);
+
+                let edges: Vec<_> = nodes
+                    .into_iter()
+                    .map(|node| $edge {
+                        cursor: node.cursor().encode(),
+                        node,
+                    })
+                    .collect();
+
+                Self {
+                    page_info: objects::relay::PageInfo {
+                        has_next_page,
+                        has_previous_page: args.after.is_some(),
+                        start_cursor: edges.first().map(|e| e.cursor.clone()),
+                        end_cursor: edges.last().map(|e| e.cursor.clone()),
+                    },
+                    edges,
+                    total_count: None,
+                }
+            }
+        }
+    };
+}
+
+/// Define a connection for an auction house trade node type, along with its
+/// [`Keyset`] implementation and a `load` function paging through `$table`
+/// newest first.  `$table` must have `auction_house`, `metadata`, `created_at`
+/// and `id` columns, and `$model` rows must convert into `$node` with
+/// `TryInto`.
+macro_rules! trade_connection {
+    ($conn:ident, $edge:ident, $node:ty, $table:ident, $model:ty, $name:literal) => {
+        impl objects::relay::Keyset for $node {
+            type Key = indexer_core::uuid::Uuid;
+
+            fn cursor(&self) -> objects::relay::KeysetCursor {
+                objects::relay::KeysetCursor {
+                    created_at: self.created_at.naive_utc(),
+                    id: self.id,
+                }
+            }
+        }
+
+        objects::relay::connection!($conn, $edge, $node);
+
+        impl $conn {
+            #[doc = concat!(
+                "Load a page of ",
+                $name,
+                ", newest first, optionally only those on the given auction houses or NFTs.  \
+                 The total count is only reported when filtering by NFT."
+            )]
+            pub fn load(
+                conn: &indexer_core::db::Connection,
+                auction_houses: Option<&[scalars::PublicKey<objects::auction_house::AuctionHouse>]>,
+                nfts: Option<&[scalars::PublicKey<objects::nft::Nft>]>,
+                args: &objects::relay::PageArgs,
+            ) -> FieldResult<Self> {
+                let mut query = $table::table.select($table::all_columns).into_boxed();
+
+                if let Some(auction_houses) = auction_houses {
+                    query = query.filter($table::auction_house.eq(any(auction_houses)));
+                }
+
+                if let Some(nfts) = nfts {
+                    query = query.filter($table::metadata.eq(any(nfts)));
+                }
+
+                if let Some(objects::relay::KeysetCursor { created_at, id }) = args.after {
+                    query = query.filter(
+                        $table::created_at
+                            .lt(created_at)
+                            .or($table::created_at.eq(created_at).and($table::id.lt(id))),
+                    );
+                }
+
+                let rows: Vec<$model> = query
+                    .order(($table::created_at.desc(), $table::id.desc()))
+                    .limit(args.limit())
+                    .load(conn)
+                    .context(concat!("Failed to load ", $name))?;
+
+                let nodes = rows
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?;
+
+                // Counting is only cheap when the rows are scoped to a few NFTs
+                let total_count = nfts
+                    .map(|nfts| {
+                        let mut query = $table::table
+                            .filter($table::metadata.eq(any(nfts)))
+                            .into_boxed();
+
+                        if let Some(auction_houses) = auction_houses {
+                            query = query.filter($table::auction_house.eq(any(auction_houses)));
+                        }
+
+                        query.count().get_result::<i64>(conn)
+                    })
+                    .transpose()
+                    .context(concat!("Failed to count ", $name))?
+                    .map(TryInto::try_into)
+                    .transpose()?;
+
+                Ok(Self {
+                    total_count,
+                    ..Self::new(nodes, args)
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use connection;
+pub(crate) use trade_connection;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip<K: Display + FromStr>(cursor: &KeysetCursor<K>) -> KeysetCursor<K> {
+        KeysetCursor::decode(&cursor.encode()).unwrap()
+    }
+
+    #[test]
+    fn decodes_encoded_cursors() {
+        let created_at = NaiveDate::from_ymd(2022, 8, 2).and_hms_nano(11, 48, 6, 123_456_789);
+        let id = Uuid::from_u128(0x1234_5678_9abc_def0_1234_5678_9abc_def0);
+
+        let cursor = roundtrip(&KeysetCursor { created_at, id });
+        assert_eq!((cursor.created_at, cursor.id), (created_at, id));
+
+        let cursor = roundtrip(&KeysetCursor {
+            created_at,
+            id: u64::MAX,
+        });
+        assert_eq!(cursor.id, u64::MAX);
+
+        // Only the timestamp is split off, so keys may contain colons
+        let cursor = roundtrip(&KeysetCursor {
+            created_at,
+            id: "listing:abc".to_owned(),
+        });
+        assert_eq!(cursor.id, "listing:abc");
+    }
+
+    #[test]
+    fn decodes_timestamps_before_the_epoch() {
+        let created_at = NaiveDate::from_ymd(1969, 12, 31).and_hms_nano(23, 59, 59, 500_000_000);
+        let cursor = roundtrip(&KeysetCursor {
+            created_at,
+            id: 1_u64,
+        });
+
+        assert_eq!(cursor.created_at, created_at);
+    }
+
+    #[test]
+    fn rejects_invalid_cursors() {
+        let encode = |s: &str| base64::encode_config(s, base64::URL_SAFE_NO_PAD);
+
+        for cursor in [
+            "not base64!".to_owned(),
+            encode("no separator"),
+            encode("nanos:1"),
+            encode("1:not a number"),
+            base64::encode_config([0xff, 0xfe], base64::URL_SAFE_NO_PAD),
+        ] {
+            assert!(
+                KeysetCursor::<u64>::decode(&cursor).is_err(),
+                "{:?} was accepted",
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_offset_cursors() {
+        let cursor = OffsetCursor(42);
+        assert_eq!(OffsetCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let encode = |s: &str| base64::encode_config(s, base64::URL_SAFE_NO_PAD);
+
+        for cursor in [encode("42"), encode("offset:-1"), encode("1:42")] {
+            assert!(
+                OffsetCursor::decode(&cursor).is_err(),
+                "{:?} was accepted",
+                cursor
+            );
+        }
+
+        let args = OffsetPageArgs::new(10, Some(&cursor.encode())).unwrap();
+        assert_eq!((args.offset(), args.limit()), (43, 11));
+        assert_eq!(OffsetPageArgs::new(10, None).unwrap().offset(), 0);
+    }
+
+    #[test]
+    fn validates_page_args() {
+        assert!(PageArgs::<u64>::new(-1, None).is_err());
+        assert!(PageArgs::<u64>::new(MAX_PAGE_SIZE + 1, None).is_err());
+        assert!(PageArgs::<u64>::new(10, Some("garbage")).is_err());
+
+        let args = PageArgs::<u64>::new(10, None).unwrap();
+        assert_eq!((args.first, args.limit()), (10, 11));
+    }
+}
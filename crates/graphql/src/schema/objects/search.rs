@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+
+use indexer_core::{
+    search::{MeiliBackend, Query},
+    util::decode_attribute,
+};
+use juniper::GraphQLUnion;
+use objects::{nft::MetadataJson, wallet::Wallet};
+use serde_json::Value;
+
+use super::prelude::*;
+
+/// The indexes searched by `searchAll`, in the order their hits are returned
+const SEARCH_ALL_INDEXES: [&str; 3] = ["metadatas", "collections", "wallets"];
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "The number of search hits with a given attribute value")]
+pub struct AttributeFacetValue {
+    pub value: String,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "The distribution of values for an attribute among search hits")]
+pub struct AttributeFacet {
+    pub trait_type: String,
+    pub values: Vec<AttributeFacetValue>,
+}
+
+impl AttributeFacet {
+    /// Group a facet distribution over encoded `trait_type:value` strings
+    /// by trait type, optionally keeping only the given trait types
+    pub fn from_distribution(
+        dist: HashMap<String, usize>,
+        trait_types: Option<&[String]>,
+    ) -> Result<Vec<Self>> {
+        let mut facets = BTreeMap::<_, Vec<_>>::new();
+
+        for (attr, count) in dist {
+            let (trait_type, value) = match decode_attribute(&attr) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            if trait_types.map_or(false, |t| !t.iter().any(|t| *t == trait_type)) {
+                continue;
+            }
+
+            facets
+                .entry(trait_type)
+                .or_default()
+                .push(AttributeFacetValue {
+                    value: value.to_owned(),
+                    count: count.try_into()?,
+                });
+        }
+
+        Ok(facets
+            .into_iter()
+            .map(|(trait_type, mut values)| {
+                values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+                Self { trait_type, values }
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A page of NFT search results")]
+pub struct NftSearchResult {
+    pub hits: Vec<MetadataJson>,
+    #[graphql(description = "The total number of NFTs matching the query and filters")]
+    pub total: i32,
+    #[graphql(description = "Attribute value counts across all matching NFTs")]
+    pub facets: Vec<AttributeFacet>,
+}
+
+#[derive(Debug, Clone, Copy, GraphQLObject)]
+#[graphql(description = "The location of a search term match within a field value")]
+pub struct MatchPosition {
+    #[graphql(description = "The byte offset of the match")]
+    pub start: i32,
+    #[graphql(description = "The length of the match, in bytes")]
+    pub length: i32,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A field of a search hit that matched the search term")]
+pub struct SearchHighlight {
+    #[graphql(description = "The path of the matching field, e.g. `name` or `attributes.value`")]
+    pub field: String,
+    #[graphql(
+        description = "The field value with matches wrapped in `<em>` tags, if the field holds \
+                       a single string"
+    )]
+    pub formatted: Option<String>,
+    pub matches: Vec<MatchPosition>,
+}
+
+impl SearchHighlight {
+    /// Remove the `_formatted` and `_matchesPosition` fields Meilisearch adds
+    /// to a hit, returning the highlights they describe
+    fn take_from(hit: &mut Value) -> Vec<Self> {
+        let obj = match hit.as_object_mut() {
+            Some(o) => o,
+            None => return vec![],
+        };
+
+        let formatted = obj.remove("_formatted");
+        let positions = match obj.remove("_matchesPosition") {
+            Some(Value::Object(p)) => p,
+            _ => return vec![],
+        };
+
+        let mut highlights: Vec<_> = positions
+            .into_iter()
+            .map(|(field, matches)| {
+                let formatted = formatted
+                    .as_ref()
+                    .and_then(|f| f.get(&field))
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned);
+
+                let matches = matches
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|m| {
+                        Some(MatchPosition {
+                            start: m.get("start")?.as_u64()?.try_into().ok()?,
+                            length: m.get("length")?.as_u64()?.try_into().ok()?,
+                        })
+                    })
+                    .collect();
+
+                Self {
+                    field,
+                    formatted,
+                    matches,
+                }
+            })
+            .collect();
+
+        highlights.sort_by(|a, b| a.field.cmp(&b.field));
+
+        highlights
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "An NFT matching a search term")]
+pub struct NftSearchHit {
+    pub nft: MetadataJson,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A collection matching a search term")]
+pub struct CollectionSearchHit {
+    pub collection: MetadataJson,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(
+    Context = AppContext,
+    description = "A profile matching a search term"
+)]
+pub struct ProfileSearchHit {
+    pub profile: Wallet,
+    pub highlights: Vec<SearchHighlight>,
+}
+
+#[derive(Debug, Clone, derive_more::From, GraphQLUnion)]
+#[graphql(
+  Context = AppContext,
+)]
+pub enum SearchHit {
+    Nft(NftSearchHit),
+    Collection(CollectionSearchHit),
+    Profile(ProfileSearchHit),
+}
+
+impl SearchHit {
+    fn new(index: &str, mut hit: Value) -> Option<Self> {
+        let highlights = SearchHighlight::take_from(&mut hit);
+
+        Some(match index {
+            "metadatas" => NftSearchHit {
+                nft: hit.into(),
+                highlights,
+            }
+            .into(),
+            "collections" => CollectionSearchHit {
+                collection: hit.into(),
+                highlights,
+            }
+            .into(),
+            "wallets" => ProfileSearchHit {
+                profile: Wallet::from_document(&hit)?,
+                highlights,
+            }
+            .into(),
+            _ => return None,
+        })
+    }
+
+    /// Search the NFT, collection and profile indexes concurrently, returning
+    /// up to `limit` hits of each type.
+    ///
+    /// Meilisearch reports highlights for each hit.  Meilisearch v1.1 and
+    /// newer answer all three queries in a single multi-search request; older
+    /// versions are queried once per index.  Other backends run the queries
+    /// separately and return hits without highlights.
+    ///
+    /// # Errors
+    /// This function fails if the search backend returns an error.
+    pub(crate) async fn search_all(
+        shared: &SharedData,
+        term: &str,
+        limit: usize,
+    ) -> Result<Vec<Self>> {
+        let results = if let Some(meili) = shared.search.as_meili() {
+            let queries: Vec<_> = SEARCH_ALL_INDEXES
+                .iter()
+                .map(|index| {
+                    serde_json::json!({
+                        "indexUid": index,
+                        "q": term,
+                        "limit": limit,
+                        "attributesToHighlight": ["*"],
+                        "showMatchesPosition": true,
+                    })
+                })
+                .collect();
+
+            match meili_multi_search(shared, meili, &queries).await? {
+                Some(results) => results,
+                None => {
+                    futures_util::future::try_join_all(
+                        queries
+                            .iter()
+                            .map(|query| meili_index_search(shared, meili, query)),
+                    )
+                    .await?
+                },
+            }
+        } else {
+            let results =
+                futures_util::future::try_join_all(SEARCH_ALL_INDEXES.iter().map(|index| {
+                    shared.search.query(Query {
+                        index,
+                        term,
+                        filters: &[],
+                        limit,
+                        offset: 0,
+                    })
+                }))
+                .await?;
+
+            SEARCH_ALL_INDEXES
+                .iter()
+                .map(|i| (*i).to_owned())
+                .zip(results.into_iter().map(|h| h.hits))
+                .collect()
+        };
+
+        Ok(results
+            .into_iter()
+            .flat_map(|(index, hits)| {
+                hits.into_iter()
+                    .filter_map(move |hit| Self::new(&index, hit))
+            })
+            .collect())
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexResult {
+    index_uid: String,
+    hits: Vec<Value>,
+}
+
+/// Run several Meilisearch queries in one multi-search request, returning
+/// the hits of each query along with its index, or `None` if the server
+/// predates the multi-search API
+async fn meili_multi_search(
+    shared: &SharedData,
+    meili: &MeiliBackend,
+    queries: &[Value],
+) -> Result<Option<Vec<(String, Vec<Value>)>>> {
+    #[derive(serde::Deserialize)]
+    struct Response {
+        results: Vec<IndexResult>,
+    }
+
+    let res = shared
+        .http
+        .post(format!(
+            "{}/multi-search",
+            meili.url().trim_end_matches('/')
+        ))
+        .bearer_auth(meili.key())
+        .json(&serde_json::json!({ "queries": queries }))
+        .send()
+        .await
+        .context("Multi-search request failed")?;
+
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let Response { results } = res
+        .error_for_status()
+        .context("Meilisearch rejected multi-search request")?
+        .json()
+        .await
+        .context("Failed to parse multi-search response")?;
+
+    Ok(Some(
+        results
+            .into_iter()
+            .map(|IndexResult { index_uid, hits }| (index_uid, hits))
+            .collect(),
+    ))
+}
+
+/// Run a single multi-search query against its index's search endpoint,
+/// returning its hits along with the index
+async fn meili_index_search(
+    shared: &SharedData,
+    meili: &MeiliBackend,
+    query: &Value,
+) -> Result<(String, Vec<Value>)> {
+    #[derive(serde::Deserialize)]
+    struct Response {
+        hits: Vec<Value>,
+    }
+
+    let mut query = query.clone();
+    let index = match query.as_object_mut().and_then(|q| q.remove("indexUid")) {
+        Some(Value::String(i)) => i,
+        _ => bail!("Search query is missing its index"),
+    };
+
+    let Response { hits } = shared
+        .http
+        .post(format!(
+            "{}/indexes/{}/search",
+            meili.url().trim_end_matches('/'),
+            index
+        ))
+        .bearer_auth(meili.key())
+        .json(&query)
+        .send()
+        .await
+        .with_context(|| format!("Search request for {:?} failed", index))?
+        .error_for_status()
+        .with_context(|| format!("Meilisearch rejected search request for {:?}", index))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse search response for {:?}", index))?;
+
+    Ok((index, hits))
+}
@@ -1,4 +1,4 @@
-use objects::auction_house::AuctionHouse;
+use objects::{auction_house::AuctionHouse, collection::Collection};
 use scalars::{PublicKey, U64};
 
 use super::prelude::*;
@@ -88,3 +88,80 @@ impl<'a> TryFrom<models::MarketStats<'a>> for MarketStats {
         })
     }
 }
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Marketplace statistics of a verified collection for a treasury mint")]
+pub struct CollectionStats {
+    #[graphql(skip)]
+    pub collection: PublicKey<Collection>,
+    pub treasury_mint: String,
+    #[graphql(description = "The lowest price of an active listing")]
+    pub floor_price: Option<U64>,
+    #[graphql(description = "The number of NFTs with active listings")]
+    pub listed_count: U64,
+    #[graphql(description = "The highest price of an open offer")]
+    pub top_offer: Option<U64>,
+    #[graphql(description = "The number of open offers")]
+    pub offer_count: U64,
+    #[graphql(description = "The number of distinct wallets holding NFTs in the collection")]
+    pub holder_count: U64,
+    #[graphql(description = "The number of NFTs in the collection")]
+    pub supply: U64,
+    #[graphql(description = "The sum of sale prices over the last day")]
+    pub volume_1d: U64,
+    #[graphql(description = "The sum of sale prices over the last seven days")]
+    pub volume_7d: U64,
+    #[graphql(description = "The sum of sale prices over the last thirty days")]
+    pub volume_30d: U64,
+    #[graphql(description = "The sum of all sale prices")]
+    pub volume_all: U64,
+    pub sales_count: U64,
+    #[graphql(description = "The mean sale price")]
+    pub average_price: Option<U64>,
+    #[graphql(description = "The time the statistics were last computed")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'a> TryFrom<models::CollectionStats<'a>> for CollectionStats {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::CollectionStats {
+            collection_address,
+            treasury_mint,
+            floor_price,
+            listed_count,
+            holder_count,
+            supply,
+            volume_1d,
+            volume_7d,
+            volume_30d,
+            volume_all,
+            sales_count,
+            average_price,
+            updated_at,
+            dirty: _,
+            top_offer,
+            offer_count,
+            version: _,
+        }: models::CollectionStats,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            collection: collection_address.into(),
+            treasury_mint: treasury_mint.into_owned(),
+            floor_price: floor_price.map(TryInto::try_into).transpose()?,
+            listed_count: listed_count.try_into()?,
+            top_offer: top_offer.map(TryInto::try_into).transpose()?,
+            offer_count: offer_count.try_into()?,
+            holder_count: holder_count.try_into()?,
+            supply: supply.try_into()?,
+            volume_1d: volume_1d.try_into()?,
+            volume_7d: volume_7d.try_into()?,
+            volume_30d: volume_30d.try_into()?,
+            volume_all: volume_all.try_into()?,
+            sales_count: sales_count.try_into()?,
+            average_price: average_price.map(TryInto::try_into).transpose()?,
+            updated_at: DateTime::from_utc(updated_at, Utc),
+        })
+    }
+}
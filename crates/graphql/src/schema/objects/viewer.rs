@@ -0,0 +1,155 @@
+use futures_util::future::try_join_all;
+use indexer_core::db::queries;
+use objects::{nft::Nft, wallet::Wallet};
+use scalars::PublicKey;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A nonce to be signed by a wallet to authenticate")]
+pub struct WalletNonce {
+    pub wallet: PublicKey<Wallet>,
+    pub nonce: String,
+    #[graphql(description = "The exact message the wallet must sign")]
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A session authenticating a wallet")]
+pub struct WalletSession {
+    pub wallet: PublicKey<Wallet>,
+    #[graphql(description = "Token to send in the `X-Wallet-Session` header")]
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "The events a wallet wants to be notified of")]
+#[allow(clippy::struct_excessive_bools)]
+pub struct NotificationPreferences {
+    pub offer_received: bool,
+    pub offer_accepted: bool,
+    pub listing_sold: bool,
+    pub outbid: bool,
+    pub watchlist_activity: bool,
+    pub new_follower: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            offer_received: true,
+            offer_accepted: true,
+            listing_sold: true,
+            outbid: true,
+            watchlist_activity: true,
+            new_follower: true,
+        }
+    }
+}
+
+impl<'a> From<models::NotificationPreference<'a>> for NotificationPreferences {
+    fn from(
+        models::NotificationPreference {
+            offer_received,
+            offer_accepted,
+            listing_sold,
+            outbid,
+            watchlist_activity,
+            new_follower,
+            ..
+        }: models::NotificationPreference,
+    ) -> Self {
+        Self {
+            offer_received,
+            offer_accepted,
+            listing_sold,
+            outbid,
+            watchlist_activity,
+            new_follower,
+        }
+    }
+}
+
+/// Private settings of the wallet authenticated by the current session
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    pub wallet: PublicKey<Wallet>,
+}
+
+/// Load NFTs by metadata address, preserving order and skipping any not
+/// found
+async fn load_nfts(context: &AppContext, addresses: Vec<String>) -> FieldResult<Vec<Nft>> {
+    let nfts = try_join_all(
+        addresses
+            .into_iter()
+            .map(|a| context.nft_loader.load(a.into())),
+    )
+    .await?;
+
+    Ok(nfts.into_iter().flatten().collect())
+}
+
+#[graphql_object(Context = AppContext)]
+impl Viewer {
+    async fn wallet(&self, context: &AppContext) -> FieldResult<Wallet> {
+        context
+            .wallet(self.wallet.clone())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "NFTs on the wallet's watchlist, most recently added first")]
+    async fn nft_watchlist(&self, context: &AppContext) -> FieldResult<Vec<Nft>> {
+        let addresses = {
+            let conn = context.shared.db.get()?;
+            queries::wallet_settings::nft_watchlist(&conn, self.wallet.as_ref())?
+        };
+
+        load_nfts(context, addresses).await
+    }
+
+    #[graphql(description = "Collections on the wallet's watchlist, most recently added first")]
+    async fn collection_watchlist(&self, context: &AppContext) -> FieldResult<Vec<Nft>> {
+        let addresses = {
+            let conn = context.shared.db.get()?;
+            queries::wallet_settings::collection_watchlist(&conn, self.wallet.as_ref())?
+        };
+
+        load_nfts(context, addresses).await
+    }
+
+    fn notification_preferences(
+        &self,
+        context: &AppContext,
+    ) -> FieldResult<NotificationPreferences> {
+        let conn = context.shared.db.get()?;
+
+        Ok(
+            queries::wallet_settings::notification_preferences(&conn, self.wallet.as_ref())?
+                .map(Into::into)
+                .unwrap_or_default(),
+        )
+    }
+
+    #[graphql(description = "NFTs the wallet has hidden from its profile")]
+    async fn hidden_nfts(&self, context: &AppContext) -> FieldResult<Vec<Nft>> {
+        let addresses = {
+            let conn = context.shared.db.get()?;
+            queries::wallet_settings::hidden_nfts(&conn, self.wallet.as_ref())?
+        };
+
+        load_nfts(context, addresses).await
+    }
+
+    #[graphql(description = "NFTs in the display order chosen for the wallet's profile")]
+    async fn profile_nft_order(&self, context: &AppContext) -> FieldResult<Vec<Nft>> {
+        let addresses = {
+            let conn = context.shared.db.get()?;
+            queries::wallet_settings::profile_nft_order(&conn, self.wallet.as_ref())?
+        };
+
+        load_nfts(context, addresses).await
+    }
+}
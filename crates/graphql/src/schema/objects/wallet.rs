@@ -1,6 +1,6 @@
 use indexer_core::db::{models, queries};
 use objects::{
-    auction_house::AuctionHouse, listing::Bid, nft::NftCreator, profile::TwitterProfile,
+    auction_house::AuctionHouse, listing::Bid, nft::NftCreator, profile::TwitterProfile, relay,
 };
 use scalars::PublicKey;
 use tables::{bids, graph_connections};
@@ -34,6 +34,17 @@ impl Wallet {
             twitter_handle,
         }
     }
+
+    /// Read a wallet from a `wallets` search index document
+    pub fn from_document(doc: &serde_json::Value) -> Option<Self> {
+        let address = doc.get("address")?.as_str()?;
+        let twitter_handle = doc
+            .get("twitter_handle")
+            .and_then(serde_json::Value::as_str)
+            .map(ToOwned::to_owned);
+
+        Some(Self::new(address.to_owned().into(), twitter_handle))
+    }
 }
 
 impl From<serde_json::Value> for Wallet {
@@ -119,6 +130,8 @@ impl WalletNftCount {
     }
 }
 
+relay::connection!(offset WalletConnection, WalletEdge, Wallet);
+
 #[graphql_object(Context = AppContext)]
 impl Wallet {
     pub fn address(&self) -> &PublicKey<Wallet> {
@@ -1,45 +1,251 @@
-use indexer_core::db::{
-    expression::dsl::all,
-    queries::{self, feed_event::EventType},
-    tables::twitter_handle_name_services,
+use indexer_core::{
+    db::{
+        expression::dsl::all,
+        queries::{self, feed_event::EventType},
+        tables::twitter_handle_name_services,
+    },
+    search::{FacetedQuery, Filter, Query, RangeFilter},
+    util::encode_attribute,
 };
 use objects::{
-    ah_listing::AhListing,
+    ah_listing::{AhListing, AhListingConnection},
+    ah_offer::OfferConnection,
+    ah_purchase::PurchaseConnection,
     auction_house::AuctionHouse,
     bid_receipt::BidReceipt,
-    bonding_change::EnrichedBondingChange,
+    bonding_change::{EnrichedBondingChange, EnrichedBondingChangeConnection},
     chart::PriceChart,
+    collection::Collection,
     creator::Creator,
     denylist::Denylist,
     feed_event::FeedEvent,
-    graph_connection::GraphConnection,
+    graph_connection::{GraphConnection, GraphConnectionConnection},
     listing::{Listing, ListingColumns, ListingRow},
-    marketplace::Marketplace,
-    nft::{MetadataJson, Nft, NftActivity, NftCount, NftCreator, NftsStats},
+    marketplace::{Marketplace, MarketplaceConnection},
+    nft::{
+        AttributeFilter, MetadataJson, MetadataJsonConnection, Nft, NftActivity,
+        NftActivityConnection, NftConnection, NftCount, NftCreator, NftsStats,
+    },
     profile::{ProfilesStats, TwitterProfile},
+    relay::{OffsetPageArgs, PageArgs},
+    search::{AttributeFacet, NftSearchResult, SearchHit},
     storefront::{Storefront, StorefrontColumns},
-    wallet::Wallet,
+    viewer::Viewer,
+    wallet::{Wallet, WalletConnection},
 };
-use scalars::PublicKey;
+use scalars::{PublicKey, U64};
 use serde_json::Value;
 use tables::{
     auction_caches, auction_datas, auction_datas_ext, bid_receipts, current_metadata_owners,
     graph_connections, metadata_jsons, metadatas, store_config_jsons, storefronts, wallet_totals,
 };
 
-use super::{enums::OrderDirection, prelude::*};
+use super::{
+    enums::{ActivityType, NftSearchSort, OrderDirection},
+    prelude::*,
+};
+use crate::cache::{Resolver, Tag};
+
+/// Maximum number of hits of each type returned by `searchAll`
+const SEARCH_ALL_MAX_LIMIT: i32 = 20;
+
 pub struct QueryRoot;
 
 #[derive(GraphQLInputObject, Clone, Debug)]
-#[graphql(description = "Filter on NFT attributes")]
-struct AttributeFilter {
-    trait_type: String,
-    values: Vec<String>,
+#[graphql(description = "Filters applied to an NFT search")]
+struct NftSearchFilters {
+    #[graphql(description = "Only return NFTs in one of these collections")]
+    collections: Option<Vec<PublicKey<Nft>>>,
+    #[graphql(description = "Only return NFTs whose first verified creator is one of these")]
+    creators: Option<Vec<PublicKey<Wallet>>>,
+    #[graphql(description = "Only return NFTs held by one of these wallets")]
+    owners: Option<Vec<PublicKey<Wallet>>>,
+    #[graphql(description = "Only return listed (or unlisted) NFTs")]
+    listed: Option<bool>,
+    #[graphql(description = "Minimum SOL listing price, in lamports")]
+    min_price: Option<U64>,
+    #[graphql(description = "Maximum SOL listing price, in lamports")]
+    max_price: Option<U64>,
+    #[graphql(description = "Only return NFTs matching all of these attribute filters")]
+    attributes: Option<Vec<AttributeFilter>>,
 }
 
-impl From<AttributeFilter> for queries::metadatas::AttributeFilter {
-    fn from(AttributeFilter { trait_type, values }: AttributeFilter) -> Self {
-        Self { trait_type, values }
+impl NftSearchFilters {
+    /// Convert these filters into search backend filters and range filters
+    fn to_search(&self) -> Result<(Vec<Filter>, Vec<RangeFilter>)> {
+        fn any_of<T: ToString>(field: &str, values: impl IntoIterator<Item = T>) -> Filter {
+            Filter::any_of(field, values.into_iter().map(|v| v.to_string()))
+        }
+
+        let Self {
+            collections,
+            creators,
+            owners,
+            listed,
+            min_price,
+            max_price,
+            attributes,
+        } = self;
+
+        let mut filters = Vec::new();
+
+        filters.extend(
+            collections
+                .as_ref()
+                .map(|c| any_of("collection_address", c)),
+        );
+        filters.extend(creators.as_ref().map(|c| any_of("creator_address", c)));
+        filters.extend(owners.as_ref().map(|o| any_of("owner", o)));
+        filters.extend(listed.map(|l| Filter::any_of("listed", [l])));
+        filters.extend(attributes.iter().flatten().map(|a| {
+            Filter::any_of(
+                "attributes",
+                a.values.iter().map(|v| encode_attribute(&a.trait_type, v)),
+            )
+        }));
+
+        let price = |p: Option<U64>| {
+            p.map(|p| i64::try_from(u64::from(p)).context("Price filter too large"))
+                .transpose()
+        };
+
+        let mut ranges = Vec::new();
+
+        if min_price.is_some() || max_price.is_some() {
+            ranges.push(RangeFilter {
+                field: "price".into(),
+                min: price(*min_price)?,
+                max: price(*max_price)?,
+            });
+        }
+
+        Ok((filters, ranges))
+    }
+}
+
+/// Filters shared by the `nfts` and `nftsConnection` fields
+struct NftListFilters {
+    owners: Option<Vec<PublicKey<Wallet>>>,
+    creators: Option<Vec<PublicKey<Wallet>>>,
+    update_authorities: Option<Vec<PublicKey<Wallet>>>,
+    offerers: Option<Vec<PublicKey<Wallet>>>,
+    attributes: Option<Vec<AttributeFilter>>,
+    listed: Option<bool>,
+    with_offers: Option<bool>,
+    auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+    collections: Option<Vec<PublicKey<Nft>>>,
+    term: Option<String>,
+    exclude_suspected_copies: Option<bool>,
+}
+
+impl NftListFilters {
+    /// Load a page of the NFTs matching these filters
+    async fn load(self, context: &AppContext, limit: u64, offset: u64) -> FieldResult<Vec<Nft>> {
+        let Self {
+            owners,
+            creators,
+            update_authorities,
+            offerers,
+            attributes,
+            listed,
+            with_offers,
+            auction_houses,
+            collections,
+            term,
+            exclude_suspected_copies,
+        } = self;
+
+        if collections.is_none()
+            && owners.is_none()
+            && creators.is_none()
+            && auction_houses.is_none()
+            && offerers.is_none()
+            && term.is_none()
+            && update_authorities.is_none()
+        {
+            return Err(FieldError::new(
+                "No filter provided! Please provide at least one of the following arguments",
+                graphql_value!([
+                    "collections",
+                    "owners",
+                    "creators",
+                    "auction_houses",
+                    "offerers",
+                    "term",
+                    "update_authorities"
+                ]),
+            ));
+        }
+
+        if let Some(false) = with_offers {
+            return Err(FieldError::new(
+                "with_offers == false is not currently supported",
+                graphql_value!({ "invalid_parameter": "with_offers" }),
+            ));
+        }
+
+        let conn = context.shared.db.get().context("failed to connect to db")?;
+
+        let addresses = match term {
+            Some(term) => {
+                // Narrow the search to the requested collections so they
+                // aren't crowded out of the pre-query limit.  Creators are only
+                // filtered in Postgres, since search documents only record the
+                // first verified creator.
+                let filters: Vec<_> = collections
+                    .iter()
+                    .map(|c| {
+                        Filter::any_of("collection_address", c.iter().map(ToString::to_string))
+                    })
+                    .collect();
+
+                let search_result = context
+                    .shared
+                    .search
+                    .query(Query {
+                        index: "metadatas",
+                        term: &term,
+                        filters: &filters,
+                        limit: context.shared.pre_query_search_limit,
+                        offset: 0,
+                    })
+                    .await
+                    .context("failed to load search result for metadata json")?
+                    .hits;
+
+                Some(
+                    search_result
+                        .into_iter()
+                        .map(|r| MetadataJson::from(r).address)
+                        .collect(),
+                )
+            },
+            None => None,
+        };
+
+        let query_options = queries::metadatas::ListQueryOptions {
+            addresses,
+            owners: owners.map(|o| o.into_iter().map(Into::into).collect()),
+            creators: creators.map(|c| c.into_iter().map(Into::into).collect()),
+            update_authorities: update_authorities.map(|a| a.into_iter().map(Into::into).collect()),
+            offerers: offerers.map(|o| o.into_iter().map(Into::into).collect()),
+            attributes: attributes.map(|a| a.into_iter().map(Into::into).collect()),
+            listed,
+            with_offers,
+            auction_houses: auction_houses.map(|h| h.into_iter().map(Into::into).collect()),
+            collections: collections.map(|c| c.into_iter().map(Into::into).collect()),
+            collection_key: None,
+            exclude_suspected_copies: exclude_suspected_copies.unwrap_or(false),
+            sort: None,
+            limit,
+            offset,
+        };
+        let nfts = queries::metadatas::list(&conn, query_options)?;
+
+        nfts.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
     }
 }
 
@@ -197,15 +403,19 @@ impl QueryRoot {
         Ok(PriceChart {
             auction_houses,
             creators,
+            collection: None,
             start_date,
             end_date,
         })
     }
 
-    #[graphql(arguments(
-        auction_housese(description = "List of auction houses"),
-        creators(description = "Optional list of creators"),
-    ))]
+    #[graphql(
+        deprecated = "Use activitiesConnection, which is paginated",
+        arguments(
+            auction_housese(description = "List of auction houses"),
+            creators(description = "Optional list of creators"),
+        )
+    )]
     pub async fn activities(
         &self,
         context: &AppContext,
@@ -327,10 +537,47 @@ impl QueryRoot {
         })
     }
 
+    #[graphql(
+        description = "Get a collection by the mint address of its collection NFT or the address \
+                       of its first verified creator, or by the name and family given by its \
+                       metadata JSON.",
+        arguments(
+            address(description = "Mint address of the collection NFT, or creator address"),
+            name(description = "Collection name given by metadata JSON"),
+            family(description = "Collection family given by metadata JSON"),
+        )
+    )]
+    fn collection(
+        &self,
+        context: &AppContext,
+        address: Option<String>,
+        name: Option<String>,
+        family: Option<String>,
+    ) -> FieldResult<Option<Collection>> {
+        let conn = context.shared.db.get()?;
+
+        let key = match (address, name) {
+            (Some(address), None) => queries::collections::find(&conn, &address)?,
+            (None, Some(name)) => queries::collections::find_family(&conn, name, family)?,
+            _ => {
+                return Err(FieldError::new(
+                    "Exactly one of address or name must be provided",
+                    graphql_value!(["address", "name"]),
+                ));
+            },
+        };
+
+        Ok(key.map(Into::into))
+    }
+
     async fn nfts(
         &self,
         context: &AppContext,
-        #[graphql(description = "Filter on owner address")] owners: Option<Vec<PublicKey<Wallet>>>,
+        #[graphql(
+            description = "Filter on owner address; NFTs hidden by an owner are excluded and \
+            their pinned NFTs are returned first"
+        )]
+        owners: Option<Vec<PublicKey<Wallet>>>,
         #[graphql(description = "Filter on creator address")] creators: Option<
             Vec<PublicKey<Wallet>>,
         >,
@@ -358,6 +605,8 @@ impl QueryRoot {
             description = "Return NFTs whose metadata contain this search term (case-insensitive)"
         )]
         term: Option<String>,
+        #[graphql(description = "Exclude NFTs suspected of copying another NFT's image")]
+        exclude_suspected_copies: Option<bool>,
         #[graphql(description = "Limit for query")] limit: i32,
         #[graphql(description = "Offset for query")] offset: i32,
     ) -> FieldResult<Vec<Nft>> {
@@ -373,80 +622,84 @@ impl QueryRoot {
             },
         };
 
-        if collections.is_none()
-            && owners.is_none()
-            && creators.is_none()
-            && auction_houses.is_none()
-            && offerers.is_none()
-            && term.is_none()
-            && update_authorities.is_none()
-        {
-            return Err(FieldError::new(
-                "No filter provided! Please provide at least one of the following arguments",
-                graphql_value!([
-                    "collections",
-                    "owners",
-                    "creators",
-                    "auction_houses",
-                    "offerers",
-                    "term",
-                    "update_authorities"
-                ]),
-            ));
-        }
-
-        if let Some(false) = with_offers {
-            return Err(FieldError::new(
-                "with_offers == false is not currently supported",
-                graphql_value!({ "invalid_parameter": "with_offers" }),
-            ));
+        NftListFilters {
+            owners,
+            creators,
+            update_authorities,
+            offerers,
+            attributes,
+            listed,
+            with_offers,
+            auction_houses,
+            collections,
+            term,
+            exclude_suspected_copies,
         }
+        .load(context, limit.try_into()?, offset.try_into()?)
+        .await
+    }
 
-        let conn = context.shared.db.get().context("failed to connect to db")?;
-
-        let addresses = match term {
-            Some(term) => {
-                let search = &context.shared.search;
-                let search_result = search
-                    .index("metadatas")
-                    .search()
-                    .with_query(&term)
-                    .with_limit(context.shared.pre_query_search_limit)
-                    .execute::<Value>()
-                    .await
-                    .context("failed to load search result for metadata json")?
-                    .hits;
-
-                Some(
-                    search_result
-                        .into_iter()
-                        .map(|r| MetadataJson::from(r.result).address)
-                        .collect(),
-                )
-            },
-            None => None,
-        };
+    #[graphql(
+        description = "Search NFTs, returning the total hit count and attribute facet \
+                       distribution along with the requested page of hits"
+    )]
+    async fn search_nfts(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search query")] query: Option<String>,
+        #[graphql(description = "Filters to apply to search hits")] filters: Option<
+            NftSearchFilters,
+        >,
+        #[graphql(
+            description = "Trait types to return facet distributions for.  Defaults to all \
+                           trait types."
+        )]
+        facets: Option<Vec<String>>,
+        #[graphql(description = "Sort order for hits.  Defaults to relevance.")] sort: Option<
+            NftSearchSort,
+        >,
+        #[graphql(description = "Limit for query")] limit: i32,
+        #[graphql(description = "Offset for query")] offset: i32,
+    ) -> FieldResult<NftSearchResult> {
+        let (filters, ranges) = filters
+            .as_ref()
+            .map(NftSearchFilters::to_search)
+            .transpose()?
+            .unwrap_or_default();
+
+        let results = context
+            .shared
+            .search
+            .faceted_query(FacetedQuery {
+                query: Query {
+                    index: "metadatas",
+                    term: query.as_deref().unwrap_or_default(),
+                    filters: &filters,
+                    limit: limit.try_into()?,
+                    offset: offset.try_into()?,
+                },
+                ranges: &ranges,
+                sort: sort.map(NftSearchSort::to_search),
+                facet: "attributes",
+            })
+            .await
+            .context("failed to load NFT search results")?;
 
-        let query_options = queries::metadatas::ListQueryOptions {
-            addresses,
-            owners: owners.map(|o| o.into_iter().map(Into::into).collect()),
-            creators: creators.map(|c| c.into_iter().map(Into::into).collect()),
-            update_authorities: update_authorities.map(|a| a.into_iter().map(Into::into).collect()),
-            offerers: offerers.map(|o| o.into_iter().map(Into::into).collect()),
-            attributes: attributes.map(|a| a.into_iter().map(Into::into).collect()),
-            listed,
-            with_offers,
-            auction_houses: auction_houses.map(|h| h.into_iter().map(Into::into).collect()),
-            collections: collections.map(|c| c.into_iter().map(Into::into).collect()),
-            limit: limit.try_into()?,
-            offset: offset.try_into()?,
-        };
-        let nfts = queries::metadatas::list(&conn, query_options)?;
+        let facets = AttributeFacet::from_distribution(
+            results.facets.into_iter().collect(),
+            facets.as_deref(),
+        )?;
 
-        nfts.into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+        Ok(NftSearchResult {
+            hits: results
+                .hits
+                .hits
+                .into_iter()
+                .map(MetadataJson::from)
+                .collect(),
+            total: results.hits.total.try_into()?,
+            facets,
+        })
     }
 
     #[graphql(description = "Stats aggregated across all indexed NFTs")]
@@ -698,22 +951,23 @@ impl QueryRoot {
         #[graphql(description = "Query limit")] limit: i32,
         #[graphql(description = "Query offset")] offset: i32,
     ) -> FieldResult<Vec<MetadataJson>> {
-        let search = &context.shared.search;
-
-        let query_result = search
-            .index("metadatas")
-            .search()
-            .with_query(&term)
-            .with_offset(offset.try_into()?)
-            .with_limit(limit.try_into()?)
-            .execute::<Value>()
+        let query_result = context
+            .shared
+            .search
+            .query(Query {
+                index: "metadatas",
+                term: &term,
+                filters: &[],
+                limit: limit.try_into()?,
+                offset: offset.try_into()?,
+            })
             .await
             .context("failed to load search result for metadata json")?
             .hits;
 
         Ok(query_result
             .into_iter()
-            .map(|r| r.result.into())
+            .map(Into::into)
             .collect::<Vec<MetadataJson>>())
     }
 
@@ -746,46 +1000,78 @@ impl QueryRoot {
         limit: i32,
         offset: i32,
     ) -> FieldResult<Vec<Nft>> {
-        let conn = context.shared.db.get().context("failed to connect to db")?;
-
-        let addresses: Option<Vec<String>> = match term {
-            Some(term) => {
-                let search = &context.shared.search;
-                let search_result = search
-                    .index("collections")
-                    .search()
-                    .with_query(&term)
-                    .with_limit(context.shared.pre_query_search_limit)
-                    .execute::<Value>()
-                    .await
-                    .context("failed to load search result for collections")?
-                    .hits;
-
-                Some(
-                    search_result
-                        .into_iter()
-                        .map(|r| MetadataJson::from(r.result).mint_address)
-                        .collect(),
-                )
-            },
-            None => None,
-        };
-
-        let collections = queries::collections::by_market_cap(
-            &conn,
-            addresses,
-            order_direction.into(),
-            start_date,
-            end_date,
-            limit,
-            offset,
-        )?;
+        let (start_date, end_date) = context
+            .shared
+            .cache
+            .featured_collections_range(start_date, end_date);
+        let collections = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::FeaturedCollections,
+                (
+                    "by_market_cap",
+                    term.clone(),
+                    order_direction,
+                    start_date,
+                    end_date,
+                    limit,
+                    offset,
+                ),
+                |nfts: &Vec<Nft>| {
+                    nfts.iter()
+                        .map(|n| Tag::Collection(n.mint_address.clone()))
+                        .collect()
+                },
+                || async move {
+                    let addresses: Option<Vec<String>> = match term {
+                        Some(term) => {
+                            let search_result = context
+                                .shared
+                                .search
+                                .query(Query {
+                                    index: "collections",
+                                    term: &term,
+                                    filters: &[],
+                                    limit: context.shared.pre_query_search_limit,
+                                    offset: 0,
+                                })
+                                .await
+                                .context("failed to load search result for collections")?
+                                .hits;
+
+                            Some(
+                                search_result
+                                    .into_iter()
+                                    .map(|r| MetadataJson::from(r).mint_address)
+                                    .collect(),
+                            )
+                        },
+                        None => None,
+                    };
+
+                    let conn = context.shared.db.get().context("failed to connect to db")?;
+                    let collections = queries::collections::by_market_cap(
+                        &conn,
+                        addresses,
+                        order_direction.into(),
+                        start_date,
+                        end_date,
+                        limit,
+                        offset,
+                    )?;
+
+                    Ok::<_, Error>(
+                        collections
+                            .into_iter()
+                            .map(TryInto::try_into)
+                            .collect::<Result<_, _>>()?,
+                    )
+                },
+            )
+            .await?;
 
-        collections
-            .into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+        Ok(collections)
     }
 
     #[graphql(
@@ -815,46 +1101,78 @@ impl QueryRoot {
         limit: i32,
         offset: i32,
     ) -> FieldResult<Vec<Nft>> {
-        let conn = context.shared.db.get().context("failed to connect to db")?;
-
-        let addresses: Option<Vec<String>> = match term {
-            Some(term) => {
-                let search = &context.shared.search;
-                let search_result = search
-                    .index("collections")
-                    .search()
-                    .with_query(&term)
-                    .with_limit(context.shared.pre_query_search_limit)
-                    .execute::<Value>()
-                    .await
-                    .context("failed to load search result for collections")?
-                    .hits;
-
-                Some(
-                    search_result
-                        .into_iter()
-                        .map(|r| MetadataJson::from(r.result).mint_address)
-                        .collect(),
-                )
-            },
-            None => None,
-        };
-
-        let collections = queries::collections::by_volume(
-            &conn,
-            addresses,
-            order_direction.into(),
-            start_date,
-            end_date,
-            limit,
-            offset,
-        )?;
+        let (start_date, end_date) = context
+            .shared
+            .cache
+            .featured_collections_range(start_date, end_date);
+        let collections = context
+            .shared
+            .cache
+            .get_or_load(
+                Resolver::FeaturedCollections,
+                (
+                    "by_volume",
+                    term.clone(),
+                    order_direction,
+                    start_date,
+                    end_date,
+                    limit,
+                    offset,
+                ),
+                |nfts: &Vec<Nft>| {
+                    nfts.iter()
+                        .map(|n| Tag::Collection(n.mint_address.clone()))
+                        .collect()
+                },
+                || async move {
+                    let addresses: Option<Vec<String>> = match term {
+                        Some(term) => {
+                            let search_result = context
+                                .shared
+                                .search
+                                .query(Query {
+                                    index: "collections",
+                                    term: &term,
+                                    filters: &[],
+                                    limit: context.shared.pre_query_search_limit,
+                                    offset: 0,
+                                })
+                                .await
+                                .context("failed to load search result for collections")?
+                                .hits;
+
+                            Some(
+                                search_result
+                                    .into_iter()
+                                    .map(|r| MetadataJson::from(r).mint_address)
+                                    .collect(),
+                            )
+                        },
+                        None => None,
+                    };
+
+                    let conn = context.shared.db.get().context("failed to connect to db")?;
+                    let collections = queries::collections::by_volume(
+                        &conn,
+                        addresses,
+                        order_direction.into(),
+                        start_date,
+                        end_date,
+                        limit,
+                        offset,
+                    )?;
+
+                    Ok::<_, Error>(
+                        collections
+                            .into_iter()
+                            .map(TryInto::try_into)
+                            .collect::<Result<_, _>>()?,
+                    )
+                },
+            )
+            .await?;
 
-        collections
-            .into_iter()
-            .map(TryInto::try_into)
-            .collect::<Result<_, _>>()
-            .map_err(Into::into)
+        Ok(collections)
     }
 
     #[graphql(description = "returns all the collections matching the search term")]
@@ -865,22 +1183,23 @@ impl QueryRoot {
         #[graphql(description = "Query limit")] limit: i32,
         #[graphql(description = "Query offset")] offset: i32,
     ) -> FieldResult<Vec<MetadataJson>> {
-        let search = &context.shared.search;
-
-        let query_result = search
-            .index("collections")
-            .search()
-            .with_query(&term)
-            .with_offset(offset.try_into()?)
-            .with_limit(limit.try_into()?)
-            .execute::<Value>()
+        let query_result = context
+            .shared
+            .search
+            .query(Query {
+                index: "collections",
+                term: &term,
+                filters: &[],
+                limit: limit.try_into()?,
+                offset: offset.try_into()?,
+            })
             .await
             .context("failed to load search result for collections")?
             .hits;
 
         Ok(query_result
             .into_iter()
-            .map(|r| r.result.into())
+            .map(Into::into)
             .collect::<Vec<MetadataJson>>())
     }
 
@@ -892,25 +1211,451 @@ impl QueryRoot {
         #[graphql(description = "Query limit")] limit: i32,
         #[graphql(description = "Query offset")] offset: i32,
     ) -> FieldResult<Vec<Wallet>> {
-        let search = &context.shared.search;
-
-        let query_result = search
-            .index("name_service")
-            .search()
-            .with_query(&term)
-            .with_offset(offset.try_into()?)
-            .with_limit(limit.try_into()?)
-            .execute::<Value>()
+        let query_result = context
+            .shared
+            .search
+            .query(Query {
+                index: "name_service",
+                term: &term,
+                filters: &[],
+                limit: limit.try_into()?,
+                offset: offset.try_into()?,
+            })
             .await
             .context("failed to load search result for twitter handle")?
             .hits;
 
         Ok(query_result
             .into_iter()
-            .map(|r| r.result.into())
+            .map(Into::into)
             .collect::<Vec<Wallet>>())
     }
 
+    #[graphql(
+        description = "Search wallets by Twitter handle, Cardinal name or address; creators \
+                       and highly-followed wallets are ranked first"
+    )]
+    async fn search_wallets(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search term")] term: String,
+        #[graphql(description = "Query limit")] limit: i32,
+        #[graphql(description = "Query offset")] offset: i32,
+    ) -> FieldResult<Vec<Wallet>> {
+        let query_result = context
+            .shared
+            .search
+            .query(Query {
+                index: "wallets",
+                term: &term,
+                filters: &[],
+                limit: limit.try_into()?,
+                offset: offset.try_into()?,
+            })
+            .await
+            .context("failed to load search result for wallets")?
+            .hits;
+
+        Ok(query_result
+            .iter()
+            .filter_map(Wallet::from_document)
+            .collect())
+    }
+
+    #[graphql(
+        description = "Search NFTs, collections and profiles at once for typeahead search, \
+                       returning NFT hits, then collection hits, then profile hits along with \
+                       the fields matching the term"
+    )]
+    async fn search_all(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search term")] term: String,
+        #[graphql(description = "Maximum number of hits of each type, at most 20")]
+        limit_per_type: i32,
+    ) -> FieldResult<Vec<SearchHit>> {
+        if !(0..=SEARCH_ALL_MAX_LIMIT).contains(&limit_per_type) {
+            return Err(FieldError::new(
+                "limit_per_type must be between 0 and 20",
+                graphql_value!({ "invalid_parameter": "limit_per_type" }),
+            ));
+        }
+
+        SearchHit::search_all(&context.shared, &term, limit_per_type.try_into()?)
+            .await
+            .context("failed to load search results")
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Auction house listings, newest first, paginated with opaque cursors",
+        arguments(
+            auction_houses(description = "Only return listings on these auction houses"),
+            nfts(description = "Only return listings of these NFTs"),
+            first(description = "Maximum number of listings to return, at most 100"),
+            after(description = "Return listings after this cursor"),
+        )
+    )]
+    fn listings_connection(
+        &self,
+        context: &AppContext,
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        nfts: Option<Vec<PublicKey<Nft>>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<AhListingConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        AhListingConnection::load(&conn, auction_houses.as_deref(), nfts.as_deref(), &args)
+    }
+
+    #[graphql(
+        description = "Auction house offers, newest first, paginated with opaque cursors",
+        arguments(
+            auction_houses(description = "Only return offers on these auction houses"),
+            nfts(description = "Only return offers of these NFTs"),
+            first(description = "Maximum number of offers to return, at most 100"),
+            after(description = "Return offers after this cursor"),
+        )
+    )]
+    fn offers_connection(
+        &self,
+        context: &AppContext,
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        nfts: Option<Vec<PublicKey<Nft>>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<OfferConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        OfferConnection::load(&conn, auction_houses.as_deref(), nfts.as_deref(), &args)
+    }
+
+    #[graphql(
+        description = "Auction house purchases, newest first, paginated with opaque cursors",
+        arguments(
+            auction_houses(description = "Only return purchases on these auction houses"),
+            nfts(description = "Only return purchases of these NFTs"),
+            first(description = "Maximum number of purchases to return, at most 100"),
+            after(description = "Return purchases after this cursor"),
+        )
+    )]
+    fn purchases_connection(
+        &self,
+        context: &AppContext,
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        nfts: Option<Vec<PublicKey<Nft>>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<PurchaseConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        PurchaseConnection::load(&conn, auction_houses.as_deref(), nfts.as_deref(), &args)
+    }
+
+    #[graphql(
+        description = "Listings, purchases, offers, cancellations and transfers, newest first, \
+                       paginated with opaque cursors.  At least one of auctionHouses, creators, \
+                       collections or wallets must be provided.  Transfers are not made on an \
+                       auction house, so filtering by auctionHouses excludes them.",
+        arguments(
+            auction_houses(description = "Only return activities on these auction houses"),
+            creators(description = "Only return activities on NFTs by these creators"),
+            collections(description = "Only return activities on NFTs in these verified \
+                                       collections"),
+            wallets(description = "Only return activities involving these wallets"),
+            activity_types(description = "Only return activities of these kinds"),
+            start_date(description = "Only return activities at or after this time"),
+            end_date(description = "Only return activities before this time"),
+            first(description = "Maximum number of activities to return, at most 100"),
+            after(description = "Return activities after this cursor"),
+        )
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn activities_connection(
+        &self,
+        context: &AppContext,
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        creators: Option<Vec<PublicKey<Creator>>>,
+        collections: Option<Vec<PublicKey<Nft>>>,
+        wallets: Option<Vec<PublicKey<Wallet>>>,
+        activity_types: Option<Vec<ActivityType>>,
+        start_date: Option<DateTime<Utc>>,
+        end_date: Option<DateTime<Utc>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<NftActivityConnection> {
+        fn strings<T>(keys: Option<Vec<PublicKey<T>>>) -> Option<Vec<String>> {
+            keys.map(|k| k.into_iter().map(Into::into).collect())
+        }
+
+        if auction_houses.is_none()
+            && creators.is_none()
+            && collections.is_none()
+            && wallets.is_none()
+        {
+            return Err(FieldError::new(
+                "No filter provided! Please provide at least one of the following arguments",
+                graphql_value!(["auction_houses", "creators", "collections", "wallets"]),
+            ));
+        }
+
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        let rows = queries::activities::list_page(
+            &conn,
+            queries::activities::ActivityFilters {
+                auction_houses: strings(auction_houses),
+                creators: strings(creators),
+                collections: strings(collections),
+                collection_key: None,
+                wallets: strings(wallets),
+                activity_types: activity_types.map(|t| t.into_iter().map(Into::into).collect()),
+                start_date: start_date.map(|d| d.naive_utc()),
+                end_date: end_date.map(|d| d.naive_utc()),
+            },
+            args.activities_after(),
+            args.limit(),
+        )?;
+
+        let nodes = rows
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+
+        Ok(NftActivityConnection::new(nodes, &args))
+    }
+
+    #[graphql(
+        description = "Active graph connections, most recently connected first, paginated with \
+                       opaque cursors.  At least one of from or to must be provided.",
+        arguments(
+            from(description = "Connections from a list of wallets"),
+            to(description = "Connections to a list of wallets"),
+            first(description = "Maximum number of connections to return, at most 100"),
+            after(description = "Return connections after this cursor"),
+        )
+    )]
+    fn connections_connection(
+        &self,
+        context: &AppContext,
+        from: Option<Vec<PublicKey<Wallet>>>,
+        to: Option<Vec<PublicKey<Wallet>>>,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<GraphConnectionConnection> {
+        if from.is_none() && to.is_none() {
+            return Err(FieldError::new(
+                "No filter provided! Please provide at least one of the filters",
+                graphql_value!({ "Filters": "from: Vec<PublicKey>, to: Vec<PublicKey>" }),
+            ));
+        }
+
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+        let from: Vec<String> = from.into_iter().flatten().map(Into::into).collect();
+        let to: Vec<String> = to.into_iter().flatten().map(Into::into).collect();
+
+        GraphConnectionConnection::load(&conn, &from, &to, &args)
+    }
+
+    #[graphql(
+        description = "Changes to a bonding curve's reserves and supply, newest first, \
+                       paginated with opaque cursors",
+        arguments(
+            address(description = "The address of the bonding curve"),
+            start_unix_time(description = "The starting unix timestamp (inclusive)"),
+            stop_unix_time(description = "The stop unix timestamp"),
+            first(description = "Maximum number of changes to return, at most 100"),
+            after(description = "Return changes after this cursor"),
+        )
+    )]
+    fn enriched_bonding_changes_connection(
+        &self,
+        context: &AppContext,
+        address: PublicKey<Wallet>,
+        start_unix_time: NaiveDateTime,
+        stop_unix_time: NaiveDateTime,
+        first: i32,
+        after: Option<String>,
+    ) -> FieldResult<EnrichedBondingChangeConnection> {
+        let args = PageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        EnrichedBondingChangeConnection::load(
+            &conn,
+            address.as_ref(),
+            start_unix_time,
+            stop_unix_time,
+            &args,
+        )
+    }
+
+    #[graphql(description = "NFTs matching the given filters, paginated with opaque cursors")]
+    async fn nfts_connection(
+        &self,
+        context: &AppContext,
+        #[graphql(
+            description = "Filter on owner address; NFTs hidden by an owner are excluded and \
+            their pinned NFTs are returned first"
+        )]
+        owners: Option<Vec<PublicKey<Wallet>>>,
+        #[graphql(description = "Filter on creator address")] creators: Option<
+            Vec<PublicKey<Wallet>>,
+        >,
+        #[graphql(description = "Filter on update authorities")] update_authorities: Option<
+            Vec<PublicKey<Wallet>>,
+        >,
+        #[graphql(description = "Filter on offerers address")] offerers: Option<
+            Vec<PublicKey<Wallet>>,
+        >,
+        #[graphql(description = "Filter on attributes")] attributes: Option<Vec<AttributeFilter>>,
+        #[graphql(description = "Filter only listed NFTs")] listed: Option<bool>,
+        #[graphql(
+            description = "Filter only NFTs with active offers; rejected if flag is 'false'"
+        )]
+        with_offers: Option<bool>,
+        #[graphql(description = "Filter NFTs associated to the list of auction houses")]
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        #[graphql(description = "Filter on one or more collections")] collections: Option<
+            Vec<PublicKey<Nft>>,
+        >,
+        #[graphql(
+            description = "Return NFTs whose metadata contain this search term (case-insensitive)"
+        )]
+        term: Option<String>,
+        #[graphql(description = "Exclude NFTs suspected of copying another NFT's image")]
+        exclude_suspected_copies: Option<bool>,
+        #[graphql(description = "Maximum number of NFTs to return, at most 100")] first: i32,
+        #[graphql(description = "Return NFTs after this cursor")] after: Option<String>,
+    ) -> FieldResult<NftConnection> {
+        let args = OffsetPageArgs::new(first, after.as_deref())?;
+
+        let nodes = NftListFilters {
+            owners,
+            creators,
+            update_authorities,
+            offerers,
+            attributes,
+            listed,
+            with_offers,
+            auction_houses,
+            collections,
+            term,
+            exclude_suspected_copies,
+        }
+        .load(context, args.limit().try_into()?, args.offset().try_into()?)
+        .await?;
+
+        Ok(NftConnection::new(nodes, &args))
+    }
+
+    #[graphql(description = "Collections matching the search term, paginated with opaque cursors")]
+    async fn search_collections_connection(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search term")] term: String,
+        #[graphql(description = "Maximum number of collections to return, at most 100")] first: i32,
+        #[graphql(description = "Return collections after this cursor")] after: Option<String>,
+    ) -> FieldResult<MetadataJsonConnection> {
+        let args = OffsetPageArgs::new(first, after.as_deref())?;
+
+        let hits = context
+            .shared
+            .search
+            .query(Query {
+                index: "collections",
+                term: &term,
+                filters: &[],
+                limit: args.limit(),
+                offset: args.offset(),
+            })
+            .await
+            .context("failed to load search result for collections")?;
+
+        Ok(MetadataJsonConnection {
+            total_count: Some(hits.total.try_into()?),
+            ..MetadataJsonConnection::new(hits.hits.into_iter().map(Into::into).collect(), &args)
+        })
+    }
+
+    #[graphql(description = "Profiles matching the search term, paginated with opaque cursors")]
+    async fn profiles_connection(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search term")] term: String,
+        #[graphql(description = "Maximum number of profiles to return, at most 100")] first: i32,
+        #[graphql(description = "Return profiles after this cursor")] after: Option<String>,
+    ) -> FieldResult<WalletConnection> {
+        let args = OffsetPageArgs::new(first, after.as_deref())?;
+
+        let hits = context
+            .shared
+            .search
+            .query(Query {
+                index: "name_service",
+                term: &term,
+                filters: &[],
+                limit: args.limit(),
+                offset: args.offset(),
+            })
+            .await
+            .context("failed to load search result for twitter handle")?;
+
+        Ok(WalletConnection {
+            total_count: Some(hits.total.try_into()?),
+            ..WalletConnection::new(hits.hits.into_iter().map(Into::into).collect(), &args)
+        })
+    }
+
+    #[graphql(
+        description = "Marketplaces in alphabetical order by name, paginated with opaque cursors"
+    )]
+    fn marketplaces_connection(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Maximum number of marketplaces to return, at most 100")]
+        first: i32,
+        #[graphql(description = "Return marketplaces after this cursor")] after: Option<String>,
+    ) -> FieldResult<MarketplaceConnection> {
+        let args = OffsetPageArgs::new(first, after.as_deref())?;
+        let conn = context.shared.db.get()?;
+
+        let query = || {
+            store_config_jsons::table
+                .filter(
+                    store_config_jsons::store_address
+                        .ne(all(&context.shared.marketplaces_store_address_exclusions)),
+                )
+                .into_boxed()
+        };
+
+        let rows: Vec<models::StoreConfigJson> = query()
+            .select(store_config_jsons::all_columns)
+            .order((
+                store_config_jsons::name.asc(),
+                store_config_jsons::config_address.asc(),
+            ))
+            .limit(args.limit().try_into()?)
+            .offset(args.offset().try_into()?)
+            .load(&conn)
+            .context("Failed to load store config JSON")?;
+
+        let total_count: i64 = query()
+            .count()
+            .get_result(&conn)
+            .context("Failed to count store config JSON")?;
+
+        Ok(MarketplaceConnection {
+            total_count: Some(total_count.try_into()?),
+            ..MarketplaceConnection::new(rows.into_iter().map(Into::into).collect(), &args)
+        })
+    }
+
     #[graphql(description = "returns stats about profiles")]
     async fn profiles_stats(&self) -> ProfilesStats {
         ProfilesStats
@@ -966,4 +1711,11 @@ impl QueryRoot {
     fn denylist() -> Denylist {
         Denylist
     }
+
+    #[graphql(description = "Settings of the wallet authenticated by the X-Wallet-Session header")]
+    fn viewer(&self, context: &AppContext) -> FieldResult<Viewer> {
+        Ok(Viewer {
+            wallet: context.session_wallet()?,
+        })
+    }
 }
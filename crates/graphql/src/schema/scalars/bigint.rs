@@ -63,3 +63,9 @@ impl TryFrom<i64> for U64 {
         value.try_into().map(Self)
     }
 }
+
+impl From<U64> for u64 {
+    fn from(value: U64) -> Self {
+        value.0
+    }
+}
@@ -0,0 +1,265 @@
+use std::pin::Pin;
+
+use futures_util::{future, stream, Stream};
+use indexer_core::{
+    db::{queries, select, tables::metadata_collection_keys},
+    uuid::Uuid,
+};
+use juniper::graphql_subscription;
+use objects::{
+    ah_listing::AhListing,
+    ah_offer::Offer,
+    ah_purchase::Purchase,
+    auction_house::AuctionHouse,
+    feed_event::FeedEvent,
+    nft::{Nft, NftOwner},
+    wallet::Wallet,
+};
+use scalars::PublicKey;
+use serde::de::DeserializeOwned;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::prelude::*;
+
+pub struct SubscriptionRoot;
+
+type EventStream<T> = Pin<Box<dyn Stream<Item = FieldResult<T>> + Send>>;
+
+/// The columns of a `listings`, `offers` or `purchases` notification used for
+/// filtering
+#[derive(Debug, serde::Deserialize)]
+struct TradeRow {
+    id: String,
+    auction_house: String,
+    metadata: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FeedEventRow {
+    id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct OwnerRow {
+    mint_address: String,
+    owner_address: String,
+    token_account_address: String,
+}
+
+/// Stream the rows sent on a notification channel
+fn rows<T: DeserializeOwned + Send + 'static>(
+    context: &AppContext,
+    channel: &'static str,
+) -> impl Stream<Item = T> + Send {
+    stream::unfold(
+        context.shared.notifications.subscribe(),
+        move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(n) if n.channel == channel => match serde_json::from_str(&n.payload) {
+                        Ok(row) => break Some((row, rx)),
+                        Err(e) => warn!("Failed to parse {:?} notification: {}", channel, e),
+                    },
+                    Ok(_) => (),
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("Subscriber missed {} notification(s)", n);
+                    },
+                    Err(RecvError::Closed) => break None,
+                }
+            }
+        },
+    )
+}
+
+fn accepts<T>(keys: Option<&[PublicKey<T>]>, value: &str) -> bool {
+    keys.map_or(true, |k| k.iter().any(|k| k.as_ref() == value))
+}
+
+fn parse_id(id: &str) -> FieldResult<Uuid> {
+    Uuid::parse_str(id)
+        .context("Invalid row ID in notification")
+        .map_err(FieldError::from)
+}
+
+fn load_feed_event(
+    context: &AppContext,
+    id: &str,
+    wallet: Option<PublicKey<Wallet>>,
+) -> FieldResult<Option<FeedEvent>> {
+    let id = parse_id(id)?;
+    let conn = context.db().get()?;
+
+    queries::feed_event::get(&conn, id, wallet.map(Into::into))?
+        .map(FeedEvent::try_from)
+        .transpose()
+        .map_err(Into::into)
+}
+
+#[graphql_subscription(Context = AppContext)]
+impl SubscriptionRoot {
+    #[graphql(description = "Auction house listings as they are created")]
+    async fn listing_created(
+        context: &AppContext,
+        #[graphql(description = "Only report listings on these auction houses")]
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+        #[graphql(description = "Only report listings of NFTs in these verified collections")]
+        collections: Option<Vec<PublicKey<Nft>>>,
+    ) -> EventStream<AhListing> {
+        let ctx = context.clone();
+        let collections = Arc::new(collections);
+
+        rows::<TradeRow>(context, "listings")
+            .filter(move |r| future::ready(accepts(auction_houses.as_deref(), &r.auction_house)))
+            .filter_map(move |row| {
+                let ctx = ctx.clone();
+                let collections = collections.clone();
+
+                async move {
+                    if let Some(ref collections) = *collections {
+                        let in_collection = ctx
+                            .db()
+                            .get()
+                            .context("Failed to connect to the database")
+                            .and_then(|conn| {
+                                select(exists(
+                                    metadata_collection_keys::table
+                                        .filter(
+                                            metadata_collection_keys::metadata_address
+                                                .eq(&row.metadata),
+                                        )
+                                        .filter(
+                                            metadata_collection_keys::collection_address
+                                                .eq(any(collections)),
+                                        )
+                                        .filter(metadata_collection_keys::verified),
+                                ))
+                                .get_result::<bool>(&conn)
+                                .context("Failed to check listing collection")
+                            });
+
+                        match in_collection {
+                            Ok(true) => (),
+                            Ok(false) => return None,
+                            Err(e) => return Some(Err(FieldError::from(e))),
+                        }
+                    }
+
+                    let id = match parse_id(&row.id) {
+                        Ok(i) => i,
+                        Err(e) => return Some(Err(e)),
+                    };
+
+                    ctx.ah_listing_loader
+                        .load(id)
+                        .await
+                        .map_err(FieldError::from)
+                        .transpose()
+                }
+            })
+            .boxed()
+    }
+
+    #[graphql(description = "Auction house offers as they are created")]
+    async fn offer_created(
+        context: &AppContext,
+        #[graphql(description = "Only report offers on this NFT")] nft: Option<PublicKey<Nft>>,
+    ) -> EventStream<Offer> {
+        let ctx = context.clone();
+
+        rows::<TradeRow>(context, "offers")
+            .filter(move |r| future::ready(nft.as_ref().map_or(true, |n| n.as_ref() == r.metadata)))
+            .filter_map(move |row| {
+                let ctx = ctx.clone();
+
+                async move {
+                    let id = match parse_id(&row.id) {
+                        Ok(i) => i,
+                        Err(e) => return Some(Err(e)),
+                    };
+
+                    ctx.offer_loader
+                        .load(id)
+                        .await
+                        .map_err(FieldError::from)
+                        .transpose()
+                }
+            })
+            .boxed()
+    }
+
+    #[graphql(description = "Auction house purchases as they are completed")]
+    async fn purchase_completed(
+        context: &AppContext,
+        #[graphql(description = "Only report purchases on these auction houses")]
+        auction_houses: Option<Vec<PublicKey<AuctionHouse>>>,
+    ) -> EventStream<Purchase> {
+        let ctx = context.clone();
+
+        rows::<TradeRow>(context, "purchases")
+            .filter(move |r| future::ready(accepts(auction_houses.as_deref(), &r.auction_house)))
+            .filter_map(move |row| {
+                let ctx = ctx.clone();
+
+                async move {
+                    let id = match parse_id(&row.id) {
+                        Ok(i) => i,
+                        Err(e) => return Some(Err(e)),
+                    };
+
+                    ctx.purchase_loader
+                        .load(id)
+                        .await
+                        .map_err(FieldError::from)
+                        .transpose()
+                }
+            })
+            .boxed()
+    }
+
+    #[graphql(description = "Feed events as they are created")]
+    async fn feed_event(
+        context: &AppContext,
+        #[graphql(description = "Only report events in the feed of this wallet")] wallet: Option<
+            PublicKey<Wallet>,
+        >,
+    ) -> EventStream<FeedEvent> {
+        let ctx = context.clone();
+
+        rows::<FeedEventRow>(context, "feed_events")
+            .filter_map(move |row| {
+                let ctx = ctx.clone();
+                let wallet = wallet.clone();
+
+                async move { load_feed_event(&ctx, &row.id, wallet).transpose() }
+            })
+            .boxed()
+    }
+
+    #[graphql(description = "NFT ownership changes as they are indexed")]
+    async fn nft_owner_changed(
+        context: &AppContext,
+        #[graphql(description = "Only report ownership changes of this mint")] mint: Option<String>,
+    ) -> EventStream<NftOwner> {
+        let ctx = context.clone();
+
+        rows::<OwnerRow>(context, "current_metadata_owners")
+            .filter(move |r| future::ready(mint.as_ref().map_or(true, |m| *m == r.mint_address)))
+            .then(move |row| {
+                let ctx = ctx.clone();
+
+                async move {
+                    let twitter_handle = ctx
+                        .twitter_handle_loader
+                        .load(row.owner_address.clone().into())
+                        .await?;
+
+                    Ok::<_, FieldError>(NftOwner {
+                        address: row.owner_address,
+                        associated_token_account_address: row.token_account_address,
+                        twitter_handle,
+                    })
+                }
+            })
+            .boxed()
+    }
+}
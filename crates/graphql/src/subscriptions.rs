@@ -0,0 +1,170 @@
+//! WebSocket handler for the `graphql-ws` subscription protocol, checking the
+//! query limits of each operation before it is started
+
+use std::sync::Arc;
+
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler, WrapFuture};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use indexer_core::prelude::*;
+use juniper_graphql_ws::{ClientMessage, Connection, Init, Schema, ServerMessage};
+use tokio::sync::Mutex;
+
+use crate::cost;
+
+type Sink<S, I> = SplitSink<Connection<S, I>, ClientMessage<<S as Schema>::ScalarValue>>;
+
+struct SubscriptionActor<S: Schema, I: Init<S::ScalarValue, S::Context>> {
+    limits: Option<cost::Args>,
+    tx: Arc<Mutex<Sink<S, I>>>,
+    rx: Option<SplitStream<Connection<S, I>>>,
+}
+
+impl<S, I> SubscriptionActor<S, I>
+where
+    S: Schema,
+    I: Init<S::ScalarValue, S::Context> + Send,
+{
+    /// Check the query limits of a `start` message, returning the error
+    /// message to send in its place if the operation is rejected
+    fn check(&self, text: &str) -> Option<serde_json::Value> {
+        let limits = self.limits.as_ref()?;
+        let msg = serde_json::from_str::<serde_json::Value>(text).ok()?;
+
+        if msg.get("type").and_then(serde_json::Value::as_str) != Some("start") {
+            return None;
+        }
+
+        let payload = msg.get("payload")?;
+        let query = payload.get("query").and_then(serde_json::Value::as_str)?;
+        let operation_name = payload
+            .get("operationName")
+            .and_then(serde_json::Value::as_str);
+        let variables = payload
+            .get("variables")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+
+        let rejection = crate::check_limits(limits, query, operation_name, &variables).err()?;
+
+        Some(serde_json::json!({
+            "type": "error",
+            "id": msg.get("id"),
+            "payload": rejection.to_response()["errors"],
+        }))
+    }
+}
+
+impl<S, I> Actor for SubscriptionActor<S, I>
+where
+    S: Schema,
+    I: Init<S::ScalarValue, S::Context> + Send,
+{
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(rx) = self.rx.take() {
+            ctx.add_stream(rx);
+        }
+    }
+}
+
+impl<S, I> StreamHandler<Result<ws::Message, ws::ProtocolError>> for SubscriptionActor<S, I>
+where
+    S: Schema,
+    I: Init<S::ScalarValue, S::Context> + Send,
+{
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Text(text)) => {
+                if let Some(err) = self.check(&text) {
+                    ctx.text(err.to_string());
+                    return;
+                }
+
+                let msg = match serde_json::from_str::<ClientMessage<S::ScalarValue>>(&text) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        ctx.close(Some(ws::CloseReason {
+                            code: ws::CloseCode::Error,
+                            description: Some(e.to_string()),
+                        }));
+                        ctx.stop();
+                        return;
+                    },
+                };
+
+                let tx = Arc::clone(&self.tx);
+
+                // Wait on the send so client messages are handled in order
+                ctx.wait(
+                    async move {
+                        tx.lock().await.send(msg).await.ok();
+                    }
+                    .into_actor(self),
+                );
+            },
+            Ok(ws::Message::Ping(p)) => ctx.pong(&p),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            },
+            Ok(_) => (),
+            Err(e) => {
+                warn!("WebSocket protocol error: {}", e);
+                ctx.stop();
+            },
+        }
+    }
+}
+
+impl<S, I> StreamHandler<ServerMessage<S::ScalarValue>> for SubscriptionActor<S, I>
+where
+    S: Schema,
+    I: Init<S::ScalarValue, S::Context> + Send,
+{
+    fn handle(&mut self, msg: ServerMessage<S::ScalarValue>, ctx: &mut Self::Context) {
+        match serde_json::to_string(&msg) {
+            Ok(text) => ctx.text(text),
+            Err(e) => error!("Failed to serialize subscription message: {}", e),
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+/// Serve GraphQL subscriptions over a WebSocket, rejecting operations that
+/// exceed the given query limits, or checking none if `limits` is `None`
+///
+/// # Errors
+/// This function fails if the WebSocket handshake cannot be completed.
+pub async fn handler<S, I>(
+    req: HttpRequest,
+    stream: web::Payload,
+    schema: S,
+    init: I,
+    limits: Option<cost::Args>,
+) -> Result<HttpResponse, Error>
+where
+    S: Schema,
+    I: Init<S::ScalarValue, S::Context> + Send,
+{
+    let (tx, rx) = Connection::new(schema, init).split::<ClientMessage<S::ScalarValue>>();
+
+    ws::start_with_protocols(
+        SubscriptionActor {
+            limits,
+            tx: Arc::new(Mutex::new(tx)),
+            rx: Some(rx),
+        },
+        &["graphql-ws"],
+        &req,
+        stream,
+    )
+}
@@ -0,0 +1,180 @@
+//! Backfill job that discovers Arweave-hosted metadata JSONs and pre-warms
+//! the asset proxy cache for them and their images
+
+use futures_util::StreamExt;
+use indexer_core::{
+    assets::{proxy_url, AssetIdentifier, AssetProxyArgs, ImageSize},
+    clap,
+};
+use serde_json::Value;
+
+use super::Transaction;
+use crate::{
+    prelude::*,
+    reqwest::{self, Url},
+};
+
+/// Common arguments for metadata pre-warm jobs
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[clap(flatten)]
+    asset_proxy: AssetProxyArgs,
+
+    #[clap(flatten)]
+    politeness: reqwest::Args,
+
+    /// Only pre-warm metadata JSONs listing one of these Solana creators.
+    /// Arweave can't filter on this, so it is checked after each JSON is
+    /// fetched.
+    #[clap(long, use_value_delimiter(true))]
+    creator: Vec<Pubkey>,
+
+    /// Number of transactions to pre-warm concurrently
+    #[clap(long, default_value_t = 16)]
+    concurrency: usize,
+
+    /// HTTP request timeout, in seconds
+    #[clap(long, env = "HTTP_INDEXER_TIMEOUT")]
+    timeout: f64,
+}
+
+/// Shared state for a metadata pre-warm job
+#[derive(Debug)]
+pub struct Prewarm {
+    http: reqwest::Client,
+    proxy_args: AssetProxyArgs,
+    arweave_url: Url,
+    creators: Vec<String>,
+    concurrency: usize,
+}
+
+/// Returns true if a metadata JSON lists one of the given creator addresses
+/// under `properties.creators`
+fn lists_creator(json: &Value, creators: &[String]) -> bool {
+    json.pointer("/properties/creators")
+        .and_then(Value::as_array)
+        .map_or(false, |c| {
+            c.iter()
+                .filter_map(|c| c.get("address").and_then(Value::as_str))
+                .any(|a| creators.iter().any(|c| c == a))
+        })
+}
+
+impl Prewarm {
+    /// Construct a new pre-warm job.  `arweave_url` is the base URL of the
+    /// gateway used to construct the canonical URL of each transaction.
+    ///
+    /// # Errors
+    /// This function fails if the HTTP client cannot be constructed.
+    pub fn new(args: Args, arweave_url: Url) -> Result<Self> {
+        let Args {
+            asset_proxy,
+            politeness,
+            creator,
+            concurrency,
+            timeout,
+        } = args;
+
+        Ok(Self {
+            http: reqwest::Client::new(StdDuration::from_secs_f64(timeout), politeness)?,
+            proxy_args: asset_proxy,
+            arweave_url,
+            creators: creator.iter().map(ToString::to_string).collect(),
+            concurrency,
+        })
+    }
+
+    async fn get(&self, url: Url) -> Result<reqwest::Response> {
+        self.http
+            .send(|h| h.get(url))
+            .await?
+            .error_for_status()
+            .map_err(Into::into)
+    }
+
+    async fn process_one(&self, tx: Transaction) -> Result<()> {
+        let mut tx_url = self.arweave_url.clone();
+        tx_url.set_path(&tx.id);
+        let id = AssetIdentifier::new(&tx_url);
+
+        let json_url = proxy_url(&self.proxy_args, &id, None)?
+            .ok_or_else(|| anyhow!("Couldn't parse {:?} as an Arweave transaction", tx.id))?;
+
+        let json: Value = self
+            .get(json_url)
+            .await
+            .context("Failed to fetch metadata JSON")?
+            .json()
+            .await
+            .context("Failed to parse metadata JSON")?;
+
+        if !self.creators.is_empty() && !lists_creator(&json, &self.creators) {
+            trace!(
+                "Transaction {} has none of the given creators, skipping",
+                tx.id
+            );
+            return Ok(());
+        }
+
+        let image = match json.get("image").and_then(Value::as_str) {
+            Some(i) => i,
+            None => {
+                trace!("Transaction {} has no image, skipping", tx.id);
+                return Ok(());
+            },
+        };
+
+        let image_url = Url::parse(image).context("Failed to parse image URL")?;
+        let image_id = AssetIdentifier::new(&image_url);
+        let width = (ImageSize::XSmall as i32).to_string();
+
+        for query in [None, Some(("width", &*width))] {
+            if let Some(url) = proxy_url(&self.proxy_args, &image_id, query)? {
+                self.get(url).await.context("Failed to fetch image")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pre-warm the asset cache for a page of transactions.  Failures on
+    /// individual transactions are logged and ignored.
+    pub async fn process(&self, txs: Vec<Transaction>) {
+        futures_util::stream::iter(txs)
+            .for_each_concurrent(self.concurrency, |tx| async move {
+                let id = tx.id.clone();
+
+                self.process_one(tx)
+                    .await
+                    .map_err(|e| debug!("Failed to pre-warm {}: {:?}", id, e))
+                    .ok();
+            })
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn matches_solana_creators() {
+        let json = json!({
+            "properties": {
+                "creators": [
+                    { "address": "a", "share": 50 },
+                    { "address": "b", "share": 50 },
+                ],
+            },
+        });
+
+        assert!(lists_creator(&json, &["b".into()]));
+        assert!(!lists_creator(&json, &["c".into()]));
+        assert!(!lists_creator(
+            &json!({ "creators": [{ "address": "b" }] }),
+            &["b".into()]
+        ));
+    }
+}
@@ -0,0 +1,594 @@
+//! Resumable paging over the Arweave GraphQL transaction index.
+//!
+//! Backfill jobs describe the transactions they are interested in with a
+//! [`TransactionFilter`] and walk the results with a [`Pager`].  If a job is
+//! given a name its progress is checkpointed to the `arweave_backfill_cursors`
+//! table after every page, so an interrupted job picks up where it left off.
+
+use indexer_core::{
+    db::{delete, insert_into, models::ArweaveBackfillCursor, tables::arweave_backfill_cursors},
+    hash::HashMap,
+    util,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{db::Pool, prelude::*};
+
+pub mod metadata;
+
+/// The default number of transactions requested per page
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+#[derive(Serialize)]
+struct Query {
+    query: &'static str,
+    variables: HashMap<&'static str, Value>,
+}
+
+#[repr(transparent)]
+#[derive(Debug, Deserialize)]
+struct QueryResponse {
+    data: QueryData,
+}
+
+#[repr(transparent)]
+#[derive(Debug, Deserialize)]
+struct QueryData {
+    transactions: QueryTransactions,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryTransactions {
+    edges: Vec<QueryEdge>,
+    #[serde(rename = "pageInfo")]
+    page_info: QueryPageInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryEdge {
+    cursor: String,
+    node: QueryNode,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryNode {
+    id: String,
+    owner: QueryOwner,
+    tags: Vec<QueryTag>,
+    block: Option<QueryBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryOwner {
+    address: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryBlock {
+    timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryTag {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryPageInfo {
+    #[serde(rename = "hasNextPage")]
+    has_next_page: bool,
+}
+
+const QUERY: &str = r#"query GetTransactions(
+    $after: String,
+    $first: Int,
+    $owners: [String!],
+    $tags: [TagFilter!],
+) {
+    transactions(
+        owners: $owners,
+        tags: $tags,
+        after: $after,
+        sort: HEIGHT_DESC,
+        first: $first,
+    ) {
+        pageInfo {
+            hasNextPage
+        }
+
+        edges {
+            cursor
+
+            node {
+                id
+                owner {
+                    address
+                }
+                tags {
+                    name
+                    value
+                }
+                block {
+                    timestamp
+                }
+            }
+        }
+    }
+}"#;
+
+/// A tag constraint on an Arweave transaction query
+#[derive(Debug, Clone, Serialize)]
+pub struct TagFilter {
+    /// The tag name to match
+    pub name: String,
+    /// The set of accepted values for the tag
+    pub values: Vec<String>,
+}
+
+impl std::str::FromStr for TagFilter {
+    type Err = Error;
+
+    /// Parse a tag filter of the form `name=value1,value2,...`
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Tag filter {:?} is not of the form name=value", s))?;
+
+        Ok(Self {
+            name: name.into(),
+            values: values.split(',').map(Into::into).collect(),
+        })
+    }
+}
+
+/// The set of transactions a backfill job is interested in
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    /// Only return transactions signed by one of these Arweave wallets
+    pub owners: Vec<String>,
+    /// Only return transactions matching all of these tags
+    pub tags: Vec<TagFilter>,
+}
+
+impl TransactionFilter {
+    fn variables(&self, after: &str, first: usize) -> Result<HashMap<&'static str, Value>> {
+        let mut vars: HashMap<_, _> = [
+            ("after", Value::String(after.into())),
+            ("first", Value::Number(first.into())),
+        ]
+        .into_iter()
+        .collect();
+
+        if !self.owners.is_empty() {
+            vars.insert(
+                "owners",
+                serde_json::to_value(&self.owners).context("Failed to serialize owners")?,
+            );
+        }
+
+        if !self.tags.is_empty() {
+            vars.insert(
+                "tags",
+                serde_json::to_value(&self.tags).context("Failed to serialize tag filters")?,
+            );
+        }
+
+        Ok(vars)
+    }
+}
+
+/// A single transaction returned by the Arweave GraphQL index
+#[derive(Debug)]
+pub struct Transaction {
+    /// The Arweave transaction ID
+    pub id: String,
+    /// The address of the wallet that signed the transaction
+    pub owner: String,
+    /// The transaction's tags, keyed by name
+    pub tags: HashMap<String, String>,
+    /// The timestamp of the block containing the transaction, if it has been
+    /// mined
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+/// Cursor-based pager over the results of a [`TransactionFilter`]
+#[derive(Debug)]
+pub struct Pager {
+    http: reqwest::Client,
+    url: Url,
+    filter: TransactionFilter,
+    page_size: usize,
+    after: String,
+    done: bool,
+}
+
+impl Pager {
+    /// Construct a new pager for the GraphQL endpoint of the given Arweave
+    /// node, optionally resuming from a previously-saved cursor
+    #[must_use]
+    pub fn new(mut url: Url, filter: TransactionFilter, after: Option<String>) -> Self {
+        url.set_path("/graphql");
+
+        Self {
+            http: reqwest::Client::new(),
+            url,
+            filter,
+            page_size: DEFAULT_PAGE_SIZE,
+            after: after.unwrap_or_default(),
+            done: false,
+        }
+    }
+
+    /// Set the number of transactions requested per page
+    #[must_use]
+    pub fn page_size(self, page_size: usize) -> Self {
+        Self { page_size, ..self }
+    }
+
+    /// The cursor of the last transaction returned by this pager
+    #[must_use]
+    pub fn cursor(&self) -> &str {
+        &self.after
+    }
+
+    /// Fetch the next page of transactions, or `None` if all pages have been
+    /// returned
+    ///
+    /// # Errors
+    /// This function fails if the GraphQL request fails, its response cannot
+    /// be parsed, or the endpoint returns the same cursor twice in a row.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Transaction>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let QueryResponse {
+            data:
+                QueryData {
+                    transactions:
+                        QueryTransactions {
+                            edges,
+                            page_info: QueryPageInfo { has_next_page },
+                        },
+                },
+        } = self
+            .http
+            .post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .json(&Query {
+                query: QUERY,
+                variables: self.filter.variables(&self.after, self.page_size)?,
+            })
+            .send()
+            .await
+            .context("Arweave GraphQL request failed")?
+            .error_for_status()
+            .context("Arweave GraphQL endpoint returned an error")?
+            .json()
+            .await
+            .context("Couldn't parse Arweave GraphQL response")?;
+
+        let mut next_after = None;
+        let mut txs = Vec::with_capacity(edges.len());
+
+        for QueryEdge { cursor, node } in edges {
+            let QueryNode {
+                id,
+                owner: QueryOwner { address: owner },
+                tags,
+                block,
+            } = node;
+
+            next_after = Some(cursor);
+
+            let timestamp = match block.map(|b| util::unix_timestamp(b.timestamp)).transpose() {
+                Ok(t) => t,
+                Err(e) => {
+                    warn!(
+                        "Skipping Arweave transaction {} with a bad timestamp: {:?}",
+                        id, e
+                    );
+                    continue;
+                },
+            };
+
+            txs.push(Transaction {
+                id,
+                owner,
+                tags: tags
+                    .into_iter()
+                    .map(|QueryTag { name, value }| (name, value))
+                    .collect(),
+                timestamp,
+            });
+        }
+
+        match next_after {
+            Some(a) if a == self.after => {
+                return Err(anyhow!("Arweave fetch got stuck in a loop"));
+            },
+            Some(a) => self.after = a,
+            None if has_next_page => {
+                warn!("Got zero edges in a request");
+                self.done = true;
+            },
+            None => (),
+        }
+
+        if !has_next_page {
+            self.done = true;
+        }
+
+        Ok(Some(txs))
+    }
+}
+
+/// Load the saved cursor for the named backfill job, if any
+///
+/// # Errors
+/// This function fails if the database query fails.
+pub async fn load_cursor(db: &Pool, name: String) -> Result<Option<String>> {
+    db.run(move |db| {
+        arweave_backfill_cursors::table
+            .filter(arweave_backfill_cursors::name.eq(name))
+            .select(arweave_backfill_cursors::cursor)
+            .first(db)
+            .optional()
+    })
+    .await
+    .context("Failed to load backfill cursor")
+}
+
+/// Save the cursor for the named backfill job
+///
+/// # Errors
+/// This function fails if the database upsert fails.
+pub async fn save_cursor(db: &Pool, name: String, cursor: String) -> Result<()> {
+    db.run(move |db| {
+        let row = ArweaveBackfillCursor {
+            name: Owned(name),
+            cursor: Owned(cursor),
+            updated_at: Local::now().naive_utc(),
+        };
+
+        insert_into(arweave_backfill_cursors::table)
+            .values(&row)
+            .on_conflict(arweave_backfill_cursors::name)
+            .do_update()
+            .set(&row)
+            .execute(db)
+    })
+    .await
+    .context("Failed to save backfill cursor")?;
+
+    Ok(())
+}
+
+/// Remove the saved cursor for the named backfill job, so that the next run
+/// starts from the most recent transaction
+///
+/// # Errors
+/// This function fails if the database delete fails.
+pub async fn clear_cursor(db: &Pool, name: String) -> Result<()> {
+    db.run(move |db| {
+        delete(arweave_backfill_cursors::table.filter(arweave_backfill_cursors::name.eq(name)))
+            .execute(db)
+    })
+    .await
+    .context("Failed to clear backfill cursor")?;
+
+    Ok(())
+}
+
+/// Walk every transaction matching a filter, calling `f` on each page.
+///
+/// If `checkpoint` is given, the job resumes from the cursor saved under that
+/// name and saves its progress after each page is processed.  Once the final
+/// page has been processed the checkpoint is cleared.
+///
+/// # Errors
+/// This function fails if a page cannot be fetched, if `f` fails, or if the
+/// checkpoint cannot be loaded or saved.
+pub async fn backfill<F: std::future::Future<Output = Result<()>>>(
+    db: &Pool,
+    url: Url,
+    filter: TransactionFilter,
+    checkpoint: Option<&str>,
+    page_size: usize,
+    mut f: impl FnMut(Vec<Transaction>) -> F,
+) -> Result<()> {
+    let after = match checkpoint {
+        Some(name) => load_cursor(db, name.into()).await?,
+        None => None,
+    };
+
+    if let Some(ref after) = after {
+        info!("Resuming Arweave backfill from cursor {:?}", after);
+    }
+
+    let mut pager = Pager::new(url, filter, after).page_size(page_size);
+
+    while let Some(txs) = pager.next_page().await? {
+        debug!("Processing {} Arweave transaction(s)", txs.len());
+
+        f(txs).await?;
+
+        if let Some(name) = checkpoint {
+            if !pager.cursor().is_empty() {
+                save_cursor(db, name.into(), pager.cursor().into()).await?;
+            }
+        }
+    }
+
+    if let Some(name) = checkpoint {
+        clear_cursor(db, name.into()).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../../fixtures/arweave-transactions.json");
+
+    const OWNER: &str = "vLRHFqCw1uHu75xqB4fCDW-QxpkpJxBtFD9g4QYUbfw";
+
+    const IDS: [&str; 3] = [
+        "bzOq6E2PKTMXnXS-ELcSvdh3XJMcwBPJN-Q7tZ6kTXI",
+        "Qq1SvMGYb5D7bZ8rqEYgaBvNlyOpQ2PjTSq9ZnHu1sI",
+        "h3W0nKcO6zQ6TvYwYbEr3ZfB3Zk9fWw1j7oG2pX8aLc",
+    ];
+
+    /// Answer a transaction query the way the Arweave GraphQL index would,
+    /// over the transactions in the fixture
+    fn respond(variables: &Value) -> Value {
+        let fixture: Value = serde_json::from_str(FIXTURE).unwrap();
+        let edges = fixture["data"]["transactions"]["edges"].as_array().unwrap();
+        let owners = variables.get("owners").and_then(Value::as_array);
+        let after = variables["after"].as_str().unwrap();
+        let first = usize::try_from(variables["first"].as_u64().unwrap()).unwrap();
+
+        let matching: Vec<_> = edges
+            .iter()
+            .filter(|e| owners.map_or(true, |o| o.contains(&e["node"]["owner"]["address"])))
+            .collect();
+        let start = matching
+            .iter()
+            .position(|e| e["cursor"] == after)
+            .map_or(0, |i| i + 1);
+        let page: Vec<_> = matching[start..].iter().take(first).collect();
+
+        json!({
+            "data": {
+                "transactions": {
+                    "pageInfo": { "hasNextPage": start + page.len() < matching.len() },
+                    "edges": page,
+                },
+            },
+        })
+    }
+
+    /// Serve [`respond`] over HTTP on a local port, returning the server's URL
+    async fn serve() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+                let mut len = 0;
+
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+
+                    match line.trim_end().split_once(':') {
+                        Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                            len = value.trim().parse().unwrap();
+                        },
+                        Some(_) => (),
+                        None if line.trim_end().is_empty() => break,
+                        None => (),
+                    }
+                }
+
+                let mut body = vec![0; len];
+                stream.read_exact(&mut body).await.unwrap();
+
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let response = respond(&request["variables"]).to_string();
+
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            response.len(),
+                            response
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        });
+
+        url
+    }
+
+    /// Collect the transaction IDs of each remaining page of a pager
+    async fn pages(pager: &mut Pager) -> Vec<Vec<String>> {
+        let mut ids = vec![];
+
+        while let Some(txs) = pager.next_page().await.unwrap() {
+            ids.push(txs.into_iter().map(|t| t.id).collect());
+        }
+
+        ids
+    }
+
+    #[tokio::test]
+    async fn pages_through_transactions() {
+        let mut pager = Pager::new(serve().await, TransactionFilter::default(), None).page_size(2);
+
+        assert_eq!(pages(&mut pager).await, vec![vec![IDS[0], IDS[1]], vec![
+            IDS[2]
+        ]]);
+        assert!(pager.next_page().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn parses_transactions() {
+        let mut pager = Pager::new(serve().await, TransactionFilter::default(), None);
+        let txs = pager.next_page().await.unwrap().unwrap();
+
+        assert_eq!(txs.len(), 3);
+        assert_eq!(txs[0].owner, OWNER);
+        assert_eq!(txs[0].tags["Arweave-App"], "holaplex");
+        assert_eq!(
+            txs[0].timestamp,
+            Some(NaiveDateTime::from_timestamp(1_658_136_821, 0))
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_from_cursor() {
+        let url = serve().await;
+        let mut pager = Pager::new(url.clone(), TransactionFilter::default(), None).page_size(2);
+        pager.next_page().await.unwrap();
+
+        let mut resumed = Pager::new(
+            url,
+            TransactionFilter::default(),
+            Some(pager.cursor().to_owned()),
+        )
+        .page_size(2);
+
+        assert_eq!(pages(&mut resumed).await, vec![vec![IDS[2]]]);
+    }
+
+    #[tokio::test]
+    async fn filters_by_owner() {
+        let filter = TransactionFilter {
+            owners: vec![OWNER.into()],
+            tags: vec![],
+        };
+        let mut pager = Pager::new(serve().await, filter, None).page_size(1);
+
+        assert_eq!(pages(&mut pager).await, vec![vec![IDS[0]], vec![IDS[2]]]);
+    }
+}
@@ -0,0 +1,79 @@
+use holaplex_indexer::arweave::{
+    self,
+    metadata::{self, Prewarm},
+    TagFilter, TransactionFilter,
+};
+use indexer_core::{clap, prelude::*};
+
+#[derive(Debug, clap::Args)]
+struct Args {
+    /// Base URL of the Arweave node to query, e.g. `https://arweave.net`
+    #[clap(long, env)]
+    arweave_url: String,
+
+    /// Only scan transactions signed by these Arweave wallets.  To filter on
+    /// the Solana creators listed in each metadata JSON, use `--creator`.
+    #[clap(long, use_value_delimiter(true))]
+    owner: Vec<String>,
+
+    /// Only scan transactions with the given tag, in the form
+    /// `name=value1,value2,...`.  May be given multiple times.
+    #[clap(long, multiple_occurrences(true))]
+    tag: Vec<TagFilter>,
+
+    /// Name under which to checkpoint progress.  If set, an interrupted run
+    /// will resume from its last completed page.
+    #[clap(long)]
+    checkpoint: Option<String>,
+
+    /// Number of transactions requested from Arweave per page
+    #[clap(long, default_value_t = arweave::DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+
+    #[clap(flatten)]
+    prewarm: metadata::Args,
+}
+
+fn main() {
+    holaplex_indexer::run(|args: Args, _params, db| async move {
+        let Args {
+            arweave_url,
+            owner,
+            tag,
+            checkpoint,
+            page_size,
+            prewarm,
+        } = args;
+
+        if owner.is_empty() && tag.is_empty() {
+            return Err(anyhow!(
+                "At least one of --owner or --tag must be given to select the transactions to \
+                 scan"
+            ));
+        }
+
+        let arweave_url = arweave_url.parse().context("Failed to parse Arweave URL")?;
+        let prewarm = Prewarm::new(prewarm, arweave_url.clone())?;
+
+        arweave::backfill(
+            &db,
+            arweave_url,
+            TransactionFilter {
+                owners: owner,
+                tags: tag,
+            },
+            checkpoint.as_deref(),
+            page_size,
+            |txs| {
+                let prewarm = &prewarm;
+
+                async move {
+                    prewarm.process(txs).await;
+
+                    Ok(())
+                }
+            },
+        )
+        .await
+    })
+}
@@ -0,0 +1,38 @@
+use holaplex_indexer::collection_stats;
+use indexer_core::{chrono::Duration, clap, prelude::*};
+
+#[derive(Debug, clap::Args)]
+struct Args {
+    /// Number of minutes after which a collection's stats are recomputed,
+    /// even if it has not been traded
+    #[clap(long, env, default_value_t = 60)]
+    collection_stats_max_age_mins: i64,
+
+    /// Number of collections refreshed per batch
+    #[clap(long, env, default_value_t = collection_stats::DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+
+    /// Number of seconds to wait before checking for dirty or stale
+    /// collections once all are up to date, bounding how long the stats of a
+    /// traded collection lag behind
+    #[clap(long, env, default_value_t = 10)]
+    poll_interval_secs: u64,
+}
+
+fn main() {
+    holaplex_indexer::run(|args: Args, _params, db| async move {
+        let Args {
+            collection_stats_max_age_mins,
+            batch_size,
+            poll_interval_secs,
+        } = args;
+
+        collection_stats::refresh(
+            &db,
+            Duration::minutes(collection_stats_max_age_mins),
+            batch_size,
+            StdDuration::from_secs(poll_interval_secs),
+        )
+        .await
+    })
+}
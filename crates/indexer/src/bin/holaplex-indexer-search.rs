@@ -1,22 +1,34 @@
-use holaplex_indexer::search::{Client, ClientArgs};
-use indexer_core::{clap, prelude::*};
+use holaplex_indexer::search::{denylist, reindex, Client, ClientArgs};
+use indexer_core::{assets::AssetProxyArgs, clap, prelude::*};
 use indexer_rabbitmq::search_indexer;
 
 #[derive(Debug, clap::Parser)]
 struct Args {
     /// The address of an AMQP server to connect to
     #[clap(long, env)]
-    amqp_url: String,
+    amqp_url: Option<String>,
 
     /// The ID of the indexer sending events to listen for
     #[clap(long, env)]
-    sender: String,
+    sender: Option<String>,
 
     #[clap(flatten)]
     queue_suffix: indexer_rabbitmq::suffix::Suffix,
 
     #[clap(flatten)]
     client: ClientArgs,
+
+    #[clap(flatten)]
+    asset_proxy: AssetProxyArgs,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Rebuild a search index from the database
+    Reindex(reindex::Args),
 }
 
 fn main() {
@@ -26,9 +38,18 @@ fn main() {
              sender,
              queue_suffix,
              client,
+             asset_proxy,
+             command,
          },
          params,
          db| async move {
+            if let Some(Command::Reindex(args)) = command {
+                return reindex::run(db, client, asset_proxy, args).await;
+            }
+
+            let amqp_url = amqp_url.ok_or_else(|| anyhow!("Missing --amqp-url"))?;
+            let sender = sender.ok_or_else(|| anyhow!("Missing --sender"))?;
+
             let conn = holaplex_indexer::amqp_connect(amqp_url, env!("CARGO_BIN_NAME")).await?;
 
             let queue_type = search_indexer::QueueType::new(&sender, &queue_suffix)?;
@@ -41,6 +62,8 @@ fn main() {
                 .await
                 .context("Failed to construct Client")?;
 
+            let denylist_task = tokio::spawn(denylist::run(client.clone(), asset_proxy));
+
             let ret = holaplex_indexer::amqp_consume(
                 &params,
                 conn,
@@ -54,6 +77,8 @@ fn main() {
             )
             .await;
 
+            denylist_task.abort();
+
             if let Err(()) = stop_upsert.send(()) {
                 error!("Failed to stop upsert task");
                 upsert_task.abort();
@@ -0,0 +1,40 @@
+use holaplex_indexer::slot_times;
+use indexer_core::{clap, prelude::*};
+
+#[derive(Debug, clap::Args)]
+struct Args {
+    /// URL of the Solana RPC node to request block times from
+    #[clap(long, env)]
+    solana_endpoint: String,
+
+    /// Number of slots requested from the RPC node per batch
+    #[clap(long, env, default_value_t = slot_times::DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+
+    /// Number of seconds to wait before checking for new slots once all
+    /// known slots have been recorded
+    #[clap(long, env, default_value_t = 60)]
+    poll_interval_secs: u64,
+}
+
+fn main() {
+    holaplex_indexer::run(|args: Args, _params, db| async move {
+        let Args {
+            solana_endpoint,
+            batch_size,
+            poll_interval_secs,
+        } = args;
+
+        let rpc_url = solana_endpoint
+            .parse()
+            .context("Failed to parse Solana RPC URL")?;
+
+        slot_times::backfill(
+            &db,
+            rpc_url,
+            batch_size,
+            StdDuration::from_secs(poll_interval_secs),
+        )
+        .await
+    })
+}
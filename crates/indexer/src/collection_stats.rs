@@ -0,0 +1,55 @@
+//! Periodic refresh of the `collection_stats` table.
+//!
+//! Listing, offer and purchase triggers mark a collection dirty as it is
+//! traded, bumping the version of its statistics.  A refresh only marks the
+//! collection clean if the version is unchanged once it completes.
+//! This job recomputes dirty collections in batches, along with the least
+//! recently refreshed ones, since rolling volumes go stale for collections
+//! that stop trading, and holder counts and supply change without
+//! marketplace activity.
+
+use indexer_core::{chrono::Duration, db::queries::collection_stats};
+
+use crate::{db::Pool, prelude::*};
+
+/// The default number of collections refreshed per batch
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// Recompute the statistics of every dirty collection and every collection
+/// last refreshed more than `max_age` ago, then poll for newly stale
+/// collections every `interval`
+///
+/// # Errors
+/// This function fails if the database cannot be queried or written to.
+pub async fn refresh(
+    db: &Pool,
+    max_age: Duration,
+    batch_size: usize,
+    interval: StdDuration,
+) -> Result<()> {
+    let limit = batch_size.try_into().context("Batch size too large")?;
+
+    loop {
+        let stale = db
+            .run(move |db| collection_stats::stale(db, Utc::now().naive_utc() - max_age, limit))
+            .await?;
+
+        if stale.is_empty() {
+            tokio::time::sleep(interval).await;
+            continue;
+        }
+
+        let count = stale.len();
+
+        db.run(move |db| {
+            for (collection, treasury_mint, version) in stale {
+                collection_stats::refresh(db, &collection, &treasury_mint, version)?;
+            }
+
+            Result::<_>::Ok(())
+        })
+        .await?;
+
+        debug!("Refreshed stats for {} collection(s)", count);
+    }
+}
@@ -7,11 +7,11 @@ use indexer_core::{db, db::PooledConnection};
 use crate::prelude::*;
 
 /// Handle to a database pool used by an indexer consumer
-pub struct Pool(db::Pool, db::ConnectionType);
+pub struct Pool(db::Pool, db::ConnectionType, String);
 
 impl Pool {
-    pub(crate) fn new((pool, ty): (db::Pool, db::ConnectionType)) -> Self {
-        Self(pool, ty)
+    pub(crate) fn new((pool, ty): (db::Pool, db::ConnectionType), url: String) -> Self {
+        Self(pool, ty, url)
     }
 
     /// Get the connection-type hint for this database connection
@@ -20,6 +20,22 @@ impl Pool {
         self.1
     }
 
+    /// Get a reference to the underlying connection pool
+    #[must_use]
+    pub(crate) fn inner(&self) -> &db::Pool {
+        &self.0
+    }
+
+    /// Open a dedicated connection to the database listening for
+    /// notifications on each of `channels`
+    ///
+    /// # Errors
+    /// This function fails if the connection cannot be established.
+    #[cfg(feature = "search")]
+    pub(crate) async fn listen(&self, channels: &[&str]) -> Result<db::listen::Listener> {
+        db::listen::Listener::connect(&self.2, channels).await
+    }
+
     /// Spawn a blocking thread to perform operations on the database.
     ///
     /// # Errors
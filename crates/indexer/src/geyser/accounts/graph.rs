@@ -34,7 +34,10 @@ pub(crate) async fn process(
         write_version: write_version.try_into()?,
     };
 
-    client
+    let from_account = row.from_account.to_string();
+    let to_account = row.to_account.to_string();
+
+    let existed = client
         .db()
         .run(move |db| {
             let graph_connection_exists = select(exists(
@@ -50,7 +53,7 @@ pub(crate) async fn process(
                 .execute(db)?;
 
             if Ok(true) == graph_connection_exists {
-                return Ok(());
+                return Ok(true);
             }
 
             db.build_transaction().read_write().run(|| {
@@ -76,11 +79,18 @@ pub(crate) async fn process(
                     .execute(db)
                     .context("Failed to insert follow feed event wallet")?;
 
-                Result::<_>::Ok(())
+                Result::<_>::Ok(false)
             })
         })
         .await
         .context("Failed to insert graph connection")?;
 
+    for wallet in [from_account, to_account] {
+        client
+            .dispatch_wallet_document(!existed, wallet)
+            .await
+            .context("Failed to dispatch upsert wallet document job")?;
+    }
+
     Ok(())
 }
@@ -27,6 +27,9 @@ pub(crate) async fn process(
 ) -> Result<()> {
     let addr = bs58::encode(key).into_string();
     let (edition_pda_key, _bump) = find_edition(meta.mint);
+    let slot_i64: i64 = slot
+        .try_into()
+        .context("Metadata slot was too big to store")?;
     let row = Metadata {
         address: Owned(addr.clone()),
         name: Owned(meta.data.name.trim_end_matches('\0').to_owned()),
@@ -45,11 +48,9 @@ pub(crate) async fn process(
             TokenStandard::Fungible => TokenStandardEnum::Fungible,
             TokenStandard::NonFungibleEdition => TokenStandardEnum::NonFungibleEdition,
         }),
-        slot: Some(
-            slot.try_into()
-                .context("Metadata slot was too big to store")?,
-        ),
+        slot: Some(slot_i64),
         burned: false,
+        created_slot: Some(slot_i64),
     };
 
     let first_verified_creator: Option<Pubkey> = meta
@@ -1,6 +1,8 @@
 use borsh::BorshDeserialize;
 use indexer_core::{
-    db::{insert_into, models::TwitterHandle, tables::twitter_handle_name_services, update},
+    db::{
+        delete, insert_into, models::TwitterHandle, tables::twitter_handle_name_services, update,
+    },
     prelude::*,
 };
 
@@ -98,6 +100,50 @@ pub(crate) async fn process(
             .upsert_twitter_handle(backfill, key, document)
             .await
             .context("Failed to dispatch upsert twitter handle document job")?;
+
+        client
+            .dispatch_wallet_document(backfill, wallet.to_string())
+            .await
+            .context("Failed to dispatch upsert wallet document job")?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn process_delete(client: &Client, key: Pubkey, slot: u64) -> Result<()> {
+    let slot: i64 = slot.try_into()?;
+
+    let wallets = client
+        .db()
+        .run(move |db| {
+            delete(
+                twitter_handle_name_services::table
+                    .filter(twitter_handle_name_services::address.eq(key.to_string()))
+                    .filter(twitter_handle_name_services::from_bonfida)
+                    .filter(twitter_handle_name_services::slot.le(slot)),
+            )
+            .returning(twitter_handle_name_services::wallet_address)
+            .get_results::<String>(db)
+        })
+        .await
+        .context("failed to delete twitter handle")?;
+
+    if wallets.is_empty() {
+        return Ok(());
+    }
+
+    // The name has been released, so stop returning it in search
+    client
+        .search()
+        .delete_twitter_handle(key)
+        .await
+        .context("Failed to dispatch delete twitter handle document job")?;
+
+    for wallet in wallets {
+        client
+            .dispatch_wallet_document(false, wallet)
+            .await
+            .context("Failed to dispatch upsert wallet document job")?;
     }
 
     Ok(())
@@ -2,6 +2,7 @@ use indexer_core::{
     db::{
         insert_into,
         models::{CardinalEntry, CardinalNamespace, TwitterHandle},
+        select,
         tables::{cardinal_entries, cardinal_namespaces, twitter_handle_name_services},
         update,
     },
@@ -37,19 +38,35 @@ pub(crate) async fn process(
         write_version: write_version.try_into()?,
     };
 
-    client
+    let wallet = entry.data.map(|a| a.to_string());
+
+    let existed = client
         .db()
         .run(move |db| {
+            let existed = select(exists(
+                cardinal_entries::table.filter(cardinal_entries::address.eq(row.address.clone())),
+            ))
+            .get_result::<bool>(db)?;
+
             insert_into(cardinal_entries::table)
                 .values(&row)
                 .on_conflict(cardinal_entries::address)
                 .do_update()
                 .set(&row)
-                .execute(db)
+                .execute(db)?;
+
+            Result::<_>::Ok(existed)
         })
         .await
         .context("failed to insert cardinal entry")?;
 
+    if let Some(wallet) = wallet {
+        client
+            .dispatch_wallet_document(!existed, wallet)
+            .await
+            .context("Failed to dispatch upsert wallet document job")?;
+    }
+
     Ok(())
 }
 
@@ -105,6 +122,13 @@ async fn process_twitter_namespace(
     let wallet_address: String = if let Some(wallet_address) = entry.data {
         bs58::encode(wallet_address).into_string()
     } else {
+        // The handle has been released, so stop returning it in search
+        client
+            .search()
+            .delete_twitter_handle(key)
+            .await
+            .context("Failed to dispatch delete twitter handle document job")?;
+
         return Ok(());
     };
 
@@ -129,7 +129,9 @@ pub(crate) async fn process_listing_receipt(
         .await
         .context("Failed to insert listing receipt!")?;
 
-    Ok(())
+    client
+        .dispatch_metadata_document(listing.metadata.to_string())
+        .await
 }
 
 pub(crate) async fn process_purchase_receipt(
@@ -1,5 +1,10 @@
 use indexer_core::{
-    db::{insert_into, models::CurrentMetadataOwner, tables::current_metadata_owners, update},
+    db::{
+        insert_into,
+        models::CurrentMetadataOwner,
+        tables::{current_metadata_owners, metadatas},
+        update,
+    },
     prelude::*,
 };
 use spl_token::state::Account as TokenAccount;
@@ -35,7 +40,7 @@ pub async fn process(
         slot: incoming_slot,
     };
 
-    client
+    let transferred = client
         .db()
         .run(move |db| {
             let rows = current_metadata_owners::table
@@ -49,7 +54,7 @@ pub async fn process(
                 .load::<CurrentMetadataOwner>(db)
                 .context("failed to load metadata owner!")?;
 
-            match rows.get(0) {
+            let changed = match rows.get(0) {
                 Some(r) if incoming_slot > r.slot => {
                     db.build_transaction().read_write().run(|| {
                         update(
@@ -60,9 +65,11 @@ pub async fn process(
                         .execute(db)
                         .context("transaction failed! unable to update metadata_owners when incoming slot > indexed slot")
                         .map(|_| ())
-                    })
+                    })?;
+
+                    r.owner_address != values.owner_address
                 },
-                Some(_) => Ok(()),
+                Some(_) => false,
                 None => {
                     db.build_transaction()
                         .read_write()
@@ -77,11 +84,28 @@ pub async fn process(
                         })
                         .context("transaction failed! unable to insert metadata owner")?;
 
-                    Ok(())
+                    true
                 },
+            };
+
+            if !changed {
+                return Ok(None);
             }
+
+            metadatas::table
+                .filter(metadatas::mint_address.eq(values.mint_address))
+                .select(metadatas::address)
+                .first::<String>(db)
+                .optional()
+                .context("failed to load metadata address of transferred token")
         })
         .await
         .context("failed to insert token metadata owner!")?;
+
+    // The owner is part of the NFT's search document
+    if let Some(metadata) = transferred {
+        client.dispatch_metadata_document(metadata).await?;
+    }
+
     Ok(())
 }
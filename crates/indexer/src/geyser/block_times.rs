@@ -0,0 +1,244 @@
+//! Batched lookup of the block times marketplace instructions are dated by.
+//!
+//! Geyser instruction notifications carry only the slot of the instruction,
+//! so its block time is read from the `slot_times` table or else requested
+//! from a Solana RPC node, if one is configured.  Lookups made while a batch is
+//! collected share one database query and one JSON-RPC batch call, and the
+//! block times of recent slots are kept in memory.  Instructions whose block
+//! time is not available yet are retried later, and once a slot has been
+//! waited on for [`MAX_WAIT`] they are dated by an estimate from the nearest
+//! known slot instead, which the slot time backfill later corrects.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use indexer_core::{
+    db::{self, models::SlotTime, queries::slot_times},
+    hash::HashMap,
+};
+use reqwest::Url;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{prelude::*, RetryLater};
+
+/// Maximum number of lookups collected into one batch
+const BATCH_SIZE: usize = 100;
+
+/// Time to wait for further lookups to join a batch
+const BATCH_WINDOW: Duration = Duration::from_millis(20);
+
+/// Number of the most recent slots whose block times are kept in memory
+const CACHE_SLOTS: usize = 10_000;
+
+/// Timeout for block time requests to the RPC node
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time to wait before retrying an instruction whose block time is not
+/// available yet
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Time after which instructions in a slot whose block time is still not
+/// available are dated by an estimate rather than retried again
+const MAX_WAIT: Duration = Duration::from_secs(120);
+
+/// Approximate duration of a slot, used to estimate block times
+const SLOT_MILLIS: i64 = 400;
+
+type Cache = Arc<Mutex<BTreeMap<i64, NaiveDateTime>>>;
+type Lookup = (i64, oneshot::Sender<Option<NaiveDateTime>>);
+
+/// Handle to a task looking up slot block times in batches
+#[derive(Debug)]
+pub struct BlockTimes {
+    cache: Cache,
+    tx: mpsc::UnboundedSender<Lookup>,
+    waiting: Mutex<HashMap<i64, Instant>>,
+}
+
+impl BlockTimes {
+    /// Spawn a task looking up block times from the given database and, if
+    /// given, RPC node
+    ///
+    /// # Errors
+    /// This function fails if the HTTP client cannot be constructed.
+    pub fn spawn(db: db::Pool, rpc_url: Option<Url>) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(RPC_TIMEOUT)
+            .build()
+            .context("Failed to build RPC client")?;
+        let cache = Cache::default();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(run(db, http, rpc_url, Arc::clone(&cache), rx));
+
+        Ok(Self {
+            cache,
+            tx,
+            waiting: Mutex::default(),
+        })
+    }
+
+    /// Get the block time of a slot
+    ///
+    /// # Errors
+    /// This function fails with [`RetryLater`] if the block time cannot be
+    /// found yet and the slot has not been waited on for [`MAX_WAIT`].
+    pub async fn get(&self, slot: u64) -> Result<NaiveDateTime> {
+        let slot = i64::try_from(slot)?;
+
+        if let Some(time) = self
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&slot)
+        {
+            return Ok(*time);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send((slot, tx))
+            .map_err(|_| anyhow!("Block time lookup task stopped"))?;
+
+        if let Some(time) = rx.await.ok().flatten() {
+            return Ok(time);
+        }
+
+        let waited = {
+            let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+            waiting.retain(|_, t| t.elapsed() < MAX_WAIT * 2);
+
+            waiting.entry(slot).or_insert_with(Instant::now).elapsed()
+        };
+
+        if waited < MAX_WAIT {
+            return Err(RetryLater { delay: RETRY_DELAY })
+                .with_context(|| format!("Block time for slot {} not yet available", slot));
+        }
+
+        let time = self.estimate(slot);
+        warn!(
+            "Block time for slot {} unavailable after {:?}, estimating {}",
+            slot, waited, time
+        );
+
+        Ok(time)
+    }
+
+    /// Estimate the block time of a slot from the nearest slot in the cache,
+    /// or else the current time
+    fn estimate(&self, slot: i64) -> NaiveDateTime {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let nearest = cache
+            .range(..=slot)
+            .next_back()
+            .or_else(|| cache.range(slot..).next());
+
+        match nearest {
+            Some((known, time)) => {
+                *time + chrono::Duration::milliseconds((slot - known).saturating_mul(SLOT_MILLIS))
+            },
+            None => Utc::now().naive_utc(),
+        }
+    }
+}
+
+async fn run(
+    db: db::Pool,
+    http: reqwest::Client,
+    rpc_url: Option<Url>,
+    cache: Cache,
+    mut rx: mpsc::UnboundedReceiver<Lookup>,
+) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let window = tokio::time::sleep(BATCH_WINDOW);
+        tokio::pin!(window);
+
+        while batch.len() < BATCH_SIZE {
+            tokio::select! {
+                Some(lookup) = rx.recv() => batch.push(lookup),
+                () = &mut window => break,
+                else => break,
+            }
+        }
+
+        let mut slots: Vec<_> = batch.iter().map(|(s, _)| *s).collect();
+        slots.sort_unstable();
+        slots.dedup();
+
+        let times = lookup(&db, &http, rpc_url.as_ref(), slots)
+            .await
+            .map_err(|e| warn!("Failed to look up block times: {:?}", e))
+            .unwrap_or_default();
+
+        {
+            let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+            cache.extend(times.iter().map(|(s, t)| (*s, *t)));
+
+            while cache.len() > CACHE_SLOTS {
+                match cache.keys().next().copied() {
+                    Some(oldest) => cache.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+
+        for (slot, tx) in batch {
+            tx.send(times.get(&slot).copied()).ok();
+        }
+    }
+}
+
+/// Look up the block times of the given slots, recording those fetched from
+/// the RPC node.  Slots whose block time is not known yet are omitted.
+async fn lookup(
+    db: &db::Pool,
+    http: &reqwest::Client,
+    rpc_url: Option<&Url>,
+    slots: Vec<i64>,
+) -> Result<HashMap<i64, NaiveDateTime>> {
+    let known = blocking(db, {
+        let slots = slots.clone();
+        move |conn| slot_times::load(conn, &slots)
+    })
+    .await?;
+
+    let mut times: HashMap<_, _> = known.into_iter().map(|t| (t.slot, t.block_time)).collect();
+    let missing: Vec<_> = slots
+        .into_iter()
+        .filter(|s| !times.contains_key(s))
+        .collect();
+
+    let rpc_url = match rpc_url {
+        Some(u) if !missing.is_empty() => u,
+        _ => return Ok(times),
+    };
+
+    let rows: Vec<SlotTime> = crate::slot_times::fetch(http, rpc_url, &missing).await?;
+    times.extend(rows.iter().map(|r| (r.slot, r.block_time)));
+
+    if !rows.is_empty() {
+        blocking(db, move |conn| slot_times::insert(conn, &rows)).await?;
+    }
+
+    Ok(times)
+}
+
+async fn blocking<T: Send + 'static>(
+    db: &db::Pool,
+    f: impl FnOnce(&db::PooledConnection) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let db = db.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let conn = db.get().context("Failed to acquire database connection")?;
+
+        f(&conn)
+    })
+    .await
+    .context("Blocking task failed")?
+}
@@ -1,9 +1,10 @@
 use std::{sync::Arc, time::Duration};
 
-use indexer_core::clap;
+use indexer_core::{assets::AssetProxyArgs, clap};
 use indexer_rabbitmq::{http_indexer, search_indexer};
 
-use crate::{db::Pool, prelude::*, reqwest, search_dispatch};
+use super::block_times::BlockTimes;
+use crate::{db::Pool, prelude::*, reqwest, search_dispatch, search_documents};
 
 struct HttpProducers {
     metadata_json: http_indexer::Producer<http_indexer::MetadataJson>,
@@ -21,8 +22,21 @@ pub struct Args {
     #[clap(long, env, requires("dialect-api-endpoint"))]
     dialect_api_key: Option<String>,
 
+    /// Solana RPC endpoint to request block times from for slots not yet
+    /// recorded by the slot time backfill.  If not set, instructions in such
+    /// slots are retried until the backfill records them or are eventually
+    /// dated by an estimate.
+    #[clap(long, env)]
+    solana_endpoint: Option<String>,
+
+    #[clap(flatten)]
+    politeness: reqwest::Args,
+
     #[clap(flatten)]
     search: search_dispatch::Args,
+
+    #[clap(flatten)]
+    asset_proxy: AssetProxyArgs,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -43,6 +57,8 @@ pub struct Client {
     http: reqwest::Client,
     http_prod: HttpProducers,
     search: search_dispatch::Client,
+    block_times: BlockTimes,
+    asset_proxy: AssetProxyArgs,
     dialect_api_endpoint: Option<String>,
     dialect_api_key: Option<String>,
 }
@@ -62,7 +78,10 @@ impl Client {
         Args {
             dialect_api_endpoint,
             dialect_api_key,
+            solana_endpoint,
+            politeness,
             search,
+            asset_proxy,
         }: Args,
     ) -> Result<Arc<Self>> {
         if dialect_api_endpoint.is_none() {
@@ -71,9 +90,19 @@ impl Client {
             debug!("Dialect integration enabled");
         }
 
+        let rpc_url = match solana_endpoint {
+            Some(url) => Some(url.parse().context("Failed to parse Solana RPC URL")?),
+            None => {
+                warn!("No Solana RPC endpoint given, unrecorded block times will not be fetched");
+                None
+            },
+        };
+
+        let block_times = BlockTimes::spawn(db.inner().clone(), rpc_url)?;
+
         Ok(Arc::new(Self {
             db,
-            http: reqwest::Client::new(Duration::from_millis(500))?,
+            http: reqwest::Client::new(Duration::from_millis(500), politeness)?,
             http_prod: HttpProducers {
                 metadata_json: http_indexer::Producer::new(conn, meta_queue)
                     .await
@@ -83,6 +112,8 @@ impl Client {
                     .context("Couldn't create AMQP store config producer")?,
             },
             search: search_dispatch::Client::new(conn, search_queue, search).await?,
+            block_times,
+            asset_proxy,
             dialect_api_endpoint,
             dialect_api_key,
         }))
@@ -100,6 +131,17 @@ impl Client {
         &self.search
     }
 
+    /// Get the block time of a slot, from the `slot_times` table or else the
+    /// Solana RPC node, falling back to an estimate once the slot has been
+    /// waited on for too long
+    ///
+    /// # Errors
+    /// This function fails with [`RetryLater`](crate::RetryLater) if the block
+    /// time is not available yet.
+    pub async fn block_time(&self, slot: u64) -> Result<NaiveDateTime> {
+        self.block_times.get(slot).await
+    }
+
     /// Dispatch an AMQP message to the HTTP indexer to request off-chain
     /// metadata JSON
     ///
@@ -142,6 +184,74 @@ impl Client {
             .await
     }
 
+    /// Rebuild the search document for a wallet and dispatch an upsert for it
+    ///
+    /// # Errors
+    /// This function fails if the document cannot be built or the AMQP payload
+    /// cannot be sent.
+    pub async fn dispatch_wallet_document(
+        &self,
+        is_for_backfill: bool,
+        address: String,
+    ) -> Result<()> {
+        let document = self
+            .db
+            .run({
+                let address = address.clone();
+                move |db| search_documents::wallet_document(db, &address)
+            })
+            .await
+            .context("Failed to build wallet search document")?;
+
+        self.search
+            .upsert_wallet(is_for_backfill, address, document)
+            .await
+    }
+
+    /// Rebuild the search document for an NFT from its indexed metadata JSON
+    /// and dispatch an upsert for it, or a delete if it has been removed from
+    /// search.  Marketplace activity and transfers call this to keep the
+    /// listing price and owner of the document current.
+    ///
+    /// # Errors
+    /// This function fails if the AMQP payload cannot be sent.  Failures to
+    /// build the document are logged and skipped.
+    pub async fn dispatch_metadata_document(&self, address: String) -> Result<()> {
+        let document = self
+            .db
+            .run({
+                let address = address.clone();
+                let proxy_args = self.asset_proxy.clone();
+
+                move |db| {
+                    if search_documents::metadata_removed(db, &address)? {
+                        return Ok((true, None));
+                    }
+
+                    search_documents::indexed_metadata_document(db, &proxy_args, &address)
+                        .map(|d| (false, d))
+                }
+            })
+            .await;
+
+        match document {
+            Ok((true, _)) => self.search.delete_metadata(address).await,
+            Ok((false, Some(document))) => {
+                self.search.upsert_metadata(false, address, document).await
+            },
+            // The HTTP indexer sends the document once the JSON is indexed
+            Ok((false, None)) => Ok(()),
+            Err(e) => {
+                warn!(
+                    "Failed to get search document data for metadata {}: {:?}",
+                    address, e
+                );
+
+                Ok(())
+            },
+        }
+    }
+
     /// Dispatch a POST request to Dialect
     ///
     /// # Errors
@@ -176,11 +286,10 @@ impl Client {
 
         let res = self
             .http
-            .run(|h| {
+            .send(|h| {
                 h.post(endpoint)
                     .basic_auth("holaplex", Some(key))
                     .json(&msg)
-                    .send()
             })
             .await
             .context("Dialect dispatch call failed")?;
@@ -22,6 +22,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = Buy::try_from_slice(data).context("failed to deserialize")?;
 
@@ -48,7 +49,7 @@ pub(crate) async fn process(
         escrow_payment_bump: params.escrow_payment_bump.try_into()?,
         buyer_price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
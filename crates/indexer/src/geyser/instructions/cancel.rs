@@ -16,6 +16,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = Cancel::try_from_slice(data).context("failed to deserialize")?;
 
@@ -36,11 +37,11 @@ pub(crate) async fn process(
         trade_state: Owned(accts[6].clone()),
         buyer_price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
-    client
+    let canceled = client
         .db()
         .run(move |db| {
             insert_into(cancel_instructions::table)
@@ -70,7 +71,8 @@ pub(crate) async fn process(
                         listings::canceled_at.eq(Some(row.created_at)),
                         listings::slot.eq(row.slot),
                     ))
-                    .execute(db)
+                    .returning(listings::metadata)
+                    .get_results::<String>(db)
                 } else {
                     update(
                         offers::table.filter(
@@ -85,11 +87,16 @@ pub(crate) async fn process(
                         offers::slot.eq(row.slot),
                     ))
                     .execute(db)
+                    .map(|_| Vec::new())
                 }
             })
         })
         .await
         .context("failed to insert cancel instruction ")?;
 
+    for metadata in canceled {
+        client.dispatch_metadata_document(metadata).await?;
+    }
+
     Ok(())
 }
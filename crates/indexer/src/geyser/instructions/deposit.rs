@@ -10,6 +10,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = Deposit::try_from_slice(data).context("failed to deserialize")?;
 
@@ -31,7 +32,7 @@ pub(crate) async fn process(
         auction_house_fee_account: Owned(accts[7].clone()),
         escrow_payment_bump: params.escrow_payment_bump.try_into()?,
         amount: params.amount.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
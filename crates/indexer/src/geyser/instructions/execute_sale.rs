@@ -23,6 +23,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = ExecuteSale::try_from_slice(data).context("failed to deserialize")?;
 
@@ -56,7 +57,7 @@ pub(crate) async fn process(
         program_as_signer_bump: params.program_as_signer_bump.try_into()?,
         buyer_price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
@@ -98,6 +99,8 @@ pub(crate) async fn upsert_into_purchases_table<'a>(
     buyer_trade_state: String,
     seller_trade_state: String,
 ) -> Result<()> {
+    let metadata = data.metadata.to_string();
+
     client
         .db()
         .run(move |db| {
@@ -185,5 +188,5 @@ pub(crate) async fn upsert_into_purchases_table<'a>(
         .await
         .context("Failed to insert purchase!")?;
 
-    Ok(())
+    client.dispatch_metadata_document(metadata).await
 }
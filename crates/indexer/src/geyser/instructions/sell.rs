@@ -21,6 +21,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = Sell::try_from_slice(data).context("failed to deserialize")?;
 
@@ -46,7 +47,7 @@ pub(crate) async fn process(
         program_as_signer_bump: params._program_as_signer_bump.try_into()?,
         buyer_price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
@@ -83,6 +84,8 @@ pub(crate) async fn process(
 }
 
 pub async fn upsert_into_listings_table<'a>(client: &Client, row: Listing<'static>) -> Result<()> {
+    let metadata = row.metadata.to_string();
+
     client
         .db()
         .run(move |db| {
@@ -140,5 +143,5 @@ pub async fn upsert_into_listings_table<'a>(client: &Client, row: Listing<'stati
         .await
         .context("Failed to insert listing!")?;
 
-    Ok(())
+    client.dispatch_metadata_document(metadata).await
 }
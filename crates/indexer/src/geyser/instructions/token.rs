@@ -18,15 +18,24 @@ pub(crate) async fn process_burn_instruction(
     let mint = accounts[2].to_string();
     let slot = i64::try_from(slot)?;
 
-    client
+    let addresses: Vec<String> = client
         .db()
         .run(move |db| {
             update(metadatas::table.filter(metadatas::mint_address.eq(mint)))
                 .set((metadatas::burned.eq(true), metadatas::slot.eq(slot)))
-                .execute(db)
+                .returning(metadatas::address)
+                .get_results(db)
         })
         .await
         .context("failed to update metadata")?;
 
+    for address in addresses {
+        client
+            .search()
+            .delete_metadata(address)
+            .await
+            .context("Failed to dispatch metadata document delete job")?;
+    }
+
     Ok(())
 }
@@ -12,6 +12,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = WithdrawFromFee::try_from_slice(data).context("failed to deserialize")?;
 
@@ -28,7 +29,7 @@ pub(crate) async fn process(
         auction_house_fee_account: Owned(accts[2].clone()),
         auction_house: Owned(accts[3].clone()),
         amount: params.amount.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
@@ -13,6 +13,7 @@ pub(crate) async fn process(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = WithdrawFromTreasury::try_from_slice(data).context("failed to deserialize")?;
 
@@ -29,7 +30,7 @@ pub(crate) async fn process(
         auction_house_treasury: Owned(accts[3].clone()),
         auction_house: Owned(accts[4].clone()),
         amount: params.amount.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         slot: slot.try_into()?,
     };
 
@@ -1,6 +1,7 @@
 //! Support features for the Geyser indexer
 
 mod accounts;
+mod block_times;
 mod client;
 mod instructions;
 mod programs;
@@ -105,15 +106,26 @@ pub async fn process_message<H: std::hash::BuildHasher>(
             Ok(())
         },
         Message::InstructionNotify(ins) if ins.program == pubkeys::AUCTION_HOUSE => {
-            programs::auction_house::process_instruction(client, &ins.data, &ins.accounts, ins.slot)
-                .await
+            let block_time = client.block_time(ins.slot).await?;
+
+            programs::auction_house::process_instruction(
+                client,
+                &ins.data,
+                &ins.accounts,
+                ins.slot,
+                block_time,
+            )
+            .await
         },
         Message::InstructionNotify(ins) if ins.program == pubkeys::ME_HAUS => {
+            let block_time = client.block_time(ins.slot).await?;
+
             programs::magic_eden_haus::process_instruction(
                 client,
                 &ins.data,
                 &ins.accounts,
                 ins.slot,
+                block_time,
             )
             .await
         },
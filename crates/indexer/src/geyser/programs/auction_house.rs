@@ -94,21 +94,24 @@ pub(crate) async fn process_instruction(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let discriminator: [u8; 8] = data[..8].try_into()?;
     let params = data[8..].to_vec();
 
     match discriminator {
-        BUY => buy::process(client, &params, accounts, slot).await,
-        PUBLIC_BUY => public_buy::process(client, &params, accounts, slot).await,
-        SELL => sell::process(client, &params, accounts, slot).await,
-        EXECUTE_SALE => execute_sale::process(client, &params, accounts, slot).await,
-        CANCEL => cancel::process(client, &params, accounts, slot).await,
-        DEPOSIT => deposit::process(client, &params, accounts, slot).await,
-        WITHDRAW => withdraw::process(client, &params, accounts, slot).await,
-        WITHDRAW_FROM_FEE => withdraw_from_fee::process(client, &params, accounts, slot).await,
+        BUY => buy::process(client, &params, accounts, slot, block_time).await,
+        PUBLIC_BUY => public_buy::process(client, &params, accounts, slot, block_time).await,
+        SELL => sell::process(client, &params, accounts, slot, block_time).await,
+        EXECUTE_SALE => execute_sale::process(client, &params, accounts, slot, block_time).await,
+        CANCEL => cancel::process(client, &params, accounts, slot, block_time).await,
+        DEPOSIT => deposit::process(client, &params, accounts, slot, block_time).await,
+        WITHDRAW => withdraw::process(client, &params, accounts, slot, block_time).await,
+        WITHDRAW_FROM_FEE => {
+            withdraw_from_fee::process(client, &params, accounts, slot, block_time).await
+        },
         WITHDRAW_FROM_TREASURY => {
-            withdraw_from_treasury::process(client, &params, accounts, slot).await
+            withdraw_from_treasury::process(client, &params, accounts, slot, block_time).await
         },
         _ => Ok(()),
     }
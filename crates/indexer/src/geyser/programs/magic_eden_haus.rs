@@ -34,6 +34,7 @@ async fn process_execute_sale(
     mut data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = MEInstructionData::deserialize(&mut data)
         .context("failed to deserialize ME ExecuteSale instruction")?;
@@ -50,7 +51,7 @@ async fn process_execute_sale(
             metadata: Owned(accts[5].clone()),
             token_size: params.token_size.try_into()?,
             price: params.buyer_price.try_into()?,
-            created_at: Utc::now().naive_utc(),
+            created_at: block_time,
             slot: slot.try_into()?,
             write_version: None,
         },
@@ -68,6 +69,7 @@ async fn process_sale(
     mut data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = MEInstructionData::deserialize(&mut data)
         .context("failed to deserialize ME Sell instruction")?;
@@ -84,7 +86,7 @@ async fn process_sale(
         price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
         trade_state_bump: params.trade_state_bump.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         canceled_at: None,
         slot: slot.try_into()?,
         write_version: None,
@@ -100,6 +102,7 @@ async fn process_buy(
     mut data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let params = MEInstructionData::deserialize(&mut data)
         .context("failed to deserialize ME Buy instruction")?;
@@ -122,7 +125,7 @@ async fn process_buy(
         price: params.buyer_price.try_into()?,
         token_size: params.token_size.try_into()?,
         trade_state_bump: params.trade_state_bump.try_into()?,
-        created_at: Utc::now().naive_utc(),
+        created_at: block_time,
         canceled_at: None,
         slot: slot.try_into()?,
         write_version: None,
@@ -133,13 +136,17 @@ async fn process_buy(
     Ok(())
 }
 
-async fn process_cancel_sale(client: &Client, accounts: &[Pubkey], slot: u64) -> Result<()> {
+async fn process_cancel_sale(
+    client: &Client,
+    accounts: &[Pubkey],
+    slot: u64,
+    block_time: NaiveDateTime,
+) -> Result<()> {
     let accts: Vec<_> = accounts.iter().map(ToString::to_string).collect();
-    let canceled_at = Utc::now().naive_utc();
     let trade_state = accts[6].clone();
     let slot = i64::try_from(slot)?;
 
-    client
+    let canceled = client
         .db()
         .run(move |db| {
             update(
@@ -151,20 +158,29 @@ async fn process_cancel_sale(client: &Client, accounts: &[Pubkey], slot: u64) ->
                 ),
             )
             .set((
-                listings::canceled_at.eq(Some(canceled_at)),
+                listings::canceled_at.eq(Some(block_time)),
                 listings::slot.eq(slot),
             ))
-            .execute(db)
+            .returning(listings::metadata)
+            .get_results::<String>(db)
         })
         .await
         .context("failed to cancel ME listing ")?;
 
+    for metadata in canceled {
+        client.dispatch_metadata_document(metadata).await?;
+    }
+
     Ok(())
 }
 
-async fn process_cancel_buy(client: &Client, accounts: &[Pubkey], slot: u64) -> Result<()> {
+async fn process_cancel_buy(
+    client: &Client,
+    accounts: &[Pubkey],
+    slot: u64,
+    block_time: NaiveDateTime,
+) -> Result<()> {
     let accts: Vec<_> = accounts.iter().map(ToString::to_string).collect();
-    let canceled_at = Utc::now().naive_utc();
     let trade_state = accts[5].clone();
     let slot = i64::try_from(slot)?;
 
@@ -180,7 +196,7 @@ async fn process_cancel_buy(client: &Client, accounts: &[Pubkey], slot: u64) ->
                 ),
             )
             .set((
-                offers::canceled_at.eq(Some(canceled_at)),
+                offers::canceled_at.eq(Some(block_time)),
                 offers::slot.eq(slot),
             ))
             .execute(db)
@@ -196,16 +212,17 @@ pub(crate) async fn process_instruction(
     data: &[u8],
     accounts: &[Pubkey],
     slot: u64,
+    block_time: NaiveDateTime,
 ) -> Result<()> {
     let (discriminator, params) = data.split_at(8);
     let discriminator = <[u8; 8]>::try_from(discriminator)?;
 
     match discriminator {
-        BUY => process_buy(client, params, accounts, slot).await,
-        SELL => process_sale(client, params, accounts, slot).await,
-        EXECUTE_SALE => process_execute_sale(client, params, accounts, slot).await,
-        CANCEL_SELL => process_cancel_sale(client, accounts, slot).await,
-        CANCEL_BUY => process_cancel_buy(client, accounts, slot).await,
+        BUY => process_buy(client, params, accounts, slot, block_time).await,
+        SELL => process_sale(client, params, accounts, slot, block_time).await,
+        EXECUTE_SALE => process_execute_sale(client, params, accounts, slot, block_time).await,
+        CANCEL_SELL => process_cancel_sale(client, accounts, slot, block_time).await,
+        CANCEL_BUY => process_cancel_buy(client, accounts, slot, block_time).await,
         _ => Ok(()),
     }
 }
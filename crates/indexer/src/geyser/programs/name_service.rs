@@ -19,6 +19,11 @@ struct Header {
 }
 
 pub(crate) async fn process(client: &Client, update: AccountUpdate) -> Result<()> {
+    // Deleting a name drains its account, so its data can no longer be read
+    if update.lamports == 0 {
+        return name_service::process_delete(client, update.key, update.slot).await;
+    }
+
     if update.data.len() <= HEADER_LENGTH {
         return Ok(());
     }
@@ -2,7 +2,12 @@ use std::{sync::Arc, time::Duration};
 
 use indexer_core::{assets::AssetProxyArgs, clap};
 use indexer_rabbitmq::search_indexer;
+use tokio::sync::mpsc;
 
+use super::{
+    gateway::{self, Gateways},
+    image_hash,
+};
 use crate::{db::Pool, prelude::*, reqwest, search_dispatch};
 
 /// Common arguments for internal HTTP indexer usage
@@ -12,9 +17,18 @@ pub struct Args {
     #[clap(flatten)]
     asset_proxy: AssetProxyArgs,
 
+    #[clap(flatten)]
+    gateways: gateway::Args,
+
+    #[clap(flatten)]
+    politeness: reqwest::Args,
+
     #[clap(flatten)]
     search: search_dispatch::Args,
 
+    #[clap(flatten)]
+    image_hash: image_hash::Args,
+
     /// HTTP request timeout, in seconds
     #[clap(long, env = "HTTP_INDEXER_TIMEOUT")]
     timeout: f64,
@@ -26,15 +40,17 @@ pub struct Client {
     db: Pool,
     http: reqwest::Client,
     asset_proxy: AssetProxyArgs,
+    gateways: Gateways,
     search: search_dispatch::Client,
+    image_hashes: mpsc::Sender<image_hash::Job>,
 }
 
 impl Client {
     /// Construct a new client, wrapped in an `Arc`.
     ///
     /// # Errors
-    /// This function fails if an invalid URL is given for `ipfs_cdn` or
-    /// `arweave_cdn`.
+    /// This function fails if an invalid URL is given for `ipfs_cdn`,
+    /// `arweave_cdn`, or any of the IPFS or Arweave gateways.
     pub async fn new_rc(
         db: Pool,
         conn: &indexer_rabbitmq::lapin::Connection,
@@ -43,18 +59,32 @@ impl Client {
     ) -> Result<Arc<Self>> {
         let Args {
             asset_proxy,
+            gateways,
+            politeness,
             timeout,
             search,
+            image_hash,
         } = args;
 
         let timeout = Duration::from_secs_f64(timeout);
+        let (image_hashes, image_hash_rx) = image_hash.channel();
 
-        Ok(Arc::new(Self {
+        let arc_self = Arc::new(Self {
             db,
-            http: reqwest::Client::new(timeout)?,
+            http: reqwest::Client::new(timeout, politeness)?,
             asset_proxy,
+            gateways: Gateways::new(gateways)?,
             search: search_dispatch::Client::new(conn, search_queue, search).await?,
-        }))
+            image_hashes,
+        });
+
+        tokio::spawn(image_hash::run(
+            Arc::downgrade(&arc_self),
+            image_hash_rx,
+            image_hash,
+        ));
+
+        Ok(arc_self)
     }
 
     /// Get a reference to the database
@@ -80,4 +110,19 @@ impl Client {
     pub fn proxy_args(&self) -> &AssetProxyArgs {
         &self.asset_proxy
     }
+
+    /// Get a reference to the permaweb gateways, along with their health
+    /// scores
+    #[inline]
+    pub fn gateways(&self) -> &Gateways {
+        &self.gateways
+    }
+
+    /// Queue the image of an NFT to be perceptually hashed, waiting if the
+    /// queue is full
+    pub(super) async fn queue_image_hash(&self, addr: String, image: String) {
+        if self.image_hashes.send((addr, image)).await.is_err() {
+            warn!("Image hash queue closed unexpectedly");
+        }
+    }
 }
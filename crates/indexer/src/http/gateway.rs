@@ -0,0 +1,319 @@
+//! Hedged fetching of permaweb assets across the asset proxy and any
+//! additional IPFS or Arweave gateways
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use indexer_core::{
+    assets::{proxy_url_hinted, AssetHint, AssetIdentifier, AssetProxyArgs},
+    clap,
+};
+use reqwest::Url;
+
+use crate::prelude::*;
+
+/// The number of most recent requests used to compute a gateway's health
+const HEALTH_WINDOW: usize = 100;
+
+/// Common arguments for configuring permaweb gateways
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Base URLs of IPFS gateways to try in addition to the asset proxy, e.g.
+    /// `https://ipfs.io/ipfs`
+    #[clap(long, env, use_value_delimiter(true))]
+    ipfs_gateways: Vec<String>,
+
+    /// Base URLs of Arweave gateways to try in addition to the asset proxy,
+    /// e.g. `https://arweave.net`
+    #[clap(long, env, use_value_delimiter(true))]
+    arweave_gateways: Vec<String>,
+
+    /// Time in milliseconds to wait on a gateway before also sending the
+    /// request to the next-healthiest gateway
+    #[clap(long, env, default_value_t = 1000)]
+    gateway_hedge_delay_ms: u64,
+}
+
+/// Rolling record of request outcomes for a single gateway
+#[derive(Debug, Default)]
+struct Health {
+    /// The latency of each recent request, or `None` if it failed
+    samples: VecDeque<Option<Duration>>,
+}
+
+impl Health {
+    fn record(&mut self, sample: Option<Duration>) {
+        if self.samples.len() >= HEALTH_WINDOW {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(sample);
+    }
+
+    /// Score this gateway by its success rate, penalized by its mean latency
+    /// in seconds.  Gateways with no history are scored optimistically.
+    #[allow(clippy::cast_precision_loss)]
+    fn score(&self) -> f64 {
+        let (successes, latency) = self
+            .samples
+            .iter()
+            .flatten()
+            .fold((0_usize, Duration::ZERO), |(n, l), d| (n + 1, l + *d));
+
+        if self.samples.is_empty() {
+            return 1.0;
+        }
+
+        let success_rate = successes as f64 / self.samples.len() as f64;
+        let mean_latency = if successes == 0 {
+            0.0
+        } else {
+            latency.as_secs_f64() / successes as f64
+        };
+
+        success_rate / (1.0 + mean_latency)
+    }
+}
+
+#[derive(Debug)]
+enum GatewayKind {
+    /// The Holaplex asset proxy configured by [`AssetProxyArgs`]
+    Proxy,
+    /// A plain gateway serving assets under a base URL
+    Base(Url),
+}
+
+#[derive(Debug)]
+struct Gateway {
+    kind: GatewayKind,
+    health: Mutex<Health>,
+}
+
+impl Gateway {
+    fn new(kind: GatewayKind) -> Self {
+        Self {
+            kind,
+            health: Mutex::default(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.kind {
+            GatewayKind::Proxy => "asset proxy",
+            GatewayKind::Base(ref u) => u.as_str(),
+        }
+    }
+
+    fn score(&self) -> f64 {
+        self.health.lock().map_or(0.0, |h| h.score())
+    }
+
+    fn record(&self, sample: Option<Duration>) {
+        if let Ok(mut h) = self.health.lock() {
+            h.record(sample);
+        }
+    }
+
+    fn url(
+        &self,
+        proxy_args: &AssetProxyArgs,
+        id: &AssetIdentifier,
+        hint: AssetHint,
+    ) -> Result<Option<Url>> {
+        let base = match self.kind {
+            GatewayKind::Proxy => return proxy_url_hinted(proxy_args, id, hint, None),
+            GatewayKind::Base(ref b) => b,
+        };
+
+        let (asset, path) = match (hint, &id.ipfs, &id.arweave) {
+            (AssetHint::Ipfs, Some((cid, path)), _) => (cid.to_string(), path),
+            (AssetHint::Arweave, _, Some((txid, path))) => (
+                base64::encode_config(&txid.0, base64::URL_SAFE_NO_PAD),
+                path,
+            ),
+            _ => return Ok(None),
+        };
+
+        let mut url = base.clone();
+        url.path_segments_mut()
+            .map_err(|()| anyhow!("Gateway URL {:?} cannot be a base", base.as_str()))?
+            .pop_if_empty()
+            .push(&asset)
+            .extend(path.split('/').filter(|s| !s.is_empty()));
+
+        Ok(Some(url))
+    }
+}
+
+/// Guard for a request to a gateway which records a failure if the request is
+/// dropped before completing, e.g. because a hedged request to another gateway
+/// finished first.  Without it slow gateways would only ever be scored on the
+/// requests they happened to win.
+struct InFlight<'a> {
+    gateway: &'a Gateway,
+    start: Instant,
+    done: bool,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let elapsed = self.start.elapsed();
+
+            trace!(
+                "Request to {} cancelled after {:?}",
+                self.gateway.name(),
+                elapsed
+            );
+            self.gateway.record(None);
+        }
+    }
+}
+
+/// The set of gateways available for fetching permaweb assets, along with
+/// their health scores
+#[derive(Debug)]
+pub struct Gateways {
+    proxy: Gateway,
+    ipfs: Vec<Gateway>,
+    arweave: Vec<Gateway>,
+    hedge_delay: Duration,
+}
+
+impl Gateways {
+    /// Construct a new gateway set from the given arguments
+    ///
+    /// # Errors
+    /// This function fails if any of the given gateway URLs is invalid.
+    pub fn new(args: Args) -> Result<Self> {
+        let Args {
+            ipfs_gateways,
+            arweave_gateways,
+            gateway_hedge_delay_ms,
+        } = args;
+
+        let parse = |urls: Vec<String>| {
+            urls.into_iter()
+                .map(|u| {
+                    Url::parse(&u)
+                        .with_context(|| format!("Invalid gateway URL {:?}", u))
+                        .map(|u| Gateway::new(GatewayKind::Base(u)))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            proxy: Gateway::new(GatewayKind::Proxy),
+            ipfs: parse(ipfs_gateways)?,
+            arweave: parse(arweave_gateways)?,
+            hedge_delay: Duration::from_millis(gateway_hedge_delay_ms),
+        })
+    }
+
+    /// List the URLs at which an asset can be found, healthiest gateway first
+    fn candidates(
+        &self,
+        proxy_args: &AssetProxyArgs,
+        id: &AssetIdentifier,
+        hint: AssetHint,
+    ) -> Result<Vec<(&Gateway, Url)>> {
+        let extra = match hint {
+            AssetHint::Ipfs => &self.ipfs,
+            AssetHint::Arweave => &self.arweave,
+        };
+
+        let mut candidates = Some(&self.proxy)
+            .into_iter()
+            .chain(extra)
+            .filter_map(|g| g.url(proxy_args, id, hint).transpose().map(|u| (g, u)))
+            .map(|(g, u)| u.map(|u| (g.score(), g, u)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Stable sort, so ties are broken by configuration order
+        candidates
+            .sort_by(|(a, ..), (b, ..)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(candidates.into_iter().map(|(_, g, u)| (g, u)).collect())
+    }
+
+    fn attempt<'a, T, F: Future<Output = Result<T>>, G: Fn(Url) -> F>(
+        (gateway, url): (&'a Gateway, Url),
+        f: &G,
+    ) -> impl Future<Output = (&'a Gateway, Duration, Result<T>)> {
+        trace!("Requesting {:?} from {}", url.as_str(), gateway.name());
+        let fut = f(url);
+
+        async move {
+            let mut in_flight = InFlight {
+                gateway,
+                start: Instant::now(),
+                done: false,
+            };
+            let res = fut.await;
+            in_flight.done = true;
+
+            (gateway, in_flight.start.elapsed(), res)
+        }
+    }
+
+    /// Fetch an asset from the healthiest available gateway.  If a request
+    /// has not completed after the configured hedge delay, or fails, the
+    /// request is additionally sent to the next gateway, and the first
+    /// successful response is returned.
+    ///
+    /// # Errors
+    /// This function fails if the asset cannot be located on any gateway, or
+    /// if the request to every gateway fails.
+    pub async fn fetch<T, F: Future<Output = Result<T>>, G: Fn(Url) -> F>(
+        &self,
+        proxy_args: &AssetProxyArgs,
+        id: &AssetIdentifier<'_>,
+        hint: AssetHint,
+        f: G,
+    ) -> Result<T> {
+        let mut pending = self.candidates(proxy_args, id, hint)?.into_iter();
+        let mut running = FuturesUnordered::new();
+        let mut last_err = None;
+
+        loop {
+            if running.is_empty() {
+                match pending.next() {
+                    Some(c) => running.push(Self::attempt(c, &f)),
+                    None => break,
+                }
+            }
+
+            tokio::select! {
+                Some((gateway, elapsed, res)) = running.next() => match res {
+                    Ok(v) => {
+                        gateway.record(Some(elapsed));
+
+                        return Ok(v);
+                    },
+                    Err(e) => {
+                        gateway.record(None);
+                        debug!("Fetch from {} failed: {:?}", gateway.name(), e);
+                        last_err = Some(e);
+
+                        if let Some(c) = pending.next() {
+                            running.push(Self::attempt(c, &f));
+                        }
+                    },
+                },
+                () = tokio::time::sleep(self.hedge_delay), if !pending.as_slice().is_empty() => {
+                    if let Some(c) = pending.next() {
+                        running.push(Self::attempt(c, &f));
+                    }
+                },
+            }
+        }
+
+        Err(last_err
+            .unwrap_or_else(|| anyhow!("No gateway could serve asset {:?}", id.url.as_str())))
+    }
+}
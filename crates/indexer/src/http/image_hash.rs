@@ -0,0 +1,429 @@
+//! Perceptual image hashing for detecting copymints
+
+use std::sync::{Arc, Weak};
+
+use image::imageops::FilterType;
+use indexer_core::{
+    assets::{proxy_url, AssetIdentifier},
+    clap,
+    db::{
+        delete, insert_into,
+        models::{ImageHashCandidate, MetadataImageHash, SuspectedCopy},
+        queries,
+        tables::{metadata_creators, metadata_image_hashes, metadatas, suspected_copies},
+    },
+};
+use reqwest::Url;
+use tokio::sync::{mpsc, Semaphore};
+
+use super::Client;
+use crate::prelude::*;
+
+/// The maximum Hamming distance between two image hashes for the images to be
+/// considered copies of one another.  Must not exceed 3, as this is the
+/// largest distance guaranteed to be found by the banded hash lookup.
+const MAX_DISTANCE: u32 = 3;
+
+/// The minimum number of bits a hash must have set, and unset, to be compared
+/// against other hashes.  Blank, uniform and smooth gradient images hash to
+/// all or almost all zeroes or ones, and would otherwise all match each other.
+const MIN_SET_BITS: u32 = 8;
+
+/// Options for the image hashing queue
+#[derive(Debug, Clone, Copy, clap::Args)]
+pub struct Args {
+    /// Maximum number of images waiting to be hashed.  Metadata JSON jobs
+    /// queueing an image past this bound wait for room, which delays their
+    /// acknowledgement.
+    #[clap(long, env, default_value_t = 1000)]
+    image_hash_queue_size: usize,
+
+    /// Number of images downloaded and hashed concurrently
+    #[clap(long, env, default_value_t = 4)]
+    image_hash_workers: usize,
+}
+
+/// An NFT address and image URL waiting to be hashed
+pub(super) type Job = (String, String);
+
+impl Args {
+    /// Create the channel image hash jobs are queued on
+    pub(super) fn channel(self) -> (mpsc::Sender<Job>, mpsc::Receiver<Job>) {
+        mpsc::channel(self.image_hash_queue_size.max(1))
+    }
+}
+
+/// Hash queued images until the client is dropped, separately from the
+/// metadata JSON jobs that queued them so slow image hosts don't hold those
+/// jobs up.  Images still queued when the process exits are hashed the next
+/// time their metadata JSON is indexed.
+pub(super) async fn run(client: Weak<Client>, mut rx: mpsc::Receiver<Job>, args: Args) {
+    let permits = Arc::new(Semaphore::new(args.image_hash_workers.max(1)));
+
+    while let Some((addr, image)) = rx.recv().await {
+        let permit = match permits.clone().acquire_owned().await {
+            Ok(p) => p,
+            Err(_) => break,
+        };
+        let client = match client.upgrade() {
+            Some(c) => c,
+            None => break,
+        };
+
+        tokio::spawn(async move {
+            process(&client, addr.clone(), image)
+                .await
+                .map_err(|e| warn!("Failed to hash image for {}: {:?}", addr, e))
+                .ok();
+
+            std::mem::drop(permit);
+        });
+    }
+}
+
+/// Compute a 64-bit difference hash for an encoded image.
+///
+/// The image is downscaled to 9x8 grayscale pixels, and each bit of the hash
+/// records whether a pixel is darker than its right-hand neighbor.
+fn dhash(bytes: &[u8]) -> Result<u64> {
+    let img = image::load_from_memory(bytes)
+        .context("Failed to decode image")?
+        .resize_exact(9, 8, FilterType::Triangle)
+        .into_luma8();
+
+    let mut hash = 0_u64;
+
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+
+            if img.get_pixel(x, y)[0] < img.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+/// Returns true if a hash is too close to all zeroes or all ones to tell
+/// images apart
+fn is_degenerate(hash: u64) -> bool {
+    hash.count_ones() < MIN_SET_BITS || hash.count_zeros() < MIN_SET_BITS
+}
+
+/// Determine which of a pair of visually-identical NFTs is the original.
+///
+/// Returns `None` if the pair should not be flagged, i.e. because they share a
+/// verified creator or neither one has a verified creator.
+fn pick_original<'a>(
+    this: (&'a str, Option<i64>, &[String]),
+    other: (&'a str, Option<i64>, &[String]),
+) -> Option<(&'a str, &'a str)> {
+    let (this_addr, this_slot, this_creators) = this;
+    let (other_addr, other_slot, other_creators) = other;
+
+    if this_creators.iter().any(|c| other_creators.contains(c)) {
+        return None;
+    }
+
+    match (this_creators.is_empty(), other_creators.is_empty()) {
+        (true, true) => None,
+        (true, false) => Some((other_addr, this_addr)),
+        (false, true) => Some((this_addr, other_addr)),
+        (false, false) => {
+            if this_slot.unwrap_or(i64::MAX) < other_slot.unwrap_or(i64::MAX) {
+                Some((this_addr, other_addr))
+            } else {
+                Some((other_addr, this_addr))
+            }
+        },
+    }
+}
+
+/// Hash the image of an NFT and record any near-duplicates of it from
+/// different verified creators in the `suspected_copies` table.
+///
+/// # Errors
+/// This function fails if the image cannot be downloaded or decoded, or if a
+/// database operation fails.
+pub async fn process(client: &Client, addr: String, image: String) -> Result<()> {
+    let url = match Url::parse(&image) {
+        Ok(u) => u,
+        Err(e) => {
+            trace!("Couldn't parse image URL for {}: {:?}", addr, e);
+            return Ok(());
+        },
+    };
+    let id = AssetIdentifier::new(&url);
+    // Images whose permaweb location is ambiguous are identified by their URL
+    let image_fingerprint = id
+        .fingerprint(None, true)
+        .map_or_else(|| url.as_str().as_bytes().to_vec(), Cow::into_owned);
+
+    let existing = client
+        .db()
+        .run({
+            let addr = addr.clone();
+            move |db| {
+                metadata_image_hashes::table
+                    .filter(metadata_image_hashes::metadata_address.eq(addr))
+                    .select(metadata_image_hashes::image_fingerprint)
+                    .first::<Vec<u8>>(db)
+                    .optional()
+            }
+        })
+        .await
+        .context("Failed to check for an existing image hash")?;
+
+    if existing.map_or(false, |f| f == image_fingerprint) {
+        trace!("Skipping already-hashed image for {}", addr);
+        return Ok(());
+    }
+
+    let fetch_url =
+        proxy_url(client.proxy_args(), &id, Some(("width", "100")))?.unwrap_or_else(|| url.clone());
+
+    let bytes = client
+        .http()
+        .send(|h| h.get(fetch_url.clone()))
+        .await
+        .and_then(|r| r.error_for_status().map_err(Into::into))
+        .with_context(|| format!("Failed to download image {:?}", fetch_url.as_str()))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read image {:?}", fetch_url.as_str()))?;
+
+    let hash = tokio::task::spawn_blocking(move || dhash(&bytes))
+        .await
+        .context("Blocking task failed")??;
+    let degenerate = is_degenerate(hash);
+    let hash = i64::from_ne_bytes(hash.to_ne_bytes());
+
+    client
+        .db()
+        .run(move |db| {
+            let row = MetadataImageHash {
+                metadata_address: Borrowed(&addr),
+                image_fingerprint: Owned(image_fingerprint),
+                dhash: hash,
+                updated_at: Local::now().naive_utc(),
+            };
+
+            insert_into(metadata_image_hashes::table)
+                .values(&row)
+                .on_conflict(metadata_image_hashes::metadata_address)
+                .do_update()
+                .set(&row)
+                .execute(db)
+                .context("Failed to insert image hash")?;
+
+            let slot: Option<i64> = metadatas::table
+                .filter(metadatas::address.eq(&addr))
+                .select(metadatas::created_slot)
+                .first(db)
+                .optional()
+                .context("Failed to load metadata creation slot")?
+                .flatten();
+
+            let creators: Vec<String> = metadata_creators::table
+                .filter(metadata_creators::metadata_address.eq(&addr))
+                .filter(metadata_creators::verified.eq(true))
+                .select(metadata_creators::creator_address)
+                .load(db)
+                .context("Failed to load verified creators")?;
+
+            let candidates = if degenerate {
+                trace!("Not comparing degenerate image hash for {}", addr);
+
+                vec![]
+            } else {
+                queries::suspected_copies::candidates(
+                    db,
+                    &addr,
+                    hash,
+                    (MIN_SET_BITS.into(), (64 - MIN_SET_BITS).into()),
+                )?
+            };
+            let detected_at = Local::now().naive_utc();
+
+            db.build_transaction().read_write().run(|| {
+                delete(
+                    suspected_copies::table.filter(
+                        suspected_copies::original_address
+                            .eq(&addr)
+                            .or(suspected_copies::copy_address.eq(&addr)),
+                    ),
+                )
+                .execute(db)
+                .context("Failed to clear stale suspected copies")?;
+
+                for ImageHashCandidate {
+                    metadata_address,
+                    dhash: other_hash,
+                    created_slot: other_slot,
+                    verified_creators,
+                } in &candidates
+                {
+                    let distance = (hash ^ other_hash).count_ones();
+
+                    if distance > MAX_DISTANCE {
+                        continue;
+                    }
+
+                    let (original, copy) = match pick_original(
+                        (&addr, slot, &creators),
+                        (metadata_address, *other_slot, verified_creators),
+                    ) {
+                        Some(p) => p,
+                        None => continue,
+                    };
+
+                    debug!(
+                        "Suspected copy {} of {} (distance {})",
+                        copy, original, distance
+                    );
+
+                    let row = SuspectedCopy {
+                        original_address: Borrowed(original),
+                        copy_address: Borrowed(copy),
+                        similarity: 1.0 - f64::from(distance) / 64.0,
+                        detected_at,
+                    };
+
+                    insert_into(suspected_copies::table)
+                        .values(&row)
+                        .on_conflict((
+                            suspected_copies::original_address,
+                            suspected_copies::copy_address,
+                        ))
+                        .do_update()
+                        .set(&row)
+                        .execute(db)
+                        .context("Failed to insert suspected copy")?;
+                }
+
+                Result::<_>::Ok(())
+            })
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, GrayImage, ImageOutputFormat, Luma};
+
+    use super::*;
+
+    fn png(width: u32, height: u32, f: impl Fn(u32, u32) -> u8) -> Vec<u8> {
+        let img = GrayImage::from_fn(width, height, |x, y| Luma([f(x, y)]));
+        let mut buf = Cursor::new(vec![]);
+
+        DynamicImage::ImageLuma8(img)
+            .write_to(&mut buf, ImageOutputFormat::Png)
+            .unwrap();
+
+        buf.into_inner()
+    }
+
+    #[test]
+    fn dhash_compares_horizontal_neighbors() {
+        #[allow(clippy::cast_possible_truncation)]
+        let brightening = png(90, 80, |x, _| (x * 2) as u8);
+        #[allow(clippy::cast_possible_truncation)]
+        let darkening = png(90, 80, |x, _| (255 - x * 2) as u8);
+
+        assert_eq!(dhash(&brightening).unwrap(), u64::MAX);
+        assert_eq!(dhash(&darkening).unwrap(), 0);
+        assert_eq!(dhash(&png(90, 80, |_, _| 128)).unwrap(), 0);
+    }
+
+    #[test]
+    fn dhash_ignores_scale() {
+        // A 9x8 checkerboard, drawn with cells of any size
+        let checkerboard = |w: u32, h: u32| {
+            png(w, h, |x, y| {
+                if (x * 9 / w + y * 8 / h) % 2 == 0 {
+                    255
+                } else {
+                    0
+                }
+            })
+        };
+
+        let small = dhash(&checkerboard(90, 80)).unwrap();
+        let large = dhash(&checkerboard(360, 320)).unwrap();
+
+        assert!((small ^ large).count_ones() <= MAX_DISTANCE);
+    }
+
+    #[test]
+    fn flat_images_are_degenerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        let gradient = png(90, 80, |x, _| (x * 2) as u8);
+
+        assert!(is_degenerate(dhash(&png(90, 80, |_, _| 255)).unwrap()));
+        assert!(is_degenerate(dhash(&gradient).unwrap()));
+        assert!(is_degenerate(0b111));
+        assert!(!is_degenerate(0xf0f0_f0f0_f0f0_f0f0));
+    }
+
+    #[test]
+    fn dhash_rejects_invalid_images() {
+        assert!(dhash(b"not an image").is_err());
+    }
+
+    #[test]
+    fn pick_original_prefers_verified_creators() {
+        let verified = ["creator".to_owned()];
+
+        assert_eq!(
+            pick_original(("a", Some(1), &[]), ("b", Some(2), &verified)),
+            Some(("b", "a"))
+        );
+        assert_eq!(
+            pick_original(("a", Some(2), &verified), ("b", Some(1), &[])),
+            Some(("a", "b"))
+        );
+        assert_eq!(
+            pick_original(("a", Some(1), &[]), ("b", Some(2), &[])),
+            None
+        );
+    }
+
+    #[test]
+    fn pick_original_ignores_shared_creators() {
+        let this = ["shared".to_owned(), "a".to_owned()];
+        let other = ["b".to_owned(), "shared".to_owned()];
+
+        assert_eq!(
+            pick_original(("a", Some(1), &this), ("b", Some(2), &other)),
+            None
+        );
+    }
+
+    #[test]
+    fn pick_original_prefers_earlier_slots() {
+        let this = ["a".to_owned()];
+        let other = ["b".to_owned()];
+
+        assert_eq!(
+            pick_original(("a", Some(5), &this), ("b", Some(3), &other)),
+            Some(("b", "a"))
+        );
+        assert_eq!(
+            pick_original(("a", Some(3), &this), ("b", Some(5), &other)),
+            Some(("a", "b"))
+        );
+        assert_eq!(
+            pick_original(("a", None, &this), ("b", Some(5), &other)),
+            Some(("b", "a"))
+        );
+        assert_eq!(
+            pick_original(("a", Some(5), &this), ("b", None, &other)),
+            Some(("a", "b"))
+        );
+    }
+}
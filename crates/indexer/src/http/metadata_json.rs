@@ -1,17 +1,14 @@
 use std::fmt::{self, Debug, Display};
 
 use indexer_core::{
-    assets::{proxy_url, proxy_url_hinted, AssetIdentifier},
+    assets::AssetIdentifier,
     db::{
         insert_into,
         models::{
             File as DbFile, MetadataAttributeWrite, MetadataCollection,
             MetadataJson as DbMetadataJson,
         },
-        tables::{
-            attributes, files, metadata_collection_keys, metadata_collections, metadata_creators,
-            metadata_jsons, metadatas, twitter_handle_name_services,
-        },
+        tables::{attributes, files, metadata_collections, metadata_jsons},
         update, Connection,
     },
     hash::HashMap,
@@ -24,10 +21,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use super::Client;
-use crate::{
-    prelude::*,
-    search_dispatch::{CollectionDocument, MetadataDocument},
-};
+use crate::{prelude::*, search_documents, RetryLater};
 
 type SlotInfo = (i64, i64);
 
@@ -131,12 +125,12 @@ async fn fetch_json(
 
     let bytes = client
         .http()
-        .run(|h| {
-            let url = url.clone();
-            async move { h.get(url).send().await?.bytes().await }
-        })
+        .send(|h| h.get(url.clone()))
+        .await
+        .context("Failed to download metadata JSON")?
+        .bytes()
         .await
-        .context("Failed to download metadata JSON")?;
+        .context("Failed to read metadata JSON")?;
 
     let end_time = Local::now();
 
@@ -190,29 +184,46 @@ async fn try_locate_json(
     let mut resp = Ok(None);
 
     for (fingerprint, hint) in id.fingerprints_hinted() {
-        let url = if let Some(hint) = hint {
-            proxy_url_hinted(client.proxy_args(), id, hint, None)
-                .map(|u| u.unwrap_or_else(|| unreachable!()))
+        let res = if let Some(hint) = hint {
+            client
+                .gateways()
+                .fetch(client.proxy_args(), id, hint, |url| {
+                    fetch_json(client, meta_key, Ok(url))
+                })
+                .await
         } else if FETCH_NON_PERMAWEB {
-            Ok(id.url.clone())
+            fetch_json(client, meta_key, Ok(id.url.clone())).await
         } else {
             continue;
         };
-        let url_str = url.as_ref().map_or("???", Url::as_str).to_owned();
 
-        match fetch_json(client, meta_key, url).await {
+        match res {
             Ok((url, json)) => {
-                trace!("Using fetch from {:?} for metadata {}", url_str, meta_key);
+                trace!(
+                    "Using fetch from {:?} for metadata {}",
+                    url.as_str(),
+                    meta_key
+                );
                 resp = Ok(Some((json, fingerprint, url)));
                 break;
             },
             Err(e) => {
                 warn!(
-                    "Metadata fetch {:?} for {} failed: {:?}",
-                    url_str, meta_key, e
+                    "Metadata fetch {:?} ({:?}) for {} failed: {:?}",
+                    id.url.as_str(),
+                    hint,
+                    meta_key,
+                    e
                 );
 
-                resp = Err(());
+                // If any gateway asked us to back off, requeue after the
+                // longest requested delay
+                let delay = e
+                    .chain()
+                    .find_map(|e| e.downcast_ref::<RetryLater>())
+                    .map(|r| r.delay);
+                let prev = resp.err().flatten().map(|r: RetryLater| r.delay);
+                resp = Err(prev.max(delay).map(|delay| RetryLater { delay }));
             },
         }
     }
@@ -228,7 +239,16 @@ async fn try_locate_json(
 
             None
         },
-        Err(()) if TRY_LAST_RESORT => {
+        Err(Some(retry)) => {
+            return Err(retry).with_context(|| {
+                format!(
+                    "Metadata fetch {:?} for {} was rate-limited",
+                    id.url.as_str(),
+                    meta_key
+                )
+            });
+        },
+        Err(None) if TRY_LAST_RESORT => {
             let (url, json) = fetch_json(client, meta_key, Ok(id.url.clone()))
                 .await
                 .with_context(|| {
@@ -241,7 +261,7 @@ async fn try_locate_json(
 
             Some((json, vec![], url))
         },
-        Err(()) => {
+        Err(None) => {
             bail!(
                 "Cached metadata fetch {:?} for {} failed (not trying last-resort)",
                 id.url.as_str(),
@@ -290,7 +310,7 @@ async fn process_full(
         fingerprint: Owned(fingerprint),
         updated_at: Local::now().naive_utc(),
         description: description.map(Owned),
-        image: image.map(Owned),
+        image: image.clone().map(Owned),
         animation_url: animation_url.map(Owned),
         external_url: external_url.map(Owned),
         category: category.map(Owned),
@@ -303,29 +323,36 @@ async fn process_full(
 
     client
         .db()
-        .run(move |db| {
-            insert_into(metadata_jsons::table)
-                .values(&row)
-                .on_conflict(metadata_jsons::metadata_address)
-                .do_update()
-                .set(&row)
-                .execute(db)
-                .context("Failed to insert metadata")?;
-
-            // TODO: if the row updates the following functions do not clear the
-            //       previous rows from the old metadata JSON:
-
-            process_files(db, &addr, files, slot_info)?;
-            process_attributes(
-                db,
-                &addr,
-                first_verified_creator.as_deref(),
-                json.attributes,
-                slot_info,
-            )?;
-            process_collection(db, &addr, json.collection, slot_info)
+        .run({
+            let addr = addr.clone();
+            move |db| {
+                insert_into(metadata_jsons::table)
+                    .values(&row)
+                    .on_conflict(metadata_jsons::metadata_address)
+                    .do_update()
+                    .set(&row)
+                    .execute(db)
+                    .context("Failed to insert metadata")?;
+
+                // TODO: if the row updates the following functions do not clear the
+                //       previous rows from the old metadata JSON:
+
+                process_files(db, &addr, files, slot_info)?;
+                process_attributes(
+                    db,
+                    &addr,
+                    first_verified_creator.as_deref(),
+                    json.attributes,
+                    slot_info,
+                )?;
+                process_collection(db, &addr, json.collection, slot_info)
+            }
         })
-        .await
+        .await?;
+
+    hash_image(client, addr, image).await;
+
+    Ok(())
 }
 
 async fn process_minimal(
@@ -364,12 +391,13 @@ async fn process_minimal(
         extra: _,
     } = json;
 
+    let image = to_opt_string(&image);
     let row = DbMetadataJson {
         metadata_address: Owned(addr.clone()),
         fingerprint: Owned(fingerprint),
         updated_at: Local::now().naive_utc(),
         description: to_opt_string(&description),
-        image: to_opt_string(&image),
+        image: image.clone(),
         animation_url: to_opt_string(&animation_url),
         external_url: to_opt_string(&external_url),
         category: to_opt_string(&category),
@@ -393,9 +421,18 @@ async fn process_minimal(
         .await
         .context("Failed to insert minimal metadata")?;
 
+    hash_image(client, addr, image.map(Cow::into_owned)).await;
+
     Ok(())
 }
 
+/// Queue the image of an NFT, if it has one, to be perceptually hashed
+async fn hash_image(client: &Client, addr: String, image: Option<String>) {
+    if let Some(image) = image {
+        client.queue_image_hash(addr, image).await;
+    }
+}
+
 fn process_files(
     db: &Connection,
     addr: &str,
@@ -626,76 +663,38 @@ async fn dispatch_metadata_document(
         bail!("Metadata JSON content was not an object");
     };
 
-    let image = raw.get("image").and_then(Value::as_str);
-
-    let image = image
-        .and_then(|i| Url::parse(i).ok())
-        .and_then(|u| {
-            let id = AssetIdentifier::new(&u);
-
-            proxy_url(client.proxy_args(), &id, Some(("width", "200")))
-                .map(|o| o.map(|u| u.to_string()))
-                .transpose()
+    let removed = client
+        .db()
+        .run({
+            let addr = addr.clone();
+            move |db| search_documents::metadata_removed(db, &addr)
         })
-        .or_else(|| image.map(|s| Ok(s.into())))
-        .transpose()?;
+        .await?;
 
-    if let Ok((name, mint_address, collection_address, creator_address, creator_twitter_handle)) =
-        client
-            .db()
-            .run({
-                let addr = addr.clone();
-                move |db| {
-                    let (name, mint_address, collection_address): (String, String, Option<String>) =
-                        metadatas::table
-                            .left_join(metadata_collection_keys::table.on(
-                                metadatas::address.eq(metadata_collection_keys::metadata_address),
-                            ))
-                            .filter(metadatas::address.eq(&addr))
-                            .select((
-                                metadatas::name,
-                                metadatas::mint_address,
-                                metadata_collection_keys::collection_address.nullable(),
-                            ))
-                            .first(db)
-                            .context("failed to load mint and name for search doc")?;
-
-                    let (creator_address, creator_twitter_handle) = metadata_creators::table
-                        .left_join(
-                            twitter_handle_name_services::table
-                                .on(metadata_creators::creator_address
-                                    .eq(twitter_handle_name_services::wallet_address)),
-                        )
-                        .filter(metadata_creators::metadata_address.eq(&addr))
-                        .filter(metadata_creators::verified.eq(true))
-                        .filter(metadata_creators::position.eq(0))
-                        .select((
-                            metadata_creators::creator_address,
-                            twitter_handle_name_services::twitter_handle.nullable(),
-                        ))
-                        .first(db)
-                        .context("failed to load creators for search document")?;
-
-                    Result::<_>::Ok((
-                        name,
-                        mint_address,
-                        collection_address,
-                        creator_address,
-                        creator_twitter_handle,
-                    ))
-                }
-            })
+    if removed {
+        return client
+            .search()
+            .delete_metadata(addr)
             .await
-            .map_err(|e| warn!("Failed to get search document data for metadata: {:?}", e))
+            .context("Failed to dispatch metadata document delete job");
+    }
+
+    let image = search_documents::proxy_image(
+        client.proxy_args(),
+        raw.get("image").and_then(Value::as_str),
+    )?;
+    let attributes = search_documents::metadata_attributes(&Value::Object(raw));
+
+    if let Ok(document) = client
+        .db()
+        .run({
+            let addr = addr.clone();
+            move |db| search_documents::metadata_document(db, &addr, image, attributes)
+        })
+        .await
+        .map_err(|e| warn!("Failed to get search document data for metadata: {:?}", e))
     {
-        let document = MetadataDocument {
-            name,
-            mint_address,
-            image,
-            creator_address,
-            creator_twitter_handle,
-            collection_address: collection_address.clone(),
-        };
+        let collection_address = document.collection_address.clone();
 
         client
             .search()
@@ -718,56 +717,27 @@ async fn upsert_collection_metadata(
     mint_address: String,
     is_for_backfill: bool,
 ) -> Result<()> {
-    let (address, name, image) = client
+    let (address, document) = client
         .db()
         .run({
-            let mint_address = mint_address.clone();
-            move |db| {
-                let (address, name, image): (String, String, Option<String>) = metadatas::table
-                    .inner_join(
-                        metadata_jsons::table
-                            .on(metadatas::address.eq(metadata_jsons::metadata_address)),
-                    )
-                    .filter(metadatas::mint_address.eq(&mint_address))
-                    .select((metadatas::address, metadatas::name, metadata_jsons::image))
-                    .first(db)?;
-
-                Result::<_>::Ok((address, name, image))
-            }
+            let proxy_args = client.proxy_args().clone();
+            move |db| search_documents::collection_document(db, &proxy_args, &mint_address)
         })
-        .await
-        .context("failed to fetch collection metadata")?;
+        .await?;
 
-    let document = client
+    let existing = client
         .search()
         .get_document("collections".to_string(), address.clone())
         .await;
 
-    match document {
+    match existing {
         Err(Meilisearch(MeilisearchError {
             error_code: MeiliSearchErrorCode::DocumentNotFound,
             ..
         })) => {
-            let image = image
-                .clone()
-                .and_then(|i| Url::parse(&i).ok())
-                .and_then(|u| {
-                    let id = AssetIdentifier::new(&u);
-
-                    proxy_url(client.proxy_args(), &id, Some(("width", "200")))
-                        .map(|o| o.map(|u| u.to_string()))
-                        .transpose()
-                })
-                .or_else(|| image.map(Ok))
-                .transpose()?;
-
             client
                 .search()
-                .upsert_collection(is_for_backfill, address, CollectionDocument {
-                    name,
-                    image,
-                    mint_address,
-                })
+                .upsert_collection(is_for_backfill, address, document)
                 .await
                 .context("Failed to dispatch collection document job")?;
         },
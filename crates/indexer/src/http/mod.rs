@@ -1,6 +1,8 @@
 //! Support features for the HTTP indexer
 
 pub(self) mod client;
+mod gateway;
+mod image_hash;
 mod metadata_json;
 mod store_config;
 
@@ -9,6 +9,10 @@
 )]
 #![warn(clippy::pedantic, clippy::cargo, missing_docs)]
 
+#[cfg(feature = "http")]
+pub mod arweave;
+#[cfg(feature = "collection-stats")]
+pub mod collection_stats;
 pub mod db;
 #[cfg(feature = "geyser")]
 pub mod geyser;
@@ -22,6 +26,10 @@ pub(crate) mod reqwest;
 pub mod search;
 #[cfg(feature = "search-dispatch")]
 pub(crate) mod search_dispatch;
+#[cfg(any(feature = "search", feature = "search-dispatch"))]
+pub(crate) mod search_documents;
+#[cfg(feature = "slot-times")]
+pub mod slot_times;
 pub(crate) mod util;
 
 pub use runtime::*;
@@ -65,6 +73,22 @@ mod runtime {
         extra: T,
     }
 
+    /// Error indicating that a message could not be processed yet and should be
+    /// requeued after the given delay rather than discarded
+    #[derive(Debug, Clone, Copy)]
+    pub struct RetryLater {
+        /// The time to wait before processing the message again
+        pub delay: StdDuration,
+    }
+
+    impl std::fmt::Display for RetryLater {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "Retry requested after {:?}", self.delay)
+        }
+    }
+
+    impl std::error::Error for RetryLater {}
+
     /// Common parameters for all indexers
     #[allow(missing_copy_implementations)]
     #[derive(Debug)]
@@ -87,8 +111,10 @@ mod runtime {
                 extra,
             } = opts;
 
+            let (_, url) = db.clone().into_url(db::ConnectMode::Write)?;
             let db = Pool::new(
                 db::connect(db, db::ConnectMode::Write).context("Failed to connect to Postgres")?,
+                url,
             );
 
             let rt = {
@@ -187,6 +213,26 @@ mod runtime {
                     .await
                     .context("Failed to send ACK for delivery")?,
                 Err(e) => {
+                    if let Some(&RetryLater { delay }) =
+                        e.chain().find_map(|e| e.downcast_ref::<RetryLater>())
+                    {
+                        debug!("Requeueing message after {:?}: {:?}", delay, e);
+
+                        // Hold the delivery in a separate task so this worker
+                        // can move on to the next message
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+
+                            acker
+                                .reject(BasicRejectOptions { requeue: true })
+                                .await
+                                .map_err(|e| error!("Failed to requeue delivery: {:?}", e))
+                                .ok();
+                        });
+
+                        continue;
+                    }
+
                     warn!("Failed to process message: {:?}", e);
 
                     acker
@@ -1,31 +1,444 @@
 //! Support module for managing a reqwest HTTP client.
+//!
+//! Requests are subject to per-host politeness rules: each host has a token
+//! bucket limiting its request rate, a cap on in-flight requests, and an
+//! optional deny list.  Hosts responding with `429 Too Many Requests` or
+//! `503 Service Unavailable` are backed off for the duration given by their
+//! `Retry-After` header, and requests made to them in the meantime fail with
+//! [`RetryLater`] so the message being processed can be requeued.
+//!
+//! Redirects are followed by this module rather than by reqwest, so each hop
+//! is checked against the deny list and charged to its own host.
 
-use std::time::Duration;
+use std::{
+    str::FromStr,
+    sync::{Arc, PoisonError},
+    time::{Duration, Instant},
+};
 
 pub use ::reqwest::*;
 use indexer_core::{
-    error::Result as IResult,
-    prelude::{anyhow, error, warn, Context},
+    clap,
+    error::{Error as IError, Result as IResult},
+    hash::DashMap,
+    prelude::{anyhow, debug, error, trace, warn, Context, DateTime, Utc},
 };
-use tokio::sync::Mutex;
+use serde::de::DeserializeOwned;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+use crate::RetryLater;
+
+/// The backoff applied to a host that responds with 429 or 503 without a
+/// usable `Retry-After` header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The maximum number of redirects followed for one request
+const MAX_REDIRECTS: usize = 10;
+
+/// The time after which an unused host's politeness state is discarded
+const HOST_IDLE_TTL: Duration = Duration::from_secs(600);
+
+/// Common arguments for configuring HTTP request politeness
+#[derive(Debug, Clone, clap::Args)]
+pub struct Args {
+    /// Per-host request rate limits, in the form `host=rate[/burst]` where
+    /// `rate` is in requests per second.  A limit for a host also applies to
+    /// its subdomains.
+    #[clap(long, env, use_value_delimiter(true))]
+    http_host_limits: Vec<HostLimit>,
+
+    /// Request rate limit in requests per second for hosts not listed in
+    /// `--http-host-limits`.  Unlimited if not set.
+    #[clap(long, env)]
+    http_default_rate_limit: Option<f64>,
+
+    /// Maximum number of concurrent requests to a single host
+    #[clap(long, env, default_value_t = 16)]
+    http_max_per_host: usize,
+
+    /// Robots-style deny rules, in the form `host` or `host/path/prefix`.  A
+    /// rule for a host also applies to its subdomains.
+    #[clap(long, env, use_value_delimiter(true))]
+    http_deny: Vec<DenyRule>,
+
+    /// Upper bound, in seconds, on the backoff requested by a `Retry-After`
+    /// header
+    #[clap(long, env, default_value_t = 600)]
+    http_max_retry_after: u64,
+}
+
+fn host_matches(host: &str, pattern: &str) -> bool {
+    host.eq_ignore_ascii_case(pattern)
+        || (host.len() > pattern.len()
+            && host.as_bytes()[host.len() - pattern.len() - 1] == b'.'
+            && host[host.len() - pattern.len()..].eq_ignore_ascii_case(pattern))
+}
+
+/// A token-bucket rate limit for a single host
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    rate: f64,
+    burst: f64,
+}
+
+/// A rate limit for a host and its subdomains
+#[derive(Debug, Clone)]
+pub struct HostLimit {
+    host: String,
+    limit: Limit,
+}
+
+impl FromStr for HostLimit {
+    type Err = IError;
+
+    fn from_str(s: &str) -> IResult<Self> {
+        let (host, limit) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Host limit {:?} is not of the form host=rate", s))?;
+        let (rate, burst) = limit
+            .split_once('/')
+            .map_or((limit, None), |(r, b)| (r, Some(b)));
+
+        let rate: f64 = rate
+            .parse()
+            .with_context(|| format!("Invalid rate in host limit {:?}", s))?;
+        let burst = burst
+            .map(|b| {
+                b.parse()
+                    .with_context(|| format!("Invalid burst in host limit {:?}", s))
+            })
+            .transpose()?
+            .unwrap_or_else(|| rate.max(1.0));
+
+        if !(rate > 0.0 && burst >= 1.0) {
+            return Err(anyhow!(
+                "Host limit {:?} must have a positive rate and burst",
+                s
+            ));
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            limit: Limit { rate, burst },
+        })
+    }
+}
+
+/// A rule forbidding requests to a host, or to a path prefix on a host
+#[derive(Debug, Clone)]
+pub struct DenyRule {
+    host: String,
+    path: String,
+}
+
+impl DenyRule {
+    fn matches(&self, url: &Url) -> bool {
+        url.host_str()
+            .map_or(false, |h| host_matches(h, &self.host))
+            && url.path().starts_with(&self.path)
+    }
+}
+
+impl FromStr for DenyRule {
+    type Err = IError;
+
+    fn from_str(s: &str) -> IResult<Self> {
+        let (host, path) = s.find('/').map_or((s, "/"), |i| s.split_at(i));
+
+        if host.is_empty() {
+            return Err(anyhow!("Deny rule {:?} has no host", s));
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+    blocked_until: Option<Instant>,
+    used: Instant,
+}
+
+#[derive(Debug)]
+struct HostState {
+    limit: Option<Limit>,
+    bucket: std::sync::Mutex<Bucket>,
+    permits: Arc<Semaphore>,
+}
+
+impl HostState {
+    fn new(limit: Option<Limit>, max_concurrent: usize) -> Self {
+        Self {
+            limit,
+            bucket: std::sync::Mutex::new(Bucket {
+                tokens: limit.map_or(0.0, |l| l.burst),
+                last: Instant::now(),
+                blocked_until: None,
+                used: Instant::now(),
+            }),
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+        }
+    }
+
+    /// Reserve a token for one request, returning the time to wait before
+    /// sending it, or an error if the host is currently backed off
+    fn reserve(&self) -> std::result::Result<Duration, RetryLater> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        bucket.used = now;
+
+        if let Some(until) = bucket.blocked_until {
+            if until > now {
+                return Err(RetryLater { delay: until - now });
+            }
+
+            bucket.blocked_until = None;
+        }
+
+        let Limit { rate, burst } = match self.limit {
+            Some(l) => l,
+            None => return Ok(Duration::ZERO),
+        };
+
+        let elapsed = now.duration_since(bucket.last).as_secs_f64();
+        bucket.last = now;
+        // Tokens may go negative, in which case the request waits for the
+        // deficit to be refilled
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst) - 1.0;
+
+        Ok(if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        })
+    }
+
+    /// Whether this state can be discarded without losing a backoff or
+    /// releasing a host from its concurrency limit early
+    fn is_idle(&self, now: Instant) -> bool {
+        let bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+
+        now.saturating_duration_since(bucket.used) > HOST_IDLE_TTL
+            && bucket.blocked_until.map_or(true, |b| b <= now)
+    }
+
+    fn back_off(&self, delay: Duration) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let until = Instant::now() + delay;
+
+        if bucket.blocked_until.map_or(true, |b| b < until) {
+            bucket.blocked_until = Some(until);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Politeness {
+    limits: Vec<HostLimit>,
+    default_limit: Option<Limit>,
+    max_per_host: usize,
+    deny: Vec<DenyRule>,
+    max_retry_after: Duration,
+    hosts: DashMap<String, Arc<HostState>>,
+    last_eviction: std::sync::Mutex<Instant>,
+}
+
+impl Politeness {
+    fn new(args: Args) -> IResult<Self> {
+        let Args {
+            http_host_limits,
+            http_default_rate_limit,
+            http_max_per_host,
+            http_deny,
+            http_max_retry_after,
+        } = args;
+
+        let default_limit = http_default_rate_limit
+            .map(|r| {
+                if r > 0.0 {
+                    Ok(Limit {
+                        rate: r,
+                        burst: r.max(1.0),
+                    })
+                } else {
+                    Err(anyhow!("Default rate limit must be positive"))
+                }
+            })
+            .transpose()?;
+
+        if http_max_per_host == 0 {
+            return Err(anyhow!("Per-host concurrency limit must be nonzero"));
+        }
+
+        Ok(Self {
+            limits: http_host_limits,
+            default_limit,
+            max_per_host: http_max_per_host,
+            deny: http_deny,
+            max_retry_after: Duration::from_secs(http_max_retry_after),
+            hosts: DashMap::default(),
+            last_eviction: std::sync::Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Check a URL against the deny rules
+    fn check(&self, url: &Url) -> IResult<()> {
+        if self.deny.iter().any(|r| r.matches(url)) {
+            return Err(anyhow!("Request to {:?} is denied", url.as_str()));
+        }
+
+        Ok(())
+    }
+
+    /// Discard the state of hosts that have not been requested recently, at
+    /// most once per idle period
+    fn evict_idle(&self) {
+        let now = Instant::now();
+
+        {
+            let mut last = self
+                .last_eviction
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            if now.saturating_duration_since(*last) < HOST_IDLE_TTL {
+                return;
+            }
+
+            *last = now;
+        }
+
+        // States still referenced by an in-flight request are kept
+        self.hosts
+            .retain(|_, s| Arc::strong_count(s) > 1 || !s.is_idle(now));
+    }
+
+    fn host(&self, host: &str) -> Arc<HostState> {
+        self.evict_idle();
+
+        if let Some(state) = self.hosts.get(host) {
+            return Arc::clone(&state);
+        }
+
+        // Prefer the most specific matching rule
+        let limit = self
+            .limits
+            .iter()
+            .filter(|l| host_matches(host, &l.host))
+            .max_by_key(|l| l.host.len())
+            .map(|l| l.limit)
+            .or(self.default_limit);
+
+        Arc::clone(
+            &self
+                .hosts
+                .entry(host.to_owned())
+                .or_insert_with(|| Arc::new(HostState::new(limit, self.max_per_host))),
+        )
+    }
+
+    fn retry_after(&self, res: &reqwest::Response) -> Duration {
+        let delay = res
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| {
+                v.trim().parse().map(Duration::from_secs).ok().or_else(|| {
+                    DateTime::parse_from_rfc2822(v.trim())
+                        .ok()
+                        .and_then(|d| (d.with_timezone(&Utc) - Utc::now()).to_std().ok())
+                })
+            })
+            .unwrap_or(DEFAULT_RETRY_AFTER);
+
+        delay.min(self.max_retry_after)
+    }
+}
+
+/// An HTTP response holding its host's request permit until the body has
+/// been read
+#[derive(Debug)]
+pub struct Response {
+    inner: reqwest::Response,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Response {
+    /// Get the status code of the response
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    /// Get the headers of the response
+    #[must_use]
+    pub fn headers(&self) -> &header::HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Turn a response with an error status into an error
+    ///
+    /// # Errors
+    /// This function fails if the response status is a client or server
+    /// error.
+    pub fn error_for_status(self) -> Result<Self> {
+        let Self { inner, _permit } = self;
+
+        inner
+            .error_for_status()
+            .map(|inner| Self { inner, _permit })
+    }
+
+    /// Read the full response body
+    ///
+    /// # Errors
+    /// This function fails if the body cannot be read.
+    pub async fn bytes(self) -> Result<bytes::Bytes> {
+        self.inner.bytes().await
+    }
+
+    /// Read the full response body as text
+    ///
+    /// # Errors
+    /// This function fails if the body cannot be read or decoded.
+    pub async fn text(self) -> Result<String> {
+        self.inner.text().await
+    }
+
+    /// Read the full response body as JSON
+    ///
+    /// # Errors
+    /// This function fails if the body cannot be read or deserialized.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T> {
+        self.inner.json().await
+    }
+}
 
 #[derive(Debug)]
 pub struct Client {
     inner: Mutex<(u8, reqwest::Client)>,
     timeout: Duration,
+    politeness: Politeness,
 }
 
 impl Client {
-    pub fn new(timeout: Duration) -> IResult<Self> {
+    pub fn new(timeout: Duration, args: Args) -> IResult<Self> {
         Ok(Self {
             inner: Mutex::new((0, Self::build_client(timeout)?)),
             timeout,
+            politeness: Politeness::new(args)?,
         })
     }
 
     fn build_client(timeout: Duration) -> IResult<reqwest::Client> {
         ClientBuilder::new()
             .timeout(timeout)
+            .redirect(redirect::Policy::none())
             .pool_idle_timeout(
                 timeout
                     .checked_mul(2)
@@ -36,46 +449,140 @@ impl Client {
             .context("Failed to build HTTP client")
     }
 
-    /// Acquire an HTTP client and execute a request with i
+    async fn handle_error(&self, hint: u8, e: &Error) {
+        if e.is_connect()
+            || !(e.is_redirect() || e.is_status() || e.is_timeout() || e.is_body() || e.is_decode())
+        {
+            // Something may have happened, close the connection pool
+            let (ref mut hint2, ref mut http) = *self.inner.lock().await;
+
+            if *hint2 == hint {
+                warn!("Connection error detected, rotating HTTP client");
+
+                match Self::build_client(self.timeout) {
+                    Ok(client) => {
+                        *hint2 = hint2.wrapping_add(1);
+                        *http = client;
+                    },
+                    Err(e) => error!("Failed to rotate HTTP client: {:?}", e),
+                }
+            }
+        }
+    }
+
+    /// Build a request with an HTTP client and send it, subject to the
+    /// politeness rules for its host and the host of each redirect followed
     ///
     /// # Errors
-    /// This function does not generate errors, it simply passes any errors
-    /// raised by the given closure through to the function return.
-    #[inline]
-    pub async fn run<F: std::future::Future<Output = Result<T>>, T>(
+    /// This function fails if the request cannot be built or sent, if its URL
+    /// or the URL it redirects to is denied, if it redirects too many times,
+    /// or with [`RetryLater`] if a host is rate-limiting us.
+    pub async fn send(
         &self,
-        f: impl FnOnce(reqwest::Client) -> F,
-    ) -> IResult<T> {
+        f: impl FnOnce(&reqwest::Client) -> RequestBuilder,
+    ) -> IResult<Response> {
         let (hint, http) = self.inner.lock().await.clone();
+        let mut req = f(&http).build().context("Failed to build HTTP request")?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let url = req.url().clone();
+            let replay = req.try_clone();
+            let res = self.send_once(hint, &http, req).await?;
+            let status = res.status();
+
+            let location = match res.headers().get(header::LOCATION) {
+                Some(l) if status.is_redirection() => l,
+                _ => return Ok(res),
+            };
+
+            let target = location
+                .to_str()
+                .ok()
+                .and_then(|l| url.join(l).ok())
+                .ok_or_else(|| anyhow!("{:?} redirected to an invalid location", url.as_str()))?;
+
+            trace!(
+                "Following redirect from {:?} to {:?}",
+                url.as_str(),
+                target.as_str()
+            );
+
+            let preserves_method = status == StatusCode::TEMPORARY_REDIRECT
+                || status == StatusCode::PERMANENT_REDIRECT;
 
-        match f(http).await {
-            Ok(v) => Ok(v),
+            req = match replay {
+                Some(r) => r,
+                None if preserves_method => {
+                    return Err(anyhow!(
+                        "Cannot replay the body of {:?} to follow its redirect",
+                        url.as_str()
+                    ));
+                },
+                None => Request::new(Method::GET, target.clone()),
+            };
+
+            *req.url_mut() = target;
+
+            if !preserves_method {
+                *req.method_mut() = Method::GET;
+                *req.body_mut() = None;
+            }
+        }
+
+        Err(anyhow!("Too many redirects"))
+    }
+
+    /// Send a single request without following redirects, subject to the
+    /// politeness rules for its host
+    async fn send_once(&self, hint: u8, http: &reqwest::Client, req: Request) -> IResult<Response> {
+        let url = req.url().clone();
+
+        self.politeness.check(&url)?;
+
+        let host = self.politeness.host(url.host_str().unwrap_or_default());
+        let permit = Arc::clone(&host.permits)
+            .acquire_owned()
+            .await
+            .context("Failed to acquire host request permit")?;
+
+        let wait = host
+            .reserve()
+            .with_context(|| format!("Host for {:?} is backed off", url.as_str()))?;
+
+        if !wait.is_zero() {
+            trace!("Waiting {:?} to request {:?}", wait, url.as_str());
+            tokio::time::sleep(wait).await;
+        }
+
+        let res = match http.execute(req).await {
+            Ok(r) => r,
             Err(e) => {
-                if e.is_connect()
-                    || !(e.is_redirect()
-                        || e.is_status()
-                        || e.is_timeout()
-                        || e.is_body()
-                        || e.is_decode())
-                {
-                    // Something may have happened, close the connection pool
-                    let (ref mut hint2, ref mut http) = *self.inner.lock().await;
-
-                    if *hint2 == hint {
-                        warn!("Connection error detected, rotating HTTP client");
-
-                        match Self::build_client(self.timeout) {
-                            Ok(client) => {
-                                *hint2 = hint2.wrapping_add(1);
-                                *http = client;
-                            },
-                            Err(e) => error!("Failed to rotate HTTP client: {:?}", e),
-                        }
-                    }
-                }
+                self.handle_error(hint, &e).await;
 
-                Err(e).context("HTTP request failed")
+                return Err(e).context("HTTP request failed");
             },
+        };
+
+        let status = res.status();
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            let delay = self.politeness.retry_after(&res);
+
+            debug!(
+                "{:?} responded with {}, backing off for {:?}",
+                url.as_str(),
+                status,
+                delay
+            );
+            host.back_off(delay);
+
+            return Err(RetryLater { delay })
+                .with_context(|| format!("{:?} responded with {}", url.as_str(), status));
         }
+
+        Ok(Response {
+            inner: res,
+            _permit: permit,
+        })
     }
 }
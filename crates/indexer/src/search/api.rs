@@ -0,0 +1,229 @@
+//! Calls to Meilisearch endpoints not covered by the Meilisearch SDK
+
+use indexer_core::search::MeiliBackend;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::prelude::*;
+
+/// Timeout for a single request to Meilisearch
+const TIMEOUT: StdDuration = StdDuration::from_secs(30);
+
+/// Interval between polls while waiting for a Meilisearch task to finish
+const POLL_INTERVAL: StdDuration = StdDuration::from_millis(500);
+
+/// The first Meilisearch version using the pluralized task filter parameters
+const PLURAL_TASK_FILTERS_VERSION: (u64, u64, u64) = (0, 30, 0);
+
+/// Number of tasks requested per page while counting tasks
+const TASK_PAGE_SIZE: usize = 1000;
+
+/// The faceting settings of an index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Faceting {
+    /// The maximum number of values returned for each facet
+    pub max_values_per_facet: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskInfo {
+    task_uid: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Version {
+    pkg_version: String,
+}
+
+#[derive(Deserialize)]
+struct TaskPage {
+    results: Vec<serde::de::IgnoredAny>,
+    next: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TaskStatus {
+    status: String,
+    error: Option<Value>,
+}
+
+/// HTTP client for the Meilisearch API
+#[derive(Debug, Clone)]
+pub struct Api {
+    http: ::reqwest::Client,
+    url: String,
+    key: String,
+}
+
+impl Api {
+    /// Construct a client for the server used by a Meilisearch backend
+    ///
+    /// # Errors
+    /// This function fails if the HTTP client cannot be built.
+    pub fn new(meili: &MeiliBackend) -> Result<Self> {
+        let http = ::reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .context("Failed to build Meilisearch HTTP client")?;
+
+        Ok(Self {
+            http,
+            url: meili.url().trim_end_matches('/').to_owned(),
+            key: meili.key().to_owned(),
+        })
+    }
+
+    async fn send<T: DeserializeOwned>(&self, req: ::reqwest::RequestBuilder) -> Result<T> {
+        req.bearer_auth(&self.key)
+            .send()
+            .await
+            .context("Meilisearch request failed")?
+            .error_for_status()
+            .context("Meilisearch rejected request")?
+            .json()
+            .await
+            .context("Failed to parse Meilisearch response")
+    }
+
+    /// Wait for an enqueued task to finish
+    ///
+    /// # Errors
+    /// This function fails if the task fails or is canceled, or if its
+    /// status cannot be retrieved.
+    pub async fn wait_for_task(&self, uid: u64) -> Result<()> {
+        loop {
+            let TaskStatus { status, error } = self
+                .send(self.http.get(format!("{}/tasks/{}", self.url, uid)))
+                .await
+                .context("Failed to get task status")?;
+
+            match status.as_str() {
+                "succeeded" => break Ok(()),
+                "failed" | "canceled" => {
+                    return Err(anyhow!("Task {} {}: {:?}", uid, status, error));
+                },
+                _ => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    /// Get the faceting settings of an index
+    ///
+    /// # Errors
+    /// This function fails if the request fails.
+    pub async fn faceting(&self, index: &str) -> Result<Faceting> {
+        self.send(
+            self.http
+                .get(format!("{}/indexes/{}/settings/faceting", self.url, index)),
+        )
+        .await
+        .context("Failed to get faceting settings")
+    }
+
+    /// Update the faceting settings of an index and wait for the update to
+    /// be applied
+    ///
+    /// # Errors
+    /// This function fails if the request or the resulting task fails.
+    pub async fn set_faceting(&self, index: &str, faceting: Faceting) -> Result<()> {
+        let TaskInfo { task_uid } = self
+            .send(
+                self.http
+                    .patch(format!("{}/indexes/{}/settings/faceting", self.url, index))
+                    .json(&faceting),
+            )
+            .await
+            .context("Failed to update faceting settings")?;
+
+        self.wait_for_task(task_uid).await
+    }
+
+    /// Get the version of the Meilisearch server as `(major, minor, patch)`
+    ///
+    /// # Errors
+    /// This function fails if the request fails or the version cannot be
+    /// parsed.
+    pub async fn version(&self) -> Result<(u64, u64, u64)> {
+        let Version { pkg_version } = self
+            .send(self.http.get(format!("{}/version", self.url)))
+            .await
+            .context("Failed to get Meilisearch version")?;
+
+        // Ignore any pre-release or build suffix
+        let mut parts = pkg_version
+            .split(|c: char| !c.is_ascii_digit())
+            .take(3)
+            .map(str::parse);
+
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) => Ok((major, minor, patch)),
+            _ => Err(anyhow!("Invalid Meilisearch version {:?}", pkg_version)),
+        }
+    }
+
+    /// Swap the documents, settings and task history of two indexes, and
+    /// wait for the swap to finish
+    ///
+    /// # Errors
+    /// This function fails if the request or the resulting task fails.
+    pub async fn swap_indexes(&self, a: &str, b: &str) -> Result<()> {
+        let TaskInfo { task_uid } = self
+            .send(
+                self.http
+                    .post(format!("{}/swap-indexes", self.url))
+                    .json(&serde_json::json!([{ "indexes": [a, b] }])),
+            )
+            .await
+            .context("Failed to swap indexes")?;
+
+        self.wait_for_task(task_uid).await
+    }
+
+    /// Count the enqueued and processing tasks, either for a single index
+    /// or across all indexes.  Every page of matching tasks is read, since
+    /// task lists are paginated and older Meilisearch versions do not report
+    /// a total.
+    ///
+    /// # Errors
+    /// This function fails if the version or task list cannot be retrieved.
+    pub async fn pending_tasks(&self, index: Option<&str>) -> Result<usize> {
+        let (status_param, index_param) = if self.version().await? >= PLURAL_TASK_FILTERS_VERSION {
+            ("statuses", "indexUids")
+        } else {
+            ("status", "indexUid")
+        };
+
+        let mut count = 0;
+        let mut from = None;
+
+        loop {
+            let mut req = self.http.get(format!("{}/tasks", self.url)).query(&[
+                (status_param, "enqueued,processing".to_owned()),
+                ("limit", TASK_PAGE_SIZE.to_string()),
+            ]);
+
+            if let Some(index) = index {
+                req = req.query(&[(index_param, index)]);
+            }
+
+            if let Some(from) = from {
+                req = req.query(&[("from", from)]);
+            }
+
+            let TaskPage { results, next } = self
+                .send(req)
+                .await
+                .context("Failed to list pending tasks")?;
+
+            count += results.len();
+
+            match next {
+                Some(next) => from = Some(next),
+                None => break Ok(count),
+            }
+        }
+    }
+}
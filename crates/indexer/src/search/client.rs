@@ -1,23 +1,37 @@
-use std::{collections::BinaryHeap, sync::Arc, time::Duration};
+use std::{
+    collections::BinaryHeap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
 
 use crossbeam::queue::SegQueue;
 use indexer_core::{
     clap,
-    hash::HashMap,
+    hash::{DashMap, HashMap},
     meilisearch::{
-        self,
         client::Client as MeiliClient,
         tasks::{DocumentAddition, ProcessedTask, Task, TaskType},
     },
+    search::{self, MeiliBackend, SearchBackend},
     util,
 };
+use serde::Serialize;
 use tokio::{
-    sync::{mpsc, oneshot, RwLock},
+    net::TcpListener,
+    sync::{mpsc, oneshot, watch, RwLock},
     task,
 };
 
+use super::api::Api;
 use crate::{db::Pool, prelude::*};
 
+/// Upsert interval used for backends that don't report task durations
+const DEFAULT_UPSERT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time to wait before polling Meilisearch again while holding off upserts
+const BACKPRESSURE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Common arguments for internal search indexer usage
 #[derive(Debug, clap::Parser)]
 pub struct Args {
@@ -30,28 +44,100 @@ pub struct Args {
     #[clap(long, env, default_value_t = 30)]
     upsert_interval_sample_size: usize,
 
+    /// Maximum number of queued document changes.  Callers queueing changes
+    /// past this bound wait for the queue to drain, which in turn delays the
+    /// acknowledgement of the messages they are processing.
+    #[clap(long, env, default_value_t = 50_000)]
+    upsert_queue_limit: usize,
+
+    /// Maximum number of enqueued or processing Meilisearch tasks before
+    /// upserts are held back to let Meilisearch catch up
+    #[clap(long, env, default_value_t = 20)]
+    upsert_max_pending_tasks: usize,
+
+    /// Address to serve upsert queue statistics on, as JSON over HTTP
+    #[clap(long, env)]
+    search_status_addr: Option<SocketAddr>,
+
     /// Don't perform any upserts, just print what would be upserted
     #[clap(long, short = 'n', env)]
     dry_run: bool,
 
     #[clap(flatten)]
-    meili: meilisearch::Args,
+    search: search::Args,
+}
+
+/// A pending change to a single document
+#[derive(Debug)]
+enum Pending {
+    Upsert(super::Document),
+    Delete(String),
+}
+
+impl Pending {
+    fn id(&self) -> &str {
+        match self {
+            Self::Upsert(d) => &d.id,
+            Self::Delete(i) => i,
+        }
+    }
+}
+
+/// The state of a Meilisearch task
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    /// The ID of the task
+    pub uid: u64,
+    /// The status of the task, e.g. `succeeded` or `failed`
+    pub status: &'static str,
+    /// The reason the task failed, if it did
+    pub error: Option<String>,
+}
+
+/// A snapshot of the state of the upsert worker
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    /// The number of queued document changes for each index
+    pub queue_depth: HashMap<String, usize>,
+    /// The number of Meilisearch tasks enqueued or processing
+    pub pending_tasks: usize,
+    /// The most recent Meilisearch task for each index
+    pub last_tasks: HashMap<String, TaskStatus>,
+    /// The observed indexing throughput, in documents per second
+    pub throughput: Option<f64>,
+}
+
+/// Worker statistics not derived from the upsert queue
+#[derive(Debug, Default)]
+struct WorkerStats {
+    pending_tasks: usize,
+    last_tasks: HashMap<String, TaskStatus>,
+    throughput: Option<f64>,
+    /// The most recent failed task that has already been reported
+    last_failure: Option<u64>,
 }
 
 /// Wrapper for handling network logic
 #[derive(Debug)]
 pub struct Client {
     db: Pool,
+    backend: Arc<dyn SearchBackend>,
+    meili_api: Option<Api>,
     upsert_batch: usize,
-    upsert_queue: RwLock<SegQueue<(String, super::Document)>>,
+    upsert_queue_limit: usize,
+    upsert_queue: RwLock<SegQueue<(String, Pending)>>,
+    queue_depth: DashMap<String, usize>,
+    stats: Mutex<WorkerStats>,
     trigger_upsert: mpsc::Sender<()>,
+    flushed: watch::Sender<()>,
+    flushed_rx: watch::Receiver<()>,
 }
 
 impl Client {
     /// Construct a new client, wrapped in an `Arc`.
     ///
     /// # Errors
-    /// This function fails if the Meilisearch database cannot be initialized.
+    /// This function fails if the search backend cannot be initialized.
     pub async fn new_rc(
         db: Pool,
         args: Args,
@@ -59,38 +145,65 @@ impl Client {
         let Args {
             upsert_batch,
             upsert_interval_sample_size,
+            upsert_queue_limit,
+            upsert_max_pending_tasks,
+            search_status_addr,
             dry_run,
-            meili,
+            search,
         } = args;
 
-        let meili = meili.into_client();
+        if upsert_queue_limit < upsert_batch {
+            return Err(anyhow!(
+                "Upsert queue limit must be at least the upsert batch size"
+            ));
+        }
 
-        create_index(meili.clone(), "metadatas", "id")
-            .await
-            .context("failed to create metadatas index")?;
+        let backend = search
+            .connect(db.inner().clone())
+            .context("Failed to connect to search backend")?;
 
-        create_index(meili.clone(), "name_service", "id")
-            .await
-            .context("failed to create name service index")?;
+        let meili_api = backend.as_meili().map(Api::new).transpose()?;
 
-        create_index(meili.clone(), "collections", "id")
-            .await
-            .context("failed to create collections index")?;
+        if let Some(meili) = backend.as_meili() {
+            for index in super::settings::INDEXES {
+                index
+                    .apply(meili)
+                    .await
+                    .with_context(|| format!("Failed to set up {} index", index.name))?;
+            }
+        }
 
         let (trigger_upsert, upsert_rx) = mpsc::channel(1);
         let (stop_tx, stop_rx) = oneshot::channel();
+        let (flushed, flushed_rx) = watch::channel(());
 
         let arc_self = Arc::new(Self {
             db,
+            backend,
+            meili_api,
             upsert_batch,
+            upsert_queue_limit,
             upsert_queue: RwLock::new(SegQueue::new()),
+            queue_depth: DashMap::default(),
+            stats: Mutex::default(),
             trigger_upsert,
+            flushed,
+            flushed_rx,
         });
 
+        if let Some(addr) = search_status_addr {
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind search status address {}", addr))?;
+
+            info!("Serving search upsert statistics on {}", addr);
+            task::spawn(super::status::serve(listener, Arc::downgrade(&arc_self)));
+        }
+
         let upsert_task = task::spawn(arc_self.clone().run_upserts(
-            meili.clone(),
             upsert_interval_sample_size,
             upsert_batch,
+            upsert_max_pending_tasks,
             dry_run,
             upsert_rx,
             stop_rx,
@@ -101,9 +214,9 @@ impl Client {
 
     async fn run_upserts(
         self: Arc<Self>,
-        meili: MeiliClient,
         interval_sample_size: usize,
         batch_size: usize,
+        max_pending_tasks: usize,
         dry_run: bool,
         mut rx: mpsc::Receiver<()>,
         mut stop_rx: oneshot::Receiver<()>,
@@ -111,9 +224,9 @@ impl Client {
         loop {
             match self
                 .try_run_upserts(
-                    meili.clone(),
                     interval_sample_size,
                     batch_size,
+                    max_pending_tasks,
                     dry_run,
                     &mut rx,
                     &mut stop_rx,
@@ -122,7 +235,7 @@ impl Client {
             {
                 Ok(()) => break,
                 Err(e) => {
-                    error!("Meilisearch upsert task crashed: {:?}", e);
+                    error!("Search upsert task crashed: {:?}", e);
                 },
             }
 
@@ -130,8 +243,76 @@ impl Client {
         }
     }
 
+    fn lock_stats(&self) -> MutexGuard<WorkerStats> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record the state of the Meilisearch task queue, reporting any task
+    /// failures not seen before.  `tasks` holds only the most recent tasks, so
+    /// the number of pending tasks is counted separately.
+    fn update_task_stats(&self, tasks: &[Task], pending_tasks: usize) {
+        let mut last_tasks = HashMap::<String, TaskStatus>::default();
+
+        for task in tasks {
+            let (index, status) = match task {
+                Task::Enqueued { content } => (&content.index_uid, TaskStatus {
+                    uid: content.uid,
+                    status: "enqueued",
+                    error: None,
+                }),
+                Task::Processing { content } => (&content.index_uid, TaskStatus {
+                    uid: content.uid,
+                    status: "processing",
+                    error: None,
+                }),
+                Task::Failed { content } => (&content.task.index_uid, TaskStatus {
+                    uid: content.task.uid,
+                    status: "failed",
+                    error: Some(content.error.error_message.clone()),
+                }),
+                Task::Succeeded { content } => (&content.index_uid, TaskStatus {
+                    uid: content.uid,
+                    status: "succeeded",
+                    error: None,
+                }),
+            };
+
+            if last_tasks.get(index).map_or(true, |t| t.uid < status.uid) {
+                last_tasks.insert(index.clone(), status);
+            }
+        }
+
+        let mut stats = self.lock_stats();
+
+        let mut failures: Vec<_> = tasks
+            .iter()
+            .filter_map(|t| match t {
+                Task::Failed { content } => Some(content),
+                _ => None,
+            })
+            .filter(|t| stats.last_failure.map_or(true, |f| t.task.uid > f))
+            .collect();
+        failures.sort_unstable_by_key(|t| t.task.uid);
+
+        for failure in &failures {
+            error!(
+                "Meilisearch task {} for index {:?} failed: {}",
+                failure.task.uid, failure.task.index_uid, failure.error.error_message
+            );
+        }
+
+        if let Some(last) = failures.last() {
+            stats.last_failure = Some(last.task.uid);
+        }
+
+        stats.pending_tasks = pending_tasks;
+        stats.last_tasks = last_tasks;
+    }
+
     async fn update_upsert_interval(
+        &self,
         meili: &MeiliClient,
+        api: &Api,
         sample_size: usize,
         batch_size: usize,
     ) -> Result<Duration> {
@@ -142,6 +323,13 @@ impl Client {
             .await
             .context("Failed to get Meilisearch task list")?;
 
+        let pending_tasks = api
+            .pending_tasks(None)
+            .await
+            .context("Failed to count pending Meilisearch tasks")?;
+
+        self.update_task_stats(&tasks, pending_tasks);
+
         let mut set: BinaryHeap<_> = tasks
             .into_iter()
             .filter_map(|task| {
@@ -180,9 +368,24 @@ impl Client {
             })
             .collect();
 
-        let mut times: Vec<_> = std::iter::from_fn(|| set.pop())
+        let sample: Vec<_> = std::iter::from_fn(|| set.pop())
             .take(sample_size)
-            .map(|(_, c, d)| {
+            .map(|(_, c, d)| (c, d))
+            .collect();
+
+        let docs: usize = sample.iter().map(|(c, _)| c).sum();
+        let secs: f64 = sample.iter().map(|(_, d)| d.as_secs_f64()).sum();
+
+        if secs > 0.0 {
+            #[allow(clippy::cast_precision_loss)]
+            let throughput = docs as f64 / secs;
+
+            self.lock_stats().throughput = Some(throughput);
+        }
+
+        let mut times: Vec<_> = sample
+            .into_iter()
+            .map(|(c, d)| {
                 #[allow(clippy::cast_precision_loss)]
                 d.mul_f64(batch_size as f64 / c as f64)
             })
@@ -224,11 +427,94 @@ impl Client {
         Ok(interval)
     }
 
+    /// Send a batch of queued document changes to the search backend
+    async fn flush(&self, queue: SegQueue<(String, Pending)>, dry_run: bool) -> Result<()> {
+        use futures_util::StreamExt;
+
+        debug!("Ticking document upsert for {} document(s)...", queue.len());
+
+        // Only the most recent change to each document is kept, so the order
+        // of deletes and upserts within a batch doesn't matter
+        let map = std::iter::from_fn(|| queue.pop()).fold(
+            HashMap::<_, HashMap<_, _>>::default(),
+            |mut h, (k, v)| {
+                h.entry(k).or_default().insert(v.id().to_owned(), v);
+                h
+            },
+        );
+
+        let start = Instant::now();
+        let mut total = 0;
+        let mut futures = futures_util::stream::FuturesUnordered::new();
+
+        for (idx, pending) in map {
+            let (docs, deletes) = pending.into_values().fold(
+                (Vec::new(), Vec::new()),
+                |(mut docs, mut deletes), p| {
+                    match p {
+                        Pending::Upsert(d) => docs.push(d),
+                        Pending::Delete(i) => deletes.push(i),
+                    }
+
+                    (docs, deletes)
+                },
+            );
+
+            debug!(
+                "{} document(s) in upsert queue and {} in delete queue flagged for {:?}",
+                docs.len(),
+                deletes.len(),
+                idx
+            );
+
+            total += docs.len() + deletes.len();
+
+            if dry_run {
+                info!("Upsert to {:?} of {:#?}", idx, serde_json::to_value(&docs));
+                info!("Delete from {:?} of {:?}", idx, deletes);
+            } else {
+                let backend = &self.backend;
+                futures.push(async move {
+                    if !deletes.is_empty() {
+                        backend.delete(&idx, deletes).await?;
+                    }
+
+                    if !docs.is_empty() {
+                        backend.upsert(&idx, docs).await?;
+                    }
+
+                    Result::<_>::Ok(())
+                });
+            }
+        }
+
+        while let Some(res) = futures.next().await {
+            res.context("Search backend call failed")?;
+        }
+
+        // Meilisearch only enqueues the changes here, so its throughput is
+        // measured from its completed tasks instead
+        if self.backend.as_meili().is_none() && !dry_run {
+            let secs = start.elapsed().as_secs_f64();
+
+            if secs > 0.0 {
+                #[allow(clippy::cast_precision_loss)]
+                let throughput = total as f64 / secs;
+
+                self.lock_stats().throughput = Some(throughput);
+            }
+        }
+
+        debug!("Flushed {} document change(s): {:?}", total, self.stats());
+
+        Ok(())
+    }
+
     async fn try_run_upserts(
         &self,
-        meili: MeiliClient,
         interval_sample_size: usize,
         batch_size: usize,
+        max_pending_tasks: usize,
         dry_run: bool,
         rx: &mut mpsc::Receiver<()>,
         mut stop_rx: &mut oneshot::Receiver<()>,
@@ -242,10 +528,19 @@ impl Client {
         let mut lock_if_stopping = None;
 
         let stop_reason = loop {
-            use futures_util::StreamExt;
-
-            let interval =
-                Self::update_upsert_interval(&meili, interval_sample_size, batch_size).await?;
+            // Only Meilisearch reports task durations to estimate from
+            let interval = match (self.backend.as_meili(), &self.meili_api) {
+                (Some(meili), Some(api)) => {
+                    self.update_upsert_interval(
+                        meili.client(),
+                        api,
+                        interval_sample_size,
+                        batch_size,
+                    )
+                    .await?
+                },
+                _ => DEFAULT_UPSERT_INTERVAL,
+            };
 
             let evt = tokio::select! {
                 o = rx.recv() => Event::Rx(o),
@@ -264,6 +559,20 @@ impl Client {
                 },
             };
 
+            let pending_tasks = self.lock_stats().pending_tasks;
+
+            // Let the queue fill while Meilisearch catches up, which
+            // eventually makes callers wait for the queue to drain
+            if stop_reason.is_none() && pending_tasks >= max_pending_tasks {
+                warn!(
+                    "Holding back upserts with {} Meilisearch task(s) pending",
+                    pending_tasks
+                );
+
+                tokio::time::sleep(BACKPRESSURE_POLL_INTERVAL).await;
+                continue;
+            }
+
             debug_assert!(lock_if_stopping.is_none());
             let mut lock = self.upsert_queue.write().await;
 
@@ -272,6 +581,7 @@ impl Client {
             }
 
             let queue = std::mem::take(&mut *lock);
+            self.queue_depth.clear();
 
             if stop_reason.is_none() {
                 std::mem::drop(lock);
@@ -279,35 +589,10 @@ impl Client {
                 lock_if_stopping = Some(lock);
             }
 
-            debug!("Ticking document upsert for {} document(s)...", queue.len());
-
-            let map =
-                std::iter::from_fn(|| queue.pop()).fold(HashMap::default(), |mut h, (k, v)| {
-                    h.entry(k).or_insert_with(Vec::new).push(v);
-                    h
-                });
-
-            let mut futures = futures_util::stream::FuturesUnordered::new();
+            // Wake any callers waiting for room in the queue
+            self.flushed.send(()).ok();
 
-            for (idx, docs) in &map {
-                debug!(
-                    "{} document(s) in upsert queue flagged for {:?}",
-                    docs.len(),
-                    idx
-                );
-
-                if dry_run {
-                    info!("Upsert to {:?} of {:#?}", idx, serde_json::to_value(&docs));
-                } else {
-                    let meili = meili.clone();
-                    futures
-                        .push(async move { meili.index(idx).add_or_replace(&*docs, None).await });
-                }
-            }
-
-            while let Some(res) = futures.next().await {
-                res.context("Meilisearch API call failed")?;
-            }
+            self.flush(queue, dry_run).await?;
 
             if let Some(reason) = stop_reason {
                 break reason;
@@ -330,6 +615,35 @@ impl Client {
         &self.db
     }
 
+    /// Get a reference to the search backend
+    #[must_use]
+    pub fn backend(&self) -> &dyn SearchBackend {
+        &*self.backend
+    }
+
+    /// Get a reference to the Meilisearch backend, if one is in use
+    #[must_use]
+    pub fn meili(&self) -> Option<&MeiliBackend> {
+        self.backend.as_meili()
+    }
+
+    /// Get a snapshot of the upsert queue depth and worker statistics
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        let stats = self.lock_stats();
+
+        Stats {
+            queue_depth: self
+                .queue_depth
+                .iter()
+                .map(|e| (e.key().clone(), *e.value()))
+                .collect(),
+            pending_tasks: stats.pending_tasks,
+            last_tasks: stats.last_tasks.clone(),
+            throughput: stats.throughput,
+        }
+    }
+
     /// Upsert a document to the `foo` index
     ///
     /// # Errors
@@ -339,37 +653,74 @@ impl Client {
         idx: String,
         docs: D,
     ) -> Result<()> {
-        let q = self.upsert_queue.read().await;
-        std::iter::repeat(idx).zip(docs).for_each(|p| q.push(p));
+        self.enqueue(idx, docs.into_iter().map(Pending::Upsert))
+            .await
+    }
 
-        if q.len() >= self.upsert_batch {
-            use mpsc::error::TrySendError;
+    /// Delete documents by ID from the `foo` index
+    ///
+    /// # Errors
+    /// This function fails if the HTTP call returns an error
+    pub async fn delete_documents<I: IntoIterator<Item = String>>(
+        &self,
+        idx: String,
+        ids: I,
+    ) -> Result<()> {
+        self.enqueue(idx, ids.into_iter().map(Pending::Delete))
+            .await
+    }
 
-            match self.trigger_upsert.try_send(()) {
-                // TrySendError::Full means an upsert has already been triggered
-                Ok(()) | Err(TrySendError::Full(())) => (),
-                Err(e) => return Err(e).context("Failed to trigger upsert")?,
-            }
-        }
+    fn request_upsert(&self) -> Result<()> {
+        use mpsc::error::TrySendError;
 
-        Ok(())
+        match self.trigger_upsert.try_send(()) {
+            // TrySendError::Full means an upsert has already been triggered
+            Ok(()) | Err(TrySendError::Full(())) => Ok(()),
+            Err(e) => Err(e).context("Failed to trigger upsert"),
+        }
     }
-}
 
-async fn create_index(meili: MeiliClient, index_name: &str, primary_key: &str) -> Result<()> {
-    if let Ok(mut idx) = meili.get_index(index_name).await {
-        ensure!(
-            idx.get_primary_key()
+    /// Wait until the upsert queue is below its size limit
+    async fn wait_for_capacity(&self) -> Result<()> {
+        let mut flushed = self.flushed_rx.clone();
+        let mut waited = false;
+
+        loop {
+            let len = self.upsert_queue.read().await.len();
+
+            if len < self.upsert_queue_limit {
+                break Ok(());
+            }
+
+            if !waited {
+                debug!("Upsert queue is full with {} change(s), waiting", len);
+                waited = true;
+            }
+
+            self.request_upsert()?;
+            flushed
+                .changed()
                 .await
-                .context("Failed to check primary key name")?
-                .map_or(false, |k| k == primary_key),
-            "Primary key mismatch for index {}",
-            index_name
-        );
-    } else {
-        let task = meili.create_index(index_name, Some(primary_key)).await?;
-        meili.wait_for_task(task, None, None).await?;
-    };
+                .context("Upsert queue flush signal closed")?;
+        }
+    }
+
+    async fn enqueue(&self, idx: String, ops: impl Iterator<Item = Pending>) -> Result<()> {
+        self.wait_for_capacity().await?;
 
-    Ok(())
+        let q = self.upsert_queue.read().await;
+        let mut count = 0;
+
+        std::iter::repeat(idx.clone()).zip(ops).for_each(|p| {
+            q.push(p);
+            count += 1;
+        });
+        *self.queue_depth.entry(idx).or_default() += count;
+
+        if q.len() >= self.upsert_batch {
+            self.request_upsert()?;
+        }
+
+        Ok(())
+    }
 }
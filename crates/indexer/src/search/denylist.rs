@@ -0,0 +1,172 @@
+//! Removal of NFTs from search as their listings are denylisted
+
+use std::sync::Arc;
+
+use indexer_core::{
+    assets::AssetProxyArgs,
+    db::tables::{listing_denylist, listing_metadatas, metadata_jsons},
+    hash::HashSet,
+};
+
+use super::{settings, Client, Document};
+use crate::{prelude::*, search_documents};
+
+/// Channel notified by the `listing_denylist_notify` database trigger with
+/// the address of each listing whose denylist entry changed
+const CHANNEL: &str = "listing_denylist";
+
+/// Follow changes to the listing denylist, deleting the documents of NFTs as
+/// their listings are hard-banned and restoring them when the ban is lifted.
+///
+/// Each time the worker connects it checks every hard-banned NFT, covering
+/// bans made or lifted while it was not listening.  After that only the NFTs
+/// of listings named in change notifications are checked.
+pub async fn run(client: Arc<Client>, proxy_args: AssetProxyArgs) {
+    let mut banned = HashSet::default();
+
+    loop {
+        if let Err(e) = follow(&client, &proxy_args, &mut banned).await {
+            error!("Failed to apply listing denylist to search: {:?}", e);
+        }
+
+        tokio::time::sleep(StdDuration::from_secs(5)).await;
+    }
+}
+
+async fn follow(
+    client: &Client,
+    proxy_args: &AssetProxyArgs,
+    banned: &mut HashSet<String>,
+) -> Result<()> {
+    // Listen before the full check so no change slips in between
+    let mut listener = client.db().listen(&[CHANNEL]).await?;
+
+    apply(client, proxy_args, banned, None).await?;
+
+    info!("Following listing denylist changes");
+
+    loop {
+        let mut listings = vec![listener.recv().await?.payload];
+
+        while let Some(n) = listener.try_recv()? {
+            listings.push(n.payload);
+        }
+
+        listings.sort_unstable();
+        listings.dedup();
+
+        apply(client, proxy_args, banned, Some(listings)).await?;
+    }
+}
+
+/// Check the NFTs of the given listings, or of every listing if `listings`
+/// is `None`, against the denylist and update `banned` and the search index
+/// with any that were banned or had their ban lifted
+async fn apply(
+    client: &Client,
+    proxy_args: &AssetProxyArgs,
+    banned: &mut HashSet<String>,
+    listings: Option<Vec<String>>,
+) -> Result<()> {
+    let (scope, now_banned) = client
+        .db()
+        .run(move |db| {
+            let scope = listings
+                .map(|l| {
+                    listing_metadatas::table
+                        .filter(listing_metadatas::listing_address.eq_any(l))
+                        .select(listing_metadatas::metadata_address)
+                        .load::<String>(db)
+                })
+                .transpose()
+                .context("Failed to load NFTs of changed listings")?;
+
+            let mut query =
+                listing_metadatas::table
+                    .inner_join(listing_denylist::table.on(
+                        listing_metadatas::listing_address.eq(listing_denylist::listing_address),
+                    ))
+                    .filter(listing_denylist::hard_ban)
+                    .select(listing_metadatas::metadata_address)
+                    .distinct()
+                    .into_boxed();
+
+            if let Some(ref scope) = scope {
+                query = query.filter(listing_metadatas::metadata_address.eq_any(scope));
+            }
+
+            let now_banned = query
+                .load::<String>(db)
+                .context("Failed to load denylisted NFTs")?;
+
+            Result::<_>::Ok((
+                scope.map(|s| s.into_iter().collect::<HashSet<_>>()),
+                now_banned.into_iter().collect::<HashSet<_>>(),
+            ))
+        })
+        .await?;
+
+    let added: Vec<_> = now_banned.difference(banned).cloned().collect();
+    let lifted: Vec<_> = banned
+        .iter()
+        .filter(|a| scope.as_ref().map_or(true, |s| s.contains(*a)) && !now_banned.contains(*a))
+        .cloned()
+        .collect();
+
+    if !added.is_empty() {
+        info!("Removing {} denylisted NFT(s) from search", added.len());
+
+        client
+            .delete_documents(settings::METADATAS.name.to_owned(), added.clone())
+            .await?;
+
+        banned.extend(added);
+    }
+
+    if !lifted.is_empty() {
+        info!(
+            "Restoring {} NFT(s) removed from the denylist",
+            lifted.len()
+        );
+
+        let docs = client
+            .db()
+            .run({
+                let proxy_args = proxy_args.clone();
+                let lifted = lifted.clone();
+
+                move |db| {
+                    let jsons = metadata_jsons::table
+                        .filter(metadata_jsons::metadata_address.eq_any(&lifted))
+                        .select((
+                            metadata_jsons::metadata_address,
+                            metadata_jsons::raw_content,
+                            metadata_jsons::image,
+                        ))
+                        .load(db)
+                        .context("Failed to load metadata JSONs")?;
+
+                    search_documents::metadata_documents(db, &proxy_args, jsons)
+                }
+            })
+            .await?
+            .into_iter()
+            .map(|(id, doc)| {
+                Ok(Document {
+                    id,
+                    body: serde_json::to_value(doc).context("Failed to upcast document body")?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        client
+            .upsert_documents(settings::METADATAS.name.to_owned(), docs)
+            .await?;
+
+        for addr in &lifted {
+            banned.remove(addr);
+        }
+    }
+
+    Ok(())
+}
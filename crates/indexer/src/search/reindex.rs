@@ -0,0 +1,421 @@
+//! Full rebuild of a search index from Postgres
+
+use std::collections::BTreeSet;
+
+use indexer_core::{
+    assets::AssetProxyArgs,
+    clap,
+    db::{
+        tables::{
+            cardinal_entries, graph_connections, metadata_collection_keys, metadata_creators,
+            metadata_jsons, metadatas, twitter_handle_name_services,
+        },
+        Connection,
+    },
+    search::MeiliBackend,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    api::Api,
+    settings::{self, IndexConfig},
+    Client, ClientArgs, Document,
+};
+use crate::{db::Pool, prelude::*, search_documents, search_documents::TwitterHandleDocument};
+
+/// Suffix of the staging index built when swapping in a fresh index
+const STAGING_SUFFIX: &str = "_reindex";
+
+/// The first Meilisearch version supporting the swap-indexes API
+const SWAP_INDEXES_VERSION: (u64, u64, u64) = (0, 30, 0);
+
+/// Interval between polls while waiting for Meilisearch tasks to finish
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+/// A search index that can be rebuilt
+#[derive(Debug, Clone, Copy, clap::ArgEnum)]
+pub enum IndexKind {
+    /// NFT documents
+    #[clap(name = "metadatas")]
+    Metadatas,
+    /// Collection documents
+    #[clap(name = "collections")]
+    Collections,
+    /// Twitter handle documents
+    #[clap(name = "name_service")]
+    NameService,
+    /// Wallet documents
+    #[clap(name = "wallets")]
+    Wallets,
+}
+
+/// A keyset-paginated source of documents, called with the page cursor and
+/// size and returning the next cursor along with the page's documents
+type Source =
+    fn(&Connection, &AssetProxyArgs, &str, i64) -> Result<(Option<String>, Vec<Document>)>;
+
+impl IndexKind {
+    fn config(self) -> &'static IndexConfig {
+        match self {
+            Self::Metadatas => &settings::METADATAS,
+            Self::Collections => &settings::COLLECTIONS,
+            Self::NameService => &settings::NAME_SERVICE,
+            Self::Wallets => &settings::WALLETS,
+        }
+    }
+
+    fn source(self) -> Source {
+        match self {
+            Self::Metadatas => metadatas_page,
+            Self::Collections => collections_page,
+            Self::NameService => name_service_page,
+            Self::Wallets => wallets_page,
+        }
+    }
+}
+
+/// Arguments for a search reindex
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// The index to rebuild
+    #[clap(long, arg_enum)]
+    index: IndexKind,
+
+    /// Build the documents into a fresh index and swap it with the live one
+    /// once complete, rather than upserting into the live index.  Requires
+    /// Meilisearch v0.30 or newer for the swap-indexes API, which is checked
+    /// before the rebuild starts.
+    ///
+    /// Changes the search consumer writes to the live index during the
+    /// rebuild are not copied to the fresh index, and are lost if they land
+    /// after the rebuild has read the affected rows.  To avoid this, stop the
+    /// search consumer for the duration of the rebuild; its queue holds the
+    /// changes until it restarts and applies them to the swapped-in index.
+    #[clap(long)]
+    swap: bool,
+
+    /// Number of rows to load from Postgres per batch
+    #[clap(long, default_value_t = 1000)]
+    page_size: i64,
+}
+
+fn document(id: String, body: impl Serialize) -> Result<Document> {
+    Ok(Document {
+        id,
+        body: serde_json::to_value(body).context("Failed to upcast document body")?,
+    })
+}
+
+fn metadatas_page(
+    db: &Connection,
+    proxy_args: &AssetProxyArgs,
+    after: &str,
+    limit: i64,
+) -> Result<(Option<String>, Vec<Document>)> {
+    let rows: Vec<(String, Value, Option<String>)> = metadata_jsons::table
+        .inner_join(metadatas::table.on(metadatas::address.eq(metadata_jsons::metadata_address)))
+        .filter(metadata_jsons::metadata_address.gt(after))
+        .select((
+            metadata_jsons::metadata_address,
+            metadata_jsons::raw_content,
+            metadata_jsons::image,
+        ))
+        .order(metadata_jsons::metadata_address)
+        .limit(limit)
+        .load(db)
+        .context("Failed to load metadata JSONs")?;
+
+    let last = rows.last().map(|(a, ..)| a.clone());
+    let docs = search_documents::metadata_documents(db, proxy_args, rows)?
+        .into_iter()
+        .map(|(addr, d)| document(addr, d))
+        .collect::<Result<_>>()?;
+
+    Ok((last, docs))
+}
+
+fn collections_page(
+    db: &Connection,
+    proxy_args: &AssetProxyArgs,
+    after: &str,
+    limit: i64,
+) -> Result<(Option<String>, Vec<Document>)> {
+    let mints: Vec<String> = metadata_collection_keys::table
+        .filter(metadata_collection_keys::collection_address.gt(after))
+        .select(metadata_collection_keys::collection_address)
+        .distinct()
+        .order(metadata_collection_keys::collection_address)
+        .limit(limit)
+        .load(db)
+        .context("Failed to load collection addresses")?;
+
+    let last = mints.last().cloned();
+    let mut docs = Vec::with_capacity(mints.len());
+
+    for mint in mints {
+        match search_documents::collection_document(db, proxy_args, &mint) {
+            Ok((addr, d)) => docs.push(document(addr, d)?),
+            Err(e) => trace!("Skipping collection {}: {:?}", mint, e),
+        }
+    }
+
+    Ok((last, docs))
+}
+
+fn name_service_page(
+    db: &Connection,
+    _: &AssetProxyArgs,
+    after: &str,
+    limit: i64,
+) -> Result<(Option<String>, Vec<Document>)> {
+    let rows: Vec<(String, String, String)> = twitter_handle_name_services::table
+        .filter(twitter_handle_name_services::address.gt(after))
+        .select((
+            twitter_handle_name_services::address,
+            twitter_handle_name_services::wallet_address,
+            twitter_handle_name_services::twitter_handle,
+        ))
+        .order(twitter_handle_name_services::address)
+        .limit(limit)
+        .load(db)
+        .context("Failed to load twitter handles")?;
+
+    let last = rows.last().map(|(a, ..)| a.clone());
+    let docs = rows
+        .into_iter()
+        .map(|(addr, owner, handle)| document(addr, TwitterHandleDocument { owner, handle }))
+        .collect::<Result<_>>()?;
+
+    Ok((last, docs))
+}
+
+/// Load a page of wallets found in any of the tables a wallet document
+/// aggregates, building each wallet's document once
+fn wallets_page(
+    db: &Connection,
+    _: &AssetProxyArgs,
+    after: &str,
+    limit: i64,
+) -> Result<(Option<String>, Vec<Document>)> {
+    let mut addresses = BTreeSet::new();
+
+    // Each table is walked down its own index separately; the first `limit`
+    // wallets overall are among the first `limit` of each table
+    addresses.extend(
+        twitter_handle_name_services::table
+            .filter(twitter_handle_name_services::wallet_address.gt(after))
+            .select(twitter_handle_name_services::wallet_address)
+            .distinct()
+            .order(twitter_handle_name_services::wallet_address)
+            .limit(limit)
+            .load::<String>(db)
+            .context("Failed to load wallets with twitter handles")?,
+    );
+    addresses.extend(
+        cardinal_entries::table
+            .filter(cardinal_entries::data.gt(after))
+            .select(cardinal_entries::data)
+            .distinct()
+            .order(cardinal_entries::data)
+            .limit(limit)
+            .load::<Option<String>>(db)
+            .context("Failed to load wallets with Cardinal names")?
+            .into_iter()
+            .flatten(),
+    );
+    addresses.extend(
+        metadata_creators::table
+            .filter(metadata_creators::verified)
+            .filter(metadata_creators::creator_address.gt(after))
+            .select(metadata_creators::creator_address)
+            .distinct()
+            .order(metadata_creators::creator_address)
+            .limit(limit)
+            .load::<String>(db)
+            .context("Failed to load verified creators")?,
+    );
+    addresses.extend(
+        graph_connections::table
+            .filter(graph_connections::to_account.gt(after))
+            .select(graph_connections::to_account)
+            .distinct()
+            .order(graph_connections::to_account)
+            .limit(limit)
+            .load::<String>(db)
+            .context("Failed to load followed wallets")?,
+    );
+    addresses.extend(
+        graph_connections::table
+            .filter(graph_connections::from_account.gt(after))
+            .select(graph_connections::from_account)
+            .distinct()
+            .order(graph_connections::from_account)
+            .limit(limit)
+            .load::<String>(db)
+            .context("Failed to load following wallets")?,
+    );
+
+    let addresses: Vec<_> = addresses
+        .into_iter()
+        .take(limit.try_into().unwrap_or(usize::MAX))
+        .collect();
+    let last = addresses.last().cloned();
+    let docs = search_documents::wallet_documents(db, addresses)?
+        .into_iter()
+        .map(|d| document(d.address.clone(), d))
+        .collect::<Result<_>>()?;
+
+    Ok((last, docs))
+}
+
+async fn wait_for_index(meili: &MeiliBackend, name: &str) -> Result<()> {
+    let api = Api::new(meili)?;
+
+    loop {
+        let pending = api.pending_tasks(Some(name)).await?;
+
+        if pending == 0 {
+            break Ok(());
+        }
+
+        debug!("Waiting on {} task(s) for index {:?}", pending, name);
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Rebuild a search index from Postgres
+///
+/// # Errors
+/// This function fails if a database query or search backend call fails, or
+/// if an index swap is requested for a backend other than Meilisearch or a
+/// Meilisearch server too old to support it.
+pub async fn run(
+    db: Pool,
+    client_args: ClientArgs,
+    asset_proxy: AssetProxyArgs,
+    args: Args,
+) -> Result<()> {
+    let Args {
+        index,
+        swap,
+        page_size,
+    } = args;
+
+    let config = index.config();
+    let (client, upsert_task, stop_upsert) = Client::new_rc(db, client_args)
+        .await
+        .context("Failed to construct Client")?;
+
+    let target = if swap {
+        let meili = client
+            .meili()
+            .ok_or_else(|| anyhow!("Index swapping is only supported by Meilisearch"))?;
+        let version = Api::new(meili)?.version().await?;
+
+        ensure!(
+            version >= SWAP_INDEXES_VERSION,
+            "Index swapping requires Meilisearch v{}.{}.{} or newer, but the server is \
+             v{}.{}.{}",
+            SWAP_INDEXES_VERSION.0,
+            SWAP_INDEXES_VERSION.1,
+            SWAP_INDEXES_VERSION.2,
+            version.0,
+            version.1,
+            version.2,
+        );
+
+        let staging = format!("{}{}", config.name, STAGING_SUFFIX);
+
+        if meili.client().get_index(&staging).await.is_ok() {
+            warn!("Deleting leftover staging index {:?}", staging);
+
+            let task = meili
+                .client()
+                .index(&staging)
+                .delete()
+                .await
+                .context("Failed to delete staging index")?;
+            meili
+                .client()
+                .wait_for_task(task, None, None)
+                .await
+                .context("Failed to wait for staging index deletion")?;
+        }
+
+        config.apply_as(meili, &staging).await?;
+
+        warn!(
+            "Live updates to {:?} will not reach the rebuilt index unless the search \
+             consumer is stopped until the swap completes",
+            config.name
+        );
+
+        staging
+    } else {
+        config.name.to_owned()
+    };
+
+    info!("Reindexing {:?} into {:?}", config.name, target);
+
+    let mut total = 0_usize;
+
+    let source = index.source();
+    let mut after = String::new();
+
+    loop {
+        let (last, docs) = client
+            .db()
+            .run({
+                let proxy_args = asset_proxy.clone();
+                let after = after.clone();
+
+                move |db| source(db, &proxy_args, &after, page_size)
+            })
+            .await?;
+
+        total += docs.len();
+        client.upsert_documents(target.clone(), docs).await?;
+
+        match last {
+            Some(last) => after = last,
+            None => break,
+        }
+
+        debug!("Queued {} document(s), up to {:?}", total, after);
+    }
+
+    info!("Queued {} document(s) for {:?}, flushing", total, target);
+
+    if let Err(()) = stop_upsert.send(()) {
+        return Err(anyhow!("Upsert task stopped unexpectedly"));
+    }
+
+    upsert_task.await.context("Join for upsert task failed")?;
+
+    if let Some(meili) = client.meili() {
+        wait_for_index(meili, &target).await?;
+
+        if swap {
+            info!("Swapping {:?} into {:?}", target, config.name);
+            Api::new(meili)?.swap_indexes(config.name, &target).await?;
+
+            // The staging name now refers to the old index
+            let task = meili
+                .client()
+                .index(&target)
+                .delete()
+                .await
+                .context("Failed to delete old index")?;
+            meili
+                .client()
+                .wait_for_task(task, None, None)
+                .await
+                .context("Failed to wait for old index deletion")?;
+        }
+    }
+
+    info!("Reindex of {:?} complete", config.name);
+
+    Ok(())
+}
@@ -0,0 +1,298 @@
+//! Declarative settings for the Meilisearch indexes managed by the search
+//! indexer
+
+// Meilisearch settings use the standard hasher
+use std::collections::HashMap;
+
+use indexer_core::{
+    meilisearch::settings::Settings,
+    search::{MeiliBackend, MAX_FACET_VALUES},
+};
+
+use super::api::{Api, Faceting};
+use crate::prelude::*;
+
+/// Ranking rules shared by all indexes.  These match the Meilisearch
+/// defaults, but are pinned here so an upgrade can't silently change them.
+const RANKING_RULES: &[&str] = &[
+    "words",
+    "typo",
+    "proximity",
+    "attribute",
+    "sort",
+    "exactness",
+];
+
+const STOP_WORDS: &[&str] = &["a", "an", "the", "of"];
+
+const SYNONYMS: &[(&str, &[&str])] = &[
+    ("sol", &["solana"]),
+    ("solana", &["sol"]),
+    ("pfp", &["profile picture"]),
+];
+
+/// The configuration of a single Meilisearch index
+#[derive(Debug, Clone, Copy)]
+pub struct IndexConfig {
+    /// The name of the index
+    pub name: &'static str,
+    /// The primary key of the index's documents
+    pub primary_key: &'static str,
+    /// Attributes to search, in descending order of importance
+    pub searchable: &'static [&'static str],
+    /// Attributes usable in search filters
+    pub filterable: &'static [&'static str],
+    /// Attributes usable for sorting search results
+    pub sortable: &'static [&'static str],
+    /// Groups of words to treat as equivalent
+    pub synonyms: &'static [(&'static str, &'static [&'static str])],
+    /// Words to ignore in search queries
+    pub stop_words: &'static [&'static str],
+    /// Ranking rules, in descending order of importance
+    pub ranking_rules: &'static [&'static str],
+    /// The maximum number of values returned for each facet, or `None` to
+    /// use the server default
+    pub max_values_per_facet: Option<usize>,
+}
+
+/// The `metadatas` index, containing NFT documents
+pub const METADATAS: IndexConfig = IndexConfig {
+    name: "metadatas",
+    primary_key: "id",
+    searchable: &[
+        "name",
+        "collection_name",
+        "creator_twitter_handle",
+        "creator_address",
+        "mint_address",
+    ],
+    filterable: &[
+        "creator_address",
+        "collection_address",
+        "attributes",
+        "listed",
+        "price",
+        "owner",
+    ],
+    sortable: &["name", "price"],
+    synonyms: SYNONYMS,
+    stop_words: STOP_WORDS,
+    ranking_rules: RANKING_RULES,
+    // Every trait type shares the `attributes` facet, so the default of 100
+    // values would truncate the distribution for most collections
+    max_values_per_facet: Some(MAX_FACET_VALUES),
+};
+
+/// The `collections` index, containing collection NFT documents
+pub const COLLECTIONS: IndexConfig = IndexConfig {
+    name: "collections",
+    primary_key: "id",
+    searchable: &["name", "mint_address"],
+    filterable: &[],
+    sortable: &["name"],
+    synonyms: SYNONYMS,
+    stop_words: STOP_WORDS,
+    ranking_rules: RANKING_RULES,
+    max_values_per_facet: None,
+};
+
+/// The `name_service` index, containing Twitter handle documents
+pub const NAME_SERVICE: IndexConfig = IndexConfig {
+    name: "name_service",
+    primary_key: "id",
+    searchable: &["handle", "owner"],
+    filterable: &["owner"],
+    sortable: &["handle"],
+    synonyms: &[],
+    stop_words: &[],
+    ranking_rules: RANKING_RULES,
+    max_values_per_facet: None,
+};
+
+/// The `wallets` index, containing wallet documents aggregated from name
+/// services, creator verifications and follows
+pub const WALLETS: IndexConfig = IndexConfig {
+    name: "wallets",
+    primary_key: "id",
+    searchable: &["twitter_handle", "cardinal_names", "address"],
+    filterable: &["is_creator"],
+    sortable: &["followers", "verified_collections"],
+    synonyms: &[],
+    stop_words: &[],
+    // Creators and highly-followed wallets outrank other equally good matches
+    ranking_rules: &[
+        "words",
+        "typo",
+        "verified_collections:desc",
+        "followers:desc",
+        "proximity",
+        "attribute",
+        "sort",
+        "exactness",
+    ],
+    max_values_per_facet: None,
+};
+
+/// All indexes managed by the search indexer
+pub const INDEXES: &[IndexConfig] = &[METADATAS, COLLECTIONS, NAME_SERVICE, WALLETS];
+
+fn strings(s: &[&str]) -> Vec<String> {
+    s.iter().map(|s| (*s).to_owned()).collect()
+}
+
+fn sorted(mut v: Vec<String>) -> Vec<String> {
+    v.sort_unstable();
+    v
+}
+
+impl IndexConfig {
+    fn synonym_map(&self) -> HashMap<String, Vec<String>> {
+        self.synonyms
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), strings(v)))
+            .collect()
+    }
+
+    fn settings(&self) -> Settings {
+        Settings::new()
+            .with_searchable_attributes(self.searchable)
+            .with_filterable_attributes(self.filterable)
+            .with_sortable_attributes(self.sortable)
+            .with_synonyms(self.synonym_map())
+            .with_stop_words(self.stop_words)
+            .with_ranking_rules(self.ranking_rules)
+    }
+
+    /// List the names of any settings on the live index that differ from
+    /// this configuration
+    fn drift(&self, live: &Settings) -> Vec<&'static str> {
+        let mut drift = Vec::new();
+
+        // Searchable attribute order is significant, but Meilisearch treats
+        // the remaining lists as sets
+        if live.searchable_attributes.as_deref() != Some(&*strings(self.searchable)) {
+            drift.push("searchable attributes");
+        }
+
+        if live.filterable_attributes.clone().map(sorted) != Some(sorted(strings(self.filterable)))
+        {
+            drift.push("filterable attributes");
+        }
+
+        if live.sortable_attributes.clone().map(sorted) != Some(sorted(strings(self.sortable))) {
+            drift.push("sortable attributes");
+        }
+
+        if live.stop_words.clone().map(sorted) != Some(sorted(strings(self.stop_words))) {
+            drift.push("stop words");
+        }
+
+        let live_synonyms = live.synonyms.as_ref().map(|s| {
+            s.iter()
+                .map(|(k, v)| (k.clone(), sorted(v.clone())))
+                .collect::<HashMap<_, _>>()
+        });
+        let synonyms = self
+            .synonym_map()
+            .into_iter()
+            .map(|(k, v)| (k, sorted(v)))
+            .collect();
+
+        if live_synonyms != Some(synonyms) {
+            drift.push("synonyms");
+        }
+
+        if live.ranking_rules.as_deref() != Some(&*strings(self.ranking_rules)) {
+            drift.push("ranking rules");
+        }
+
+        drift
+    }
+
+    /// Create this index if it does not exist, and update its settings if
+    /// they have drifted from this configuration
+    ///
+    /// # Errors
+    /// This function fails if the index has a different primary key, or if a
+    /// Meilisearch API call fails.
+    pub async fn apply(&self, meili: &MeiliBackend) -> Result<()> {
+        self.apply_as(meili, self.name).await
+    }
+
+    /// Apply this configuration to the index with the given name, rather than
+    /// the one named by this configuration
+    ///
+    /// # Errors
+    /// This function fails for the same reasons as [`apply`](Self::apply).
+    pub async fn apply_as(&self, meili: &MeiliBackend, name: &str) -> Result<()> {
+        let meili_client = meili.client();
+        let idx = if let Ok(mut idx) = meili_client.get_index(name).await {
+            ensure!(
+                idx.get_primary_key()
+                    .await
+                    .context("Failed to check primary key name")?
+                    .map_or(false, |k| k == self.primary_key),
+                "Primary key mismatch for index {}",
+                name
+            );
+
+            idx
+        } else {
+            let task = meili_client
+                .create_index(name, Some(self.primary_key))
+                .await
+                .context("Failed to create index")?;
+            meili_client
+                .wait_for_task(task, None, None)
+                .await
+                .context("Failed to wait for index creation")?;
+
+            meili_client.index(name)
+        };
+
+        if let Some(max_values_per_facet) = self.max_values_per_facet {
+            let api = Api::new(meili)?;
+            let faceting = Faceting {
+                max_values_per_facet,
+            };
+
+            if api.faceting(name).await? == faceting {
+                debug!("Faceting settings for index {:?} are up to date", name);
+            } else {
+                warn!(
+                    "Faceting settings for index {:?} have drifted, updating",
+                    name
+                );
+                api.set_faceting(name, faceting).await?;
+            }
+        }
+
+        let live = idx
+            .get_settings()
+            .await
+            .context("Failed to get index settings")?;
+        let drift = self.drift(&live);
+
+        if drift.is_empty() {
+            debug!("Settings for index {:?} are up to date", name);
+            return Ok(());
+        }
+
+        warn!(
+            "Settings for index {:?} have drifted ({}), updating",
+            name,
+            drift.join(", ")
+        );
+
+        let task = idx
+            .set_settings(&self.settings())
+            .await
+            .context("Failed to update index settings")?;
+        meili_client
+            .wait_for_task(task, None, None)
+            .await
+            .context("Failed to wait for settings update")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Minimal HTTP endpoint reporting the state of the search upsert worker
+
+use std::sync::Weak;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use super::Client;
+use crate::prelude::*;
+
+/// Serve a JSON snapshot of [`Client::stats`] to every connection accepted on
+/// `listener`, until the client is dropped
+pub(super) async fn serve(listener: TcpListener, client: Weak<Client>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((s, _)) => s,
+            Err(e) => {
+                warn!("Failed to accept search status connection: {}", e);
+                continue;
+            },
+        };
+
+        let stats = match client.upgrade() {
+            Some(c) => c.stats(),
+            None => break,
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &stats).await {
+                debug!("Failed to send search status: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, stats: &super::client::Stats) -> Result<()> {
+    // The request itself is ignored; every path serves the same snapshot
+    let mut buf = [0; 1024];
+    stream
+        .read(&mut buf)
+        .await
+        .context("Failed to read request")?;
+
+    let body = serde_json::to_string(stats).context("Failed to serialize stats")?;
+
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: \
+                 {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        )
+        .await
+        .context("Failed to write response")?;
+
+    stream
+        .shutdown()
+        .await
+        .context("Failed to close connection")
+}
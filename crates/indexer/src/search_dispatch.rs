@@ -3,29 +3,9 @@ use indexer_rabbitmq::search_indexer::{Document, Message, Producer, QueueType};
 use serde::Serialize;
 
 use crate::prelude::*;
-
-#[derive(Debug, Serialize)]
-pub struct TwitterHandleDocument {
-    pub owner: String,
-    pub handle: String,
-}
-
-#[derive(Debug, Serialize, Clone)]
-pub struct MetadataDocument {
-    pub name: String,
-    pub mint_address: String,
-    pub image: Option<String>,
-    pub creator_address: String,
-    pub creator_twitter_handle: Option<String>,
-    pub collection_address: Option<String>,
-}
-
-#[derive(Debug, Serialize, Clone)]
-pub struct CollectionDocument {
-    pub name: String,
-    pub image: Option<String>,
-    pub mint_address: String,
-}
+pub use crate::search_documents::{
+    CollectionDocument, MetadataDocument, TwitterHandleDocument, WalletDocument,
+};
 
 #[derive(Debug, Clone, clap::Parser)]
 pub struct Args {
@@ -102,6 +82,17 @@ impl Client {
             .context("Failed to send upsert message")
     }
 
+    #[inline]
+    async fn dispatch_delete(&self, index: &'static str, id: impl std::fmt::Display) -> Result<()> {
+        self.producer
+            .write(Message::Delete {
+                index: index.to_owned(),
+                id: id.to_string(),
+            })
+            .await
+            .context("Failed to send delete message")
+    }
+
     pub async fn upsert_metadata(
         &self,
         is_for_backfill: bool,
@@ -135,4 +126,26 @@ impl Client {
         self.dispatch_upsert(is_for_backfill, "name_service", key, body)
             .await
     }
+
+    pub async fn upsert_wallet(
+        &self,
+        is_for_backfill: bool,
+        key: String,
+        body: WalletDocument,
+    ) -> Result<()> {
+        debug_assert!(key.parse::<Pubkey>().is_ok());
+
+        self.dispatch_upsert(is_for_backfill, "wallets", key, body)
+            .await
+    }
+
+    pub async fn delete_metadata(&self, key: String) -> Result<()> {
+        debug_assert!(key.parse::<Pubkey>().is_ok());
+
+        self.dispatch_delete("metadatas", key).await
+    }
+
+    pub async fn delete_twitter_handle(&self, key: Pubkey) -> Result<()> {
+        self.dispatch_delete("name_service", key).await
+    }
 }
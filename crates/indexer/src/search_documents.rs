@@ -0,0 +1,501 @@
+//! Search document types, and the queries used to build them from Postgres
+
+use indexer_core::{
+    assets::{proxy_url, AssetIdentifier, AssetProxyArgs},
+    db::{
+        queries, select,
+        tables::{
+            auction_houses, cardinal_entries, cardinal_namespaces, current_metadata_owners,
+            listing_denylist, listing_metadatas, listings, metadata_collection_keys,
+            metadata_creators, metadata_jsons, metadatas, twitter_handle_name_services,
+        },
+        Connection,
+    },
+    hash::{HashMap, HashSet},
+    pubkeys,
+    url::Url,
+    util::encode_attribute,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize)]
+pub struct TwitterHandleDocument {
+    pub owner: String,
+    pub handle: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MetadataDocument {
+    pub name: String,
+    pub mint_address: String,
+    pub image: Option<String>,
+    pub creator_address: String,
+    pub creator_twitter_handle: Option<String>,
+    pub collection_address: Option<String>,
+    pub collection_name: Option<String>,
+    /// Attributes encoded by [`encode_attribute`] for filtering and faceting
+    pub attributes: Vec<String>,
+    pub listed: bool,
+    /// The lowest active listing price in SOL, in lamports
+    pub price: Option<i64>,
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CollectionDocument {
+    pub name: String,
+    pub image: Option<String>,
+    pub mint_address: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WalletDocument {
+    pub address: String,
+    pub twitter_handle: Option<String>,
+    /// Names pointing at this wallet, encoded as `name.namespace`
+    pub cardinal_names: Vec<String>,
+    pub is_creator: bool,
+    /// The number of collections this wallet is a verified creator of
+    pub verified_collections: i64,
+    pub followers: i64,
+    pub following: i64,
+}
+
+/// Rewrite an image URL to point at a thumbnail served by the asset proxy,
+/// falling back to the original URL if it isn't a permaweb asset
+///
+/// # Errors
+/// This function fails if the asset proxy URL is invalid.
+pub fn proxy_image(proxy_args: &AssetProxyArgs, image: Option<&str>) -> Result<Option<String>> {
+    image
+        .and_then(|i| Url::parse(i).ok())
+        .and_then(|u| {
+            let id = AssetIdentifier::new(&u);
+
+            proxy_url(proxy_args, &id, Some(("width", "200")))
+                .map(|o| o.map(|u| u.to_string()))
+                .transpose()
+        })
+        .or_else(|| image.map(|s| Ok(s.into())))
+        .transpose()
+}
+
+/// Extract the attributes of a metadata JSON object as `trait_type:value`
+/// strings
+#[must_use]
+pub fn metadata_attributes(raw: &Value) -> Vec<String> {
+    raw.get("attributes")
+        .and_then(Value::as_array)
+        .map_or_else(Vec::new, |a| {
+            a.iter()
+                .filter_map(|a| {
+                    let trait_type = a.get("trait_type").and_then(Value::as_str)?;
+                    let value = match a.get("value")? {
+                        Value::String(s) => s.clone(),
+                        Value::Null => return None,
+                        v => v.to_string(),
+                    };
+
+                    Some(encode_attribute(trait_type, &value))
+                })
+                .collect()
+        })
+}
+
+/// Check whether an NFT should be excluded from search, because it has been
+/// burned or belongs to a hard-banned listing
+///
+/// # Errors
+/// This function fails if a database query fails.
+pub fn metadata_removed(db: &Connection, addr: &str) -> Result<bool> {
+    let burned = metadatas::table
+        .filter(metadatas::address.eq(addr))
+        .select(metadatas::burned)
+        .first(db)
+        .optional()
+        .context("Failed to check if metadata was burned")?
+        .unwrap_or(false);
+
+    let denylisted = select(exists(
+        listing_metadatas::table
+            .inner_join(
+                listing_denylist::table
+                    .on(listing_metadatas::listing_address.eq(listing_denylist::listing_address)),
+            )
+            .filter(listing_metadatas::metadata_address.eq(addr))
+            .filter(listing_denylist::hard_ban),
+    ))
+    .get_result(db)
+    .context("Failed to check listing denylist")?;
+
+    Ok(burned || denylisted)
+}
+
+/// Build the search document for an NFT
+///
+/// # Errors
+/// This function fails if a database query fails, or if the NFT has no
+/// verified first creator.
+pub fn metadata_document(
+    db: &Connection,
+    addr: &str,
+    image: Option<String>,
+    attributes: Vec<String>,
+) -> Result<MetadataDocument> {
+    let (name, mint_address, collection_address): (String, String, Option<String>) =
+        metadatas::table
+            .left_join(
+                metadata_collection_keys::table
+                    .on(metadatas::address.eq(metadata_collection_keys::metadata_address)),
+            )
+            .filter(metadatas::address.eq(addr))
+            .select((
+                metadatas::name,
+                metadatas::mint_address,
+                metadata_collection_keys::collection_address.nullable(),
+            ))
+            .first(db)
+            .context("failed to load mint and name for search doc")?;
+
+    let collection_name = collection_address
+        .as_ref()
+        .map(|c| {
+            metadatas::table
+                .filter(metadatas::mint_address.eq(c))
+                .select(metadatas::name)
+                .first::<String>(db)
+                .optional()
+        })
+        .transpose()
+        .context("failed to load collection name for search doc")?
+        .flatten();
+
+    let (creator_address, creator_twitter_handle) = metadata_creators::table
+        .left_join(twitter_handle_name_services::table.on(
+            metadata_creators::creator_address.eq(twitter_handle_name_services::wallet_address),
+        ))
+        .filter(metadata_creators::metadata_address.eq(addr))
+        .filter(metadata_creators::verified.eq(true))
+        .filter(metadata_creators::position.eq(0))
+        .select((
+            metadata_creators::creator_address,
+            twitter_handle_name_services::twitter_handle.nullable(),
+        ))
+        .first(db)
+        .context("failed to load creators for search document")?;
+
+    let price = lowest_prices(db, &[addr.to_owned()])?.remove(addr);
+
+    let owner = current_metadata_owners::table
+        .filter(current_metadata_owners::mint_address.eq(&mint_address))
+        .select(current_metadata_owners::owner_address)
+        .first(db)
+        .optional()
+        .context("failed to load owner for search document")?;
+
+    Ok(MetadataDocument {
+        name,
+        mint_address,
+        image,
+        creator_address,
+        creator_twitter_handle,
+        collection_address,
+        collection_name,
+        attributes,
+        listed: price.is_some(),
+        price: price.flatten(),
+        owner,
+    })
+}
+
+/// Find which of a set of NFTs have an active listing, along with the
+/// lowest price each is listed for in SOL.  NFTs listed only in other
+/// currencies map to `None`, so prices are always comparable in lamports.
+fn lowest_prices(db: &Connection, addrs: &[String]) -> Result<HashMap<String, Option<i64>>> {
+    let sol = pubkeys::SOL.to_string();
+
+    Ok(listings::table
+        .left_join(auction_houses::table.on(listings::auction_house.eq(auction_houses::address)))
+        .filter(listings::metadata.eq_any(addrs))
+        .filter(listings::purchase_id.is_null())
+        .filter(listings::canceled_at.is_null())
+        .select((
+            listings::metadata,
+            listings::price,
+            auction_houses::treasury_mint.nullable(),
+        ))
+        .load::<(String, i64, Option<String>)>(db)
+        .context("failed to load listing prices for search docs")?
+        .into_iter()
+        .fold(HashMap::default(), |mut h, (addr, price, mint)| {
+            let price = (mint.as_ref() == Some(&sol)).then(|| price);
+            let lowest = h.entry(addr).or_insert(None);
+
+            *lowest = match (*lowest, price) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+
+            h
+        }))
+}
+
+/// Build the search documents for a batch of NFTs from their metadata JSON
+/// rows, given as `(address, raw_content, image)`.  Unlike
+/// [`metadata_document`], the number of queries run does not grow with the
+/// size of the batch.  NFTs excluded from search or with no verified first
+/// creator are skipped.
+///
+/// # Errors
+/// This function fails if a database query fails or if the asset proxy URL is
+/// invalid.
+pub fn metadata_documents(
+    db: &Connection,
+    proxy_args: &AssetProxyArgs,
+    jsons: Vec<(String, Value, Option<String>)>,
+) -> Result<Vec<(String, MetadataDocument)>> {
+    let addrs: Vec<String> = jsons.iter().map(|(a, ..)| a.clone()).collect();
+
+    let mut names: HashMap<String, (String, String)> = metadatas::table
+        .filter(metadatas::address.eq_any(&addrs))
+        .filter(metadatas::burned.eq(false))
+        .select((metadatas::address, metadatas::name, metadatas::mint_address))
+        .load::<(String, String, String)>(db)
+        .context("failed to load mints and names for search docs")?
+        .into_iter()
+        .map(|(addr, name, mint)| (addr, (name, mint)))
+        .collect();
+
+    let denylisted: HashSet<String> = listing_metadatas::table
+        .inner_join(
+            listing_denylist::table
+                .on(listing_metadatas::listing_address.eq(listing_denylist::listing_address)),
+        )
+        .filter(listing_metadatas::metadata_address.eq_any(&addrs))
+        .filter(listing_denylist::hard_ban)
+        .select(listing_metadatas::metadata_address)
+        .load::<String>(db)
+        .context("failed to check listing denylist for search docs")?
+        .into_iter()
+        .collect();
+
+    let mut collections: HashMap<String, String> = metadata_collection_keys::table
+        .filter(metadata_collection_keys::metadata_address.eq_any(&addrs))
+        .select((
+            metadata_collection_keys::metadata_address,
+            metadata_collection_keys::collection_address,
+        ))
+        .load::<(String, String)>(db)
+        .context("failed to load collections for search docs")?
+        .into_iter()
+        .collect();
+
+    let collection_names: HashMap<String, String> = metadatas::table
+        .filter(metadatas::mint_address.eq_any(collections.values().collect::<Vec<_>>()))
+        .select((metadatas::mint_address, metadatas::name))
+        .load::<(String, String)>(db)
+        .context("failed to load collection names for search docs")?
+        .into_iter()
+        .collect();
+
+    let mut creators: HashMap<String, (String, Option<String>)> = metadata_creators::table
+        .left_join(twitter_handle_name_services::table.on(
+            metadata_creators::creator_address.eq(twitter_handle_name_services::wallet_address),
+        ))
+        .filter(metadata_creators::metadata_address.eq_any(&addrs))
+        .filter(metadata_creators::verified.eq(true))
+        .filter(metadata_creators::position.eq(0))
+        .select((
+            metadata_creators::metadata_address,
+            metadata_creators::creator_address,
+            twitter_handle_name_services::twitter_handle.nullable(),
+        ))
+        .load::<(String, String, Option<String>)>(db)
+        .context("failed to load creators for search docs")?
+        .into_iter()
+        .map(|(addr, creator, handle)| (addr, (creator, handle)))
+        .collect();
+
+    let prices = lowest_prices(db, &addrs)?;
+    let mints: Vec<&String> = names.values().map(|(_, m)| m).collect();
+    let mut owners: HashMap<String, String> = current_metadata_owners::table
+        .filter(current_metadata_owners::mint_address.eq_any(mints))
+        .select((
+            current_metadata_owners::mint_address,
+            current_metadata_owners::owner_address,
+        ))
+        .load::<(String, String)>(db)
+        .context("failed to load owners for search docs")?
+        .into_iter()
+        .collect();
+
+    let mut docs = Vec::with_capacity(jsons.len());
+
+    for (addr, raw, image) in jsons {
+        if denylisted.contains(&addr) {
+            continue;
+        }
+
+        let ((name, mint_address), (creator_address, creator_twitter_handle)) =
+            if let (Some(n), Some(c)) = (names.remove(&addr), creators.remove(&addr)) {
+                (n, c)
+            } else {
+                trace!("Skipping metadata {} with no search document", addr);
+                continue;
+            };
+        let price = prices.get(&addr).copied();
+
+        docs.push((addr.clone(), MetadataDocument {
+            name,
+            owner: owners.remove(&mint_address),
+            mint_address,
+            image: proxy_image(proxy_args, image.as_deref())?,
+            creator_address,
+            creator_twitter_handle,
+            collection_name: collections
+                .get(&addr)
+                .and_then(|c| collection_names.get(c).cloned()),
+            collection_address: collections.remove(&addr),
+            attributes: metadata_attributes(&raw),
+            listed: price.is_some(),
+            price: price.flatten(),
+        }));
+    }
+
+    Ok(docs)
+}
+
+/// Build the search document for an NFT from its indexed metadata JSON,
+/// returning `None` if its JSON has not been indexed yet
+///
+/// # Errors
+/// This function fails if a database query fails, if the asset proxy URL is
+/// invalid, or if the NFT has no verified first creator.
+pub fn indexed_metadata_document(
+    db: &Connection,
+    proxy_args: &AssetProxyArgs,
+    addr: &str,
+) -> Result<Option<MetadataDocument>> {
+    let json: Option<(Value, Option<String>)> = metadata_jsons::table
+        .filter(metadata_jsons::metadata_address.eq(addr))
+        .select((metadata_jsons::raw_content, metadata_jsons::image))
+        .first(db)
+        .optional()
+        .context("failed to load metadata JSON for search document")?;
+
+    let (raw, image) = match json {
+        Some(j) => j,
+        None => return Ok(None),
+    };
+
+    let image = proxy_image(proxy_args, image.as_deref())?;
+    let attributes = metadata_attributes(&raw);
+
+    metadata_document(db, addr, image, attributes).map(Some)
+}
+
+/// Build the search document for a collection from its mint address,
+/// returning the collection's metadata address and its document
+///
+/// # Errors
+/// This function fails if a database query fails or the asset proxy URL is
+/// invalid.
+pub fn collection_document(
+    db: &Connection,
+    proxy_args: &AssetProxyArgs,
+    mint_address: &str,
+) -> Result<(String, CollectionDocument)> {
+    let (address, name, image): (String, String, Option<String>) = metadatas::table
+        .inner_join(
+            metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+        )
+        .filter(metadatas::mint_address.eq(mint_address))
+        .select((metadatas::address, metadatas::name, metadata_jsons::image))
+        .first(db)
+        .context("failed to fetch collection metadata")?;
+
+    Ok((address, CollectionDocument {
+        name,
+        image: proxy_image(proxy_args, image.as_deref())?,
+        mint_address: mint_address.to_owned(),
+    }))
+}
+
+/// Build the search document for a wallet from its address
+///
+/// # Errors
+/// This function fails if a database query fails.
+pub fn wallet_document(db: &Connection, address: &str) -> Result<WalletDocument> {
+    wallet_documents(db, vec![address.to_owned()])?
+        .pop()
+        .ok_or_else(|| anyhow!("Missing wallet document for {}", address))
+}
+
+/// Build the search documents for a batch of wallets, in the order of their
+/// addresses
+///
+/// # Errors
+/// This function fails if a database query fails.
+pub fn wallet_documents(db: &Connection, addresses: Vec<String>) -> Result<Vec<WalletDocument>> {
+    let mut twitter_handles: HashMap<String, String> = twitter_handle_name_services::table
+        .filter(twitter_handle_name_services::wallet_address.eq_any(&addresses))
+        .select((
+            twitter_handle_name_services::wallet_address,
+            twitter_handle_name_services::twitter_handle,
+        ))
+        .load(db)
+        .context("failed to load twitter handles")?
+        .into_iter()
+        .collect();
+
+    let mut cardinal_names = HashMap::<String, Vec<String>>::default();
+
+    for (wallet, name, namespace) in cardinal_entries::table
+        .inner_join(
+            cardinal_namespaces::table
+                .on(cardinal_entries::namespace.eq(cardinal_namespaces::address)),
+        )
+        .filter(cardinal_entries::data.eq_any(&addresses))
+        .select((
+            cardinal_entries::data,
+            cardinal_entries::name,
+            cardinal_namespaces::name,
+        ))
+        .order(cardinal_entries::name)
+        .load::<(Option<String>, String, String)>(db)
+        .context("failed to load cardinal entries")?
+    {
+        if let Some(wallet) = wallet {
+            cardinal_names
+                .entry(wallet)
+                .or_default()
+                .push(format!("{}.{}", name, namespace));
+        }
+    }
+
+    let mut counts: HashMap<_, _> = queries::wallets::counts(db, &addresses)?
+        .into_iter()
+        .map(|c| (c.address.clone(), c))
+        .collect();
+
+    Ok(addresses
+        .into_iter()
+        .map(|address| {
+            let counts = counts.remove(&address);
+            let verified_collections = counts.as_ref().map_or(0, |c| c.verified_collections);
+
+            WalletDocument {
+                twitter_handle: twitter_handles.remove(&address),
+                cardinal_names: cardinal_names.remove(&address).unwrap_or_default(),
+                is_creator: verified_collections > 0,
+                verified_collections,
+                followers: counts.as_ref().map_or(0, |c| c.followers),
+                following: counts.as_ref().map_or(0, |c| c.following),
+                address,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,156 @@
+//! Backfill of the block times of indexed slots from a Solana RPC node.
+//!
+//! Slots referenced by indexed metadata and marketplace activity are walked
+//! newest-first in batches, and the block time of each is requested with a
+//! single JSON-RPC batch call and recorded in the `slot_times` table.
+//! Marketplace rows from those slots that were dated by processing time are
+//! then re-dated by block time.  Slots the node cannot report a block time for
+//! are skipped until the next pass.
+
+use indexer_core::{
+    db::{models::SlotTime, queries::slot_times},
+    util::unix_timestamp,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{db::Pool, prelude::*};
+
+/// The default number of slots requested per RPC batch
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize)]
+struct RpcRequest {
+    jsonrpc: &'static str,
+    id: i64,
+    method: &'static str,
+    params: [i64; 1],
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    id: i64,
+    result: Option<i64>,
+    error: Option<Value>,
+}
+
+/// Request the block times of the given slots from a Solana RPC node,
+/// omitting any the node cannot report
+///
+/// # Errors
+/// This function fails if the RPC request fails or returns an invalid
+/// response.
+pub(crate) async fn fetch(
+    http: &reqwest::Client,
+    rpc_url: &Url,
+    slots: &[i64],
+) -> Result<Vec<SlotTime>> {
+    let requests: Vec<_> = slots
+        .iter()
+        .map(|&slot| RpcRequest {
+            jsonrpc: "2.0",
+            id: slot,
+            method: "getBlockTime",
+            params: [slot],
+        })
+        .collect();
+
+    let responses: Vec<RpcResponse> = http
+        .post(rpc_url.clone())
+        .json(&requests)
+        .send()
+        .await
+        .context("getBlockTime request failed")?
+        .error_for_status()
+        .context("getBlockTime request returned an error")?
+        .json()
+        .await
+        .context("Failed to parse getBlockTime response")?;
+
+    responses
+        .into_iter()
+        .filter_map(|res| match res {
+            RpcResponse {
+                id,
+                result: Some(time),
+                ..
+            } => Some(unix_timestamp(time).map(|block_time| SlotTime {
+                slot: id,
+                block_time,
+            })),
+            RpcResponse { id, error, .. } => {
+                debug!("No block time for slot {}: {:?}", id, error);
+                None
+            },
+        })
+        .collect()
+}
+
+/// Record block times for every slot referenced by indexed metadata, then
+/// poll for new slots every `interval`
+///
+/// # Errors
+/// This function fails if the database cannot be queried or written to.
+/// RPC failures are logged and the failed batch is retried.
+pub async fn backfill(
+    db: &Pool,
+    rpc_url: Url,
+    batch_size: usize,
+    interval: StdDuration,
+) -> Result<()> {
+    let http = reqwest::Client::new();
+    let limit = batch_size.try_into().context("Batch size too large")?;
+    let mut before = None;
+    let mut pass_count = 0;
+
+    loop {
+        let (slots, next) = db
+            .run(move |db| slot_times::missing(db, before, limit))
+            .await?;
+
+        if let Some(&last) = slots.last() {
+            let rows = match fetch(&http, &rpc_url, &slots).await {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("Failed to fetch block times: {:?}", e);
+                    tokio::time::sleep(interval).await;
+                    continue;
+                },
+            };
+
+            let recorded: Vec<_> = rows.iter().map(|r| r.slot).collect();
+            let (count, corrected) = db
+                .run(move |db| {
+                    let count = slot_times::insert(db, &rows)?;
+                    let corrected = slot_times::correct_created_at(db, &recorded)?;
+
+                    Result::<_>::Ok((count, corrected))
+                })
+                .await?;
+            debug!(
+                "Recorded {} of {} slot time(s) down to slot {}, re-dating {} row(s)",
+                count,
+                slots.len(),
+                last,
+                corrected
+            );
+
+            pass_count += count;
+        }
+
+        before = next;
+
+        if before.is_none() {
+            if pass_count > 0 {
+                info!(
+                    "Slot time backfill pass complete, recorded {} slot time(s)",
+                    pass_count
+                );
+            }
+
+            pass_count = 0;
+            tokio::time::sleep(interval).await;
+        }
+    }
+}